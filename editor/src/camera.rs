@@ -17,6 +17,8 @@ use engine::{
 };
 use shipyard::{Unique, UniqueView, UniqueViewMut};
 
+use crate::workbench_scene::WORKBENCH_SCENE;
+
 /// Represents the editor camera used for navigating the scene.
 #[derive(Unique)]
 pub struct EditorCamera {
@@ -92,7 +94,7 @@ fn update_camera_distance_based_on_mouse_wheel(
 
     let scene = s_state
         .sub_scenes
-        .get_mut("WorkbenchScene")
+        .get_mut(WORKBENCH_SCENE)
         .expect("Unable to find workbench scene.");
 
     let delta = m_delta.y * clock.delta_seconds() as f32 * 20.0;
@@ -148,7 +150,7 @@ fn update_fly_camera_when_mouse_key_is_pressed_system(
 ) {
     let scene = s_state
         .sub_scenes
-        .get_mut("WorkbenchScene")
+        .get_mut(WORKBENCH_SCENE)
         .expect("Unable to find workbench scene.");
 
     // Rotate camera if the center mouse button is pressed
@@ -213,7 +215,7 @@ fn update_fly_camera_when_keys_are_pressed_system(
 
     let scene = s_state
         .sub_scenes
-        .get_mut("WorkbenchScene")
+        .get_mut(WORKBENCH_SCENE)
         .expect("Unable to find workbench scene.");
 
     // If no mouse keys are pressed, reset camera speed
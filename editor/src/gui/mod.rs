@@ -10,15 +10,18 @@ pub mod windows;
 
 use egui_extras::install_image_loaders;
 use shipyard::{
-    AllStoragesViewMut, EntitiesView, Get, SparseSet, Unique, UniqueView,
-    UniqueViewMut, ViewMut, World,
+    AllStoragesViewMut, EntitiesView, Get, IntoIter, IntoWithId, SparseSet,
+    Unique, UniqueView, UniqueViewMut, View, ViewMut, World,
 };
 use std::ops::{Deref, DerefMut};
 
 use engine::{
     app::App,
     egui::{Frame, Margin, Rect, TextureId},
-    graphics::{components::MeshComponent, gpu::AbstractGpu},
+    graphics::{
+        components::{MeshComponent, Outline},
+        gpu::AbstractGpu,
+    },
     plugin::{
         graphics::egui::{EguiContext, EguiRenderer},
         Pluggable,
@@ -36,6 +39,7 @@ use crate::gui::{
     sections::viewport_section::extract_viewport_information,
     sections::{
         log_section::render_log_section,
+        render_stats_section::render_render_stats_section,
         viewport_section::render_viewport_section,
     },
     split_panel_tree::{
@@ -60,6 +64,7 @@ use crate::gui::{
         top_toolbar_widget::render_top_toolbar_widget,
     },
 };
+use crate::workbench_scene::WORKBENCH_SCENE;
 
 use self::{
     config::GuiState,
@@ -116,6 +121,7 @@ impl Pluggable for GuiPlugin {
         app.schedule(Schedule::Update, |world| {
             sync_egui_asset_server(world);
             world.run(sync_aspect_ratio_when_viewport_changes);
+            world.run(sync_selection_outline_system);
         });
 
         app.schedule(Schedule::RequestRedraw, |world| {
@@ -152,7 +158,7 @@ fn register_workbench_texture(world: &World) {
     // TODO(Angel): Try to make this reasonable.
     let texture = s_state
         .sub_scenes
-        .get("WorkbenchScene")
+        .get(WORKBENCH_SCENE)
         .unwrap()
         .target_texture
         .downcast_ref::<WGPUTexture>()
@@ -197,7 +203,7 @@ fn sync_aspect_ratio_when_viewport_changes(
         .sub_scenes
         .iter_mut()
         .filter(|(id, scene)| {
-            *id == "WorkbenchScene"
+            *id == WORKBENCH_SCENE
                 && matches!(scene.projection, Projection::Perspective { .. })
         })
         .map(|e| e.1)
@@ -259,6 +265,7 @@ fn configure_panels(tree: &mut SplitPanelTree) {
     tree.insert_tab(viewport, "Viewport", "Viewport");
     tree.insert_tab(asset_zone, "Asset server", "AssetServer");
     tree.insert_tab(log_zone, "General logs", "GeneralLogs");
+    tree.insert_tab(log_zone, "Render stats", "RenderStats");
 
     tree.insert_tab(right_zone, "Properties", "Properties");
     tree.insert_tab(right_zone, "Scenes", "ScenesConfig");
@@ -304,6 +311,7 @@ fn render_gui_system(world: &World) {
                 &mut |ui, tab: &Tab| match tab.identification.as_str() {
                     "Viewport" => render_viewport_section(ui, world, &info),
                     "GeneralLogs" => render_log_section(ui),
+                    "RenderStats" => render_render_stats_section(ui, world),
                     "Properties" => properties_widget(ui, &world),
                     "ScenesConfig" => render_scene_config_section(ui, world),
                     "EntityHierarchy" => render_hierarchy_widget(ui, &world),
@@ -316,6 +324,18 @@ fn render_gui_system(world: &World) {
         });
 }
 
+/// Outlines the selected entities in the viewport.
+fn sync_selection_outline_system(
+    selection: View<HierarchySelectionFlag>,
+    mut outlines: ViewMut<Outline>,
+) {
+    outlines.clear();
+
+    for (id, _) in selection.iter().with_id() {
+        outlines.add_component_unchecked(id, Outline::default());
+    }
+}
+
 fn mantain_removed_entities_system(mut all_storages: AllStoragesViewMut) {
     all_storages.delete_any::<SparseSet<HierarchyDeletionFlag>>();
 }
@@ -1,6 +1,8 @@
-use image::{io::Reader as ImageReader, GenericImageView};
-
-use std::{future::Future, io::Cursor};
+use std::{
+    future::Future,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
 
 use shipyard::{Unique, UniqueView, UniqueViewMut, World};
 
@@ -9,11 +11,15 @@ use engine::{
         ahash::AHashMap, vec2, Grid, Image, Response, Rounding, ScrollArea,
         Sense, TextureId, Ui,
     },
-    graphics::gpu::AbstractGpu,
-    log::{info, warn},
+    graphics::{gpu::AbstractGpu, mesh::Mesh, Texture},
+    log::{error, info},
     plugin::graphics::egui::EguiRenderer,
-    scene::assets::{asset_server::AssetServer, model::ModelType},
-    types::Size,
+    scene::assets::{
+        asset_server::{AssetServer, AssetTypeMemory},
+        handle::Handle,
+        pack::AssetPack,
+        placeholder::PLACEHOLDER_TEXTURE_ID,
+    },
     wgpu_graphics::{buffer::WGPUTexture, gpu::Gpu},
 };
 
@@ -25,6 +31,32 @@ use crate::gui::config::{AssetServerSection, GuiState};
 #[derive(Unique, Default)]
 pub struct EguiAssetServer {
     pub textures: AHashMap<String, TextureId>,
+    /// Contains the generation of each texture when it was registered in
+    /// Egui, used to detect reloaded textures.
+    texture_generations: AHashMap<String, u32>,
+    /// Contains the files picked by the user which must be requested to the
+    /// `AssetServer`, the file dialog runs outside the main thread.
+    pub pending_files: Arc<Mutex<Vec<PendingAssetFile>>>,
+    /// Keeps alive the assets loaded from the editor until they are deleted.
+    texture_handles: AHashMap<String, Handle<dyn Texture>>,
+    mesh_handles: AHashMap<String, Handle<Mesh>>,
+}
+
+impl EguiAssetServer {
+    /// Drops the editor handle of the asset and unloads it from the
+    /// `AssetServer`.
+    fn delete(&mut self, asset_server: &mut AssetServer, id: &str) {
+        self.texture_handles.remove(id);
+        self.mesh_handles.remove(id);
+
+        asset_server.unload(id);
+    }
+}
+
+/// A file picked from the file dialog waiting to be loaded.
+pub enum PendingAssetFile {
+    Texture(PathBuf),
+    Mesh(PathBuf),
 }
 
 /// Syncs the Engine's `AssetStore` to the Editor Egui's `EguiAssetStore`.
@@ -37,26 +69,92 @@ pub fn sync_egui_asset_server(world: &World) {
     let mut egui_asset_server =
         world.borrow::<UniqueViewMut<EguiAssetServer>>().unwrap();
 
+    // Request all the files picked by the user, the `AssetServer` decodes
+    // them in the background.
+    let pending_files =
+        std::mem::take(&mut *egui_asset_server.pending_files.lock().unwrap());
+
+    for file in pending_files {
+        match file {
+            PendingAssetFile::Texture(path) => {
+                let handle = asset_server.load_texture_file(path);
+                egui_asset_server
+                    .texture_handles
+                    .insert(handle.id().to_owned(), handle);
+            }
+            PendingAssetFile::Mesh(path) => {
+                let handle = asset_server.load_mesh_file(path);
+                egui_asset_server
+                    .mesh_handles
+                    .insert(handle.id().to_owned(), handle);
+            }
+        }
+    }
+
     // Take read lock over the asset server.
     let data_lock = asset_server.data.read().unwrap();
 
+    // Release the Egui textures whose asset was unloaded.
+    let unloaded: Vec<String> = egui_asset_server
+        .textures
+        .keys()
+        .filter(|id| !data_lock.textures.contains_key(id.as_str()))
+        .cloned()
+        .collect();
+
+    for id in unloaded {
+        if let Some(egui_texture_id) = egui_asset_server.textures.remove(&id) {
+            egui_renderer.renderer.free_texture(&egui_texture_id);
+        }
+
+        egui_asset_server.texture_generations.remove(&id);
+        egui_asset_server.texture_handles.remove(&id);
+    }
+
     for (id, texture) in &data_lock.textures {
-        // Do not sync of the texture is already registered.
-        if egui_asset_server.textures.contains_key(id.as_str()) {
+        if id == PLACEHOLDER_TEXTURE_ID {
+            continue;
+        }
+
+        let generation =
+            data_lock.generations.get(id).copied().unwrap_or_default();
+
+        // Do not sync of the texture is already registered and it was not
+        // reloaded.
+        if egui_asset_server.texture_generations.get(id) == Some(&generation) {
             continue;
         }
 
         let texture = texture.downcast_ref::<WGPUTexture>().unwrap();
 
-        let egui_texture_id = egui_renderer.renderer.register_native_texture(
-            &gpu.device,
-            &texture.view,
-            engine::wgpu::FilterMode::Linear,
-        );
+        match egui_asset_server.textures.get(id.as_str()) {
+            Some(egui_texture_id) => {
+                egui_renderer
+                    .renderer
+                    .update_egui_texture_from_wgpu_texture(
+                        &gpu.device,
+                        &texture.view,
+                        engine::wgpu::FilterMode::Linear,
+                        *egui_texture_id,
+                    );
+            }
+            None => {
+                let egui_texture_id =
+                    egui_renderer.renderer.register_native_texture(
+                        &gpu.device,
+                        &texture.view,
+                        engine::wgpu::FilterMode::Linear,
+                    );
+
+                egui_asset_server
+                    .textures
+                    .insert((*id).to_owned(), egui_texture_id);
+            }
+        }
 
         egui_asset_server
-            .textures
-            .insert((*id).to_owned(), egui_texture_id);
+            .texture_generations
+            .insert((*id).to_owned(), generation);
     }
 }
 
@@ -64,8 +162,8 @@ pub fn render_asset_server(ui: &mut Ui, world: &World) -> Response {
     let mut gui_state = world.borrow::<UniqueViewMut<GuiState>>().unwrap();
 
     let gpu = world.borrow::<UniqueView<AbstractGpu>>().unwrap();
-    let egui_asset_server =
-        world.borrow::<UniqueView<EguiAssetServer>>().unwrap();
+    let mut egui_asset_server =
+        world.borrow::<UniqueViewMut<EguiAssetServer>>().unwrap();
     let mut asset_server =
         world.borrow::<UniqueViewMut<AssetServer>>().unwrap();
 
@@ -90,6 +188,19 @@ pub fn render_asset_server(ui: &mut Ui, world: &World) -> Response {
                 Some(AssetServerSection::Mesh),
                 "Meshes",
             );
+
+            ui.separator();
+
+            let report = asset_server.memory_report();
+            render_memory_usage(ui, "Textures", &report.textures);
+            render_memory_usage(ui, "Meshes", &report.meshes);
+            ui.label(format!("Total: {}", format_bytes(report.total_bytes())));
+
+            ui.separator();
+
+            if ui.button("Build asset pack").clicked() {
+                build_asset_pack();
+            }
         });
 
         ui.separator();
@@ -98,13 +209,16 @@ pub fn render_asset_server(ui: &mut Ui, world: &World) -> Response {
             Some(AssetServerSection::Texture) => render_texture_section(
                 ui,
                 &mut asset_server,
-                &egui_asset_server,
+                &mut egui_asset_server,
                 height,
             ),
 
-            Some(AssetServerSection::Mesh) => {
-                render_mesh_section(ui, &mut asset_server, height)
-            }
+            Some(AssetServerSection::Mesh) => render_mesh_section(
+                ui,
+                &mut asset_server,
+                &mut egui_asset_server,
+                height,
+            ),
 
             _ => ui.label("No selected"),
         }
@@ -112,10 +226,66 @@ pub fn render_asset_server(ui: &mut Ui, world: &World) -> Response {
     .response
 }
 
+/// Asks for a manifest and where the pack must be saved, the assets are
+/// imported and written outside the main thread.
+fn build_asset_pack() {
+    let task = rfd::AsyncFileDialog::new()
+        .add_filter("Asset manifest", &["ron"])
+        .pick_file();
+
+    execute(async move {
+        let manifest = match task.await {
+            Some(f) => f,
+            _ => return,
+        };
+
+        let pack = rfd::AsyncFileDialog::new()
+            .add_filter("Asset pack", &["pack"])
+            .set_file_name("assets.pack")
+            .save_file()
+            .await;
+
+        let pack = match pack {
+            Some(f) => f,
+            _ => return,
+        };
+
+        match AssetPack::build(manifest.path(), pack.path()) {
+            Ok(()) => info!("Asset pack saved in {:?}", pack.path()),
+            Err(e) => error!("Unable to build the asset pack: {}", e),
+        }
+    });
+}
+
+/// Renders the number of assets and the memory they use.
+fn render_memory_usage(ui: &mut Ui, label: &str, memory: &AssetTypeMemory) {
+    ui.label(format!(
+        "{}: {} ({})",
+        label,
+        memory.count,
+        format_bytes(memory.bytes)
+    ));
+}
+
+/// Formats the number of bytes using the most suitable unit.
+pub(super) fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
 fn render_texture_section(
     ui: &mut Ui,
     asset_server: &mut AssetServer,
-    egui_asset_server: &EguiAssetServer,
+    egui_asset_server: &mut EguiAssetServer,
     height: f32,
 ) -> Response {
     ui.vertical(|ui| {
@@ -124,7 +294,7 @@ fn render_texture_section(
                 let task = rfd::AsyncFileDialog::new().pick_files();
                 let ctx = ui.ctx().clone();
 
-                let loader = asset_server.loader.clone();
+                let pending_files = egui_asset_server.pending_files.clone();
 
                 execute(async move {
                     let files = match task.await {
@@ -132,33 +302,9 @@ fn render_texture_section(
                         _ => return,
                     };
 
-                    // TODO(Angel): Load in parallel.
-                    for file in files {
-                        let buffer = file.read().await;
-
-                        let raw_img =
-                            match ImageReader::new(Cursor::new(buffer))
-                                .with_guessed_format()
-                            {
-                                Ok(i) => i,
-                                _ => return,
-                            };
-
-                        let img = match raw_img.decode() {
-                            Ok(i) => i,
-                            _ => return,
-                        };
-
-                        let (width, height) = img.dimensions();
-
-                        let buffer = img.to_rgba8().into_raw();
-                        let mut loader_lock = loader.lock().unwrap();
-                        loader_lock.load_texture(
-                            file.file_name(),
-                            buffer,
-                            Size::new(width, height),
-                        );
-                    }
+                    pending_files.lock().unwrap().extend(files.iter().map(
+                        |f| PendingAssetFile::Texture(f.path().to_path_buf()),
+                    ));
 
                     ctx.request_repaint();
                 });
@@ -170,6 +316,8 @@ fn render_texture_section(
         let (_, rect) = ui.allocate_space(vec2(width, height));
         let mut ui = ui.child_ui(rect, Default::default());
 
+        let mut deleted = None;
+
         ScrollArea::vertical()
             .auto_shrink([false; 2])
             .show(&mut ui, |ui| {
@@ -189,13 +337,18 @@ fn render_texture_section(
                             .response
                             .context_menu(|ui| {
                                 if ui.button("Delete").clicked() {
-                                    println!("Delete texture...")
+                                    deleted = Some(id.clone());
+                                    ui.close_menu();
                                 }
                             })
                         });
                     }
                 })
             });
+
+        if let Some(id) = deleted {
+            egui_asset_server.delete(asset_server, &id);
+        }
     })
     .response
 }
@@ -203,6 +356,7 @@ fn render_texture_section(
 fn render_mesh_section(
     ui: &mut Ui,
     asset_server: &mut AssetServer,
+    egui_asset_server: &mut EguiAssetServer,
     height: f32,
 ) -> Response {
     ui.vertical(|ui| {
@@ -210,7 +364,7 @@ fn render_mesh_section(
             let task = rfd::AsyncFileDialog::new().pick_files();
             let ctx = ui.ctx().clone();
 
-            let loader = asset_server.loader.clone();
+            let pending_files = egui_asset_server.pending_files.clone();
 
             execute(async move {
                 let files = match task.await {
@@ -218,25 +372,11 @@ fn render_mesh_section(
                     _ => return,
                 };
 
-                let mut loader_lock = loader.lock().unwrap();
-
-                for file in files {
-                    let models = match ModelType::Obj(file.path()).load_model()
-                    {
-                        Ok(m) => m,
-                        _ => {
-                            warn!("Error loading {:?}", file.path());
-                            continue;
-                        }
-                    };
-
-                    models.into_iter().for_each(|m| {
-                        loader_lock.load_model(
-                            format!("{}#{}", file.file_name(), m.name),
-                            m,
-                        )
-                    });
-                }
+                pending_files.lock().unwrap().extend(
+                    files.iter().map(|f| {
+                        PendingAssetFile::Mesh(f.path().to_path_buf())
+                    }),
+                );
 
                 ctx.request_repaint();
             });
@@ -247,6 +387,8 @@ fn render_mesh_section(
         let (_, rect) = ui.allocate_space(vec2(width, height));
         let mut ui = ui.child_ui(rect, Default::default());
 
+        let mut deleted = None;
+
         ScrollArea::vertical()
             .auto_shrink([false; 2])
             .show(&mut ui, |ui| {
@@ -263,13 +405,18 @@ fn render_mesh_section(
                             .response
                             .context_menu(|ui| {
                                 if ui.button("Delete").clicked() {
-                                    println!("Delete texture...")
+                                    deleted = Some(mesh.0.clone());
+                                    ui.close_menu();
                                 }
                             })
                         });
                     }
                 })
             });
+
+        if let Some(id) = deleted {
+            egui_asset_server.delete(asset_server, &id);
+        }
     })
     .response
 }
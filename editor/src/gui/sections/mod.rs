@@ -1,4 +1,5 @@
 pub mod asset_server_section;
 pub mod log_section;
+pub mod render_stats_section;
 pub mod scene_config_section;
 pub mod viewport_section;
@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+use shipyard::{UniqueView, World};
+
+use engine::{
+    egui::{Grid, Response, ScrollArea, Ui},
+    graphics::stats::RenderStats,
+};
+
+use super::asset_server_section::format_bytes;
+
+/// Renders the statistics of the last frame and the cost of each pass.
+pub fn render_render_stats_section(ui: &mut Ui, world: &World) -> Response {
+    let stats = world.borrow::<UniqueView<RenderStats>>().unwrap();
+
+    ui.vertical(|ui| {
+        ui.label(format!(
+            "Draw calls: {}, instances: {}, triangles: {}",
+            stats.draws.draw_calls,
+            stats.draws.instances,
+            stats.draws.triangles
+        ));
+        ui.label(format!(
+            "Buffer uploads: {}, texture memory: {}",
+            format_bytes(stats.buffer_upload_bytes),
+            format_bytes(stats.texture_memory)
+        ));
+        ui.label(format!(
+            "CPU: {}, GPU: {}",
+            format_duration(Some(stats.cpu_time())),
+            format_duration(stats.gpu_time())
+        ));
+
+        ui.separator();
+
+        ScrollArea::vertical()
+            .id_source("render_stats_scroll")
+            .auto_shrink(false)
+            .show(ui, |ui| {
+                Grid::new("render_stats_passes")
+                    .striped(true)
+                    .num_columns(6)
+                    .show(ui, |ui| {
+                        for header in [
+                            "Pass",
+                            "Draw calls",
+                            "Instances",
+                            "Triangles",
+                            "CPU",
+                            "GPU",
+                        ] {
+                            ui.strong(header);
+                        }
+                        ui.end_row();
+
+                        for pass in &stats.passes {
+                            ui.label(&pass.label);
+                            ui.label(pass.draws.draw_calls.to_string());
+                            ui.label(pass.draws.instances.to_string());
+                            ui.label(pass.draws.triangles.to_string());
+                            ui.label(format_duration(Some(pass.cpu_time)));
+                            ui.label(format_duration(pass.gpu_time));
+                            ui.end_row();
+                        }
+                    });
+            });
+    })
+    .response
+}
+
+/// Formats the duration in milliseconds, the missing GPU timings are shown
+/// as N/A.
+fn format_duration(duration: Option<Duration>) -> String {
+    match duration {
+        Some(d) => format!("{:.3} ms", d.as_secs_f64() * 1000.0),
+        None => "N/A".to_owned(),
+    }
+}
@@ -1,24 +1,33 @@
 use egui_gizmo::{mint::ColumnMatrix4, Gizmo, GizmoMode, GizmoVisuals};
 use engine::{
-    egui::{Image, Rect, Response, Rounding, TextureId, Ui},
+    egui::{
+        vec2, ComboBox, Image, Rect, Response, Rounding, Sense, TextureId, Ui,
+    },
     nalgebra::{
-        convert_unchecked, Matrix4, Quaternion, Unit, UnitQuaternion, Vector3,
-        Vector4,
+        convert_unchecked, Matrix4, Quaternion, Unit, UnitQuaternion, Vector2,
+        Vector3, Vector4,
     },
     scene::{
+        assets::asset_server::AssetServer,
         components::Transform,
         hierarchy::{get_global_transform_matrix_of_entity, Hierarchy},
+        scene::DebugView,
         scene_state::SceneState,
     },
+    types::Size,
 };
 use shipyard::{
-    EntityId, Get, IntoIter, IntoWithId, UniqueView, View, ViewMut, World,
+    EntityId, Get, IntoIter, IntoWithId, UniqueView, UniqueViewMut, View,
+    ViewMut, World,
 };
 
-use crate::gui::{
-    config::{GizmoConfig, GizmoState, GuiConfig, GuiState},
-    widgets::hierarchy_widget::HierarchySelectionFlag,
-    GuiPanelState, GuiResources,
+use crate::{
+    gui::{
+        config::{GizmoConfig, GizmoState, GuiConfig, GuiState},
+        widgets::hierarchy_widget::HierarchySelectionFlag,
+        GuiPanelState, GuiResources,
+    },
+    workbench_scene::WORKBENCH_SCENE,
 };
 
 pub struct ViewportInformation {
@@ -50,7 +59,7 @@ pub fn extract_viewport_information(world: &World) -> ViewportInformation {
         world.borrow::<View<HierarchySelectionFlag>>().unwrap();
 
     let scene = world.borrow::<UniqueView<SceneState>>().unwrap();
-    let scene = scene.sub_scenes.get("WorkbenchScene").unwrap();
+    let scene = scene.sub_scenes.get(WORKBENCH_SCENE).unwrap();
 
     let gui_state = world.borrow::<UniqueView<GuiState>>().unwrap();
     let gui_config = world.borrow::<UniqueView<GuiConfig>>().unwrap();
@@ -98,14 +107,21 @@ pub fn render_viewport_section(ui: &mut Ui, world: &World, info: &ViewportInform
         se: 4.0,
     });
 
-    // Render the scene.
-    let response = ui.add(image);
+    // Render the scene, clicking it selects the entity under the cursor.
+    let response = ui.add(image.sense(Sense::click()));
+
+    render_viewport_toolbar(ui, world, response.rect);
 
     let gizmo_type = match info.gizmo_state.kind {
         Some(g) => g,
-        _ => return response,
+        _ => {
+            select_clicked_entity(world, &response);
+            return response;
+        }
     };
 
+    let mut gizmo_used = false;
+
     for (e, m) in &info.gizmos_transformations {
         let gizmo = Gizmo::new("Editor gizmo")
             .view_matrix(info.camera_view)
@@ -126,6 +142,8 @@ pub fn render_viewport_section(ui: &mut Ui, world: &World, info: &ViewportInform
             .mode(gizmo_type);
 
         if let Some(response) = gizmo.interact(ui) {
+            gizmo_used = true;
+
             let t: &mut Transform = match (&mut transforms).get(*e) {
                 Ok(t) => t,
                 _ => continue,
@@ -179,9 +197,66 @@ pub fn render_viewport_section(ui: &mut Ui, world: &World, info: &ViewportInform
         }
     }
 
+    // The clicks on the gizmos don't change the selection.
+    if !gizmo_used {
+        select_clicked_entity(world, &response);
+    }
+
     response
 }
 
+/// Renders the viewport toolbar over the top left corner of the scene.
+fn render_viewport_toolbar(ui: &mut Ui, world: &World, viewport: Rect) {
+    let mut scenes = world.borrow::<UniqueViewMut<SceneState>>().unwrap();
+    let scene = scenes.sub_scenes.get_mut(WORKBENCH_SCENE).unwrap();
+
+    let rect = Rect::from_min_size(
+        viewport.min + vec2(8.0, 8.0),
+        vec2(150.0, 20.0),
+    );
+
+    ui.allocate_ui_at_rect(rect, |ui| {
+        ComboBox::from_id_source("viewport_debug_view")
+            .selected_text(scene.debug_view.name())
+            .show_ui(ui, |ui| {
+                for view in DebugView::ALL {
+                    ui.selectable_value(
+                        &mut scene.debug_view,
+                        view,
+                        view.name(),
+                    );
+                }
+            });
+    });
+}
+
+/// Selects the entity under the cursor when the viewport is clicked, clicking
+/// the background clears the selection.
+fn select_clicked_entity(world: &World, response: &Response) {
+    let cursor = match response.interact_pointer_pos() {
+        Some(p) if response.clicked() => p - response.rect.min,
+        _ => return,
+    };
+
+    let scene = world.borrow::<UniqueView<SceneState>>().unwrap();
+    let scene = scene.sub_scenes.get(WORKBENCH_SCENE).unwrap();
+    let asset_server = world.borrow::<UniqueView<AssetServer>>().unwrap();
+
+    let hit = scene.pick(
+        &asset_server,
+        Vector2::new(cursor.x, cursor.y),
+        Size::new(response.rect.width(), response.rect.height()),
+    );
+
+    let mut selection =
+        world.borrow::<ViewMut<HierarchySelectionFlag>>().unwrap();
+    selection.clear();
+
+    if let Some(hit) = hit {
+        selection.add_component_unchecked(hit.entity, HierarchySelectionFlag);
+    }
+}
+
 fn rotate_vector_by_quaternion(
     v: Vector3<f32>,
     q: &Quaternion<f32>,
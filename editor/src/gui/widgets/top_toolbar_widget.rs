@@ -16,9 +16,13 @@ use engine::{
 };
 use shipyard::{AddComponent, EntitiesViewMut, UniqueView, ViewMut, World};
 
-use crate::gui::icons::{
-    ADD_ENTITY, CUBE, DOWNARROW_HLT, FILE_3D, GIZMO_ROTATE, GIZMO_SCALE,
-    MESH_CONE, MESH_CUBE, MESH_CYLINDER, MESH_PLANE, MESH_UVSPHERE, POINTER,
+use crate::{
+    gui::icons::{
+        ADD_ENTITY, CUBE, DOWNARROW_HLT, FILE_3D, GIZMO_ROTATE, GIZMO_SCALE,
+        MESH_CONE, MESH_CUBE, MESH_CYLINDER, MESH_PLANE, MESH_UVSPHERE,
+        POINTER,
+    },
+    workbench_scene::WORKBENCH_SCENE,
 };
 
 use super::{
@@ -122,7 +126,7 @@ pub fn render_top_toolbar_widget(world: &World) -> Response {
                                 rotation: UnitQuaternion::default(),
                                 scale: Vector3::new(1.0, 1.0, 1.0),
                             },
-                            SceneTarget::SubScene("WorkbenchScene".to_string()),
+                            SceneTarget::SubScene(WORKBENCH_SCENE.to_string()),
                             hierarchy,
                         ),
                     );
@@ -17,18 +17,27 @@ use engine::{
             primitives_plugin::PrimitivesPlugin, scene_plugin::ScenePlugin,
         },
     },
-    scene::{camera::Camera, projection::Projection, scene::SceneDescriptor},
+    scene::{
+        camera::Camera,
+        lights::LightLimits,
+        post_processing::{PostProcessEffect, Tonemapping},
+        projection::Projection,
+        scene::{
+            AntiAliasing, DebugView, SceneDescriptor, DEFAULT_AMBIENT_COLOR,
+            DEFAULT_SHADOW_DISTANCE,
+        },
+    },
     types::Size,
 };
 
 use gui::GuiPlugin;
 use utils::log::GuiLoggerPlugin;
-use workbench_scene::WorkbenchScenePlugin;
+use workbench_scene::{WorkbenchScenePlugin, WORKBENCH_SCENE};
 
 pub fn main() {
     App::new()
         .add_plugin(WinitWindowPlugin::new("My game", 2048, 1200))
-        .add_plugin(WgpuRendererPlugin)
+        .add_plugin(WgpuRendererPlugin::default())
         .add_plugin(ScenePlugin {
             main: SceneDescriptor {
                 label: "Main Scene".to_owned(),
@@ -38,16 +47,34 @@ pub fn main() {
                 resolution: None,
                 should_render_grid: true,
                 should_render_sky: false,
+                ambient_color: DEFAULT_AMBIENT_COLOR,
+                light_limits: LightLimits::default(),
+                shadow_distance: DEFAULT_SHADOW_DISTANCE,
+                anti_aliasing: AntiAliasing::Off,
+                post_processing: Vec::new(),
+                debug_view: DebugView::default(),
             },
             sub_scenes: vec![
                 SceneDescriptor {
                     label: "Workbench Scene".to_owned(),
-                    id: "WorkbenchScene".to_owned(),
+                    id: WORKBENCH_SCENE.to_owned(),
                     camera: Camera::default(),
                     projection: Projection::default(),
                     resolution: Some(Size::new(2048, 1200)),
                     should_render_grid: true,
                     should_render_sky: true,
+                    ambient_color: DEFAULT_AMBIENT_COLOR,
+                    light_limits: LightLimits::default(),
+                    shadow_distance: DEFAULT_SHADOW_DISTANCE,
+                    anti_aliasing: AntiAliasing::Msaa4,
+                    post_processing: vec![
+                        PostProcessEffect::Bloom {
+                            threshold: 1.0,
+                            intensity: 0.1,
+                        },
+                        PostProcessEffect::Tonemapping(Tonemapping::Aces),
+                    ],
+                    debug_view: DebugView::default(),
                 },
                 SceneDescriptor {
                     label: "Landscape Scene".to_owned(),
@@ -57,6 +84,12 @@ pub fn main() {
                     resolution: Some(Size::new(30, 30)),
                     should_render_grid: true,
                     should_render_sky: false,
+                    ambient_color: DEFAULT_AMBIENT_COLOR,
+                    light_limits: LightLimits::default(),
+                    shadow_distance: DEFAULT_SHADOW_DISTANCE,
+                    anti_aliasing: AntiAliasing::Off,
+                    post_processing: Vec::new(),
+                    debug_view: DebugView::default(),
                 },
             ],
         })
@@ -9,6 +9,7 @@ use engine::{
         camera::Camera,
         components::Transform,
         hierarchy::{add_child, Hierarchy},
+        lights::{DirectionalLight, ShadowSettings},
         scene::SceneTarget,
         scene_state::SceneState,
     },
@@ -20,6 +21,9 @@ use shipyard::{
 
 use crate::camera::EditorCamera;
 
+/// Name of the sub scene drawn in the viewport of the editor.
+pub const WORKBENCH_SCENE: &str = "WorkbenchScene";
+
 #[derive(Unique)]
 struct LandscapeCubeRotation {
     angle: f32,
@@ -59,7 +63,7 @@ impl Pluggable for WorkbenchScenePlugin {
                 rotation: rot,
                 scale: Vector3::new(1.0, 1.0, 1.0),
             },
-            SceneTarget::SubScene("WorkbenchScene".to_string()),
+            SceneTarget::SubScene(WORKBENCH_SCENE.to_string()),
             Hierarchy::new(
                 crate::gui::icons::MESH_UVSPHERE,
                 "Cube 1".to_owned(),
@@ -73,7 +77,7 @@ impl Pluggable for WorkbenchScenePlugin {
                 rotation: rot,
                 scale: Vector3::new(1.0, 1.0, 1.0),
             },
-            SceneTarget::SubScene("WorkbenchScene".to_string()),
+            SceneTarget::SubScene(WORKBENCH_SCENE.to_string()),
             Hierarchy::new(
                 crate::gui::icons::MESH_UVSPHERE,
                 "Cube 2".to_owned(),
@@ -87,7 +91,7 @@ impl Pluggable for WorkbenchScenePlugin {
                 rotation: rot,
                 scale: Vector3::new(1.0, 1.0, 1.0),
             },
-            SceneTarget::SubScene("WorkbenchScene".to_string()),
+            SceneTarget::SubScene(WORKBENCH_SCENE.to_string()),
             Hierarchy::new(
                 crate::gui::icons::MESH_UVSPHERE,
                 "Cube 3".to_owned(),
@@ -101,7 +105,7 @@ impl Pluggable for WorkbenchScenePlugin {
                 rotation: rot,
                 scale: Vector3::new(1.0, 1.0, 1.0),
             },
-            SceneTarget::SubScene("WorkbenchScene".to_string()),
+            SceneTarget::SubScene(WORKBENCH_SCENE.to_string()),
             Hierarchy::new(
                 crate::gui::icons::MESH_UVSPHERE,
                 "Cube 4".to_owned(),
@@ -115,7 +119,7 @@ impl Pluggable for WorkbenchScenePlugin {
                 rotation: rot,
                 scale: Vector3::new(1.0, 1.0, 1.0),
             },
-            SceneTarget::SubScene("WorkbenchScene".to_string()),
+            SceneTarget::SubScene(WORKBENCH_SCENE.to_string()),
             Hierarchy::new(
                 crate::gui::icons::MESH_UVSPHERE,
                 "Cube 4".to_owned(),
@@ -129,10 +133,24 @@ impl Pluggable for WorkbenchScenePlugin {
                 rotation: rot,
                 scale: Vector3::new(1.0, 1.0, 1.0),
             },
-            SceneTarget::SubScene("WorkbenchScene".to_string()),
+            SceneTarget::SubScene(WORKBENCH_SCENE.to_string()),
             TargetCube,
         ));
 
+        app.world.add_entity((
+            DirectionalLight {
+                shadows: Some(ShadowSettings::default()),
+                ..Default::default()
+            },
+            Transform {
+                position: Vector3::new(0.0, 10.0, 0.0),
+                rotation: UnitQuaternion::from_euler_angles(-0.8, 0.6, 0.0),
+                scale: Vector3::new(1.0, 1.0, 1.0),
+            },
+            SceneTarget::SubScene(WORKBENCH_SCENE.to_string()),
+            Hierarchy::new(crate::gui::icons::LIGHT_SUN, "Sun".to_owned()),
+        ));
+
         {
             let mut h = app.world.borrow::<ViewMut<Hierarchy>>().unwrap();
             add_child(c_1, c_2, &mut h);
@@ -163,7 +181,7 @@ impl Pluggable for WorkbenchScenePlugin {
                             rotation: UnitQuaternion::default(),
                             scale: Vector3::new(1.0, 1.0, 1.0),
                         },
-                        SceneTarget::SubScene("WorkbenchScene".to_string()),
+                        SceneTarget::SubScene(WORKBENCH_SCENE.to_string()),
                         Hierarchy::new(
                             crate::gui::icons::MESH_CUBE,
                             format!("Ent({},{},{})", i, j, k),
@@ -189,7 +207,7 @@ fn rotate_landscape_cube(
 ) {
     for (t, _) in (&mut transforms, &target)
         .iter()
-        .filter(|(_, t)| matches!(t, SceneTarget::SubScene(id) if id == "WorkbenchScene"))
+        .filter(|(_, t)| matches!(t, SceneTarget::SubScene(id) if id == WORKBENCH_SCENE))
     {
         t.rotation = angle_to_quaternion(angle.angle, Vector3::y());
     }
@@ -214,7 +232,7 @@ fn attach_target_cube(
 ) {
     let scene = s_state
         .sub_scenes
-        .get_mut("WorkbenchScene")
+        .get_mut(WORKBENCH_SCENE)
         .expect("Unable to find workbench scene.");
 
     for (t, _) in (&mut transforms, &target).iter() {
@@ -1,4 +1,25 @@
-use crate::scene::{camera::Camera, projection::Projection};
+use shipyard::{IntoIter, IntoWithId, UniqueViewMut, View};
+
+use crate::{
+    graphics::scene::Scene,
+    scene::{
+        camera::{Camera, CameraComponent, Viewport},
+        components::Transform,
+        hierarchy::{get_global_transform_matrix_of_entity, Hierarchy},
+        projection::Projection,
+        scene::{DebugView, SceneTarget},
+        scene_state::SceneState,
+    },
+    types::Size,
+};
+
+/// Maximum number of cameras drawing a scene, the ones with the lowest
+/// priority are skipped.
+pub(crate) const MAX_SCENE_VIEWS: usize = 8;
+
+/// Distance between the camera uniforms of the views, the dynamic offsets
+/// must be aligned to 256 bytes.
+pub(crate) const CAMERA_UNIFORM_STRIDE: u64 = 512;
 
 #[repr(C)]
 #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
@@ -13,19 +34,337 @@ pub(crate) struct CameraUniform {
     inv_proj: [[f32; 4]; 4],
     /// Conatins the inversion of the view.
     inv_view: [[f32; 4]; 4],
+    /// Contains the `DebugView` of the scene in `x`, the rest is padding.
+    debug_view: [u32; 4],
+    /// Contains the depth range of the view in `x` and `y`, the rest is
+    /// padding.
+    depth_range: [f32; 4],
 }
 
 impl CameraUniform {
-    pub(crate) fn view_proj(camera: &Camera, projection: &Projection) -> Self {
-        let proj = projection.matrix();
-        let view = camera.view_matrix();
+    pub(crate) fn view_proj(view: &SceneView, debug_view: DebugView) -> Self {
+        let proj = view.projection.matrix();
+        let camera_view = view.camera.view_matrix();
+        let [min_depth, max_depth] = view.depth_range;
 
         Self {
-            view_position: camera.position.to_homogeneous().into(),
-            view_proj: (proj * view).into(),
-            view: view.into(),
+            view_position: view.camera.position.to_homogeneous().into(),
+            view_proj: (proj * camera_view).into(),
+            view: camera_view.into(),
             inv_proj: proj.try_inverse().unwrap().into(),
-            inv_view: view.transpose().into(),
+            inv_view: camera_view.transpose().into(),
+            debug_view: [debug_view.shader_mode(), 0, 0, 0],
+            depth_range: [min_depth, max_depth, 0.0, 0.0],
+        }
+    }
+}
+
+/// Returns the size of the buffer with the camera uniforms of a scene.
+pub(crate) fn camera_buffer_size() -> u64 {
+    CAMERA_UNIFORM_STRIDE * MAX_SCENE_VIEWS as u64
+}
+
+/// Returns the dynamic offset of the camera uniform of a view.
+pub(crate) fn camera_offset(view: usize) -> u32 {
+    (view as u64 * CAMERA_UNIFORM_STRIDE) as u32
+}
+
+/// A camera drawing a `Scene` into a rect of its target texture.
+#[derive(Copy, Clone)]
+pub(crate) struct SceneView {
+    pub(crate) camera: Camera,
+    pub(crate) projection: Projection,
+    pub(crate) viewport: Viewport,
+    /// Contains the part of the depth buffer used by the view, the views
+    /// drawn over others use a nearer part so they are not hidden.
+    pub(crate) depth_range: [f32; 2],
+}
+
+impl SceneView {
+    /// Creates and returns a view which covers the whole texture.
+    pub(crate) fn full(camera: Camera, projection: Projection) -> Self {
+        Self {
+            camera,
+            projection,
+            viewport: Viewport::FULL,
+            depth_range: [0.0, 1.0],
+        }
+    }
+
+    /// Returns the x, y, width and height in pixels of the view on a texture
+    /// of the provided size.
+    pub(crate) fn pixel_rect(&self, size: Size<u32>) -> [f32; 4] {
+        let (width, height) = (size.width as f32, size.height as f32);
+        let (x, y) = (self.viewport.x * width, self.viewport.y * height);
+
+        // The rounding errors must not take the rect out of the texture.
+        [
+            x,
+            y,
+            (self.viewport.width * width).min(width - x),
+            (self.viewport.height * height).min(height - y),
+        ]
+    }
+}
+
+/// Collects the active `CameraComponent`s of each scene into its views, the
+/// scenes without them are drawn from their own `Camera`.
+pub(crate) fn sync_scene_views_system(
+    mut scenes: UniqueViewMut<SceneState>,
+    cameras: View<CameraComponent>,
+    transforms: View<Transform>,
+    hierarchy: View<Hierarchy>,
+) {
+    let SceneState { main, sub_scenes } = &mut *scenes;
+
+    sync_scene_views(main, None, &cameras, &transforms, &hierarchy);
+
+    for (id, scene) in sub_scenes.iter_mut() {
+        sync_scene_views(scene, Some(id), &cameras, &transforms, &hierarchy);
+    }
+}
+
+fn sync_scene_views(
+    scene: &mut Scene,
+    scene_id: Option<&String>,
+    cameras: &View<CameraComponent>,
+    transforms: &View<Transform>,
+    hierarchy: &View<Hierarchy>,
+) {
+    let size = scene.target_texture.size();
+
+    let cameras = cameras
+        .iter()
+        .with_id()
+        .filter(|(_, c)| {
+            c.active
+                && match &c.target {
+                    SceneTarget::Main => scene_id.is_none(),
+                    SceneTarget::SubScene(s) => scene_id == Some(s),
+                }
+        })
+        .filter_map(|(id, component)| {
+            let matrix = get_global_transform_matrix_of_entity(
+                id, hierarchy, transforms,
+            )?;
+            let viewport = component.viewport.clamped()?;
+
+            let mut projection = component.projection;
+            projection.update_aspect_ratio(
+                (viewport.width * size.width as f32)
+                    / (viewport.height * size.height as f32).max(1.0),
+            );
+
+            Some(LayeredCamera {
+                priority: component.priority,
+                camera: Camera::from_matrix(&matrix),
+                projection,
+                viewport,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    scene.views = if cameras.is_empty() {
+        vec![SceneView::full(scene.camera, scene.projection)]
+    } else {
+        layered_views(cameras)
+    };
+}
+
+/// An active camera of a scene, with its viewport already clamped.
+#[derive(Copy, Clone)]
+struct LayeredCamera {
+    priority: i32,
+    camera: Camera,
+    projection: Projection,
+    viewport: Viewport,
+}
+
+/// Returns the views of the cameras, sorted by priority. The views covered
+/// by a view on top of them are skipped, and the ones drawn over others get
+/// a nearer depth range so the depth buffer doesn't need to be cleared.
+fn layered_views(mut cameras: Vec<LayeredCamera>) -> Vec<SceneView> {
+    // The sort is stable, the cameras with the same priority keep the order
+    // of their entities.
+    cameras.sort_by_key(|c| c.priority);
+
+    let visible = cameras
+        .iter()
+        .enumerate()
+        .filter(|(i, camera)| {
+            !cameras[i + 1..]
+                .iter()
+                .any(|top| top.viewport.contains(&camera.viewport))
+        })
+        .map(|(_, camera)| *camera)
+        .collect::<Vec<_>>();
+
+    let visible = &visible[visible.len().saturating_sub(MAX_SCENE_VIEWS)..];
+
+    // Each view is one layer above the views it overlaps.
+    let mut layers = Vec::<u32>::with_capacity(visible.len());
+
+    for (i, camera) in visible.iter().enumerate() {
+        let layer = visible[..i]
+            .iter()
+            .zip(&layers)
+            .filter(|(below, _)| below.viewport.overlaps(&camera.viewport))
+            .map(|(_, layer)| layer + 1)
+            .max()
+            .unwrap_or(0);
+
+        layers.push(layer);
+    }
+
+    let layer_count = layers.iter().max().map_or(1, |l| l + 1) as f32;
+
+    visible
+        .iter()
+        .zip(layers)
+        .map(|(camera, layer)| {
+            let far = (layer_count - layer as f32) / layer_count;
+
+            SceneView {
+                camera: camera.camera,
+                projection: camera.projection,
+                viewport: camera.viewport,
+                depth_range: [far - 1.0 / layer_count, far],
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{Point3, Vector3};
+
+    use super::*;
+
+    /// Returns a camera told apart from the others by the X of its position.
+    fn camera(id: f32, priority: i32, viewport: Viewport) -> LayeredCamera {
+        LayeredCamera {
+            priority,
+            camera: Camera::new(
+                Point3::new(id, 0.0, 0.0),
+                Point3::new(id, 0.0, -1.0),
+                Vector3::y(),
+            ),
+            projection: Projection::default(),
+            viewport,
+        }
+    }
+
+    fn ids(views: &[SceneView]) -> Vec<f32> {
+        views.iter().map(|v| v.camera.position.x).collect()
+    }
+
+    fn depth_ranges(views: &[SceneView]) -> Vec<[f32; 2]> {
+        views.iter().map(|v| v.depth_range).collect()
+    }
+
+    #[test]
+    fn fully_covered_view_is_skipped() {
+        let inset = Viewport::new(0.25, 0.25, 0.5, 0.5);
+
+        let views = layered_views(vec![
+            camera(0.0, 0, inset),
+            camera(1.0, 1, Viewport::FULL),
+        ]);
+        assert_eq!(ids(&views), [1.0]);
+        assert_eq!(depth_ranges(&views), [[0.0, 1.0]]);
+
+        // The inset is drawn over the full view when its priority is higher.
+        let views = layered_views(vec![
+            camera(0.0, 1, inset),
+            camera(1.0, 0, Viewport::FULL),
+        ]);
+        assert_eq!(ids(&views), [1.0, 0.0]);
+    }
+
+    #[test]
+    fn overlapping_views_get_nearer_depth_ranges() {
+        let views = layered_views(vec![
+            camera(2.0, 2, Viewport::new(0.5, 0.5, 0.2, 0.2)),
+            camera(0.0, 0, Viewport::new(0.0, 0.0, 0.6, 1.0)),
+            camera(1.0, 1, Viewport::new(0.4, 0.0, 0.6, 1.0)),
+        ]);
+
+        assert_eq!(ids(&views), [0.0, 1.0, 2.0]);
+
+        let third = 1.0 / 3.0;
+        for (range, expected) in depth_ranges(&views).into_iter().zip([
+            [2.0 * third, 1.0],
+            [third, 2.0 * third],
+            [0.0, third],
+        ]) {
+            assert!((range[0] - expected[0]).abs() < 1e-6);
+            assert!((range[1] - expected[1]).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn views_side_by_side_share_the_depth_range() {
+        let views = layered_views(vec![
+            camera(0.0, 0, Viewport::new(0.0, 0.0, 0.5, 1.0)),
+            camera(1.0, 1, Viewport::new(0.5, 0.0, 0.5, 1.0)),
+        ]);
+
+        assert_eq!(depth_ranges(&views), [[0.0, 1.0], [0.0, 1.0]]);
+    }
+
+    #[test]
+    fn equal_priorities_keep_entity_order() {
+        let left = Viewport::new(0.0, 0.0, 0.5, 1.0);
+        let right = Viewport::new(0.5, 0.0, 0.5, 1.0);
+
+        let views = layered_views(vec![
+            camera(0.0, 3, right),
+            camera(1.0, 3, left),
+            camera(2.0, -1, left),
+        ]);
+        assert_eq!(ids(&views), [0.0, 1.0]);
+
+        // With the same viewport the last entity covers the first one.
+        let views =
+            layered_views(vec![camera(0.0, 3, left), camera(1.0, 3, left)]);
+        assert_eq!(ids(&views), [1.0]);
+    }
+
+    #[test]
+    fn lowest_priorities_are_dropped_over_the_limit() {
+        let cameras = (0..MAX_SCENE_VIEWS + 2)
+            .map(|i| {
+                let viewport = Viewport::new(i as f32 * 0.05, 0.0, 0.05, 0.05);
+                camera(i as f32, i as i32, viewport)
+            })
+            .collect();
+
+        let views = layered_views(cameras);
+
+        assert_eq!(views.len(), MAX_SCENE_VIEWS);
+        assert_eq!(views[0].camera.position.x, 2.0);
+    }
+
+    #[test]
+    fn pixel_rect_stays_inside_the_texture() {
+        let viewports = [
+            Viewport::new(0.1, 0.3, 0.9, 0.7),
+            Viewport::new(0.7, 1.0 / 3.0, 0.3, 2.0 / 3.0),
+            Viewport::FULL,
+        ];
+
+        for size in 1..256 {
+            for viewport in viewports {
+                let view = SceneView {
+                    viewport,
+                    ..SceneView::full(Camera::default(), Projection::default())
+                };
+                let [x, y, width, height] =
+                    view.pixel_rect(Size::new(size, size + 1));
+
+                assert!(x + width <= size as f32);
+                assert!(y + height <= (size + 1) as f32);
+            }
         }
     }
 }
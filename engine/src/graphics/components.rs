@@ -1,7 +1,10 @@
 use shipyard::{Component, Unique};
 use std::ops::{Deref, DerefMut};
 
-use crate::{graphics::Texture, scene::assets::MeshResourceID};
+use crate::{
+    graphics::Texture,
+    scene::assets::{MaterialResourceID, MeshResourceID},
+};
 
 // TODO(Angel): Find a better name.
 #[derive(Component)]
@@ -15,6 +18,55 @@ impl Deref for MeshComponent {
     }
 }
 
+/// Selects the `Material` used to draw the `MeshComponent` of the entity,
+/// entities without it use the default material.
+#[derive(Component)]
+pub struct MaterialComponent(pub MaterialResourceID);
+
+impl Deref for MaterialComponent {
+    type Target = MaterialResourceID;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Controls the shadows of the `MeshComponent` of the entity, entities
+/// without it cast and receive shadows.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ShadowFlags {
+    pub casts_shadows: bool,
+    pub receives_shadows: bool,
+}
+
+impl Default for ShadowFlags {
+    fn default() -> Self {
+        Self {
+            casts_shadows: true,
+            receives_shadows: true,
+        }
+    }
+}
+
+/// Draws an outline around the `MeshComponent` of the entity, the editor
+/// uses it to show the selected entities.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct Outline {
+    /// Linear RGBA color.
+    pub color: [f32; 4],
+    /// Width in pixels.
+    pub width: f32,
+}
+
+impl Default for Outline {
+    fn default() -> Self {
+        Self {
+            color: [1.0, 0.45, 0.0, 1.0],
+            width: 2.0,
+        }
+    }
+}
+
 #[derive(Unique)]
 pub struct DepthTexture(pub(crate) Box<dyn Texture>);
 
@@ -0,0 +1,477 @@
+use nalgebra::{Matrix4, Point3, Vector3, Vector4};
+
+use crate::scene::{camera::Camera, projection::Projection};
+
+use super::vertex::Vertex;
+
+/// Axis aligned box containing a set of points.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+}
+
+impl Aabb {
+    /// Creates the smallest box containing all the points, an empty box at
+    /// the origin if there are no points.
+    pub fn from_points(points: impl IntoIterator<Item = Point3<f32>>) -> Self {
+        let mut points = points.into_iter();
+
+        let first = match points.next() {
+            Some(p) => p,
+            None => {
+                return Self {
+                    min: Point3::origin(),
+                    max: Point3::origin(),
+                }
+            }
+        };
+
+        points.fold(
+            Self {
+                min: first,
+                max: first,
+            },
+            |aabb, p| Self {
+                min: aabb.min.inf(&p),
+                max: aabb.max.sup(&p),
+            },
+        )
+    }
+
+    pub fn center(&self) -> Point3<f32> {
+        nalgebra::center(&self.min, &self.max)
+    }
+
+    pub fn half_extents(&self) -> Vector3<f32> {
+        (self.max - self.min) / 2.0
+    }
+
+    /// Returns the box containing this one once transformed by the matrix.
+    pub fn transformed(&self, matrix: &Matrix4<f32>) -> Self {
+        let center = matrix.transform_point(&self.center());
+
+        // The extents of the transformed box are the sum of the absolute
+        // contributions of each axis (Arvo's method).
+        let half_extents =
+            matrix.fixed_view::<3, 3>(0, 0).abs() * self.half_extents();
+
+        Self {
+            min: center - half_extents,
+            max: center + half_extents,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Point3<f32>,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    /// Returns the sphere containing this one once transformed by the
+    /// matrix, the radius is scaled by the largest scale of the matrix.
+    pub fn transformed(&self, matrix: &Matrix4<f32>) -> Self {
+        let linear = matrix.fixed_view::<3, 3>(0, 0);
+        let scale = linear.column_iter().map(|c| c.norm()).fold(0.0, f32::max);
+
+        Self {
+            center: matrix.transform_point(&self.center),
+            radius: self.radius * scale,
+        }
+    }
+    /// Returns the fraction of the screen height covered by the sphere, it
+    /// is used to select the level of detail.
+    pub fn screen_size(&self, camera: &Camera, projection: &Projection) -> f32 {
+        match *projection {
+            Projection::Perspective { fov, .. } => {
+                let distance =
+                    nalgebra::distance(&camera.position, &self.center);
+
+                // The camera is inside the sphere.
+                if distance <= self.radius {
+                    return f32::INFINITY;
+                }
+
+                self.radius / (distance * (fov.to_radians() / 2.0).tan())
+            }
+            Projection::Orthograpic { bottom, top, .. } => {
+                2.0 * self.radius / (top - bottom).abs()
+            }
+        }
+    }
+}
+
+/// Volumes containing all the vertices of a `Mesh`, the `AssetServer`
+/// computes them when the mesh is registered.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MeshBounds {
+    pub aabb: Aabb,
+    pub sphere: BoundingSphere,
+}
+
+impl MeshBounds {
+    pub fn from_vertices(vertices: &[Vertex]) -> Self {
+        let aabb = Aabb::from_points(vertices.iter().map(|v| v.pos.into()));
+        let center = aabb.center();
+
+        let radius = vertices
+            .iter()
+            .map(|v| nalgebra::distance(&center, &v.pos.into()))
+            .fold(0.0, f32::max);
+
+        Self {
+            aabb,
+            sphere: BoundingSphere { center, radius },
+        }
+    }
+
+    /// Returns the bounds in the space of the matrix, used to get the world
+    /// bounds of an entity from its transform.
+    pub fn transformed(&self, matrix: &Matrix4<f32>) -> Self {
+        Self {
+            aabb: self.aabb.transformed(matrix),
+            sphere: self.sphere.transformed(matrix),
+        }
+    }
+}
+
+/// The volume visible through a camera, described by six planes whose
+/// normals point inside.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Frustum {
+    /// Contains the left, right, bottom, top, near and far planes, each one
+    /// as the normal and the distance to the origin (`xyz` and `w`).
+    pub planes: [Vector4<f32>; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes from a view projection matrix (Gribb-Hartmann).
+    ///
+    /// The near plane is the one of a `-1..1` depth range, which contains the
+    /// `0..1` one, so the culling stays conservative with both conventions.
+    pub fn from_matrix(view_proj: &Matrix4<f32>) -> Self {
+        let row = |i: usize| view_proj.row(i).transpose();
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+
+        let planes = [w + x, w - x, w + y, w - y, w + z, w - z]
+            .map(|p| p / p.xyz().norm());
+
+        Self { planes }
+    }
+
+    pub fn from_camera(camera: &Camera, projection: &Projection) -> Self {
+        Self::from_matrix(&(projection.matrix() * camera.view_matrix()))
+    }
+
+    /// Returns the signed distance from the plane to the point, positive
+    /// inside the frustum.
+    fn distance(plane: &Vector4<f32>, point: &Point3<f32>) -> f32 {
+        plane.xyz().dot(&point.coords) + plane.w
+    }
+
+    pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+        self.planes
+            .iter()
+            .all(|p| Self::distance(p, &sphere.center) >= -sphere.radius)
+    }
+
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|p| {
+            // The corner furthest along the normal is the last one to leave
+            // the inside of the plane.
+            let corner = Point3::new(
+                if p.x >= 0.0 { aabb.max.x } else { aabb.min.x },
+                if p.y >= 0.0 { aabb.max.y } else { aabb.min.y },
+                if p.z >= 0.0 { aabb.max.z } else { aabb.min.z },
+            );
+
+            Self::distance(p, &corner) >= 0.0
+        })
+    }
+
+    /// Returns if the bounds are at least partially inside the frustum. The
+    /// sphere is tested first as it is cheaper, the box discards the objects
+    /// near the corners the sphere can't.
+    pub fn intersects(&self, bounds: &MeshBounds) -> bool {
+        self.intersects_sphere(&bounds.sphere)
+            && self.intersects_aabb(&bounds.aabb)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{Rotation3, Translation3, Unit};
+
+    use super::*;
+
+    const EPSILON: f32 = 1e-4;
+
+    fn assert_plane(plane: &Vector4<f32>, normal: Vector3<f32>, distance: f32) {
+        let length = normal.norm();
+        let expected = normal.push(distance) / length;
+
+        // The distance of the far plane loses some precision.
+        assert!(
+            (plane - expected).norm() < EPSILON * distance.abs().max(1.0),
+            "{:?} is not {:?}",
+            plane,
+            expected
+        );
+    }
+
+    fn assert_aabb(aabb: &Aabb, min: Point3<f32>, max: Point3<f32>) {
+        assert!(
+            (aabb.min - min).norm() < EPSILON
+                && (aabb.max - max).norm() < EPSILON,
+            "{:?} is not {:?} {:?}",
+            aabb,
+            min,
+            max
+        );
+    }
+
+    /// Frustum of a camera at the origin looking along -Z, with a 90 degrees
+    /// field of view, so the side planes are at 45 degrees.
+    fn perspective_frustum() -> Frustum {
+        let projection = Projection::new_perspective(1.0, 90.0, 1.0, 100.0);
+        Frustum::from_camera(&Camera::default(), &projection)
+    }
+
+    fn sphere(center: Point3<f32>, radius: f32) -> BoundingSphere {
+        BoundingSphere { center, radius }
+    }
+
+    fn cube(center: Point3<f32>, half_extent: f32) -> Aabb {
+        let half_extents = Vector3::repeat(half_extent);
+
+        Aabb {
+            min: center - half_extents,
+            max: center + half_extents,
+        }
+    }
+
+    /// Contains a point on each plane, in the left, right, bottom, top, near
+    /// and far order.
+    fn points_on_planes() -> [Point3<f32>; 6] {
+        [
+            Point3::new(-10.0, 0.0, -10.0),
+            Point3::new(10.0, 0.0, -10.0),
+            Point3::new(0.0, -10.0, -10.0),
+            Point3::new(0.0, 10.0, -10.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Point3::new(0.0, 0.0, -100.0),
+        ]
+    }
+
+    /// Contains a point outside of each plane and inside the rest, in the
+    /// left, right, bottom, top, near and far order.
+    fn points_outside_planes() -> [Point3<f32>; 6] {
+        [
+            Point3::new(-13.0, 0.0, -10.0),
+            Point3::new(13.0, 0.0, -10.0),
+            Point3::new(0.0, -13.0, -10.0),
+            Point3::new(0.0, 13.0, -10.0),
+            Point3::new(0.0, 0.0, -0.2),
+            Point3::new(0.0, 0.0, -103.0),
+        ]
+    }
+
+    #[test]
+    fn planes_of_perspective_projection() {
+        let [left, right, bottom, top, near, far] =
+            perspective_frustum().planes;
+
+        assert_plane(&left, Vector3::new(1.0, 0.0, -1.0), 0.0);
+        assert_plane(&right, Vector3::new(-1.0, 0.0, -1.0), 0.0);
+        assert_plane(&bottom, Vector3::new(0.0, 1.0, -1.0), 0.0);
+        assert_plane(&top, Vector3::new(0.0, -1.0, -1.0), 0.0);
+        assert_plane(&near, Vector3::new(0.0, 0.0, -1.0), -1.0);
+        assert_plane(&far, Vector3::new(0.0, 0.0, 1.0), 100.0);
+    }
+
+    #[test]
+    fn planes_of_orthographic_projection() {
+        let projection =
+            Projection::new_orthograpic(-2.0, 2.0, -1.0, 1.0, 0.5, 10.0);
+        let [left, right, bottom, top, near, far] =
+            Frustum::from_camera(&Camera::default(), &projection).planes;
+
+        assert_plane(&left, Vector3::new(1.0, 0.0, 0.0), 2.0);
+        assert_plane(&right, Vector3::new(-1.0, 0.0, 0.0), 2.0);
+        assert_plane(&bottom, Vector3::new(0.0, 1.0, 0.0), 1.0);
+        assert_plane(&top, Vector3::new(0.0, -1.0, 0.0), 1.0);
+        assert_plane(&near, Vector3::new(0.0, 0.0, -1.0), -0.5);
+        assert_plane(&far, Vector3::new(0.0, 0.0, 1.0), 10.0);
+    }
+
+    #[test]
+    fn planes_follow_the_camera() {
+        // Looking along +X from x = 5.
+        let camera = Camera::new(
+            Point3::new(5.0, 0.0, 0.0),
+            Point3::new(6.0, 0.0, 0.0),
+            Vector3::y(),
+        );
+        let projection = Projection::new_perspective(1.0, 90.0, 1.0, 100.0);
+        let frustum = Frustum::from_camera(&camera, &projection);
+
+        assert_plane(&frustum.planes[4], Vector3::new(1.0, 0.0, 0.0), -6.0);
+        assert_plane(&frustum.planes[5], Vector3::new(-1.0, 0.0, 0.0), 105.0);
+    }
+
+    #[test]
+    fn sphere_inside_straddling_and_outside() {
+        let frustum = perspective_frustum();
+
+        assert!(frustum
+            .intersects_sphere(&sphere(Point3::new(0.0, 0.0, -10.0), 1.0)));
+
+        for point in points_on_planes() {
+            assert!(
+                frustum.intersects_sphere(&sphere(point, 0.5)),
+                "{:?} straddles a plane",
+                point
+            );
+        }
+
+        for point in points_outside_planes() {
+            assert!(
+                !frustum.intersects_sphere(&sphere(point, 0.5)),
+                "{:?} is outside a plane",
+                point
+            );
+        }
+    }
+
+    #[test]
+    fn aabb_inside_straddling_and_outside() {
+        let frustum = perspective_frustum();
+
+        assert!(
+            frustum.intersects_aabb(&cube(Point3::new(0.0, 0.0, -10.0), 1.0))
+        );
+
+        for point in points_on_planes() {
+            assert!(
+                frustum.intersects_aabb(&cube(point, 0.5)),
+                "{:?} straddles a plane",
+                point
+            );
+        }
+
+        for point in points_outside_planes() {
+            assert!(
+                !frustum.intersects_aabb(&cube(point, 0.5)),
+                "{:?} is outside a plane",
+                point
+            );
+        }
+    }
+
+    #[test]
+    fn aabb_culls_long_meshes_the_sphere_keeps() {
+        let frustum = perspective_frustum();
+
+        // Outside the left plane, but long enough along Y for its sphere to
+        // reach into the frustum.
+        let aabb = Aabb {
+            min: Point3::new(-12.5, -3.0, -10.5),
+            max: Point3::new(-11.5, 3.0, -9.5),
+        };
+        let bounds = MeshBounds {
+            aabb,
+            sphere: sphere(aabb.center(), aabb.half_extents().norm()),
+        };
+
+        assert!(frustum.intersects_sphere(&bounds.sphere));
+        assert!(!frustum.intersects(&bounds));
+    }
+
+    #[test]
+    fn aabb_transformed_by_rotation_and_scale() {
+        let aabb = Aabb {
+            min: Point3::new(0.0, 0.0, 0.0),
+            max: Point3::new(2.0, 1.0, 1.0),
+        };
+
+        // Scaled to 4x3x1, rotated 90 degrees around Z and moved along X.
+        let matrix = Translation3::new(10.0, 0.0, 0.0).to_homogeneous()
+            * Rotation3::from_axis_angle(
+                &Vector3::z_axis(),
+                std::f32::consts::FRAC_PI_2,
+            )
+            .to_homogeneous()
+            * Matrix4::new_nonuniform_scaling(&Vector3::new(2.0, 3.0, 1.0));
+
+        assert_aabb(
+            &aabb.transformed(&matrix),
+            Point3::new(7.0, 0.0, 0.0),
+            Point3::new(10.0, 4.0, 1.0),
+        );
+    }
+
+    #[test]
+    fn aabb_transform_is_the_box_of_the_transformed_corners() {
+        let aabb = Aabb {
+            min: Point3::new(-1.0, 0.0, 2.0),
+            max: Point3::new(3.0, 1.0, 2.5),
+        };
+
+        let axis = Unit::new_normalize(Vector3::new(1.0, 2.0, 3.0));
+        let matrix = Translation3::new(1.0, -2.0, 3.0).to_homogeneous()
+            * Rotation3::from_axis_angle(&axis, 0.7).to_homogeneous()
+            * Matrix4::new_nonuniform_scaling(&Vector3::new(0.5, 2.0, 4.0));
+
+        let corners = (0..8).map(|i| {
+            let corner = Point3::new(
+                if i & 1 == 0 { aabb.min.x } else { aabb.max.x },
+                if i & 2 == 0 { aabb.min.y } else { aabb.max.y },
+                if i & 4 == 0 { aabb.min.z } else { aabb.max.z },
+            );
+
+            matrix.transform_point(&corner)
+        });
+        let expected = Aabb::from_points(corners);
+
+        assert_aabb(&aabb.transformed(&matrix), expected.min, expected.max);
+    }
+
+    #[test]
+    fn sphere_radius_uses_the_largest_scale() {
+        let sphere = sphere(Point3::new(1.0, 0.0, 0.0), 2.0);
+
+        let matrix = Translation3::new(0.0, 5.0, 0.0).to_homogeneous()
+            * Rotation3::from_axis_angle(&Vector3::x_axis(), 0.3)
+                .to_homogeneous()
+            * Matrix4::new_nonuniform_scaling(&Vector3::new(1.0, 3.0, 0.5));
+
+        let transformed = sphere.transformed(&matrix);
+
+        assert!((transformed.radius - 6.0).abs() < EPSILON);
+        assert!(
+            (transformed.center - Point3::new(1.0, 5.0, 0.0)).norm() < EPSILON
+        );
+    }
+
+    #[test]
+    fn screen_size_of_sphere() {
+        let camera = Camera::default();
+        let perspective = Projection::new_perspective(1.0, 90.0, 0.1, 100.0);
+        let orthographic =
+            Projection::new_orthograpic(-2.0, 2.0, -2.0, 2.0, 0.1, 100.0);
+
+        let far = sphere(Point3::new(0.0, 0.0, -10.0), 1.0);
+        let around_camera = sphere(Point3::new(0.0, 0.0, -0.5), 1.0);
+
+        assert!((far.screen_size(&camera, &perspective) - 0.1).abs() < EPSILON);
+        assert_eq!(
+            around_camera.screen_size(&camera, &perspective),
+            f32::INFINITY
+        );
+        assert!(
+            (far.screen_size(&camera, &orthographic) - 0.5).abs() < EPSILON
+        );
+    }
+}
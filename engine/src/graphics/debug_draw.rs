@@ -0,0 +1,403 @@
+use std::time::{Duration, Instant};
+
+use nalgebra::{Matrix4, Point3, Vector3};
+use shipyard::Unique;
+
+use crate::scene::{camera::Camera, scene::SceneTarget};
+
+use super::culling::Aabb;
+
+/// Number of segments of the circles drawn by `DebugDraw::sphere`.
+const CIRCLE_SEGMENTS: usize = 32;
+
+/// Fraction of an arrow used by its head.
+const ARROW_HEAD_SIZE: f32 = 0.2;
+
+/// Vertex of the lines drawn by `DebugDraw`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct DebugVertex {
+    pub(crate) position: [f32; 3],
+    /// Linear RGBA color.
+    pub(crate) color: [f32; 4],
+}
+
+/// Options applied to the shapes added to `DebugDraw`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DebugDrawOptions {
+    /// Scene the shapes are drawn into.
+    pub scene: SceneTarget,
+    /// The shapes are hidden by the geometry in front of them, otherwise
+    /// they are drawn on top of the scene.
+    pub depth_test: bool,
+    /// Time the shapes stay visible, they are only drawn in the current
+    /// frame if it is `None`.
+    pub duration: Option<Duration>,
+}
+
+impl Default for DebugDrawOptions {
+    fn default() -> Self {
+        Self {
+            scene: SceneTarget::Main,
+            depth_test: true,
+            duration: None,
+        }
+    }
+}
+
+enum DebugShape {
+    Lines(Vec<DebugVertex>),
+    /// The text is turned into lines when it is drawn, so it faces the
+    /// camera of each scene.
+    Text {
+        position: Point3<f32>,
+        text: String,
+        size: f32,
+        color: [f32; 4],
+    },
+}
+
+struct DebugItem {
+    shape: DebugShape,
+    scene: SceneTarget,
+    depth_test: bool,
+    /// `None` if the shape is only drawn in the current frame.
+    expires_at: Option<Instant>,
+}
+
+/// Immediate-mode API to draw lines into the scenes, used to visualize
+/// what the systems are doing.
+///
+/// The shapes are accumulated during the frame and drawn after the
+/// meshes, the ones without a duration are removed once drawn. The colors
+/// are linear RGBA.
+///
+/// ```ignore
+/// fn debug_system(mut debug: UniqueViewMut<DebugDraw>) {
+///     debug.options.depth_test = false;
+///     debug.arrow(Point3::origin(), Point3::new(0.0, 2.0, 0.0), [1.0; 4]);
+/// }
+/// ```
+#[derive(Unique, Default)]
+pub struct DebugDraw {
+    /// Options of the shapes added from now on.
+    pub options: DebugDrawOptions,
+    items: Vec<DebugItem>,
+}
+
+impl DebugDraw {
+    fn push(&mut self, shape: DebugShape) {
+        self.items.push(DebugItem {
+            shape,
+            scene: self.options.scene.clone(),
+            depth_test: self.options.depth_test,
+            expires_at: self.options.duration.map(|d| Instant::now() + d),
+        });
+    }
+
+    fn push_lines(&mut self, lines: Vec<DebugVertex>) {
+        if !lines.is_empty() {
+            self.push(DebugShape::Lines(lines));
+        }
+    }
+
+    pub fn line(
+        &mut self,
+        start: Point3<f32>,
+        end: Point3<f32>,
+        color: [f32; 4],
+    ) {
+        let mut lines = Vec::with_capacity(2);
+        push_line(&mut lines, start, end, color);
+
+        self.push_lines(lines);
+    }
+
+    /// Draws the twelve edges of the box.
+    pub fn aabb(&mut self, aabb: &Aabb, color: [f32; 4]) {
+        let corner = |i: usize| {
+            Point3::new(
+                if i & 1 == 0 { aabb.min.x } else { aabb.max.x },
+                if i & 2 == 0 { aabb.min.y } else { aabb.max.y },
+                if i & 4 == 0 { aabb.min.z } else { aabb.max.z },
+            )
+        };
+
+        let mut lines = Vec::with_capacity(24);
+
+        // Each edge joins two corners whose index differs by one bit.
+        for i in 0..8 {
+            for bit in [1, 2, 4] {
+                if i & bit == 0 {
+                    push_line(&mut lines, corner(i), corner(i | bit), color);
+                }
+            }
+        }
+
+        self.push_lines(lines);
+    }
+
+    /// Draws a circle around each axis.
+    pub fn sphere(
+        &mut self,
+        center: Point3<f32>,
+        radius: f32,
+        color: [f32; 4],
+    ) {
+        let mut lines = Vec::with_capacity(CIRCLE_SEGMENTS * 6);
+        let (x, y, z) = (Vector3::x(), Vector3::y(), Vector3::z());
+
+        for (a, b) in [(x, y), (y, z), (z, x)] {
+            push_circle(&mut lines, center, a * radius, b * radius, color);
+        }
+
+        self.push_lines(lines);
+    }
+
+    /// Draws a line with a head at its end.
+    pub fn arrow(
+        &mut self,
+        start: Point3<f32>,
+        end: Point3<f32>,
+        color: [f32; 4],
+    ) {
+        let mut lines = Vec::with_capacity(10);
+        push_arrow(&mut lines, start, end, color);
+
+        self.push_lines(lines);
+    }
+
+    /// Draws the X, Y and Z axes of the transform in red, green and blue.
+    pub fn axes(&mut self, transform: &Matrix4<f32>, size: f32) {
+        let origin = transform.transform_point(&Point3::origin());
+        let mut lines = Vec::with_capacity(30);
+
+        let axes = [
+            (Vector3::x(), [1.0, 0.0, 0.0, 1.0]),
+            (Vector3::y(), [0.0, 1.0, 0.0, 1.0]),
+            (Vector3::z(), [0.0, 0.0, 1.0, 1.0]),
+        ];
+
+        for (axis, color) in axes {
+            let end = transform.transform_point(&Point3::from(axis * size));
+            push_arrow(&mut lines, origin, end, color);
+        }
+
+        self.push_lines(lines);
+    }
+
+    /// Draws the text facing the camera, starting at the position. `size` is
+    /// the height of the characters, only letters, digits and a few symbols
+    /// are supported.
+    pub fn text_3d(
+        &mut self,
+        position: Point3<f32>,
+        text: &str,
+        size: f32,
+        color: [f32; 4],
+    ) {
+        self.push(DebugShape::Text {
+            position,
+            text: text.to_owned(),
+            size,
+            color,
+        });
+    }
+
+    /// Removes all the shapes, including the ones with a duration.
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    /// Returns the vertices of the lines drawn into the scene, the ones
+    /// tested against the depth first.
+    pub(crate) fn scene_vertices(
+        &self,
+        scene: &SceneTarget,
+        camera: &Camera,
+    ) -> [Vec<DebugVertex>; 2] {
+        let mut tested = Vec::new();
+        let mut overlay = Vec::new();
+
+        let view = camera.view_matrix();
+        let right = Vector3::new(view[(0, 0)], view[(0, 1)], view[(0, 2)]);
+        let up = Vector3::new(view[(1, 0)], view[(1, 1)], view[(1, 2)]);
+
+        for item in self.items.iter().filter(|i| i.scene == *scene) {
+            let target = if item.depth_test {
+                &mut tested
+            } else {
+                &mut overlay
+            };
+
+            match &item.shape {
+                DebugShape::Lines(lines) => target.extend_from_slice(lines),
+                DebugShape::Text {
+                    position,
+                    text,
+                    size,
+                    color,
+                } => push_text(
+                    target,
+                    *position,
+                    text,
+                    right * *size,
+                    up * *size,
+                    *color,
+                ),
+            }
+        }
+
+        [tested, overlay]
+    }
+
+    /// Removes the shapes drawn only in the current frame and the expired
+    /// ones, called once the frame is rendered.
+    pub(crate) fn end_frame(&mut self) {
+        let now = Instant::now();
+
+        self.items
+            .retain(|i| i.expires_at.map_or(false, |t| t > now));
+    }
+}
+
+fn push_line(
+    lines: &mut Vec<DebugVertex>,
+    start: Point3<f32>,
+    end: Point3<f32>,
+    color: [f32; 4],
+) {
+    lines.push(DebugVertex {
+        position: start.into(),
+        color,
+    });
+    lines.push(DebugVertex {
+        position: end.into(),
+        color,
+    });
+}
+
+/// Pushes the circle going through `center + a` and `center + b`.
+fn push_circle(
+    lines: &mut Vec<DebugVertex>,
+    center: Point3<f32>,
+    a: Vector3<f32>,
+    b: Vector3<f32>,
+    color: [f32; 4],
+) {
+    let point = |i: usize| {
+        let angle = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+        center + a * angle.cos() + b * angle.sin()
+    };
+
+    for i in 0..CIRCLE_SEGMENTS {
+        push_line(lines, point(i), point(i + 1), color);
+    }
+}
+
+fn push_arrow(
+    lines: &mut Vec<DebugVertex>,
+    start: Point3<f32>,
+    end: Point3<f32>,
+    color: [f32; 4],
+) {
+    push_line(lines, start, end, color);
+
+    let direction = end - start;
+    let length = direction.norm();
+    if length <= f32::EPSILON {
+        return;
+    }
+
+    let direction = direction / length;
+    let head = length * ARROW_HEAD_SIZE;
+
+    // Any axis not parallel to the arrow gives the sides of the head.
+    let other = if direction.x.abs() < 0.9 {
+        Vector3::x()
+    } else {
+        Vector3::y()
+    };
+    let side = direction.cross(&other).normalize();
+    let other_side = direction.cross(&side);
+
+    let base = end - direction * head;
+
+    for offset in [side, -side, other_side, -other_side] {
+        push_line(lines, end, base + offset * head * 0.5, color);
+    }
+}
+
+/// Pushes the strokes of the text, `right` and `up` are the size of a
+/// character along the directions of the screen.
+fn push_text(
+    lines: &mut Vec<DebugVertex>,
+    position: Point3<f32>,
+    text: &str,
+    right: Vector3<f32>,
+    up: Vector3<f32>,
+    color: [f32; 4],
+) {
+    // The glyphs are drawn in a 2x2 cell and there is half a cell between
+    // two characters.
+    let (right, up) = (right / 2.0, up / 2.0);
+
+    for (i, c) in text.chars().enumerate() {
+        let origin = position + right * (i as f32 * 3.0);
+        let point = |x: u8, y: u8| origin + right * x as f32 + up * y as f32;
+
+        for [x0, y0, x1, y1] in glyph(c.to_ascii_uppercase()) {
+            push_line(lines, point(*x0, *y0), point(*x1, *y1), color);
+        }
+    }
+}
+
+/// Returns the strokes of a character as the start and end of each one,
+/// in a cell whose corners are `(0, 0)` and `(2, 2)`.
+#[rustfmt::skip]
+fn glyph(c: char) -> &'static [[u8; 4]] {
+    match c {
+        '0' => &[[0, 0, 2, 0], [2, 0, 2, 2], [2, 2, 0, 2], [0, 2, 0, 0], [0, 0, 2, 2]],
+        '1' => &[[1, 0, 1, 2], [1, 2, 0, 1], [0, 0, 2, 0]],
+        '2' => &[[0, 2, 2, 2], [2, 2, 2, 1], [2, 1, 0, 1], [0, 1, 0, 0], [0, 0, 2, 0]],
+        '3' => &[[0, 2, 2, 2], [2, 2, 2, 0], [2, 0, 0, 0], [0, 1, 2, 1]],
+        '4' => &[[0, 2, 0, 1], [0, 1, 2, 1], [2, 2, 2, 0]],
+        '5' | 'S' => &[[2, 2, 0, 2], [0, 2, 0, 1], [0, 1, 2, 1], [2, 1, 2, 0], [2, 0, 0, 0]],
+        '6' => &[[2, 2, 0, 2], [0, 2, 0, 0], [0, 0, 2, 0], [2, 0, 2, 1], [2, 1, 0, 1]],
+        '7' => &[[0, 2, 2, 2], [2, 2, 1, 0]],
+        '8' => &[[0, 0, 2, 0], [2, 0, 2, 2], [2, 2, 0, 2], [0, 2, 0, 0], [0, 1, 2, 1]],
+        '9' => &[[2, 1, 0, 1], [0, 1, 0, 2], [0, 2, 2, 2], [2, 2, 2, 0], [2, 0, 0, 0]],
+        'A' => &[[0, 0, 0, 2], [0, 2, 2, 2], [2, 2, 2, 0], [0, 1, 2, 1]],
+        'B' => &[[0, 0, 0, 2], [0, 2, 1, 2], [1, 2, 2, 1], [0, 1, 2, 1], [2, 1, 2, 0], [2, 0, 0, 0]],
+        'C' => &[[2, 2, 0, 2], [0, 2, 0, 0], [0, 0, 2, 0]],
+        'D' => &[[0, 0, 0, 2], [0, 2, 1, 2], [1, 2, 2, 1], [2, 1, 1, 0], [1, 0, 0, 0]],
+        'E' => &[[2, 2, 0, 2], [0, 2, 0, 0], [0, 0, 2, 0], [0, 1, 1, 1]],
+        'F' => &[[2, 2, 0, 2], [0, 2, 0, 0], [0, 1, 1, 1]],
+        'G' => &[[2, 2, 0, 2], [0, 2, 0, 0], [0, 0, 2, 0], [2, 0, 2, 1], [2, 1, 1, 1]],
+        'H' => &[[0, 0, 0, 2], [2, 0, 2, 2], [0, 1, 2, 1]],
+        'I' => &[[0, 2, 2, 2], [1, 2, 1, 0], [0, 0, 2, 0]],
+        'J' => &[[2, 2, 2, 0], [2, 0, 0, 0], [0, 0, 0, 1]],
+        'K' => &[[0, 0, 0, 2], [0, 1, 2, 2], [0, 1, 2, 0]],
+        'L' => &[[0, 2, 0, 0], [0, 0, 2, 0]],
+        'M' => &[[0, 0, 0, 2], [0, 2, 1, 1], [1, 1, 2, 2], [2, 2, 2, 0]],
+        'N' => &[[0, 0, 0, 2], [0, 2, 2, 0], [2, 0, 2, 2]],
+        'O' => &[[0, 0, 2, 0], [2, 0, 2, 2], [2, 2, 0, 2], [0, 2, 0, 0]],
+        'P' => &[[0, 0, 0, 2], [0, 2, 2, 2], [2, 2, 2, 1], [2, 1, 0, 1]],
+        'Q' => &[[0, 0, 2, 0], [2, 0, 2, 2], [2, 2, 0, 2], [0, 2, 0, 0], [1, 1, 2, 0]],
+        'R' => &[[0, 0, 0, 2], [0, 2, 2, 2], [2, 2, 2, 1], [2, 1, 0, 1], [1, 1, 2, 0]],
+        'T' => &[[0, 2, 2, 2], [1, 2, 1, 0]],
+        'U' => &[[0, 2, 0, 0], [0, 0, 2, 0], [2, 0, 2, 2]],
+        'V' => &[[0, 2, 1, 0], [1, 0, 2, 2]],
+        'W' => &[[0, 2, 0, 0], [0, 0, 1, 1], [1, 1, 2, 0], [2, 0, 2, 2]],
+        'X' => &[[0, 0, 2, 2], [0, 2, 2, 0]],
+        'Y' => &[[0, 2, 1, 1], [2, 2, 1, 1], [1, 1, 1, 0]],
+        'Z' => &[[0, 2, 2, 2], [2, 2, 0, 0], [0, 0, 2, 0]],
+        '-' => &[[0, 1, 2, 1]],
+        '+' => &[[0, 1, 2, 1], [1, 0, 1, 2]],
+        '_' => &[[0, 0, 2, 0]],
+        '/' => &[[0, 0, 2, 2]],
+        '|' => &[[1, 0, 1, 2]],
+        // Spaces and the unsupported characters only move the next one.
+        _ => &[],
+    }
+}
@@ -0,0 +1,512 @@
+use nalgebra::{Matrix4, Point3, Vector3, Vector4};
+use shipyard::{
+    EntityId, Get, IntoIter, IntoWithId, UniqueView, UniqueViewMut, View,
+};
+
+use crate::scene::{
+    components::Transform,
+    hierarchy::{get_global_transform_matrix_of_entity, Hierarchy},
+    lights::{
+        DirectionalLight, LightLimits, PointLight, ShadowSettings, SpotLight,
+    },
+    projection::Projection,
+    scene::SceneTarget,
+    scene_state::SceneState,
+};
+
+use super::{gpu::AbstractGpu, scene::Scene};
+
+/// Size of each shadow map.
+pub(crate) const SHADOW_MAP_SIZE: u32 = 2048;
+/// Number of shadow maps used by each directional light, the cascades closer
+/// to the camera cover a smaller area so they have more detail.
+pub(crate) const SHADOW_CASCADES: usize = 4;
+/// Distance behind each cascade where the objects still cast shadows.
+const SHADOW_CASTER_DISTANCE: f32 = 100.0;
+/// Distance between each `ShadowCasterUniform` in the buffer, it must be a
+/// multiple of the storage buffers offset alignment.
+pub(crate) const SHADOW_CASTER_STRIDE: u64 = 256;
+
+/// Contains the information shared by all the lights of a `Scene`, it is
+/// stored at the start of the lights buffer followed by the lights.
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct LightsHeader {
+    /// The last component is not used.
+    ambient_color: [f32; 4],
+    /// Number of directional, point and spot lights, the last component is
+    /// not used.
+    counts: [u32; 4],
+    /// Distance from the camera where each cascade ends.
+    cascade_splits: [f32; SHADOW_CASCADES],
+}
+
+/// A light as it is stored in the GPU, the lights are sorted by type:
+/// directional, point and spot.
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct LightUniform {
+    /// The last component contains the range.
+    position: [f32; 4],
+    /// The last component is not used.
+    direction: [f32; 4],
+    /// The last component contains the intensity.
+    color: [f32; 4],
+    /// Contains the cosine of the inner and outer angles of the spot lights.
+    cone: [f32; 4],
+    /// Contains the first shadow map of the light (-1 without shadows), the
+    /// depth bias and the normal bias.
+    shadow: [f32; 4],
+}
+
+/// The view projection matrix used to render a shadow map, padded so it can
+/// be bound with a dynamic offset.
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct ShadowCasterUniform {
+    view_proj: [[f32; 4]; 4],
+    _padding: [[f32; 4]; 12],
+}
+
+impl LightUniform {
+    fn new(
+        position: Vector3<f32>,
+        direction: Vector3<f32>,
+        color: [f32; 3],
+        intensity: f32,
+        range: f32,
+    ) -> Self {
+        Self {
+            position: [position.x, position.y, position.z, range],
+            direction: [direction.x, direction.y, direction.z, 0.0],
+            color: [color[0], color[1], color[2], intensity],
+            cone: [0.0; 4],
+            shadow: [-1.0, 0.0, 0.0, 0.0],
+        }
+    }
+
+    /// Makes the light use the shadow maps starting at `first_shadow_map`.
+    fn set_shadows(
+        &mut self,
+        first_shadow_map: u32,
+        settings: &ShadowSettings,
+    ) {
+        self.shadow = [
+            first_shadow_map as f32,
+            settings.depth_bias,
+            settings.normal_bias,
+            0.0,
+        ];
+    }
+
+    fn position(&self) -> Point3<f32> {
+        let [x, y, z, _] = self.position;
+        Point3::new(x, y, z)
+    }
+
+    fn direction(&self) -> Vector3<f32> {
+        let [x, y, z, _] = self.direction;
+        Vector3::new(x, y, z)
+    }
+}
+
+/// Returns the number of bytes needed to store the lights of a `Scene`.
+pub(crate) fn lights_buffer_size(limits: &LightLimits) -> u64 {
+    (std::mem::size_of::<LightsHeader>()
+        + std::mem::size_of::<LightUniform>() * limits.total().max(1) as usize)
+        as u64
+}
+
+/// Returns the number of shadow maps needed by a `Scene`.
+pub(crate) fn shadow_maps_count(limits: &LightLimits) -> u32 {
+    (limits.directional_shadows * SHADOW_CASCADES as u32 + limits.spot_shadows)
+        .max(1)
+}
+
+/// Returns the number of bytes needed to store the shadow casters of a
+/// `Scene`.
+pub(crate) fn shadow_casters_buffer_size(limits: &LightLimits) -> u64 {
+    shadow_maps_count(limits) as u64 * SHADOW_CASTER_STRIDE
+}
+
+/// Uploads the lights of each `Scene` to its lights buffer.
+pub(crate) fn sync_scene_lights_system(
+    gpu: UniqueView<AbstractGpu>,
+    mut scenes: UniqueViewMut<SceneState>,
+    transforms: View<Transform>,
+    hierarchy: View<Hierarchy>,
+    scene_targets: View<SceneTarget>,
+    directional_lights: View<DirectionalLight>,
+    point_lights: View<PointLight>,
+    spot_lights: View<SpotLight>,
+) {
+    let lights = SceneLights {
+        transforms: &transforms,
+        hierarchy: &hierarchy,
+        scene_targets: &scene_targets,
+        directional: &directional_lights,
+        point: &point_lights,
+        spot: &spot_lights,
+    };
+
+    sync_scene_lights(&gpu, &mut scenes.main, None, &lights);
+
+    for (id, scene) in scenes.sub_scenes.iter_mut() {
+        sync_scene_lights(&gpu, scene, Some(id), &lights);
+    }
+}
+
+/// Groups the views needed to extract the lights.
+struct SceneLights<'a, 'v> {
+    transforms: &'a View<'v, Transform>,
+    hierarchy: &'a View<'v, Hierarchy>,
+    scene_targets: &'a View<'v, SceneTarget>,
+    directional: &'a View<'v, DirectionalLight>,
+    point: &'a View<'v, PointLight>,
+    spot: &'a View<'v, SpotLight>,
+}
+
+impl SceneLights<'_, '_> {
+    /// Returns the global transform of the entity if it must be rendered in
+    /// the scene.
+    fn transform_in_scene(
+        &self,
+        entity: EntityId,
+        scene_id: Option<&String>,
+    ) -> Option<Matrix4<f32>> {
+        let in_scene = match self.scene_targets.get(entity) {
+            // If it does not contain the component it belongs to the main
+            // scene.
+            Ok(SceneTarget::Main) | Err(_) => scene_id.is_none(),
+            Ok(SceneTarget::SubScene(s)) => scene_id == Some(s),
+        };
+
+        if !in_scene {
+            return None;
+        }
+
+        get_global_transform_matrix_of_entity(
+            entity,
+            self.hierarchy,
+            self.transforms,
+        )
+    }
+}
+
+/// Returns the position and the direction (negative Z axis) of the
+/// transformation.
+fn position_and_direction(
+    transform: &Matrix4<f32>,
+) -> (Vector3<f32>, Vector3<f32>) {
+    let position = transform.column(3).xyz();
+    let direction = (transform * Vector4::new(0.0, 0.0, -1.0, 0.0))
+        .xyz()
+        .try_normalize(f32::EPSILON)
+        .unwrap_or_else(|| -Vector3::z());
+
+    (position, direction)
+}
+
+fn sync_scene_lights(
+    gpu: &AbstractGpu,
+    scene: &mut Scene,
+    scene_id: Option<&String>,
+    lights: &SceneLights,
+) {
+    let limits = &scene.light_limits;
+    // TODO(Angel): Fit the cascades to every view, the other views only get
+    // shadows where they overlap the frustum of the main one.
+    let view = scene.main_view();
+    let camera = view.camera.position;
+
+    let mut directional = lights
+        .directional
+        .iter()
+        .with_id()
+        .filter_map(|(id, light)| {
+            let transform = lights.transform_in_scene(id, scene_id)?;
+            let (position, direction) = position_and_direction(&transform);
+
+            let uniform = LightUniform::new(
+                position,
+                direction,
+                light.color,
+                light.intensity,
+                0.0,
+            );
+
+            Some((uniform, light.shadows))
+        })
+        .take(limits.directional as usize)
+        .collect::<Vec<_>>();
+
+    let point = closest_to_camera(
+        lights.point.iter().with_id().filter_map(|(id, light)| {
+            let transform = lights.transform_in_scene(id, scene_id)?;
+            let (position, direction) = position_and_direction(&transform);
+
+            let uniform = LightUniform::new(
+                position,
+                direction,
+                light.color,
+                light.intensity,
+                light.range,
+            );
+
+            Some((uniform, None))
+        }),
+        camera,
+        limits.point,
+    );
+
+    let mut spot = closest_to_camera(
+        lights.spot.iter().with_id().filter_map(|(id, light)| {
+            let transform = lights.transform_in_scene(id, scene_id)?;
+            let (position, direction) = position_and_direction(&transform);
+
+            let mut uniform = LightUniform::new(
+                position,
+                direction,
+                light.color,
+                light.intensity,
+                light.range,
+            );
+            uniform.cone =
+                [light.inner_angle.cos(), light.outer_angle.cos(), 0.0, 0.0];
+
+            Some((uniform, light.shadows))
+        }),
+        camera,
+        limits.spot,
+    );
+
+    // Assign the shadow maps, the lights over the limit do not cast shadows.
+    let (near, far) = view.projection.depth_range();
+    let cascade_splits =
+        cascade_splits(near, far.min(near + scene.shadow_distance));
+    let frustum = frustum_corners(&view.camera.view_matrix(), &view.projection);
+
+    let mut shadow_casters = Vec::new();
+
+    for (light, settings) in directional
+        .iter_mut()
+        .filter_map(|(l, s)| s.as_ref().map(|s| (l, s)))
+        .take(limits.directional_shadows as usize)
+    {
+        light.set_shadows(shadow_casters.len() as u32, settings);
+
+        let mut cascade_near = near;
+        for split in cascade_splits {
+            let corners =
+                frustum_slice(&frustum, near, far, cascade_near, split);
+            shadow_casters
+                .push(directional_shadow_matrix(&corners, &light.direction()));
+            cascade_near = split;
+        }
+    }
+
+    for (light, settings) in spot
+        .iter_mut()
+        .filter_map(|(l, s)| s.as_ref().map(|s| (l, s)))
+        .take(limits.spot_shadows as usize)
+    {
+        light.set_shadows(shadow_casters.len() as u32, settings);
+        shadow_casters.push(spot_shadow_matrix(light));
+    }
+
+    let header = LightsHeader {
+        ambient_color: [
+            scene.ambient_color[0],
+            scene.ambient_color[1],
+            scene.ambient_color[2],
+            0.0,
+        ],
+        counts: [
+            directional.len() as u32,
+            point.len() as u32,
+            spot.len() as u32,
+            0,
+        ],
+        cascade_splits,
+    };
+
+    let mut data = bytemuck::bytes_of(&header).to_vec();
+    for (light, _) in directional.iter().chain(&point).chain(&spot) {
+        data.extend_from_slice(bytemuck::bytes_of(light));
+    }
+
+    gpu.write_storage_buffer(&scene.lights_buffer, 0, &data);
+
+    let casters = shadow_casters
+        .iter()
+        .map(|view_proj| ShadowCasterUniform {
+            view_proj: (*view_proj).into(),
+            _padding: [[0.0; 4]; 12],
+        })
+        .collect::<Vec<_>>();
+
+    gpu.write_storage_buffer(
+        &scene.shadow_casters_buffer,
+        0,
+        bytemuck::cast_slice(&casters),
+    );
+    scene.shadow_maps_in_use = casters.len() as u32;
+}
+
+/// Keeps the `limit` lights closest to the camera.
+fn closest_to_camera<T>(
+    lights: impl Iterator<Item = (LightUniform, T)>,
+    camera: Point3<f32>,
+    limit: u32,
+) -> Vec<(LightUniform, T)> {
+    let mut lights = lights.collect::<Vec<_>>();
+
+    if lights.len() > limit as usize {
+        let distance =
+            |(l, _): &(LightUniform, T)| (l.position() - camera).norm_squared();
+
+        lights.sort_by(|a, b| distance(a).total_cmp(&distance(b)));
+        lights.truncate(limit as usize);
+    }
+
+    lights
+}
+
+/// Converts the depth range of the projections created by nalgebra (-1 to 1)
+/// to the one used by wgpu (0 to 1).
+#[rustfmt::skip]
+fn opengl_to_wgpu() -> Matrix4<f32> {
+    Matrix4::new(
+        1.0, 0.0, 0.0, 0.0,
+        0.0, 1.0, 0.0, 0.0,
+        0.0, 0.0, 0.5, 0.5,
+        0.0, 0.0, 0.0, 1.0,
+    )
+}
+
+/// Returns the distance from the camera where each cascade ends, it mixes
+/// uniform and logarithmic distributions so the closer cascades are smaller.
+fn cascade_splits(near: f32, far: f32) -> [f32; SHADOW_CASCADES] {
+    let mut splits = [far; SHADOW_CASCADES];
+
+    for (i, split) in splits.iter_mut().enumerate() {
+        let p = (i + 1) as f32 / SHADOW_CASCADES as f32;
+        let uniform = near + (far - near) * p;
+        let logarithmic = near * (far / near).powf(p);
+
+        *split = uniform + (logarithmic - uniform) * 0.5;
+    }
+
+    splits
+}
+
+/// Returns the world position of the corners of the camera frustum, the
+/// first four are in the near plane and the last four in the far plane.
+fn frustum_corners(
+    view: &Matrix4<f32>,
+    projection: &Projection,
+) -> [Point3<f32>; 8] {
+    let inverse = (projection.matrix() * view)
+        .try_inverse()
+        .unwrap_or_else(Matrix4::identity);
+
+    let mut corners = [Point3::origin(); 8];
+    for (i, corner) in corners.iter_mut().enumerate() {
+        let x = if i & 1 == 0 { -1.0 } else { 1.0 };
+        let y = if i & 2 == 0 { -1.0 } else { 1.0 };
+        let z = if i < 4 { -1.0 } else { 1.0 };
+
+        *corner = inverse.transform_point(&Point3::new(x, y, z));
+    }
+
+    corners
+}
+
+/// Returns the corners of the part of the frustum between `slice_near` and
+/// `slice_far`, the frustum spans from `near` to `far`.
+fn frustum_slice(
+    frustum: &[Point3<f32>; 8],
+    near: f32,
+    far: f32,
+    slice_near: f32,
+    slice_far: f32,
+) -> [Point3<f32>; 8] {
+    let mut corners = [Point3::origin(); 8];
+    let range = (far - near).max(f32::EPSILON);
+
+    for i in 0..4 {
+        let (n, f) = (frustum[i], frustum[i + 4]);
+        // The view depth changes linearly along each edge of the frustum.
+        corners[i] = n + (f - n) * ((slice_near - near) / range);
+        corners[i + 4] = n + (f - n) * ((slice_far - near) / range);
+    }
+
+    corners
+}
+
+/// Returns the view projection matrix of the cascade which contains the
+/// provided corners.
+fn directional_shadow_matrix(
+    corners: &[Point3<f32>; 8],
+    direction: &Vector3<f32>,
+) -> Matrix4<f32> {
+    let center = corners.iter().map(|c| c.coords).sum::<Vector3<f32>>() / 8.0;
+    // A sphere keeps the same size when the camera rotates, so the shadows
+    // do not shimmer.
+    let radius = corners
+        .iter()
+        .map(|c| (c.coords - center).norm())
+        .fold(0.0, f32::max);
+    let radius = (radius * 16.0).ceil() / 16.0;
+
+    let light_view = Matrix4::look_at_rh(
+        &Point3::origin(),
+        &Point3::from(*direction),
+        &up_vector(direction),
+    );
+    let center = light_view.transform_point(&Point3::from(center));
+
+    // Moves the cascade in texel increments for the same reason.
+    let texel = 2.0 * radius / SHADOW_MAP_SIZE as f32;
+    let x = (center.x / texel).floor() * texel;
+    let y = (center.y / texel).floor() * texel;
+
+    let projection = Matrix4::new_orthographic(
+        x - radius,
+        x + radius,
+        y - radius,
+        y + radius,
+        -center.z - radius - SHADOW_CASTER_DISTANCE,
+        -center.z + radius,
+    );
+
+    opengl_to_wgpu() * projection * light_view
+}
+
+/// Returns the view projection matrix of the spot light shadow map.
+fn spot_shadow_matrix(light: &LightUniform) -> Matrix4<f32> {
+    let position = light.position();
+    let direction = light.direction();
+    let [_, cos_outer, _, _] = light.cone;
+    let range = light.position[3];
+
+    let view = Matrix4::look_at_rh(
+        &position,
+        &(position + direction),
+        &up_vector(&direction),
+    );
+    let fov = (2.0 * cos_outer.clamp(-1.0, 1.0).acos())
+        .clamp(1.0_f32.to_radians(), 170.0_f32.to_radians());
+    let projection = Matrix4::new_perspective(1.0, fov, 0.05, range.max(0.1));
+
+    opengl_to_wgpu() * projection * view
+}
+
+/// Returns an up vector which is not parallel to the direction.
+fn up_vector(direction: &Vector3<f32>) -> Vector3<f32> {
+    if direction.y.abs() > 0.99 {
+        Vector3::z()
+    } else {
+        Vector3::y()
+    }
+}
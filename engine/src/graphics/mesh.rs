@@ -23,4 +23,9 @@ impl Mesh {
             index_count,
         }
     }
+
+    /// Returns the number of bytes used by the mesh buffers in GPU RAM.
+    pub fn size_in_bytes(&self) -> u64 {
+        self.vertex_buffer.size_in_bytes() + self.index_buffer.size_in_bytes()
+    }
 }
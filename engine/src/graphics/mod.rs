@@ -4,22 +4,42 @@ use crate::types::Size;
 
 pub mod camera;
 pub mod components;
+pub mod culling;
+pub mod debug_draw;
 pub mod gpu;
+pub mod lights;
 pub mod mesh;
+pub mod picking;
+pub mod post_processing;
+pub mod render_pass;
 pub mod scene;
+pub mod stats;
+pub mod texture;
 pub mod vertex;
 
-pub trait VertexBuffer: Downcast + Send + Sync {}
+pub trait VertexBuffer: Downcast + Send + Sync {
+    /// Returns the number of bytes allocated in GPU RAM.
+    fn size_in_bytes(&self) -> u64;
+}
 impl_downcast!(VertexBuffer);
 
-pub trait IndexBuffer: Downcast {}
+pub trait IndexBuffer: Downcast + Send + Sync {
+    /// Returns the number of bytes allocated in GPU RAM.
+    fn size_in_bytes(&self) -> u64;
+}
 impl_downcast!(IndexBuffer);
 
 pub trait UniformBuffer: Downcast + Send + Sync {}
 impl_downcast!(UniformBuffer);
 
+pub trait StorageBuffer: Downcast + Send + Sync {}
+impl_downcast!(StorageBuffer);
+
 pub trait Texture: Downcast + Send + Sync {
     fn size(&self) -> Size<u32>;
+
+    /// Returns the approximated number of bytes allocated in GPU RAM.
+    fn size_in_bytes(&self) -> u64;
 }
 impl_downcast!(Texture);
 
@@ -52,6 +72,7 @@ pub trait BufferCreator {
         label: &str,
         width: u32,
         height: u32,
+        sample_count: u32,
     ) -> Box<dyn Texture>;
 
     fn allocate_target_texture(
@@ -59,14 +80,47 @@ pub trait BufferCreator {
         label: &str,
         width: u32,
         height: u32,
+        sample_count: u32,
     ) -> Box<dyn Texture>;
 
+    /// Allocates a texture which can store colors brighter than 1.0, the
+    /// scenes are rendered and post processed in this kind of texture.
+    fn allocate_hdr_texture(
+        &self,
+        label: &str,
+        width: u32,
+        height: u32,
+        sample_count: u32,
+    ) -> Box<dyn Texture>;
+
+    /// Returns the highest sample count supported by the HDR and depth
+    /// textures which is not greater than `requested`.
+    fn supported_sample_count(&self, requested: u32) -> u32;
+
     fn allocate_cubemap_texture(
         &self,
         label: &str,
         size: u32,
     ) -> Box<dyn Texture>;
 
+    /// Allocates an array of depth textures, each layer is rendered from the
+    /// point of view of a light.
+    fn allocate_shadow_maps_texture(
+        &self,
+        label: &str,
+        size: u32,
+        layers: u32,
+    ) -> Box<dyn Texture>;
+
+    /// Allocates a filterable cubemap with mip levels, used to store the
+    /// lighting computed from the sky.
+    fn allocate_environment_cubemap_texture(
+        &self,
+        label: &str,
+        size: u32,
+        mip_level_count: u32,
+    ) -> Box<dyn Texture>;
+
     fn allocate_uniform_buffer(
         &self,
         label: &str,
@@ -79,6 +133,13 @@ pub trait BufferCreator {
         size: u64,
         uses: BufferUsage,
     ) -> Box<dyn VertexBuffer>;
+
+    /// Allocates a zeroed read only storage buffer which can be updated.
+    fn allocate_storage_buffer(
+        &self,
+        label: &str,
+        size: u64,
+    ) -> Box<dyn StorageBuffer>;
 }
 
 pub trait BufferHandler {
@@ -95,9 +156,18 @@ pub trait BufferHandler {
         offset: u64,
         data: &[u8],
     );
+
+    fn write_storage_buffer(
+        &self,
+        buffer: &Box<dyn StorageBuffer>,
+        offset: u64,
+        data: &[u8],
+    );
 }
 
-// TODO(Angel): Implement this.
 pub trait ShaderHandler {
-    fn compile_program(&self);
+    /// Compiles a WGSL program to check if it is valid, the error contains
+    /// the diagnostic of the compiler.
+    fn validate_program(&self, label: &str, source: &str)
+        -> Result<(), String>;
 }
@@ -0,0 +1,500 @@
+use nalgebra::{Matrix4, Point3, Vector2, Vector3, Vector4};
+use shipyard::EntityId;
+
+use crate::{
+    scene::{
+        assets::{asset_server::AssetServer, MeshResourceID},
+        camera::Camera,
+        projection::Projection,
+    },
+    types::Size,
+};
+
+use super::{camera::SceneView, culling::Aabb, vertex::Vertex};
+
+/// Half line used to find what is under a point of the screen.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Point3<f32>,
+    /// The distances along the ray are measured in units of this vector.
+    pub direction: Vector3<f32>,
+}
+
+impl Ray {
+    /// Creates the ray going through a point of the viewport, from the near
+    /// plane of the camera. `cursor` is in pixels from the top left corner of
+    /// the viewport.
+    pub fn from_viewport(
+        camera: &Camera,
+        projection: &Projection,
+        cursor: Vector2<f32>,
+        viewport_size: Size<f32>,
+    ) -> Option<Self> {
+        let inverse =
+            (projection.matrix() * camera.view_matrix()).try_inverse()?;
+
+        let x = 2.0 * cursor.x / viewport_size.width - 1.0;
+        let y = 1.0 - 2.0 * cursor.y / viewport_size.height;
+
+        let unproject = |z: f32| {
+            let p = inverse * Vector4::new(x, y, z, 1.0);
+            Point3::from(p.xyz() / p.w)
+        };
+
+        let near = unproject(-1.0);
+        let far = unproject(1.0);
+
+        Some(Self {
+            origin: near,
+            direction: (far - near).try_normalize(f32::EPSILON)?,
+        })
+    }
+
+    /// Creates the ray going through a point of the viewport where the views
+    /// are drawn, from the topmost view under it. `None` if there is no view
+    /// under the point.
+    pub(crate) fn from_scene_views(
+        views: &[SceneView],
+        cursor: Vector2<f32>,
+        viewport_size: Size<f32>,
+    ) -> Option<Self> {
+        let point = Vector2::new(
+            cursor.x / viewport_size.width,
+            cursor.y / viewport_size.height,
+        );
+
+        let view = views
+            .iter()
+            .rev()
+            .find(|v| v.viewport.contains_point(point))?;
+
+        let viewport = view.viewport;
+        Self::from_viewport(
+            &view.camera,
+            &view.projection,
+            Vector2::new(
+                cursor.x - viewport.x * viewport_size.width,
+                cursor.y - viewport.y * viewport_size.height,
+            ),
+            Size::new(
+                viewport.width * viewport_size.width,
+                viewport.height * viewport_size.height,
+            ),
+        )
+    }
+
+    pub fn at(&self, distance: f32) -> Point3<f32> {
+        self.origin + self.direction * distance
+    }
+
+    /// Returns the ray in the space of the matrix. The direction is not
+    /// normalized, so the distances are the same in both spaces.
+    pub fn transformed(&self, matrix: &Matrix4<f32>) -> Self {
+        Self {
+            origin: matrix.transform_point(&self.origin),
+            direction: matrix.transform_vector(&self.direction),
+        }
+    }
+
+    /// Returns the distance to the box, zero if the origin is inside it.
+    pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<f32> {
+        let mut near = 0.0_f32;
+        let mut far = f32::INFINITY;
+
+        // Slab method, the ray is clipped by each pair of planes.
+        for i in 0..3 {
+            let inverse = 1.0 / self.direction[i];
+            let t0 = (aabb.min[i] - self.origin[i]) * inverse;
+            let t1 = (aabb.max[i] - self.origin[i]) * inverse;
+
+            near = near.max(t0.min(t1));
+            far = far.min(t0.max(t1));
+        }
+
+        (near <= far).then_some(near)
+    }
+
+    /// Returns the distance to the triangle, both faces are hit
+    /// (Möller-Trumbore).
+    pub fn intersect_triangle(
+        &self,
+        [a, b, c]: [Point3<f32>; 3],
+    ) -> Option<f32> {
+        let edge1 = b - a;
+        let edge2 = c - a;
+
+        let p = self.direction.cross(&edge2);
+        let determinant = edge1.dot(&p);
+
+        // The ray is parallel to the triangle.
+        if determinant.abs() < f32::EPSILON {
+            return None;
+        }
+
+        let inverse = 1.0 / determinant;
+        let s = self.origin - a;
+
+        let u = s.dot(&p) * inverse;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let q = s.cross(&edge1);
+        let v = self.direction.dot(&q) * inverse;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let distance = edge2.dot(&q) * inverse;
+        (distance >= 0.0).then_some(distance)
+    }
+}
+
+/// Copy of the triangles of a `Mesh` kept in RAM, the `AssetServer` stores
+/// it when the mesh is registered.
+#[derive(Clone, Debug, Default)]
+pub struct MeshGeometry {
+    pub positions: Vec<Point3<f32>>,
+    pub indices: Vec<u16>,
+}
+
+impl MeshGeometry {
+    pub fn new(vertices: &[Vertex], indices: &[u16]) -> Self {
+        Self {
+            positions: vertices.iter().map(|v| v.pos.into()).collect(),
+            indices: indices.to_vec(),
+        }
+    }
+
+    /// Returns the distance to the closest triangle hit by the ray.
+    pub fn raycast(&self, ray: &Ray) -> Option<f32> {
+        self.indices
+            .chunks_exact(3)
+            .filter_map(|t| {
+                let triangle = [t[0], t[1], t[2]]
+                    .map(|i| self.positions.get(i as usize).copied());
+
+                match triangle {
+                    [Some(a), Some(b), Some(c)] => {
+                        ray.intersect_triangle([a, b, c])
+                    }
+                    _ => None,
+                }
+            })
+            .min_by(f32::total_cmp)
+    }
+}
+
+/// Entity found under the cursor.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PickHit {
+    pub entity: EntityId,
+    /// World position of the hit.
+    pub position: Point3<f32>,
+    /// Distance from the near plane of the camera.
+    pub distance: f32,
+}
+
+/// Entity drawn in a `Scene` with the transform used in the last frame.
+pub(crate) struct PickTarget {
+    pub(crate) entity: EntityId,
+    pub(crate) mesh: MeshResourceID,
+    pub(crate) transform: Matrix4<f32>,
+}
+
+/// Returns the closest target hit by the ray. The bounds of the meshes are
+/// tested first, then their triangles.
+pub(crate) fn pick(
+    targets: &[PickTarget],
+    asset_server: &AssetServer,
+    ray: &Ray,
+) -> Option<PickHit> {
+    targets
+        .iter()
+        .filter_map(|target| {
+            let bounds = asset_server
+                .mesh_bounds(&target.mesh)?
+                .transformed(&target.transform);
+            let distance = ray.intersect_aabb(&bounds.aabb)?;
+
+            // Without triangles the bounds are used.
+            let distance = match asset_server.mesh_geometry(&target.mesh) {
+                Some(geometry) => {
+                    let local_ray =
+                        ray.transformed(&target.transform.try_inverse()?);
+                    geometry.raycast(&local_ray)?
+                }
+                None => distance,
+            };
+
+            Some(PickHit {
+                entity: target.entity,
+                position: ray.at(distance),
+                distance,
+            })
+        })
+        .min_by(|a, b| a.distance.total_cmp(&b.distance))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use nalgebra::Translation3;
+    use shipyard::World;
+
+    use crate::{
+        graphics::culling::{BoundingSphere, MeshBounds},
+        scene::camera::Viewport,
+    };
+
+    use super::*;
+
+    const EPSILON: f32 = 1e-4;
+    const CUBE_ID: &str = "unit_cube";
+
+    /// Returns a server with a cube of side 1 centered at the origin.
+    fn cube_asset_server() -> AssetServer {
+        let positions = (0..8)
+            .map(|i| {
+                Point3::new(
+                    if i & 1 == 0 { -0.5 } else { 0.5 },
+                    if i & 2 == 0 { -0.5 } else { 0.5 },
+                    if i & 4 == 0 { -0.5 } else { 0.5 },
+                )
+            })
+            .collect::<Vec<_>>();
+
+        // Two triangles for each face, using the corners numbered by their
+        // bits.
+        #[rustfmt::skip]
+        let indices = vec![
+            0, 1, 3, 0, 3, 2, // -Z
+            4, 5, 7, 4, 7, 6, // +Z
+            0, 2, 6, 0, 6, 4, // -X
+            1, 3, 7, 1, 7, 5, // +X
+            0, 1, 5, 0, 5, 4, // -Y
+            2, 3, 7, 2, 7, 6, // +Y
+        ];
+
+        let aabb = Aabb::from_points(positions.iter().copied());
+        let bounds = MeshBounds {
+            aabb,
+            sphere: BoundingSphere {
+                center: aabb.center(),
+                radius: aabb.half_extents().norm(),
+            },
+        };
+
+        let asset_server = AssetServer::default();
+        {
+            let mut data = asset_server.data.write().unwrap();
+
+            data.mesh_bounds.insert(CUBE_ID.to_owned(), bounds);
+            data.mesh_geometries.insert(
+                CUBE_ID.to_owned(),
+                Arc::new(MeshGeometry { positions, indices }),
+            );
+        }
+
+        asset_server
+    }
+
+    fn cube_target(entity: EntityId, transform: Matrix4<f32>) -> PickTarget {
+        PickTarget {
+            entity,
+            mesh: MeshResourceID(CUBE_ID.to_owned()),
+            transform,
+        }
+    }
+
+    /// Camera at z = 5 looking at the origin, with a 90 degrees field of
+    /// view and the near plane at 0.1.
+    fn front_view() -> SceneView {
+        SceneView::full(
+            Camera::new(
+                Point3::new(0.0, 0.0, 5.0),
+                Point3::origin(),
+                Vector3::y(),
+            ),
+            Projection::new_perspective(1.0, 90.0, 0.1, 100.0),
+        )
+    }
+
+    fn viewport_size() -> Size<f32> {
+        Size::new(100.0, 100.0)
+    }
+
+    fn ray_at(cursor: Vector2<f32>) -> Ray {
+        Ray::from_scene_views(&[front_view()], cursor, viewport_size())
+            .expect("The cursor is inside the viewport")
+    }
+
+    #[test]
+    fn ray_hits_the_cube() {
+        let asset_server = cube_asset_server();
+        let entity = World::new().add_entity(());
+        let targets = [cube_target(entity, Matrix4::identity())];
+
+        let hit =
+            pick(&targets, &asset_server, &ray_at(Vector2::new(50.0, 50.0)))
+                .expect("The center of the screen is on the cube");
+
+        assert_eq!(hit.entity, entity);
+        // The ray starts at the near plane, at z = 4.9.
+        assert!((hit.distance - 4.4).abs() < EPSILON);
+    }
+
+    #[test]
+    fn ray_misses_the_cube() {
+        let asset_server = cube_asset_server();
+        let targets = [cube_target(
+            World::new().add_entity(()),
+            Matrix4::identity(),
+        )];
+
+        let ray = ray_at(Vector2::new(5.0, 5.0));
+
+        assert_eq!(pick(&targets, &asset_server, &ray), None);
+    }
+
+    #[test]
+    fn bounds_hit_without_triangles_is_a_miss() {
+        let asset_server = cube_asset_server();
+        let targets = [cube_target(
+            World::new().add_entity(()),
+            Matrix4::identity(),
+        )];
+
+        // Inside the bounds of the cube rotated 45 degrees around Z, but
+        // outside its triangles.
+        let ray = Ray {
+            origin: Point3::new(-0.4, 0.4, 5.0),
+            direction: -Vector3::z(),
+        };
+        let rotated = [cube_target(
+            World::new().add_entity(()),
+            Matrix4::from_euler_angles(0.0, 0.0, std::f32::consts::FRAC_PI_4),
+        )];
+
+        assert!(pick(&targets, &asset_server, &ray).is_some());
+        assert_eq!(pick(&rotated, &asset_server, &ray), None);
+    }
+
+    #[test]
+    fn nearest_entity_is_returned() {
+        let asset_server = cube_asset_server();
+        let mut world = World::new();
+        let far = world.add_entity(());
+        let near = world.add_entity(());
+
+        // The far cube is first so the order doesn't decide the hit.
+        let targets = [
+            cube_target(
+                far,
+                Translation3::new(0.0, 0.0, -2.0).to_homogeneous(),
+            ),
+            cube_target(
+                near,
+                Translation3::new(0.0, 0.0, 1.0).to_homogeneous(),
+            ),
+        ];
+
+        let hit =
+            pick(&targets, &asset_server, &ray_at(Vector2::new(50.0, 50.0)))
+                .expect("Both cubes are under the cursor");
+
+        assert_eq!(hit.entity, near);
+        assert!((hit.distance - 3.4).abs() < EPSILON);
+    }
+
+    #[test]
+    fn cursor_outside_the_views_has_no_ray() {
+        let view = front_view();
+        let right_half = SceneView {
+            viewport: Viewport::new(0.5, 0.0, 0.5, 1.0),
+            ..view
+        };
+
+        assert_eq!(
+            Ray::from_scene_views(
+                &[view],
+                Vector2::new(150.0, 50.0),
+                viewport_size()
+            ),
+            None
+        );
+        assert_eq!(
+            Ray::from_scene_views(
+                &[view],
+                Vector2::new(50.0, -1.0),
+                viewport_size()
+            ),
+            None
+        );
+        assert_eq!(
+            Ray::from_scene_views(
+                &[right_half],
+                Vector2::new(25.0, 50.0),
+                viewport_size()
+            ),
+            None
+        );
+        assert!(Ray::from_scene_views(
+            &[right_half],
+            Vector2::new(75.0, 50.0),
+            viewport_size()
+        )
+        .is_some());
+    }
+
+    #[test]
+    fn ray_goes_through_the_center_of_a_view() {
+        let view = SceneView {
+            viewport: Viewport::new(0.5, 0.0, 0.5, 1.0),
+            ..front_view()
+        };
+
+        // The center of the right half of the texture.
+        let ray = Ray::from_scene_views(
+            &[view],
+            Vector2::new(75.0, 50.0),
+            viewport_size(),
+        )
+        .unwrap();
+
+        assert!((ray.origin - Point3::new(0.0, 0.0, 4.9)).norm() < EPSILON);
+        assert!((ray.direction + Vector3::z()).norm() < EPSILON);
+    }
+
+    #[test]
+    fn hit_position_is_in_world_space() {
+        let asset_server = cube_asset_server();
+        let entity = World::new().add_entity(());
+
+        // A cube of side 2 centered at (0, 1, 0), seen from +X.
+        let targets = [cube_target(
+            entity,
+            Translation3::new(0.0, 1.0, 0.0).to_homogeneous()
+                * Matrix4::new_scaling(2.0),
+        )];
+        let camera = Camera::new(
+            Point3::new(5.0, 1.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+            Vector3::y(),
+        );
+        let projection = Projection::new_perspective(2.0, 60.0, 0.1, 100.0);
+
+        let ray = Ray::from_viewport(
+            &camera,
+            &projection,
+            Vector2::new(100.0, 50.0),
+            Size::new(200.0, 100.0),
+        )
+        .unwrap();
+        let hit = pick(&targets, &asset_server, &ray).unwrap();
+
+        assert!((hit.position - Point3::new(1.0, 1.0, 0.0)).norm() < EPSILON);
+        assert!((hit.distance - 3.9).abs() < EPSILON);
+    }
+}
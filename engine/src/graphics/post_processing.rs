@@ -0,0 +1,81 @@
+use shipyard::UniqueView;
+
+use crate::scene::{
+    post_processing::{PostProcessEffect, MAX_POST_PROCESS_EFFECTS},
+    scene_state::SceneState,
+};
+
+use super::gpu::AbstractGpu;
+
+/// Distance between the parameters of each effect in the buffer, it must be
+/// a multiple of the uniform buffers offset alignment.
+pub(crate) const POST_PROCESS_PARAMS_STRIDE: u64 = 256;
+/// Number of textures of the bloom chain, each one is half the size of the
+/// previous one.
+pub(crate) const BLOOM_MIP_COUNT: u32 = 5;
+
+/// The parameters of an effect as they are stored in the GPU, padded so they
+/// can be bound with a dynamic offset.
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct PostProcessUniform {
+    /// Exposure: scale. Bloom: threshold and intensity. Gamma: inverse of
+    /// the gamma.
+    params: [f32; 4],
+    _padding: [[f32; 4]; 15],
+}
+
+impl PostProcessUniform {
+    fn new(effect: &PostProcessEffect) -> Self {
+        let params = match effect {
+            PostProcessEffect::Exposure(ev) => [ev.exp2(), 0.0, 0.0, 0.0],
+            PostProcessEffect::Bloom {
+                threshold,
+                intensity,
+            } => [*threshold, *intensity, 0.0, 0.0],
+            PostProcessEffect::Gamma(gamma) => {
+                [1.0 / gamma.max(f32::EPSILON), 0.0, 0.0, 0.0]
+            }
+            PostProcessEffect::Tonemapping(_)
+            | PostProcessEffect::ColorGrading { .. } => [0.0; 4],
+        };
+
+        Self {
+            params,
+            _padding: [[0.0; 4]; 15],
+        }
+    }
+}
+
+/// Returns the number of bytes needed to store the parameters of the effects
+/// of a `Scene`.
+pub(crate) fn post_process_buffer_size() -> u64 {
+    POST_PROCESS_PARAMS_STRIDE * MAX_POST_PROCESS_EFFECTS as u64
+}
+
+/// Writes the parameters of the post processing effects of each `Scene`.
+pub(crate) fn sync_post_process_params_system(
+    gpu: UniqueView<AbstractGpu>,
+    s_state: UniqueView<SceneState>,
+) {
+    for scene in
+        std::iter::once(&s_state.main).chain(s_state.sub_scenes.values())
+    {
+        let uniforms = scene
+            .post_processing
+            .iter()
+            .take(MAX_POST_PROCESS_EFFECTS)
+            .map(PostProcessUniform::new)
+            .collect::<Vec<_>>();
+
+        if uniforms.is_empty() {
+            continue;
+        }
+
+        gpu.write_uniform_buffer(
+            &scene.post_process_buffer,
+            0,
+            bytemuck::cast_slice(&uniforms),
+        );
+    }
+}
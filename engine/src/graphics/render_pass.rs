@@ -0,0 +1,329 @@
+use std::{error::Error, fmt};
+
+use ahash::AHashMap;
+use shipyard::Unique;
+
+use crate::{scene::scene::SceneTarget, types::Size};
+
+/// Passes drawn by the renderer, listed in the order they are executed each
+/// frame.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BuiltinPass {
+    Shadows,
+    Sky,
+    /// Draws the meshes of the scenes, the blended ones included.
+    DynamicMeshes,
+    /// Draws the outlines of the entities with an `Outline`.
+    Outlines,
+    DebugGrid,
+    /// Draws the shapes added to `DebugDraw`.
+    DebugDraw,
+    PostProcessing,
+    Fxaa,
+    /// Only drawn when the `EguiPlugin` is configured.
+    DebugGui,
+    /// Draws the target texture of the main scene on the screen.
+    FrameComposition,
+}
+
+/// Position of a custom pass relative to a built-in one, the passes placed
+/// at the same position are executed in the order they were added. A pass
+/// still runs after the passes writing its inputs, so the position matters
+/// among the passes writing the same textures.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PassOrder {
+    Before(BuiltinPass),
+    After(BuiltinPass),
+}
+
+/// Texture read or written by a custom pass.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PassTexture {
+    /// HDR image of the scene before the post processing. It is only
+    /// complete before `BuiltinPass::PostProcessing`, which overwrites it.
+    SceneColor,
+    /// Final image of the scene, after the post processing.
+    SceneTarget,
+    /// Depth of the scene, it can't be written. When the scene uses MSAA it
+    /// must be read as a `texture_depth_multisampled_2d`.
+    SceneDepth,
+    /// Texture added with `CustomRenderPasses::add_texture`.
+    Custom(String),
+}
+
+/// Format of the textures added with `CustomRenderPasses::add_texture`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PassTextureFormat {
+    Rgba8,
+    /// Stores colors brighter than 1.0, like the scene color.
+    Rgba16Float,
+}
+
+#[derive(Copy, Clone)]
+pub struct CustomTextureDescriptor {
+    pub size: Size<u32>,
+    pub format: PassTextureFormat,
+    /// The content is only kept while the passes of a frame use it, so the
+    /// texture can share its memory with other transient textures.
+    pub transient: bool,
+}
+
+/// How a pass writing the scene color uses the scene depth.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PassDepth {
+    Ignore,
+    Test,
+    TestAndWrite,
+}
+
+/// How the colors returned by the shader are combined with the output.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PassBlend {
+    Replace,
+    Alpha,
+    Additive,
+}
+
+/// Describes a pass drawn by the renderer alongside the built-in ones.
+///
+/// The shader is written in WGSL and must contain a `vs_main` and a
+/// `fs_main` entry points, the vertices are not provided so they must be
+/// generated from the vertex index. The resources are bound as follows:
+///
+/// - `@group(0) @binding(0)`: the camera of the scene.
+/// - `@group(1) @binding(0)`: the uniform of the pass, at least 16 bytes.
+/// - `@group(1) @binding(1..)`: each input, in the order they are listed.
+/// - `@group(1)` next binding: a linear sampler.
+pub struct CustomRenderPassDescriptor {
+    /// Identifies the pass, it must be unique.
+    pub label: String,
+    /// Scene whose textures and camera are used.
+    pub scene: SceneTarget,
+    pub order: PassOrder,
+    pub shader: String,
+    pub inputs: Vec<PassTexture>,
+    pub output: PassTexture,
+    /// Only available when the output is `PassTexture::SceneColor`.
+    pub depth: PassDepth,
+    pub blend: PassBlend,
+    /// Clears the output with the provided color, otherwise the pass draws
+    /// over its content.
+    pub clear: Option<[f64; 4]>,
+    pub vertex_count: u32,
+    /// Size in bytes of the uniform, it is updated with
+    /// `CustomRenderPasses::write_uniform`.
+    pub uniform_size: u64,
+}
+
+impl CustomRenderPassDescriptor {
+    /// Creates a pass which draws a triangle covering the whole output.
+    pub fn fullscreen(
+        label: &str,
+        scene: SceneTarget,
+        order: PassOrder,
+        shader: &str,
+        output: PassTexture,
+    ) -> Self {
+        Self {
+            label: label.to_owned(),
+            scene,
+            order,
+            shader: shader.to_owned(),
+            inputs: Vec::new(),
+            output,
+            depth: PassDepth::Ignore,
+            blend: PassBlend::Replace,
+            clear: None,
+            vertex_count: 3,
+            uniform_size: 0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CustomPassError {
+    /// There is already a pass with the label.
+    DuplicatedLabel(String),
+    /// There is no pass with the label.
+    UnknownPass(String),
+    /// The texture was not added before the pass which uses it.
+    UnknownTexture(String),
+    /// The pass reads the texture it writes.
+    ReadsItsOutput(String),
+    /// The scene depth can't be written as a color.
+    DepthOutput(String),
+    /// The depth is used by a pass which doesn't write the scene color.
+    DepthWithoutSceneColor(String),
+    /// The uniform data doesn't match the size of the uniform.
+    UniformSize {
+        label: String,
+        expected: u64,
+        found: u64,
+    },
+}
+
+impl Error for CustomPassError {}
+
+impl fmt::Display for CustomPassError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CustomPassError::DuplicatedLabel(l) => {
+                write!(f, "There is already a pass labeled {}", l)
+            }
+            CustomPassError::UnknownPass(l) => {
+                write!(f, "There is no pass labeled {}", l)
+            }
+            CustomPassError::UnknownTexture(t) => {
+                write!(f, "The texture {} was not added", t)
+            }
+            CustomPassError::ReadsItsOutput(l) => {
+                write!(f, "The pass {} reads the texture it writes", l)
+            }
+            CustomPassError::DepthOutput(l) => {
+                write!(f, "The pass {} can't write the scene depth", l)
+            }
+            CustomPassError::DepthWithoutSceneColor(l) => write!(
+                f,
+                "The pass {} uses the depth but doesn't write the scene color",
+                l
+            ),
+            CustomPassError::UniformSize {
+                label,
+                expected,
+                found,
+            } => write!(
+                f,
+                "The uniform of the pass {} has {} bytes, found {}",
+                label, expected, found
+            ),
+        }
+    }
+}
+
+/// A custom pass and the data of its uniform waiting to be written.
+pub(crate) struct CustomRenderPass {
+    /// Identifies the pass across removals, the renderer creates the
+    /// pipeline again when it changes.
+    pub(crate) id: u64,
+    pub(crate) descriptor: CustomRenderPassDescriptor,
+    pub(crate) pending_uniform: Option<Vec<u8>>,
+}
+
+/// Contains the passes drawn by the renderer alongside the built-in ones, it
+/// is available once the `WgpuRendererPlugin` is configured.
+///
+/// ```ignore
+/// fn configure(&self, app: &mut App) {
+///     let mut passes =
+///         app.world.borrow::<UniqueViewMut<CustomRenderPasses>>().unwrap();
+///
+///     let mut vignette = CustomRenderPassDescriptor::fullscreen(
+///         "Vignette",
+///         SceneTarget::Main,
+///         PassOrder::After(BuiltinPass::PostProcessing),
+///         include_str!("vignette.wgsl"),
+///         PassTexture::SceneTarget,
+///     );
+///     vignette.blend = PassBlend::Alpha;
+///
+///     passes.add_pass(vignette).unwrap();
+/// }
+/// ```
+#[derive(Unique, Default)]
+pub struct CustomRenderPasses {
+    pub(crate) passes: Vec<CustomRenderPass>,
+    pub(crate) textures: AHashMap<String, CustomTextureDescriptor>,
+    next_id: u64,
+}
+
+impl CustomRenderPasses {
+    /// Adds a texture which the passes can read and write, adding it again
+    /// reallocates it.
+    pub fn add_texture(
+        &mut self,
+        name: &str,
+        descriptor: CustomTextureDescriptor,
+    ) {
+        self.textures.insert(name.to_owned(), descriptor);
+    }
+
+    /// Adds a pass, it is drawn from the next frame.
+    pub fn add_pass(
+        &mut self,
+        descriptor: CustomRenderPassDescriptor,
+    ) -> Result<(), CustomPassError> {
+        let label = &descriptor.label;
+
+        if self.passes.iter().any(|p| &p.descriptor.label == label) {
+            return Err(CustomPassError::DuplicatedLabel(label.clone()));
+        }
+
+        for texture in descriptor.inputs.iter().chain([&descriptor.output]) {
+            if let PassTexture::Custom(name) = texture {
+                if !self.textures.contains_key(name) {
+                    return Err(CustomPassError::UnknownTexture(name.clone()));
+                }
+            }
+        }
+
+        if descriptor.output == PassTexture::SceneDepth {
+            return Err(CustomPassError::DepthOutput(label.clone()));
+        }
+
+        let uses_depth = descriptor.depth != PassDepth::Ignore;
+
+        if uses_depth && descriptor.output != PassTexture::SceneColor {
+            return Err(CustomPassError::DepthWithoutSceneColor(label.clone()));
+        }
+
+        if descriptor.inputs.contains(&descriptor.output)
+            || (uses_depth
+                && descriptor.inputs.contains(&PassTexture::SceneDepth))
+        {
+            return Err(CustomPassError::ReadsItsOutput(label.clone()));
+        }
+
+        self.next_id += 1;
+        self.passes.push(CustomRenderPass {
+            id: self.next_id,
+            descriptor,
+            pending_uniform: None,
+        });
+
+        Ok(())
+    }
+
+    /// Removes the pass with the provided label, returns if it existed.
+    pub fn remove_pass(&mut self, label: &str) -> bool {
+        let len = self.passes.len();
+        self.passes.retain(|p| p.descriptor.label != label);
+
+        len != self.passes.len()
+    }
+
+    /// Writes the uniform of a pass before it is drawn again, the data must
+    /// have the size of the uniform.
+    pub fn write_uniform(
+        &mut self,
+        label: &str,
+        data: &[u8],
+    ) -> Result<(), CustomPassError> {
+        let pass = self
+            .passes
+            .iter_mut()
+            .find(|p| p.descriptor.label == label)
+            .ok_or_else(|| CustomPassError::UnknownPass(label.to_owned()))?;
+
+        if data.len() as u64 != pass.descriptor.uniform_size {
+            return Err(CustomPassError::UniformSize {
+                label: label.to_owned(),
+                expected: pass.descriptor.uniform_size,
+                found: data.len() as u64,
+            });
+        }
+
+        pass.pending_uniform = Some(data.to_vec());
+
+        Ok(())
+    }
+}
@@ -1,49 +1,126 @@
-use std::cmp::Ordering;
+use std::{cmp::Ordering, ops::Range, time::Instant};
 
 use ahash::AHashMap;
-use nalgebra::Matrix4;
+use nalgebra::{Matrix4, Point3, Vector2};
 use shipyard::{
-    EntitiesView, EntityId, Get, IntoIter, UniqueView, UniqueViewMut, View,
+    EntitiesView, EntityId, Get, Unique, UniqueView, UniqueViewMut, View,
 };
 
 use crate::{
     graphics::UniformBuffer,
     scene::{
-        assets::MeshResourceID, camera::Camera, components::Transform,
-        hierarchy::Hierarchy, projection::Projection, scene::SceneTarget,
+        assets::{
+            asset_server::AssetServer,
+            handle::LoadState,
+            material::{default_material_resource, AlphaMode},
+            MaterialResourceID, MeshResourceID,
+        },
+        camera::Camera,
+        components::Transform,
+        hierarchy::Hierarchy,
+        lights::LightLimits,
+        post_processing::PostProcessEffect,
+        projection::Projection,
+        scene::{AntiAliasing, DebugView, SceneTarget},
         scene_state::SceneState,
     },
+    types::Size,
 };
 
 use super::{
-    components::MeshComponent, gpu::AbstractGpu, BindGroup, BufferUsage,
-    Texture, VertexBuffer,
+    camera::SceneView,
+    components::{MaterialComponent, MeshComponent, Outline, ShadowFlags},
+    culling::Frustum,
+    gpu::{AbstractGpu, GpuAbstractor},
+    picking::{pick, PickHit, PickTarget, Ray},
+    BindGroup, BufferUsage, StorageBuffer, Texture, VertexBuffer,
 };
 
 pub struct Scene {
     /// Contains a debug tag.
     pub label: String,
-    /// Contains the `Camera` used in the scene.
+    /// Contains the `Camera` used when no `CameraComponent` targets the scene.
     pub camera: Camera,
-    /// Contains the `Projection` used.
+    /// Contains the `Projection` used with `camera`.
     pub projection: Projection,
+    /// Linear RGB light applied to all the surfaces.
+    pub ambient_color: [f32; 3],
+    /// Contains the view used to inspect the geometry of the scene.
+    pub debug_view: DebugView,
 
-    /// Conaints the camera information allocated in the GPU RAM.
+    /// Contains the cameras drawing the scene sorted by priority, when no
+    /// `CameraComponent` targets the scene it is drawn from `camera`.
+    pub(crate) views: Vec<SceneView>,
+    /// Conaints the camera information of each view allocated in the GPU RAM.
     pub(crate) camera_buffer: Box<dyn UniformBuffer>,
-    /// Contains the buffer which holds the transform information.
-    // TODO(Angel): Set this as u32, WGPU only supports u32 for instancing
+    /// Contains the buffer which holds the transform information of each
+    /// batch and the number of instances.
     pub(crate) mesh_transform_buffers:
-        AHashMap<MeshResourceID, (Box<dyn VertexBuffer>, u64)>,
+        AHashMap<InstanceBatchKey, InstanceBuffer>,
+    /// Contains the result of the frustum culling of the last frame.
+    pub(crate) culling_stats: CullingStats,
+    /// Contains the level of detail drawn for each entity using a mesh with
+    /// levels.
+    pub(crate) lod_states: AHashMap<EntityId, LodState>,
+    /// Contains the entities drawn in the last frame, used to pick them.
+    pub(crate) pick_targets: Vec<PickTarget>,
+    /// Contains the entities with an `Outline` drawn in the last frame.
+    pub(crate) outline_targets: Vec<OutlineTarget>,
+    /// Contains the visible instances using a blended material sorted back
+    /// to front, they are drawn after the opaque ones.
+    pub(crate) transparent_draws: Vec<TransparentDraw>,
 
-    /// Contains the `Texture where the color will be rendered
-    // TODO(Angel): Add here PBR.
+    /// Contains the `Texture` where the final image is written, after the
+    /// post processing.
     pub target_texture: Box<dyn Texture>,
     /// Contains the depth `Texture`.
     pub(crate) depth_texture: Box<dyn Texture>,
+    /// Contains the anti-aliasing of the scene, the MSAA sample count is
+    /// already reduced to one supported by the GPU.
+    pub(crate) anti_aliasing: AntiAliasing,
+    /// Contains the HDR `Texture` where the scene is rendered, it is
+    /// multisampled when MSAA is used.
+    pub(crate) color_texture: Box<dyn Texture>,
+    /// Contains the HDR textures written alternately by the post processing
+    /// effects, the first one receives the MSAA resolve.
+    pub(crate) post_process_textures: [Box<dyn Texture>; 2],
+    /// Contains the mip chain used to blur the bright areas, it is only
+    /// allocated when the bloom is used.
+    pub(crate) bloom_textures: Vec<Box<dyn Texture>>,
+    /// Contains the parameters of each post processing effect.
+    pub(crate) post_process_buffer: Box<dyn UniformBuffer>,
+    /// Contains the bindgroups used to read the post processing textures.
+    pub(crate) post_process_bind_groups: Option<PostProcessBindGroups>,
+    /// Contains the effects applied in order to the HDR image.
+    pub post_processing: Vec<PostProcessEffect>,
+    /// Contains the texture filtered by FXAA into `target_texture`, only
+    /// allocated when FXAA is used.
+    pub(crate) fxaa_texture: Option<Box<dyn Texture>>,
+    /// Contains the bindgroup used by the FXAA pass to read `fxaa_texture`.
+    pub(crate) fxaa_bind_group: Option<Box<dyn BindGroup>>,
 
     /// Contains the bindgroup used to bind the camera information.
     pub(crate) camera_bind_group: Option<Box<dyn BindGroup>>,
 
+    /// Contains the max number of lights of each type.
+    pub(crate) light_limits: LightLimits,
+    /// Contains the lights of the scene allocated in the GPU RAM.
+    pub(crate) lights_buffer: Box<dyn StorageBuffer>,
+    /// Contains the bindgroup used to bind the lights.
+    pub(crate) lights_bind_group: Option<Box<dyn BindGroup>>,
+
+    /// Distance from the camera covered by the directional shadows.
+    pub shadow_distance: f32,
+    /// Contains a depth layer per cascade of each directional light and per
+    /// spot light casting shadows.
+    pub(crate) shadow_maps: Box<dyn Texture>,
+    /// Contains the view projection matrix used to render each shadow map.
+    pub(crate) shadow_casters_buffer: Box<dyn StorageBuffer>,
+    /// Number of shadow maps rendered in the current frame.
+    pub(crate) shadow_maps_in_use: u32,
+    /// Contains the bindgroup used to render the shadow maps.
+    pub(crate) shadow_caster_bind_group: Option<Box<dyn BindGroup>>,
+
     pub(crate) should_sync_resolution_to_window: bool,
 
     /// Containst the cube texture used to draw the sky.
@@ -52,6 +129,470 @@ pub struct Scene {
     pub(crate) sky_texture: Option<Box<dyn Texture>>,
     /// Contains the sky env bind group.
     pub(crate) sky_env_bind_group: Option<Box<dyn BindGroup>>,
+    /// Contains the lighting computed from the sky, only available when the
+    /// sky is rendered.
+    pub(crate) environment_maps: Option<EnvironmentMaps>,
+    /// Contains the bindgroup used to bind the environment maps.
+    pub(crate) environment_bind_group: Option<Box<dyn BindGroup>>,
+}
+
+impl Scene {
+    /// Returns how many instances were drawn and culled in the last frame.
+    pub fn culling_stats(&self) -> CullingStats {
+        self.culling_stats
+    }
+
+    /// Returns the closest entity under a point of the viewport where the
+    /// scene is displayed, and where it was hit. `cursor` is in pixels from
+    /// the top left corner of the viewport, the ray is cast from the topmost
+    /// camera drawn under it.
+    pub fn pick(
+        &self,
+        asset_server: &AssetServer,
+        cursor: Vector2<f32>,
+        viewport_size: Size<f32>,
+    ) -> Option<PickHit> {
+        let ray = Ray::from_scene_views(&self.views, cursor, viewport_size)?;
+
+        pick(&self.pick_targets, asset_server, &ray)
+    }
+
+    /// Returns the view drawn on top of the others, used where a single
+    /// camera is needed such as the level of detail or the shadow cascades.
+    pub(crate) fn main_view(&self) -> SceneView {
+        self.views
+            .last()
+            .copied()
+            .unwrap_or_else(|| SceneView::full(self.camera, self.projection))
+    }
+
+    /// Returns the number of samples per pixel of the render targets.
+    pub(crate) fn sample_count(&self) -> u32 {
+        self.anti_aliasing.sample_count()
+    }
+
+    /// Replaces the render targets, the resources derived from them are
+    /// created again.
+    pub(crate) fn set_textures(&mut self, textures: SceneTextures) {
+        self.target_texture = textures.target;
+        self.depth_texture = textures.depth;
+        self.color_texture = textures.color;
+        self.post_process_textures = textures.post_process;
+        self.fxaa_texture = textures.fxaa;
+
+        self.bloom_textures.clear();
+        self.post_process_bind_groups = None;
+        self.fxaa_bind_group = None;
+    }
+}
+
+/// Number of instances of a `Scene` inside and outside the camera frustum.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CullingStats {
+    pub visible: u32,
+    pub culled: u32,
+}
+
+/// Determines how many instances the buffer of each batch can hold, it is
+/// configured in the `WgpuRendererPlugin`.
+#[derive(Unique, Copy, Clone, Debug)]
+pub struct InstanceBufferPolicy {
+    /// Number of instances allocated for a new batch.
+    pub initial_capacity: u32,
+    /// Factor applied to the capacity until the instances fit, it must be
+    /// greater than one.
+    pub growth_factor: f32,
+    /// Fraction of the capacity below which the buffer is considered
+    /// underused.
+    pub shrink_threshold: f32,
+    /// Number of consecutive frames the buffer must be underused before it
+    /// is shrunk. The batches without entities for as long are removed.
+    pub shrink_delay_frames: u32,
+}
+
+impl Default for InstanceBufferPolicy {
+    fn default() -> Self {
+        Self {
+            initial_capacity: 64,
+            growth_factor: 2.0,
+            shrink_threshold: 0.25,
+            shrink_delay_frames: 300,
+        }
+    }
+}
+
+impl InstanceBufferPolicy {
+    /// Returns the capacity reached by growing `capacity` until `count`
+    /// instances fit.
+    fn grown_capacity(&self, capacity: u32, count: u32) -> u32 {
+        let mut capacity = capacity.max(1);
+
+        while capacity < count {
+            // Grow at least by one, in case the factor is too small.
+            capacity = ((capacity as f32 * self.growth_factor).ceil() as u32)
+                .max(capacity + 1);
+        }
+
+        capacity
+    }
+}
+
+/// Instances of a batch allocated in the GPU RAM, the visible ones are
+/// stored first.
+pub(crate) struct InstanceBuffer {
+    label: String,
+    pub(crate) buffer: Box<dyn VertexBuffer>,
+    /// Number of instances the buffer can hold.
+    pub(crate) capacity: u32,
+    /// Number of instances inside the camera frustum.
+    pub(crate) visible: u32,
+    /// Number of instances stored, the culled ones are only kept when the
+    /// batch casts shadows as their shadows may still be visible.
+    pub(crate) count: u32,
+    /// Contains the instances written in the buffer, used to only upload the
+    /// ones which changed.
+    uploaded: Vec<InstanceData>,
+    /// Number of consecutive frames the buffer was underused.
+    underused_frames: u32,
+    /// Number of consecutive frames without entities using the batch, the
+    /// culled ones included.
+    unused_frames: u32,
+    /// Determines if the material of the batch is blended, its instances are
+    /// drawn through `Scene::transparent_draws`.
+    pub(crate) blended: bool,
+}
+
+impl InstanceBuffer {
+    fn allocate(
+        gpu: &dyn GpuAbstractor,
+        key: &InstanceBatchKey,
+        policy: &InstanceBufferPolicy,
+    ) -> Self {
+        let label = format!("Mesh({}) Material({}) transform", *key.0, *key.1);
+        let capacity = policy.initial_capacity.max(1);
+        let buffer = allocate_instances(gpu, &label, capacity);
+
+        Self {
+            label,
+            buffer,
+            capacity,
+            visible: 0,
+            count: 0,
+            uploaded: Vec::new(),
+            underused_frames: 0,
+            unused_frames: 0,
+            blended: false,
+        }
+    }
+
+    /// Returns true if any entity used the batch in the last frame, even if
+    /// none of its instances was drawn.
+    pub(crate) fn is_used(&self) -> bool {
+        self.unused_frames == 0
+    }
+
+    /// Writes the instances into the buffer, only the range which changed
+    /// since the last upload is written. The buffer is reallocated when the
+    /// instances don't fit or it was underused for too long.
+    fn upload(
+        &mut self,
+        gpu: &dyn GpuAbstractor,
+        policy: &InstanceBufferPolicy,
+        instances: &[InstanceData],
+        visible: u32,
+    ) {
+        let count = instances.len() as u32;
+
+        if (count as f32) < self.capacity as f32 * policy.shrink_threshold {
+            self.underused_frames += 1;
+        } else {
+            self.underused_frames = 0;
+        }
+
+        let capacity = if count > self.capacity {
+            policy.grown_capacity(self.capacity, count)
+        } else if self.underused_frames >= policy.shrink_delay_frames {
+            policy.grown_capacity(policy.initial_capacity, count)
+        } else {
+            self.capacity
+        };
+
+        if capacity != self.capacity {
+            self.buffer = allocate_instances(gpu, &self.label, capacity);
+            self.capacity = capacity;
+            self.underused_frames = 0;
+            self.uploaded.clear();
+        }
+
+        if let Some((first, last)) = changed_range(&self.uploaded, instances) {
+            gpu.write_vertex_buffer(
+                &self.buffer,
+                first as u64 * std::mem::size_of::<InstanceData>() as u64,
+                bytemuck::cast_slice(&instances[first..=last]),
+            );
+        }
+
+        self.uploaded.clear();
+        self.uploaded.extend_from_slice(instances);
+        self.visible = visible;
+        self.count = count;
+    }
+}
+
+fn allocate_instances(
+    gpu: &dyn GpuAbstractor,
+    label: &str,
+    capacity: u32,
+) -> Box<dyn VertexBuffer> {
+    gpu.allocate_aligned_zero_vertex_buffer(
+        label,
+        capacity as u64 * std::mem::size_of::<InstanceData>() as u64,
+        BufferUsage::COPY_DST,
+    )
+}
+
+/// Returns the first and the last index of the instances which differ from
+/// the uploaded ones, `None` if all of them are already uploaded.
+fn changed_range(
+    uploaded: &[InstanceData],
+    instances: &[InstanceData],
+) -> Option<(usize, usize)> {
+    let changed = |i: &usize| uploaded.get(*i) != Some(&instances[*i]);
+
+    let first = (0..instances.len()).find(changed)?;
+    let last = (first..instances.len()).rev().find(changed)?;
+
+    Some((first, last))
+}
+
+/// Textures where a `Scene` is rendered.
+pub(crate) struct SceneTextures {
+    pub(crate) target: Box<dyn Texture>,
+    pub(crate) depth: Box<dyn Texture>,
+    pub(crate) color: Box<dyn Texture>,
+    pub(crate) post_process: [Box<dyn Texture>; 2],
+    /// Only needed with FXAA.
+    pub(crate) fxaa: Option<Box<dyn Texture>>,
+}
+
+impl SceneTextures {
+    /// Allocates the textures of a `Scene` using the provided anti-aliasing.
+    pub(crate) fn allocate(
+        gpu: &dyn GpuAbstractor,
+        label: &str,
+        size: Size<u32>,
+        anti_aliasing: AntiAliasing,
+    ) -> Self {
+        let sample_count = anti_aliasing.sample_count();
+
+        let target = gpu.allocate_target_texture(
+            format!("{} scene target texture", label).as_ref(),
+            size.width,
+            size.height,
+            1,
+        );
+
+        let depth = gpu.allocate_depth_texture(
+            format!("{} scene depth texture", label).as_ref(),
+            size.width,
+            size.height,
+            sample_count,
+        );
+
+        let color = gpu.allocate_hdr_texture(
+            format!("{} scene color texture", label).as_ref(),
+            size.width,
+            size.height,
+            sample_count,
+        );
+
+        let post_process = [0, 1].map(|i| {
+            gpu.allocate_hdr_texture(
+                format!("{} scene post process texture {}", label, i).as_ref(),
+                size.width,
+                size.height,
+                1,
+            )
+        });
+
+        let fxaa = (anti_aliasing == AntiAliasing::Fxaa).then(|| {
+            gpu.allocate_target_texture(
+                format!("{} scene FXAA texture", label).as_ref(),
+                size.width,
+                size.height,
+                1,
+            )
+        });
+
+        SceneTextures {
+            target,
+            depth,
+            color,
+            post_process,
+            fxaa,
+        }
+    }
+}
+
+/// Bindgroups used to read the textures of the post processing of a `Scene`.
+pub(crate) struct PostProcessBindGroups {
+    /// Reads the color texture, not available when it is multisampled.
+    pub(crate) color: Option<Box<dyn BindGroup>>,
+    pub(crate) post_process: [Box<dyn BindGroup>; 2],
+    /// Reads each mip of the bloom chain.
+    pub(crate) bloom: Vec<Box<dyn BindGroup>>,
+    /// Binds the parameters of the effects using a dynamic offset.
+    pub(crate) params: Box<dyn BindGroup>,
+}
+
+/// Size of each face of the irradiance cubemap, the diffuse light changes
+/// slowly so a small size is enough.
+pub(crate) const IRRADIANCE_MAP_SIZE: u32 = 32;
+/// Size of each face of the first mip level of the prefiltered cubemap.
+pub(crate) const PREFILTERED_MAP_SIZE: u32 = 128;
+/// Number of mip levels of the prefiltered cubemap, the last one is used for
+/// the roughest surfaces.
+pub(crate) const PREFILTERED_MAP_MIP_LEVELS: u32 = 5;
+
+/// Textures used to light the surfaces of a `Scene` with its sky.
+pub(crate) struct EnvironmentMaps {
+    /// Diffuse light received by a surface facing each direction.
+    pub(crate) irradiance: Box<dyn Texture>,
+    /// Specular light reflected in each direction, each mip level is
+    /// prefiltered for a higher roughness.
+    pub(crate) prefiltered: Box<dyn Texture>,
+    /// Tells the shaders if the maps are already computed.
+    pub(crate) uniform: Box<dyn UniformBuffer>,
+}
+
+/// Environment information as it is stored in the GPU.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct EnvironmentUniform {
+    /// 1 when the environment maps are computed, otherwise the ambient color
+    /// is used.
+    is_ready: u32,
+    prefiltered_mip_levels: f32,
+    _padding: [u32; 2],
+}
+
+impl EnvironmentUniform {
+    pub(crate) fn new(is_ready: bool) -> Self {
+        Self {
+            is_ready: is_ready as u32,
+            prefiltered_mip_levels: PREFILTERED_MAP_MIP_LEVELS as f32,
+            _padding: [0; 2],
+        }
+    }
+}
+
+/// Identifies a group of instances drawn together, all of them use the same
+/// mesh, material and shadow flags.
+pub(crate) type InstanceBatchKey =
+    (MeshResourceID, MaterialResourceID, ShadowFlags);
+
+/// Consecutive visible instances of a blended batch, the draws of all the
+/// blended batches are interleaved to keep the back to front order.
+pub(crate) struct TransparentDraw {
+    pub(crate) key: InstanceBatchKey,
+    pub(crate) instances: Range<u32>,
+}
+
+/// Returns the batch where the entity must be drawn.
+fn batch_key(
+    mesh: &MeshComponent,
+    material: Option<&MaterialComponent>,
+    shadows: Option<&ShadowFlags>,
+) -> InstanceBatchKey {
+    let material = material
+        .map(|m| m.0.clone())
+        .unwrap_or_else(default_material_resource);
+
+    (mesh.0.clone(), material, shadows.copied().unwrap_or_default())
+}
+
+/// Information of each instance as it is stored in the GPU.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct InstanceData {
+    transform: [[f32; 4]; 4],
+    receives_shadows: u32,
+    /// Fraction of the fragments kept while the instance fades in, between 0
+    /// and 1. The values above 1 fade the instance out, keeping the
+    /// fragments discarded by the same fraction when it fades in.
+    lod_fade: f32,
+    _padding: [u32; 2],
+}
+
+impl InstanceData {
+    fn matrix(&self) -> Matrix4<f32> {
+        self.transform.into()
+    }
+
+    /// Returns the squared distance from the instance origin to the point.
+    fn distance_squared(&self, point: &Point3<f32>) -> f32 {
+        let [x, y, z, _] = self.transform[3];
+        (Point3::new(x, y, z) - point).norm_squared()
+    }
+}
+
+/// Information of each outlined entity as it is stored in the GPU.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct OutlineInstance {
+    transform: [[f32; 4]; 4],
+    /// Linear RGBA color.
+    color: [f32; 4],
+    /// Width of the outline in normalized device coordinates, along both
+    /// axes of the screen.
+    width: [f32; 2],
+    _padding: [f32; 2],
+}
+
+/// Entity drawn with an outline in a `Scene`.
+pub(crate) struct OutlineTarget {
+    pub(crate) mesh: MeshResourceID,
+    pub(crate) instance: OutlineInstance,
+}
+
+/// Transforms of the entities of a batch, in the order they were added.
+#[derive(Default)]
+struct BatchTransforms {
+    entities: Vec<EntityId>,
+    raw: Vec<u8>,
+}
+
+impl BatchTransforms {
+    fn push(&mut self, entity: EntityId, raw_transform: &[u8]) {
+        self.entities.push(entity);
+        self.raw.extend_from_slice(raw_transform);
+    }
+}
+
+/// Level of detail drawn for an entity, kept to cross-fade the levels.
+#[derive(Copy, Clone)]
+pub(crate) struct LodState {
+    level: usize,
+    previous: usize,
+    /// Contains when the level changed.
+    changed_at: Instant,
+}
+
+/// Converts the raw transforms of a batch to the instance data.
+fn instance_data(
+    raw_transforms: &[u8],
+    flags: &ShadowFlags,
+) -> Vec<InstanceData> {
+    raw_transforms
+        .chunks_exact(Transform::raw_size() as usize)
+        .map(|transform| InstanceData {
+            // The raw transforms are not aligned.
+            transform: bytemuck::pod_read_unaligned(transform),
+            receives_shadows: flags.receives_shadows as u32,
+            lod_fade: 1.0,
+            _padding: [0; 2],
+        })
+        .collect()
 }
 
 pub(crate) fn sync_main_scene_dynamic_entities_transform(
@@ -59,9 +600,16 @@ pub(crate) fn sync_main_scene_dynamic_entities_transform(
     entities: EntitiesView,
     transforms: View<Transform>,
     scene_targets: View<SceneTarget>,
-    meshes: View<MeshComponent>,
+    (meshes, materials, shadow_flags, outlines): (
+        View<MeshComponent>,
+        View<MaterialComponent>,
+        View<ShadowFlags>,
+        View<Outline>,
+    ),
     mut scenes: UniqueViewMut<SceneState>,
     hierarchy: View<Hierarchy>,
+    asset_server: UniqueView<AssetServer>,
+    policy: UniqueView<InstanceBufferPolicy>,
 ) {
     // Main scene.
     sync_scene(
@@ -71,9 +619,14 @@ pub(crate) fn sync_main_scene_dynamic_entities_transform(
         &entities,
         &transforms,
         &meshes,
+        &materials,
+        &shadow_flags,
         &scene_targets,
         &hierarchy,
+        &asset_server,
+        &policy,
     );
+    sync_outlines(&mut scenes.main, &outlines);
     // Sub scenes.
     for (id, scene) in &mut scenes.sub_scenes {
         sync_scene(
@@ -83,12 +636,47 @@ pub(crate) fn sync_main_scene_dynamic_entities_transform(
             &entities,
             &transforms,
             &meshes,
+            &materials,
+            &shadow_flags,
             &scene_targets,
             &hierarchy,
+            &asset_server,
+            &policy,
         );
+        sync_outlines(scene, &outlines);
     }
 }
 
+/// Collects the entities of the scene with an `Outline`, once their
+/// transforms are computed.
+fn sync_outlines(scene: &mut Scene, outlines: &View<Outline>) {
+    let size = scene.target_texture.size();
+
+    // The size of a pixel in normalized device coordinates.
+    let pixel = [
+        2.0 / size.width.max(1) as f32,
+        2.0 / size.height.max(1) as f32,
+    ];
+
+    scene.outline_targets = scene
+        .pick_targets
+        .iter()
+        .filter_map(|target| {
+            let outline = outlines.get(target.entity).ok()?;
+
+            Some(OutlineTarget {
+                mesh: target.mesh.clone(),
+                instance: OutlineInstance {
+                    transform: target.transform.into(),
+                    color: outline.color,
+                    width: pixel.map(|p| p * outline.width),
+                    _padding: [0.0; 2],
+                },
+            })
+        })
+        .collect();
+}
+
 fn sync_scene(
     scene: &mut Scene,
     scene_id: Option<&String>,
@@ -96,26 +684,16 @@ fn sync_scene(
     entities: &EntitiesView,
     transforms: &View<Transform>,
     meshes: &View<MeshComponent>,
+    materials: &View<MaterialComponent>,
+    shadow_flags: &View<ShadowFlags>,
     scene_targets: &View<SceneTarget>,
     hierarchy: &View<Hierarchy>,
+    asset_server: &AssetServer,
+    policy: &InstanceBufferPolicy,
 ) {
-    let mut scene_raw_transforms: AHashMap<MeshResourceID, Vec<u8>> =
+    let mut scene_raw_transforms: AHashMap<InstanceBatchKey, BatchTransforms> =
         AHashMap::new();
 
-    for ent in meshes.iter() {
-        let id = ent.0.clone();
-
-        scene.mesh_transform_buffers.entry(id).or_insert_with(|| {
-            let buffer = gpu.allocate_aligned_zero_vertex_buffer(
-                &format!("Mesh({}) transform", ent.0 .0),
-                // TODO(Angel): The size must be configured using the pipeline props.
-                200000 * std::mem::size_of::<[[f32; 4]; 4]>() as u64,
-                BufferUsage::COPY_DST,
-            );
-            (buffer, 0)
-        });
-    }
-
     // In order to apply hierarchy transformation the entities must be
     // ordered by level, so the children can apply their final parent
     // transformation.
@@ -154,7 +732,11 @@ fn sync_scene(
             // main scene.
             Ok(SceneTarget::Main) | Err(_) => {
                 if scene_id.is_none() {
-                    let id = mesh.0.clone();
+                    let id = batch_key(
+                        mesh,
+                        materials.get(*entity_id).ok(),
+                        shadow_flags.get(*entity_id).ok(),
+                    );
 
                     scene_raw_transforms
                         .entry(id)
@@ -172,7 +754,7 @@ fn sync_scene(
                                             carry_transform.into();
                                         let a: &[u8] =
                                             bytemuck::cast_slice(&data);
-                                        e.extend_from_slice(a);
+                                        e.push(*entity_id, a);
                                         accum_transforms
                                             .insert(entity_id, carry_transform);
                                         return;
@@ -189,7 +771,7 @@ fn sync_scene(
                             // parent must be in the accum.
                             let data = transform.as_matrix_array();
                             let a: &[u8] = bytemuck::cast_slice(&data);
-                            e.extend_from_slice(a);
+                            e.push(*entity_id, a);
 
                             // Insert the entity in the accumulator most likely it has
                             // children.
@@ -197,15 +779,15 @@ fn sync_scene(
                                 .insert(entity_id, transform.as_matrix());
                         })
                         .or_insert_with(|| {
-                            let mut vec = Vec::new();
+                            let mut batch = BatchTransforms::default();
                             let data = transform.as_matrix_array();
                             let a: &[u8] = bytemuck::cast_slice(&data);
-                            vec.extend_from_slice(a);
+                            batch.push(*entity_id, a);
 
                             accum_transforms
                                 .insert(entity_id, transform.as_matrix());
 
-                            vec
+                            batch
                         });
                 }
             }
@@ -215,7 +797,11 @@ fn sync_scene(
                     // If we found an entity which is assiged to the current
                     // scene add the transformation.
                     if *scene_id == *s {
-                        let id = mesh.0.clone();
+                        let id = batch_key(
+                            mesh,
+                            materials.get(*entity_id).ok(),
+                            shadow_flags.get(*entity_id).ok(),
+                        );
 
                         scene_raw_transforms
                             .entry(id)
@@ -234,7 +820,7 @@ fn sync_scene(
                                                 carry_transform.into();
                                             let a: &[u8] =
                                                 bytemuck::cast_slice(&data);
-                                            e.extend_from_slice(a);
+                                            e.push(*entity_id, a);
                                             accum_transforms.insert(
                                                 entity_id,
                                                 carry_transform,
@@ -259,18 +845,18 @@ fn sync_scene(
                                 // parent must be in the accum.
                                 let data = transform.as_matrix_array();
                                 let a: &[u8] = bytemuck::cast_slice(&data);
-                                e.extend_from_slice(a);
+                                e.push(*entity_id, a);
                             })
                             .or_insert_with(|| {
-                                let mut vec = Vec::new();
+                                let mut batch = BatchTransforms::default();
                                 let data = transform.as_matrix_array();
                                 let a: &[u8] = bytemuck::cast_slice(&data);
-                                vec.extend_from_slice(a);
+                                batch.push(*entity_id, a);
 
                                 accum_transforms
                                     .insert(entity_id, transform.as_matrix());
 
-                                vec
+                                batch
                             });
                     }
                 }
@@ -278,13 +864,232 @@ fn sync_scene(
         }
     }
 
-    for (m, b) in scene_raw_transforms.iter() {
-        scene
+    let main_view = scene.main_view();
+    let camera = main_view.camera.position;
+    let frustums = scene
+        .views
+        .iter()
+        .map(|v| Frustum::from_camera(&v.camera, &v.projection))
+        .collect::<Vec<_>>();
+    let now = Instant::now();
+    let previous_lod_states = std::mem::take(&mut scene.lod_states);
+    let mut culling_stats = CullingStats::default();
+    let mut pick_targets = Vec::new();
+
+    // Contains the visible and the culled instances of each batch, once the
+    // level of detail of each instance is selected.
+    let mut batches = AHashMap::<
+        InstanceBatchKey,
+        (Vec<InstanceData>, Vec<InstanceData>),
+    >::new();
+
+    // The batches without instances in this frame are emptied.
+    for key in scene.mesh_transform_buffers.keys() {
+        batches.entry(key.clone()).or_default();
+    }
+
+    for (key, batch) in scene_raw_transforms.iter() {
+        let bounds = asset_server.mesh_bounds(&key.0);
+        let lod_group = asset_server.lod_group(&key.0);
+
+        // Contains the batch of each level, the levels not loaded yet are
+        // replaced by the mesh itself.
+        let level_keys = lod_group
+            .iter()
+            .flat_map(|g| &g.levels)
+            .map(|level| {
+                let mesh = match asset_server.load_state(&level.mesh) {
+                    Some(LoadState::Loaded) => level.mesh.clone(),
+                    _ => key.0.clone(),
+                };
+
+                (mesh, key.1.clone(), key.2)
+            })
+            .collect::<Vec<_>>();
+
+        let instances =
+            batch.entities.iter().zip(instance_data(&batch.raw, &key.2));
+
+        for (entity, mut instance) in instances {
+            pick_targets.push(PickTarget {
+                entity: *entity,
+                mesh: key.0.clone(),
+                transform: instance.matrix(),
+            });
+
+            let world_bounds =
+                bounds.map(|b| b.transformed(&instance.matrix()));
+
+            // The instances of a mesh without bounds are always drawn, the
+            // rest when any camera of the scene sees them.
+            let visible = world_bounds
+                .map_or(true, |b| frustums.iter().any(|f| f.intersects(&b)));
+
+            if visible {
+                culling_stats.visible += 1;
+            } else {
+                culling_stats.culled += 1;
+            }
+
+            let (group, world_bounds) = match (&lod_group, world_bounds) {
+                (Some(g), Some(b)) if !level_keys.is_empty() => (g, b),
+                _ => {
+                    let (drawn, culled) =
+                        batches.entry(key.clone()).or_default();
+                    let target = if visible { drawn } else { culled };
+                    target.push(instance);
+                    continue;
+                }
+            };
+
+            let level = group.select(
+                world_bounds
+                    .sphere
+                    .screen_size(&main_view.camera, &main_view.projection),
+            );
+
+            let state = match previous_lod_states.get(entity) {
+                Some(s) if s.level == level => *s,
+                Some(s) => LodState {
+                    level,
+                    previous: s.level,
+                    changed_at: now,
+                },
+                None => LodState {
+                    level,
+                    previous: level,
+                    changed_at: now,
+                },
+            };
+            scene.lod_states.insert(*entity, state);
+
+            let progress = match group.cross_fade {
+                Some(d) if state.previous != level => {
+                    now.duration_since(state.changed_at).as_secs_f32()
+                        / d.as_secs_f32()
+                }
+                _ => 1.0,
+            };
+
+            // During the transition the previous level is drawn too, each
+            // one discards the fragments the other keeps.
+            if let Some(previous_key) = level_keys.get(state.previous) {
+                if visible && progress < 1.0 {
+                    let mut outgoing = instance;
+                    outgoing.lod_fade = 1.0 + progress;
+                    instance.lod_fade = progress;
+
+                    batches
+                        .entry(previous_key.clone())
+                        .or_default()
+                        .0
+                        .push(outgoing);
+                }
+            }
+
+            let (drawn, culled) =
+                batches.entry(level_keys[level].clone()).or_default();
+            let target = if visible { drawn } else { culled };
+            target.push(instance);
+        }
+    }
+
+    // Contains the key of each blended batch and the distances to the camera
+    // of its visible instances, sorted back to front.
+    let mut blended_batches = Vec::new();
+
+    for (key, (mut instances, culled)) in batches {
+        let visible = instances.len() as u32;
+        let blended =
+            asset_server.load_material(&key.1).alpha_mode == AlphaMode::Blend;
+
+        // The blended instances must be drawn back to front, so the ones
+        // behind are visible.
+        if blended {
+            instances.sort_by(|a, b| {
+                b.distance_squared(&camera)
+                    .total_cmp(&a.distance_squared(&camera))
+            });
+
+            let distances = instances
+                .iter()
+                .map(|i| i.distance_squared(&camera))
+                .collect::<Vec<_>>();
+            blended_batches.push((key.clone(), distances));
+        }
+
+        // The entities whose level of detail is drawn by other batches
+        // still use the batch of their mesh.
+        let used = !instances.is_empty()
+            || !culled.is_empty()
+            || scene_raw_transforms.contains_key(&key);
+
+        if key.2.casts_shadows {
+            instances.extend(culled);
+        }
+
+        let buffer = scene
             .mesh_transform_buffers
-            .entry(m.clone())
-            .and_modify(|e| {
-                gpu.write_vertex_buffer(&e.0, 0, b);
-                e.1 = b.len() as u64 / Transform::raw_size();
+            .entry(key)
+            .or_insert_with_key(|key| {
+                InstanceBuffer::allocate(gpu.0.as_ref(), key, policy)
             });
+
+        buffer.upload(gpu.0.as_ref(), policy, &instances, visible);
+        buffer.unused_frames = if used { 0 } else { buffer.unused_frames + 1 };
+        buffer.blended = blended;
     }
+
+    // The batches without entities for as long as an underused buffer is
+    // kept are released, each combination of mesh, material and shadows
+    // ever drawn would keep its buffer alive otherwise.
+    scene
+        .mesh_transform_buffers
+        .retain(|_, b| b.unused_frames < policy.shrink_delay_frames.max(1));
+
+    scene.culling_stats = culling_stats;
+    scene.pick_targets = pick_targets;
+
+    scene.transparent_draws = transparent_draws(blended_batches);
+}
+
+/// Returns the draws of the instances of the blended batches, all sorted back
+/// to front together. The distances of each batch must be sorted already.
+fn transparent_draws(
+    batches: Vec<(InstanceBatchKey, Vec<f32>)>,
+) -> Vec<TransparentDraw> {
+    let mut instances = batches
+        .iter()
+        .enumerate()
+        .flat_map(|(batch, (_, distances))| {
+            distances
+                .iter()
+                .enumerate()
+                .map(move |(i, distance)| (batch, i as u32, *distance))
+        })
+        .collect::<Vec<_>>();
+
+    // The sort is stable, the instances of a batch keep their order.
+    instances.sort_by(|a, b| b.2.total_cmp(&a.2));
+
+    // The instances of a batch which follow each other in both orders are
+    // drawn at once.
+    let mut ranges = Vec::<(usize, Range<u32>)>::new();
+
+    for (batch, instance, _) in instances {
+        match ranges.last_mut() {
+            Some((b, range)) if *b == batch && range.end == instance => {
+                range.end += 1;
+            }
+            _ => ranges.push((batch, instance..instance + 1)),
+        }
+    }
+
+    ranges
+        .into_iter()
+        .map(|(batch, instances)| TransparentDraw {
+            key: batches[batch].0.clone(),
+            instances,
+        })
+        .collect()
 }
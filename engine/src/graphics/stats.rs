@@ -0,0 +1,72 @@
+use std::{ops::AddAssign, time::Duration};
+
+use shipyard::Unique;
+
+/// Draws recorded by the passes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DrawStats {
+    pub draw_calls: u32,
+    pub instances: u32,
+    pub triangles: u64,
+}
+
+impl DrawStats {
+    /// Adds a draw of `instances` copies of a shape made of `triangles`, the
+    /// lines do not count as triangles.
+    pub(crate) fn record(&mut self, triangles: u32, instances: u32) {
+        self.draw_calls += 1;
+        self.instances += instances;
+        self.triangles += triangles as u64 * instances as u64;
+    }
+}
+
+impl AddAssign for DrawStats {
+    fn add_assign(&mut self, other: Self) {
+        self.draw_calls += other.draw_calls;
+        self.instances += other.instances;
+        self.triangles += other.triangles;
+    }
+}
+
+/// Cost of a pass of the render graph.
+#[derive(Clone, Debug)]
+pub struct PassStats {
+    pub label: String,
+    pub draws: DrawStats,
+    /// Time taken to record the commands of the pass.
+    pub cpu_time: Duration,
+    /// Time taken by the GPU to run the render passes of the node, it is
+    /// read back some frames later. It is `None` if the GPU doesn't support
+    /// timestamp queries or the node records no render pass.
+    pub gpu_time: Option<Duration>,
+}
+
+/// Statistics of the last rendered frame, they are updated after the render
+/// graph runs.
+#[derive(Unique, Clone, Debug, Default)]
+pub struct RenderStats {
+    /// Contains the draws of all the passes.
+    pub draws: DrawStats,
+    /// Bytes written to the GPU buffers since the previous frame.
+    pub buffer_upload_bytes: u64,
+    /// Bytes used by the textures of the `AssetServer`.
+    pub texture_memory: u64,
+    /// Contains the passes in the order they run.
+    pub passes: Vec<PassStats>,
+}
+
+impl RenderStats {
+    /// Returns the time taken to record all the passes.
+    pub fn cpu_time(&self) -> Duration {
+        self.passes.iter().map(|p| p.cpu_time).sum()
+    }
+
+    /// Returns the time taken by the GPU to run all the measured passes, if
+    /// the GPU supports timestamp queries.
+    pub fn gpu_time(&self) -> Option<Duration> {
+        self.passes
+            .iter()
+            .filter_map(|p| p.gpu_time)
+            .reduce(|a, b| a + b)
+    }
+}
@@ -0,0 +1,527 @@
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::Size;
+
+/// Layout of the pixels of a texture. The compressed formats store blocks of
+/// pixels and can only be loaded from KTX2 or DDS files.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub enum TextureFormat {
+    /// 8 bits per channel in sRGB space, used by the color textures.
+    #[default]
+    Rgba8Srgb,
+    /// 8 bits per channel read as they are stored, used by the textures
+    /// which contain data like roughness or color lookup tables.
+    Rgba8Linear,
+    /// Half precision floats, used by the HDR images.
+    Rgba16Float,
+    /// Single precision floats. They can not be filtered, so they can only be
+    /// read texel by texel, like the sky does.
+    Rgba32Float,
+    Bc1Srgb,
+    Bc1Linear,
+    Bc3Srgb,
+    Bc3Linear,
+    /// A single channel, usually a height or occlusion map.
+    Bc4R,
+    /// Two channels, usually the X and Y of a normal map.
+    Bc5Rg,
+    Bc7Srgb,
+    Bc7Linear,
+    Etc2Rgb8Srgb,
+    Etc2Rgb8Linear,
+    Etc2Rgba8Srgb,
+    Etc2Rgba8Linear,
+    Astc4x4Srgb,
+    Astc4x4Linear,
+    Astc6x6Srgb,
+    Astc6x6Linear,
+    Astc8x8Srgb,
+    Astc8x8Linear,
+}
+
+impl TextureFormat {
+    /// Contains all the formats, new formats are appended at the end because
+    /// the asset packs store the index of the format.
+    pub const ALL: [TextureFormat; 22] = [
+        TextureFormat::Rgba8Srgb,
+        TextureFormat::Rgba8Linear,
+        TextureFormat::Rgba16Float,
+        TextureFormat::Rgba32Float,
+        TextureFormat::Bc1Srgb,
+        TextureFormat::Bc1Linear,
+        TextureFormat::Bc3Srgb,
+        TextureFormat::Bc3Linear,
+        TextureFormat::Bc4R,
+        TextureFormat::Bc5Rg,
+        TextureFormat::Bc7Srgb,
+        TextureFormat::Bc7Linear,
+        TextureFormat::Etc2Rgb8Srgb,
+        TextureFormat::Etc2Rgb8Linear,
+        TextureFormat::Etc2Rgba8Srgb,
+        TextureFormat::Etc2Rgba8Linear,
+        TextureFormat::Astc4x4Srgb,
+        TextureFormat::Astc4x4Linear,
+        TextureFormat::Astc6x6Srgb,
+        TextureFormat::Astc6x6Linear,
+        TextureFormat::Astc8x8Srgb,
+        TextureFormat::Astc8x8Linear,
+    ];
+
+    /// Returns the width and height of the blocks of pixels, the uncompressed
+    /// formats use blocks of a single pixel.
+    pub fn block_dimensions(&self) -> (u32, u32) {
+        match self {
+            TextureFormat::Rgba8Srgb
+            | TextureFormat::Rgba8Linear
+            | TextureFormat::Rgba16Float
+            | TextureFormat::Rgba32Float => (1, 1),
+            TextureFormat::Astc6x6Srgb | TextureFormat::Astc6x6Linear => (6, 6),
+            TextureFormat::Astc8x8Srgb | TextureFormat::Astc8x8Linear => (8, 8),
+            _ => (4, 4),
+        }
+    }
+
+    /// Returns the number of bytes used by each block of pixels.
+    pub fn block_size(&self) -> u32 {
+        match self {
+            TextureFormat::Rgba8Srgb | TextureFormat::Rgba8Linear => 4,
+            TextureFormat::Rgba16Float => 8,
+            TextureFormat::Bc1Srgb
+            | TextureFormat::Bc1Linear
+            | TextureFormat::Bc4R
+            | TextureFormat::Etc2Rgb8Srgb
+            | TextureFormat::Etc2Rgb8Linear => 8,
+            _ => 16,
+        }
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.block_dimensions() != (1, 1)
+    }
+
+    /// Returns true if the colors are stored in sRGB space.
+    pub fn is_srgb(&self) -> bool {
+        matches!(
+            self,
+            TextureFormat::Rgba8Srgb
+                | TextureFormat::Bc1Srgb
+                | TextureFormat::Bc3Srgb
+                | TextureFormat::Bc7Srgb
+                | TextureFormat::Etc2Rgb8Srgb
+                | TextureFormat::Etc2Rgba8Srgb
+                | TextureFormat::Astc4x4Srgb
+                | TextureFormat::Astc6x6Srgb
+                | TextureFormat::Astc8x8Srgb
+        )
+    }
+
+    /// Returns the number of bytes of a row of blocks.
+    pub fn bytes_per_row(&self, width: u32) -> u32 {
+        let (block_width, _) = self.block_dimensions();
+        width.div_ceil(block_width) * self.block_size()
+    }
+
+    /// Returns the number of bytes of an image of the provided size.
+    pub fn level_size_in_bytes(&self, size: Size<u32>) -> usize {
+        let (block_width, block_height) = self.block_dimensions();
+        let columns = size.width.div_ceil(block_width) as usize;
+        let rows = size.height.div_ceil(block_height) as usize;
+        columns * rows * self.block_size() as usize
+    }
+}
+
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub enum FilterMode {
+    Nearest,
+    #[default]
+    Linear,
+}
+
+/// How the coordinates out of the [0, 1] range are sampled.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub enum AddressMode {
+    #[default]
+    Repeat,
+    MirrorRepeat,
+    ClampToEdge,
+}
+
+/// Describes how a texture is sampled by the materials.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SamplerSettings {
+    pub address_mode_u: AddressMode,
+    pub address_mode_v: AddressMode,
+    pub mag_filter: FilterMode,
+    pub min_filter: FilterMode,
+    /// Filter used between two mip levels.
+    pub mipmap_filter: FilterMode,
+    /// Maximum number of samples taken by the anisotropic filtering, 1
+    /// disables it. It is only applied when all the filters are linear.
+    pub anisotropy: u16,
+}
+
+impl Default for SamplerSettings {
+    fn default() -> Self {
+        Self {
+            address_mode_u: AddressMode::Repeat,
+            address_mode_v: AddressMode::Repeat,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: FilterMode::Linear,
+            anisotropy: 1,
+        }
+    }
+}
+
+/// The pixels of a texture ready to be uploaded to the GPU.
+#[derive(Clone)]
+pub struct TextureData {
+    pub size: Size<u32>,
+    pub format: TextureFormat,
+    /// Contains the rows of the full size level without padding.
+    pub pixels: Vec<u8>,
+    /// Contains the levels after the full size one when they are stored in
+    /// the source file, each one is half the size of the previous one.
+    pub mip_levels: Vec<Vec<u8>>,
+    /// Generates the mip chain of the texture when it is uploaded, only if
+    /// the levels are not provided and the format is not compressed.
+    pub mipmaps: bool,
+    pub sampler: SamplerSettings,
+}
+
+impl TextureData {
+    /// Creates a texture with the default sampler and without mip levels.
+    pub fn new(
+        size: Size<u32>,
+        format: TextureFormat,
+        pixels: Vec<u8>,
+    ) -> Self {
+        Self {
+            size,
+            format,
+            pixels,
+            mip_levels: Vec::new(),
+            mipmaps: false,
+            sampler: SamplerSettings::default(),
+        }
+    }
+
+    /// Returns the number of levels of the texture, including the full size
+    /// one.
+    pub fn mip_level_count(&self) -> u32 {
+        if !self.mip_levels.is_empty() {
+            return 1 + self.mip_levels.len() as u32;
+        }
+
+        if !self.mipmaps || self.format.is_compressed() {
+            return 1;
+        }
+
+        let largest = self.size.width.max(self.size.height).max(1);
+        u32::BITS - largest.leading_zeros()
+    }
+
+    /// Returns the size of a level of the mip chain.
+    pub fn mip_level_size(&self, level: u32) -> Size<u32> {
+        Size::new(
+            (self.size.width >> level).max(1),
+            (self.size.height >> level).max(1),
+        )
+    }
+
+    /// Returns the size and pixels of all the levels from the full size one,
+    /// the missing levels are generated.
+    pub(crate) fn levels(&self) -> Vec<(Size<u32>, Cow<'_, [u8]>)> {
+        let first = (self.size, Cow::Borrowed(self.pixels.as_slice()));
+
+        if !self.mip_levels.is_empty() {
+            let stored =
+                self.mip_levels.iter().enumerate().map(|(i, pixels)| {
+                    let size = self.mip_level_size(i as u32 + 1);
+                    (size, Cow::Borrowed(pixels.as_slice()))
+                });
+
+            return std::iter::once(first).chain(stored).collect();
+        }
+
+        let mut size = self.size;
+        let mut texels = Vec::new();
+        let mut levels = vec![first];
+
+        for level in 1..self.mip_level_count() {
+            if level == 1 {
+                texels = decode_texels(self.format, &self.pixels);
+            }
+
+            (size, texels) = downsample(size, &texels);
+            levels
+                .push((size, Cow::Owned(encode_texels(self.format, &texels))));
+        }
+
+        levels
+    }
+
+    /// Decodes the blocks of a compressed texture into RGBA8 pixels, used when
+    /// the GPU does not support its format. The levels are kept.
+    pub fn decompress(&self) -> Result<TextureData, String> {
+        let format = if self.format.is_srgb() {
+            TextureFormat::Rgba8Srgb
+        } else {
+            TextureFormat::Rgba8Linear
+        };
+
+        let levels = std::iter::once(&self.pixels)
+            .chain(&self.mip_levels)
+            .enumerate()
+            .map(|(i, blocks)| {
+                let size = self.mip_level_size(i as u32);
+                decompress_blocks(self.format, size, blocks)
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let mut levels = levels.into_iter();
+
+        Ok(TextureData {
+            size: self.size,
+            format,
+            pixels: levels.next().unwrap_or_default(),
+            mip_levels: levels.collect(),
+            mipmaps: self.mipmaps,
+            sampler: self.sampler.clone(),
+        })
+    }
+}
+
+/// Decodes a level of a compressed texture, the decoders write the pixels as
+/// BGRA words.
+fn decompress_blocks(
+    format: TextureFormat,
+    size: Size<u32>,
+    blocks: &[u8],
+) -> Result<Vec<u8>, String> {
+    let (width, height) = (size.width as usize, size.height as usize);
+    let mut image = vec![0u32; width * height];
+
+    match format {
+        TextureFormat::Bc1Srgb | TextureFormat::Bc1Linear => {
+            texture2ddecoder::decode_bc1(blocks, width, height, &mut image)
+        }
+        TextureFormat::Bc3Srgb | TextureFormat::Bc3Linear => {
+            texture2ddecoder::decode_bc3(blocks, width, height, &mut image)
+        }
+        TextureFormat::Bc4R => {
+            texture2ddecoder::decode_bc4(blocks, width, height, &mut image)
+        }
+        TextureFormat::Bc5Rg => {
+            texture2ddecoder::decode_bc5(blocks, width, height, &mut image)
+        }
+        TextureFormat::Bc7Srgb | TextureFormat::Bc7Linear => {
+            texture2ddecoder::decode_bc7(blocks, width, height, &mut image)
+        }
+        TextureFormat::Etc2Rgb8Srgb | TextureFormat::Etc2Rgb8Linear => {
+            texture2ddecoder::decode_etc2_rgb(blocks, width, height, &mut image)
+        }
+        TextureFormat::Etc2Rgba8Srgb | TextureFormat::Etc2Rgba8Linear => {
+            texture2ddecoder::decode_etc2_rgba8(
+                blocks, width, height, &mut image,
+            )
+        }
+        TextureFormat::Astc4x4Srgb
+        | TextureFormat::Astc4x4Linear
+        | TextureFormat::Astc6x6Srgb
+        | TextureFormat::Astc6x6Linear
+        | TextureFormat::Astc8x8Srgb
+        | TextureFormat::Astc8x8Linear => {
+            let (block_width, block_height) = format.block_dimensions();
+            texture2ddecoder::decode_astc(
+                blocks,
+                width,
+                height,
+                block_width as usize,
+                block_height as usize,
+                &mut image,
+            )
+        }
+        _ => return Err(format!("{:?} is not a compressed format", format)),
+    }
+    .map_err(|e| e.to_owned())?;
+
+    Ok(image
+        .into_iter()
+        .flat_map(|pixel| {
+            let [b, g, r, a] = pixel.to_le_bytes();
+            [r, g, b, a]
+        })
+        .collect())
+}
+
+/// Averages each block of 2x2 texels. The last row or column is repeated in
+/// the textures with odd sizes.
+fn downsample(
+    size: Size<u32>,
+    texels: &[[f32; 4]],
+) -> (Size<u32>, Vec<[f32; 4]>) {
+    let half = Size::new((size.width / 2).max(1), (size.height / 2).max(1));
+    let texel = |x: u32, y: u32| {
+        let x = x.min(size.width - 1);
+        let y = y.min(size.height - 1);
+        texels[(y * size.width + x) as usize]
+    };
+
+    let mut result = Vec::with_capacity((half.width * half.height) as usize);
+
+    for y in 0..half.height {
+        for x in 0..half.width {
+            let taps = [
+                texel(2 * x, 2 * y),
+                texel(2 * x + 1, 2 * y),
+                texel(2 * x, 2 * y + 1),
+                texel(2 * x + 1, 2 * y + 1),
+            ];
+
+            result.push(std::array::from_fn(|c| {
+                taps.iter().map(|t| t[c]).sum::<f32>() / 4.0
+            }));
+        }
+    }
+
+    (half, result)
+}
+
+/// Converts the pixels to linear floats, so they can be averaged.
+fn decode_texels(format: TextureFormat, pixels: &[u8]) -> Vec<[f32; 4]> {
+    let stride = format.block_size() as usize;
+
+    pixels
+        .chunks_exact(stride)
+        .map(|p| match format {
+            TextureFormat::Rgba8Srgb => [
+                srgb_to_linear(p[0] as f32 / 255.0),
+                srgb_to_linear(p[1] as f32 / 255.0),
+                srgb_to_linear(p[2] as f32 / 255.0),
+                p[3] as f32 / 255.0,
+            ],
+            TextureFormat::Rgba8Linear => {
+                std::array::from_fn(|c| p[c] as f32 / 255.0)
+            }
+            TextureFormat::Rgba16Float => std::array::from_fn(|c| {
+                f16_to_f32(u16::from_le_bytes([p[c * 2], p[c * 2 + 1]]))
+            }),
+            TextureFormat::Rgba32Float => std::array::from_fn(|c| {
+                f32::from_le_bytes([
+                    p[c * 4],
+                    p[c * 4 + 1],
+                    p[c * 4 + 2],
+                    p[c * 4 + 3],
+                ])
+            }),
+            // The levels of the compressed formats are never generated.
+            _ => unreachable!("Unable to decode the texels of {:?}", format),
+        })
+        .collect()
+}
+
+/// Converts the linear floats back to the layout of the format.
+fn encode_texels(format: TextureFormat, texels: &[[f32; 4]]) -> Vec<u8> {
+    let to_u8 = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+    texels
+        .iter()
+        .flat_map(|t| match format {
+            TextureFormat::Rgba8Srgb => vec![
+                to_u8(linear_to_srgb(t[0])),
+                to_u8(linear_to_srgb(t[1])),
+                to_u8(linear_to_srgb(t[2])),
+                to_u8(t[3]),
+            ],
+            TextureFormat::Rgba8Linear => t.iter().map(|c| to_u8(*c)).collect(),
+            TextureFormat::Rgba16Float => t
+                .iter()
+                .flat_map(|c| f32_to_f16(*c).to_le_bytes())
+                .collect(),
+            TextureFormat::Rgba32Float => {
+                t.iter().flat_map(|c| c.to_le_bytes()).collect()
+            }
+            _ => unreachable!("Unable to encode the texels of {:?}", format),
+        })
+        .collect()
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Returns the bits of the half precision float closest to `value`, the
+/// values out of its range become infinite.
+pub(crate) fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    // Infinite and NaN.
+    if exponent == 0xff {
+        let nan = if mantissa != 0 { 0x200 } else { 0 };
+        return sign | 0x7c00 | nan;
+    }
+
+    let exponent = exponent - 127 + 15;
+
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+
+    // Too small to be a normal half, the implicit bit is stored in the
+    // mantissa of a subnormal one.
+    if exponent <= 0 {
+        if exponent < -10 {
+            return sign;
+        }
+
+        let mantissa = mantissa | 0x80_0000;
+        return sign | (mantissa >> (14 - exponent) as u32) as u16;
+    }
+
+    // Rounds to the nearest value, a carry moves to the next exponent.
+    let half =
+        (((exponent as u32) << 10) | (mantissa >> 13)) + ((mantissa >> 12) & 1);
+
+    sign | half as u16
+}
+
+/// Returns the value of the half precision float stored in `bits`.
+pub(crate) fn f16_to_f32(bits: u16) -> f32 {
+    let sign = ((bits & 0x8000) as u32) << 16;
+    let exponent = ((bits >> 10) & 0x1f) as u32;
+    let mantissa = (bits & 0x3ff) as u32;
+
+    match exponent {
+        0 => {
+            let value = mantissa as f32 * 2f32.powi(-24);
+            f32::from_bits(sign | value.to_bits())
+        }
+        0x1f => f32::from_bits(sign | 0x7f80_0000 | (mantissa << 13)),
+        _ => f32::from_bits(sign | ((exponent + 112) << 23) | (mantissa << 13)),
+    }
+}
@@ -6,4 +6,8 @@
 pub struct Vertex {
     pub pos: [f32; 3],
     pub col: [f32; 3],
+    /// Texture coordinates used to sample the material textures.
+    pub uv: [f32; 2],
+    /// Direction the surface faces, used to compute the lighting.
+    pub normal: [f32; 3],
 }
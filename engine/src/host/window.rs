@@ -8,7 +8,11 @@ use raw_window_handle::{
 
 use crate::types::Size;
 
-pub trait WindowInfoAccessible: Downcast {
+/// Exposes the information of the platform window. Implementors must be
+/// thread-safe because the `Window` is shared between systems.
+pub trait WindowInfoAccessible:
+    Downcast + HasRawWindowHandle + HasRawDisplayHandle + Send + Sync
+{
     fn inner_size(&self) -> Size<u32>;
     fn scale_factor(&self) -> f64;
 }
@@ -22,8 +26,6 @@ impl_downcast!(WindowInfoAccessible);
 pub struct Window {
     pub(crate) accesor: Box<dyn WindowInfoAccessible>,
     pub(crate) size: Size<u32>,
-    window_handle: RawWindowHandle,
-    display_handle: RawDisplayHandle,
 }
 
 impl Window {
@@ -31,15 +33,8 @@ impl Window {
     pub(crate) fn new<A: WindowInfoAccessible>(
         accesor: Box<A>,
         size: Size<u32>,
-        window_handle: RawWindowHandle,
-        display_handle: RawDisplayHandle,
     ) -> Self {
-        Window {
-            accesor,
-            size,
-            window_handle,
-            display_handle,
-        }
+        Window { accesor, size }
     }
 
     pub(crate) fn inner_size(&self) -> Size<u32> {
@@ -47,21 +42,16 @@ impl Window {
     }
 }
 
-/// TODO(Angel): Ensure thread safety for everything inside this block.
-/// The `Window` instance will only be accessed from one thread, but Shipyard
-/// does not have this knowledge. Therefore, we need to make it `Send` + `Sync`.
-/// Address this concern to ensure correct functionality.
-unsafe impl Send for Window {}
-unsafe impl Sync for Window {}
-
+/// The handles are requested to the platform window every time, so they are
+/// always valid while the `Window` is alive.
 unsafe impl HasRawWindowHandle for Window {
     fn raw_window_handle(&self) -> RawWindowHandle {
-        self.window_handle
+        self.accesor.raw_window_handle()
     }
 }
 
 unsafe impl HasRawDisplayHandle for Window {
     fn raw_display_handle(&self) -> RawDisplayHandle {
-        self.display_handle
+        self.accesor.raw_display_handle()
     }
 }
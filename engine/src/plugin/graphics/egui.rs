@@ -1,4 +1,7 @@
-use egui::{epaint::Shadow, Context, Visuals};
+use egui::{
+    epaint::{Primitive, Shadow},
+    Context, Visuals,
+};
 use egui_wgpu::{renderer::ScreenDescriptor, Renderer};
 use egui_winit::State;
 
@@ -7,7 +10,7 @@ use wgpu::{Operations, RenderPassColorAttachment, RenderPassDescriptor};
 
 use crate::{
     app::App,
-    graphics::gpu::AbstractGpu,
+    graphics::{gpu::AbstractGpu, stats::DrawStats},
     host::window::Window,
     plugin::{
         host::window::{UniqueWinitEvent, WinitWindowWrapper},
@@ -16,8 +19,13 @@ use crate::{
     scene::scene_state::SceneState,
     schedule::Schedule,
     wgpu_graphics::{
-        buffer::WGPUTexture, gpu::Gpu, CommandQueue, CommandSubmitOrder,
-        OrderCommandBuffer,
+        buffer::WGPUTexture,
+        gpu::Gpu,
+        render_graph::{
+            GraphResource, RenderGraph, RenderNode, DEBUG_GUI_NODE,
+            FRAME_COMPOSITION_NODE,
+        },
+        FrameEncoder,
     },
 };
 
@@ -103,9 +111,21 @@ impl Pluggable for EguiPlugin {
                 world.run(egui_generate_full_output);
             });
 
-            app.schedule(Schedule::AfterRequestRedraw, |world| {
-                world.run(egui_render_system);
-            });
+            // The GUI is drawn into the scene target before it is copied to
+            // the screen, and it shows the target of the other scenes.
+            app.world
+                .borrow::<UniqueViewMut<RenderGraph>>()
+                .expect("Configure the renderer before setting up egui")
+                .insert_before(
+                    FRAME_COMPOSITION_NODE,
+                    RenderNode::new(
+                        DEBUG_GUI_NODE,
+                        vec![GraphResource::SceneTarget],
+                        vec![GraphResource::SceneTarget],
+                        |world| world.run(egui_render_system),
+                    ),
+                )
+                .expect("Unable to add the egui pass");
 
             // We have to listen to any event that happen on the window,
             // nust just the Window one due the engine separetes window
@@ -139,7 +159,7 @@ fn egui_render_system(
     gpu: UniqueView<AbstractGpu>,
     window: UniqueView<Window>,
     s_state: UniqueView<SceneState>,
-    queue: UniqueView<CommandQueue>,
+    mut frame: UniqueViewMut<FrameEncoder>,
     mut egui: UniqueViewMut<EguiRenderer>,
     egui_ctx: UniqueView<EguiContext>,
 ) {
@@ -185,12 +205,6 @@ fn egui_render_system(
             .update_texture(&gpu.device, &gpu.queue, *id, image_delta)
     }
 
-    let mut encoder =
-        gpu.device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: None,
-            });
-
     let screen_descriptor = ScreenDescriptor {
         size_in_pixels: [gpu.surface_config.width, gpu.surface_config.height],
         pixels_per_point: window.accesor.scale_factor() as f32,
@@ -199,12 +213,13 @@ fn egui_render_system(
     egui.renderer.update_buffers(
         &gpu.device,
         &gpu.queue,
-        &mut encoder,
+        frame.encoder(),
         &tris,
         &screen_descriptor,
     );
 
     {
+        let (encoder, timestamp_writes) = frame.timed_encoder();
         let mut r_pass = encoder.begin_render_pass(&RenderPassDescriptor {
             label: Some("Egui render pass"),
             color_attachments: &[Some(RenderPassColorAttachment {
@@ -221,22 +236,25 @@ fn egui_render_system(
                 },
             })],
             depth_stencil_attachment: None,
-            timestamp_writes: None,
+            timestamp_writes,
             occlusion_query_set: None,
         });
 
         egui.renderer.render(&mut r_pass, &tris, &screen_descriptor);
     }
 
+    // Each mesh is drawn with its own call, the callbacks are not counted.
+    let mut draws = DrawStats::default();
+    for primitive in &tris {
+        if let Primitive::Mesh(mesh) = &primitive.primitive {
+            draws.record(mesh.indices.len() as u32 / 3, 1);
+        }
+    }
+    frame.record_draws(draws);
+
     for t in &output.textures_delta.free {
         egui.renderer.free_texture(t);
     }
-
-    let _ = queue.0.push(OrderCommandBuffer::new(
-        Some("Render egui".to_owned()),
-        CommandSubmitOrder::DebugGui,
-        encoder.finish(),
-    ));
 }
 
 fn egui_handle_events_system(
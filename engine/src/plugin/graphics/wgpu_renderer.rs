@@ -1,51 +1,95 @@
+use log::info;
 use shipyard::{UniqueView, UniqueViewMut, World};
 
 use crate::{
     app::App,
     graphics::{
         components::DepthTexture,
+        debug_draw::DebugDraw,
         gpu::{self, AbstractGpu},
         mesh::Mesh,
+        render_pass::CustomRenderPasses,
+        scene::InstanceBufferPolicy,
+        stats::RenderStats,
+        texture::{TextureData, TextureFormat},
         BufferCreator,
     },
     host::window::Window,
     plugin::Pluggable,
-    scene::assets::asset_server::AssetServer,
+    scene::assets::{
+        asset_server::AssetServer,
+        placeholder::{
+            placeholder_texture_data, PLACEHOLDER_INDICES, PLACEHOLDER_MESH_ID,
+            PLACEHOLDER_TEXTURE_ID, PLACEHOLDER_TEXTURE_SIZE,
+            PLACEHOLDER_VERTICES,
+        },
+    },
     schedule::Schedule,
     wgpu_graphics::{
         components::{ScreenFrame, ScreenTexture},
         gpu::Gpu,
+        gpu_timer::GpuTimer,
+        materials::{sync_material_bind_groups_system, MaterialBindGroups},
         passes::{
+            debug_draw_pass::debug_draw_pass_system,
             dynamic_mesh_pass::dynamic_mesh_pass_system,
             frame_composition_pass_system::frame_composition_pass_system,
+            fxaa_pass::fxaa_pass_system,
             infinite_grid_pass::infinite_grid_pass_system,
-            sky_pass::sky_pass_system,
+            outline_pass::outline_pass_system,
+            post_process_pass::post_process_pass_system,
+            shadow_pass::shadow_pass_system, sky_pass::sky_pass_system,
         },
         pipelines::{
-            create_camera_bind_group_layout,
+            create_camera_bind_group_layout, create_lights_bind_group_layout,
+            create_shadow_caster_bind_group_layout,
+            custom_pass_pipeline::{
+                setup_custom_pass_pipelines_system, CustomPassPipelines,
+            },
+            debug_draw_pipeline::DebugDrawPipeline,
             dynamic_mesh_pipeline::DynamicMeshPipeline,
             frame_composition_pipeline::{
                 setup_frame_composition_pipelines_uniforms_system,
                 FrameCompositionPipeline,
             },
+            fxaa_pipeline::{setup_fxaa_bind_groups_system, FxaaPipeline},
+            ibl_pipeline::{setup_environment_bind_groups_system, IblPipeline},
             infinite_grid_pipeline::InfiniteGridPipeline,
-            setup_scenes_uniforms_system,
+            outline_pipeline::OutlinePipeline,
+            post_process_pipeline::{
+                setup_post_process_bind_groups_system,
+                sync_lut_bind_groups_system, PostProcessPipeline,
+            },
+            scene_sample_counts, setup_scenes_uniforms_system,
+            shadow_pipeline::ShadowPipeline,
             sky_pipeline::{
                 clear_sky_updater, setup_sky_pipelines_uniforms_system,
                 sync_sky_pipeline_uniforms, SkyPipeline,
             },
             GlobalBindGroupLayouts,
         },
+        render_graph::{
+            run_render_graph, GraphResource, RenderGraph, RenderNode,
+            DEBUG_DRAW_NODE, DEBUG_GRID_NODE, DYNAMIC_MESHES_NODE,
+            FRAME_COMPOSITION_NODE, FXAA_NODE, OUTLINES_NODE,
+            POST_PROCESSING_NODE, SHADOWS_NODE, SKY_NODE,
+        },
         rendering::{
             acquire_screen_texture, present_screen_texture,
             reconfigure_main_textures_if_needed_system,
-            submit_commands_in_order,
         },
-        CommandQueue, OrderCommandQueue, MAX_NUMBER_IF_COMMANDS_PER_FRAME,
+        shader_library::{
+            reload_changed_shaders, BuiltinShader, ShaderLibrary,
+        },
+        FrameEncoder,
     },
 };
 
-pub struct WgpuRendererPlugin;
+#[derive(Default)]
+pub struct WgpuRendererPlugin {
+    /// Determines how the instance buffers of the scenes grow and shrink.
+    pub instance_buffers: InstanceBufferPolicy,
+}
 
 impl Pluggable for WgpuRendererPlugin {
     fn configure(&self, app: &mut App) {
@@ -62,8 +106,17 @@ impl Pluggable for WgpuRendererPlugin {
         {
             let world = &app.world;
 
-            setup_screen_texture_and_queue(world);
+            setup_screen_texture_and_encoder(world, &gpu);
             setup_depth_texture(world, &gpu);
+            setup_render_graph(world);
+            world.add_unique(ShaderLibrary::new());
+            world.add_unique(self.instance_buffers);
+            world.add_unique(DebugDraw::default());
+            world.add_unique(RenderStats::default());
+
+            // Available while configuring the plugins, so they can add their
+            // passes.
+            world.add_unique(CustomRenderPasses::default());
         }
 
         {
@@ -75,16 +128,20 @@ impl Pluggable for WgpuRendererPlugin {
         {
             app.schedule(Schedule::PipelineConfiguration, |world| {
                 setup_pipelines(world);
+                setup_placeholder_assets(world);
             });
 
             app.schedule(Schedule::PipelineUniformsSetup, |world| {
                 world.run(setup_frame_composition_pipelines_uniforms_system);
                 world.run(setup_scenes_uniforms_system);
                 world.run(setup_sky_pipelines_uniforms_system);
+                world.run(setup_environment_bind_groups_system);
             });
 
             app.schedule(Schedule::Start, |world| {
                 world.run(reconfigure_main_textures_if_needed_system);
+                world.run(setup_post_process_bind_groups_system);
+                world.run(setup_fxaa_bind_groups_system);
             });
 
             app.schedule(Schedule::InitFrame, |world| {
@@ -92,22 +149,20 @@ impl Pluggable for WgpuRendererPlugin {
             });
 
             app.schedule(Schedule::Update, |world| {
+                reload_changed_shaders(world);
                 load_textures(world);
                 load_models(world);
+                world.run(sync_material_bind_groups_system);
+                world.run(sync_lut_bind_groups_system);
                 sync_sky_pipeline_uniforms(world);
                 clear_sky_updater(world);
             });
 
-            app.schedule(Schedule::RequestRedraw, |world| {
-                world.run(dynamic_mesh_pass_system);
-                world.run(frame_composition_pass_system);
-                world.run(infinite_grid_pass_system);
-                world.run(sky_pass_system);
+            app.schedule(Schedule::BeforeRequestRedraw, |world| {
+                world.run(setup_custom_pass_pipelines_system);
             });
 
-            app.schedule(Schedule::QueueSubmit, |world| {
-                world.run(submit_commands_in_order);
-            });
+            app.schedule(Schedule::QueueSubmit, run_render_graph);
 
             app.schedule(Schedule::EndFrame, |world| {
                 world.run(present_screen_texture);
@@ -145,13 +200,29 @@ fn load_textures(world: &World) {
         .downcast_ref::<Gpu>()
         .expect("Unable to acquire Wgpu GPU");
 
-    for (id, buffer, size) in textures_to_load {
-        let texture = gpu.allocate_texture(
-            format!("Texture {}", id).as_ref(),
-            size.width,
-            size.height,
-            &buffer,
-        );
+    for (id, data) in textures_to_load {
+        // The compressed formats the GPU can not sample are decoded.
+        let data = if gpu.supports_texture(&data) {
+            data
+        } else {
+            match data.decompress() {
+                Ok(decompressed) => {
+                    info!(
+                        "{:?} is not supported by the GPU, texture {} is \
+                         decompressed",
+                        data.format, id
+                    );
+                    decompressed
+                }
+                Err(e) => {
+                    asset_loader.register_failure(id, e);
+                    continue;
+                }
+            }
+        };
+
+        let texture =
+            gpu.allocate_texture(format!("Texture {}", id).as_ref(), &data);
 
         asset_loader.register_texture(id, Box::new(texture));
     }
@@ -167,11 +238,13 @@ fn load_models(world: &World) {
         .expect("Unable to acquire asset loader lock");
 
     let meshes = {
-        let model_loder = asset_loader.loader.lock().unwrap();
+        let models_to_load = std::mem::take(
+            &mut asset_loader.loader.lock().unwrap().models_to_load,
+        );
 
         let mut meshes = Vec::new();
 
-        for (id, model) in &model_loder.models_to_load {
+        for (id, model) in models_to_load {
             let vertices = gpu.allocate_vertex_buffer(
                 id.as_str(),
                 bytemuck::cast_slice(&model.vertices),
@@ -183,26 +256,144 @@ fn load_models(world: &World) {
             );
 
             let mesh = Mesh::new(vertices, indices, model.indices.len() as u32);
-            meshes.push((id.clone(), mesh));
+            meshes.push((id, mesh, model));
         }
 
         meshes
     };
 
-    for (id, mesh) in meshes {
-        asset_loader.register_mesh(id.clone(), mesh);
+    for (id, mesh, model) in meshes {
+        asset_loader.register_mesh(id, mesh, &model.vertices, &model.indices);
     }
 }
 
+/// Registers the mesh and texture used while the real assets are loading.
+fn setup_placeholder_assets(world: &World) {
+    // The `AssetServer` is only available if the `ScenePlugin` is configured.
+    let mut asset_server = match world.borrow::<UniqueViewMut<AssetServer>>() {
+        Ok(a) => a,
+        Err(_) => return,
+    };
+
+    let a_gpu = world
+        .borrow::<UniqueView<AbstractGpu>>()
+        .expect("Unable to acquire AbtractGpu");
+
+    let gpu = a_gpu
+        .downcast_ref::<Gpu>()
+        .expect("Unable to acquire Wgpu GPU");
+
+    let vertices = gpu.allocate_vertex_buffer(
+        "Placeholder mesh vertices",
+        bytemuck::cast_slice(PLACEHOLDER_VERTICES),
+    );
+
+    let indices = gpu.allocate_index_buffer(
+        "Placeholder mesh indices",
+        bytemuck::cast_slice(PLACEHOLDER_INDICES),
+    );
+
+    asset_server.register_mesh(
+        PLACEHOLDER_MESH_ID.to_owned(),
+        Mesh::new(vertices, indices, PLACEHOLDER_INDICES.len() as u32),
+        PLACEHOLDER_VERTICES,
+        PLACEHOLDER_INDICES,
+    );
+
+    let texture = gpu.allocate_texture(
+        "Placeholder texture",
+        &TextureData::new(
+            PLACEHOLDER_TEXTURE_SIZE,
+            TextureFormat::Rgba8Srgb,
+            placeholder_texture_data(),
+        ),
+    );
+
+    asset_server
+        .register_texture(PLACEHOLDER_TEXTURE_ID.to_owned(), Box::new(texture));
+}
+
 /// Setups the screen texture (the texture that will be presented over the
-/// screen), and the queue user to submit all the encoder commands.
-fn setup_screen_texture_and_queue(world: &World) {
+/// screen), and the encoder where the passes record their commands.
+fn setup_screen_texture_and_encoder(world: &World, gpu: &Gpu) {
     world.add_unique(ScreenTexture(None));
     world.add_unique(ScreenFrame(None));
+    world.add_unique(FrameEncoder::new(GpuTimer::new(gpu)));
+}
+
+/// Setups the render graph with the built-in passes, the scene resources are
+/// written in the order the passes are added.
+fn setup_render_graph(world: &World) {
+    let mut graph = RenderGraph::default();
+
+    let nodes = [
+        RenderNode::new(
+            SHADOWS_NODE,
+            vec![],
+            vec![GraphResource::ShadowMaps],
+            |world| world.run(shadow_pass_system),
+        ),
+        RenderNode::new(
+            SKY_NODE,
+            vec![],
+            vec![GraphResource::SceneColor, GraphResource::SceneDepth],
+            |world| world.run(sky_pass_system),
+        ),
+        RenderNode::new(
+            DYNAMIC_MESHES_NODE,
+            vec![
+                GraphResource::ShadowMaps,
+                GraphResource::SceneColor,
+                GraphResource::SceneDepth,
+            ],
+            vec![GraphResource::SceneColor, GraphResource::SceneDepth],
+            |world| world.run(dynamic_mesh_pass_system),
+        ),
+        RenderNode::new(
+            OUTLINES_NODE,
+            vec![GraphResource::SceneColor, GraphResource::SceneDepth],
+            vec![GraphResource::SceneColor, GraphResource::SceneDepth],
+            |world| world.run(outline_pass_system),
+        ),
+        RenderNode::new(
+            DEBUG_GRID_NODE,
+            vec![GraphResource::SceneColor, GraphResource::SceneDepth],
+            vec![GraphResource::SceneColor, GraphResource::SceneDepth],
+            |world| world.run(infinite_grid_pass_system),
+        ),
+        RenderNode::new(
+            DEBUG_DRAW_NODE,
+            vec![GraphResource::SceneColor, GraphResource::SceneDepth],
+            vec![GraphResource::SceneColor, GraphResource::SceneDepth],
+            |world| world.run(debug_draw_pass_system),
+        ),
+        RenderNode::new(
+            POST_PROCESSING_NODE,
+            vec![GraphResource::SceneColor],
+            vec![GraphResource::FxaaTexture, GraphResource::SceneTarget],
+            |world| world.run(post_process_pass_system),
+        ),
+        RenderNode::new(
+            FXAA_NODE,
+            vec![GraphResource::FxaaTexture],
+            vec![GraphResource::SceneTarget],
+            |world| world.run(fxaa_pass_system),
+        ),
+        RenderNode::new(
+            FRAME_COMPOSITION_NODE,
+            vec![GraphResource::SceneTarget],
+            vec![GraphResource::Screen],
+            |world| world.run(frame_composition_pass_system),
+        ),
+    ];
+
+    for node in nodes {
+        graph
+            .add(node)
+            .expect("The built-in passes have different labels");
+    }
 
-    world.add_unique(CommandQueue(OrderCommandQueue::new(
-        MAX_NUMBER_IF_COMMANDS_PER_FRAME,
-    )));
+    world.add_unique(graph);
 }
 
 /// Setups the global depth texture.
@@ -211,6 +402,7 @@ fn setup_depth_texture(world: &World, gpu: &Gpu) {
         "Global depth texture",
         gpu.surface_config.width,
         gpu.surface_config.height,
+        1,
     );
     world.add_unique(DepthTexture(d_texture));
 }
@@ -225,22 +417,87 @@ fn setup_pipelines(world: &World) {
         .downcast_ref::<Gpu>()
         .expect("Unable to acquire Wgpu GPU");
 
+    let library = world
+        .borrow::<UniqueView<ShaderLibrary>>()
+        .expect("Unable to acquire the shader library");
+
+    let program = |shader| library.compile(gpu, shader);
+
     // Creates the commond camera bindgroup layout used in all the
     // pipelines.
     let camera_bind_group_layout = create_camera_bind_group_layout(gpu);
-
-    let dynamic_mesh = DynamicMeshPipeline::new(gpu, &camera_bind_group_layout);
-    let frame_composition = FrameCompositionPipeline::new(gpu);
-    let infinite_grid =
-        InfiniteGridPipeline::new(gpu, &camera_bind_group_layout);
-    let sky = SkyPipeline::new(gpu, &camera_bind_group_layout);
+    let lights_bind_group_layout = create_lights_bind_group_layout(gpu);
+    let shadow_caster_bind_group_layout =
+        create_shadow_caster_bind_group_layout(gpu);
+    let materials = MaterialBindGroups::new(gpu);
+    let ibl = IblPipeline::new(gpu, &program(BuiltinShader::Ibl));
+    // The scene pipelines need a variant for each MSAA sample count.
+    let sample_counts = scene_sample_counts(gpu);
+
+    let dynamic_mesh = DynamicMeshPipeline::new(
+        gpu,
+        &program(BuiltinShader::DynamicMesh),
+        &camera_bind_group_layout,
+        &materials.layout,
+        &lights_bind_group_layout,
+        &ibl.environment_layout,
+        &sample_counts,
+    );
+    let frame_composition = FrameCompositionPipeline::new(
+        gpu,
+        &program(BuiltinShader::FrameComposition),
+    );
+    let post_process =
+        PostProcessPipeline::new(gpu, &program(BuiltinShader::PostProcess));
+    let fxaa = FxaaPipeline::new(gpu, &program(BuiltinShader::Fxaa));
+    let infinite_grid = InfiniteGridPipeline::new(
+        gpu,
+        &program(BuiltinShader::InfiniteGrid),
+        &camera_bind_group_layout,
+        &sample_counts,
+    );
+    let outline = OutlinePipeline::new(
+        gpu,
+        &program(BuiltinShader::Outline),
+        &camera_bind_group_layout,
+        &sample_counts,
+    );
+    let debug_draw = DebugDrawPipeline::new(
+        gpu,
+        &program(BuiltinShader::DebugLines),
+        &camera_bind_group_layout,
+        &sample_counts,
+    );
+    let sky = SkyPipeline::new(
+        gpu,
+        &program(BuiltinShader::Sky),
+        &program(BuiltinShader::Equirectangular),
+        &camera_bind_group_layout,
+        &sample_counts,
+    );
+    let shadow = ShadowPipeline::new(
+        gpu,
+        &program(BuiltinShader::Shadow),
+        &shadow_caster_bind_group_layout,
+    );
+    let custom_passes = CustomPassPipelines::new(gpu);
 
     world.add_unique(dynamic_mesh);
     world.add_unique(frame_composition);
+    world.add_unique(post_process);
+    world.add_unique(fxaa);
     world.add_unique(infinite_grid);
+    world.add_unique(debug_draw);
+    world.add_unique(outline);
     world.add_unique(sky);
+    world.add_unique(shadow);
+    world.add_unique(custom_passes);
+    world.add_unique(ibl);
+    world.add_unique(materials);
 
     world.add_unique(GlobalBindGroupLayouts {
         camera: camera_bind_group_layout,
+        lights: lights_bind_group_layout,
+        shadow_caster: shadow_caster_bind_group_layout,
     });
 }
@@ -1,4 +1,6 @@
-use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
+use raw_window_handle::{
+    HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle, RawWindowHandle,
+};
 
 use shipyard::{Unique, UniqueViewMut};
 
@@ -42,6 +44,18 @@ impl WindowInfoAccessible for WinitWindowWrapper {
     }
 }
 
+unsafe impl HasRawWindowHandle for WinitWindowWrapper {
+    fn raw_window_handle(&self) -> RawWindowHandle {
+        self.0.raw_window_handle()
+    }
+}
+
+unsafe impl HasRawDisplayHandle for WinitWindowWrapper {
+    fn raw_display_handle(&self) -> RawDisplayHandle {
+        self.0.raw_display_handle()
+    }
+}
+
 #[derive(Unique)]
 pub(crate) struct UniqueWinitEvent {
     pub(crate) inner: Option<WindowEvent>,
@@ -83,15 +97,8 @@ impl Pluggable for WinitWindowPlugin {
             .build(&event_loop)
             .expect("Unable to spawn main `Winit` `Window`");
 
-        let raw_window_handle = winit_window.raw_window_handle();
-        let raw_display_handle = winit_window.raw_display_handle();
-
-        let host_window = Window::new(
-            Box::new(WinitWindowWrapper(winit_window)),
-            self.size,
-            raw_window_handle,
-            raw_display_handle,
-        );
+        let host_window =
+            Window::new(Box::new(WinitWindowWrapper(winit_window)), self.size);
 
         // Add the window as a resource; ensure the `winit_window` is kept alive.
         app.world.add_unique(host_window);
@@ -55,22 +55,32 @@ const PENTAGON_VERTICES: &[Vertex] = &[
     Vertex {
         pos: [-0.0868241, 0.49240386, 0.0],
         col: [0.5, 0.0, 0.5],
+        uv: [0.4131759, 0.00759614],
+        normal: [0.0, 0.0, 1.0],
     },
     Vertex {
         pos: [-0.49513406, 0.06958647, 0.0],
         col: [0.5, 0.0, 0.5],
+        uv: [0.00486594, 0.43041353],
+        normal: [0.0, 0.0, 1.0],
     },
     Vertex {
         pos: [-0.21918549, -0.44939706, 0.0],
         col: [0.5, 0.0, 0.5],
+        uv: [0.28081451, 0.94939706],
+        normal: [0.0, 0.0, 1.0],
     },
     Vertex {
         pos: [0.35966998, -0.3473291, 0.0],
         col: [0.5, 0.0, 0.5],
+        uv: [0.85966998, 0.8473291],
+        normal: [0.0, 0.0, 1.0],
     },
     Vertex {
         pos: [0.44147372, 0.2347359, 0.0],
         col: [0.5, 0.0, 0.5],
+        uv: [0.94147372, 0.2652641],
+        normal: [0.0, 0.0, 1.0],
     },
 ];
 
@@ -89,7 +99,12 @@ fn configure_pentagon_primitive(gpu: &AbstractGpu, a_server: &mut AssetServer) {
 
     let mesh = Mesh::new(v_buffer, i_buffer, PENTAGON_INDICES.len() as u32);
 
-    a_server.register_mesh(PENTAGON_PRIMITIVE_ID.to_owned(), mesh);
+    a_server.register_mesh(
+        PENTAGON_PRIMITIVE_ID.to_owned(),
+        mesh,
+        PENTAGON_VERTICES,
+        PENTAGON_INDICES,
+    );
 }
 
 // CUBE
@@ -107,103 +122,151 @@ const CUBE_VERTICES: &[Vertex] = &[
     Vertex {
         pos: [-1.0, -1.0, 1.0],
         col: [0.0, 0.0, 1.0],
+        uv: [0.0, 1.0],
+        normal: [0.0, 0.0, 1.0],
     },
     Vertex {
         pos: [1.0, -1.0, 1.0],
         col: [1.0, 0.0, 1.0],
+        uv: [1.0, 1.0],
+        normal: [0.0, 0.0, 1.0],
     },
     Vertex {
         pos: [1.0, 1.0, 1.0],
         col: [1.0, 1.0, 1.0],
+        uv: [1.0, 0.0],
+        normal: [0.0, 0.0, 1.0],
     },
     Vertex {
         pos: [-1.0, 1.0, 1.0],
         col: [0.0, 1.0, 1.0],
+        uv: [0.0, 0.0],
+        normal: [0.0, 0.0, 1.0],
     },
     // Bottom face.
     Vertex {
         pos: [-1.0, 1.0, -1.0],
         col: [1.0, 0.0, 1.0],
+        uv: [0.0, 1.0],
+        normal: [0.0, 0.0, -1.0],
     },
     Vertex {
         pos: [1.0, 1.0, -1.0],
         col: [0.0, 0.0, 1.0],
+        uv: [1.0, 1.0],
+        normal: [0.0, 0.0, -1.0],
     },
     Vertex {
         pos: [1.0, -1.0, -1.0],
         col: [0.0, 1.0, 1.0],
+        uv: [1.0, 0.0],
+        normal: [0.0, 0.0, -1.0],
     },
     Vertex {
         pos: [-1.0, -1.0, -1.0],
         col: [1.0, 1.0, 1.0],
+        uv: [0.0, 0.0],
+        normal: [0.0, 0.0, -1.0],
     },
     // Right face.
     Vertex {
         pos: [1.0, -1.0, -1.0],
         col: [0.0, 0.0, 1.0],
+        uv: [0.0, 1.0],
+        normal: [1.0, 0.0, 0.0],
     },
     Vertex {
         pos: [1.0, 1.0, -1.0],
         col: [1.0, 0.0, 1.0],
+        uv: [1.0, 1.0],
+        normal: [1.0, 0.0, 0.0],
     },
     Vertex {
         pos: [1.0, 1.0, 1.0],
         col: [1.0, 1.0, 1.0],
+        uv: [1.0, 0.0],
+        normal: [1.0, 0.0, 0.0],
     },
     Vertex {
         pos: [1.0, -1.0, 1.0],
         col: [0.0, 1.0, 1.0],
+        uv: [0.0, 0.0],
+        normal: [1.0, 0.0, 0.0],
     },
     // Left face.
     Vertex {
         pos: [-1.0, -1.0, 1.0],
         col: [1.0, 0.0, 1.0],
+        uv: [0.0, 1.0],
+        normal: [-1.0, 0.0, 0.0],
     },
     Vertex {
         pos: [-1.0, 1.0, 1.0],
         col: [0.0, 0.0, 1.0],
+        uv: [1.0, 1.0],
+        normal: [-1.0, 0.0, 0.0],
     },
     Vertex {
         pos: [-1.0, 1.0, -1.0],
         col: [0.0, 1.0, 1.0],
+        uv: [1.0, 0.0],
+        normal: [-1.0, 0.0, 0.0],
     },
     Vertex {
         pos: [-1.0, -1.0, -1.0],
         col: [1.0, 1.0, 1.0],
+        uv: [0.0, 0.0],
+        normal: [-1.0, 0.0, 0.0],
     },
     // Front face.
     Vertex {
         pos: [1.0, 1.0, -1.0],
         col: [1.0, 0.0, 1.0],
+        uv: [0.0, 1.0],
+        normal: [0.0, 1.0, 0.0],
     },
     Vertex {
         pos: [-1.0, 1.0, -1.0],
         col: [0.0, 0.0, 1.0],
+        uv: [1.0, 1.0],
+        normal: [0.0, 1.0, 0.0],
     },
     Vertex {
         pos: [-1.0, 1.0, 1.0],
         col: [0.0, 1.0, 1.0],
+        uv: [1.0, 0.0],
+        normal: [0.0, 1.0, 0.0],
     },
     Vertex {
         pos: [1.0, 1.0, 1.0],
         col: [1.0, 1.0, 1.0],
+        uv: [0.0, 0.0],
+        normal: [0.0, 1.0, 0.0],
     },
     // Back face.
     Vertex {
         pos: [1.0, -1.0, 1.0],
         col: [0.0, 0.0, 1.0],
+        uv: [0.0, 1.0],
+        normal: [0.0, -1.0, 0.0],
     },
     Vertex {
         pos: [-1.0, -1.0, 1.0],
         col: [1.0, 0.0, 1.0],
+        uv: [1.0, 1.0],
+        normal: [0.0, -1.0, 0.0],
     },
     Vertex {
         pos: [-1.0, -1.0, -1.0],
         col: [1.0, 1.0, 1.0],
+        uv: [1.0, 0.0],
+        normal: [0.0, -1.0, 0.0],
     },
     Vertex {
         pos: [1.0, -1.0, -1.0],
         col: [0.0, 1.0, 1.0],
+        uv: [0.0, 0.0],
+        normal: [0.0, -1.0, 0.0],
     },
 ];
 
@@ -229,7 +292,12 @@ fn configure_cube_primitive(gpu: &AbstractGpu, a_server: &mut AssetServer) {
 
     let mesh = Mesh::new(v_buffer, i_buffer, CUBE_INDICES.len() as u32);
 
-    a_server.register_mesh(CUBE_PRIMITIVE_ID.to_owned(), mesh);
+    a_server.register_mesh(
+        CUBE_PRIMITIVE_ID.to_owned(),
+        mesh,
+        CUBE_VERTICES,
+        CUBE_INDICES,
+    );
 }
 
 // SPHERE
@@ -280,7 +348,12 @@ fn configure_sphere_primitive(gpu: &AbstractGpu, a_server: &mut AssetServer) {
 
     let mesh = Mesh::new(v_buffer, i_buffer, indices.len() as u32);
 
-    a_server.register_mesh(SPHERE_PRIMITIVE_ID.to_owned(), mesh);
+    a_server.register_mesh(
+        SPHERE_PRIMITIVE_ID.to_owned(),
+        mesh,
+        &vertices,
+        &indices,
+    );
 }
 
 fn face(dir: &Vector3<f32>, resolution: usize) -> (Vec<Vertex>, Vec<u16>) {
@@ -288,7 +361,9 @@ fn face(dir: &Vector3<f32>, resolution: usize) -> (Vec<Vertex>, Vec<u16>) {
     let mut vertices = vec![
         Vertex {
             pos: [0.0, 0.0, 0.0],
-            col: [0.0, 0.0, 0.0]
+            col: [0.0, 0.0, 0.0],
+            uv: [0.0, 0.0],
+            normal: [0.0, 0.0, 0.0],
         };
         resolution.pow(2)
     ];
@@ -315,6 +390,9 @@ fn face(dir: &Vector3<f32>, resolution: usize) -> (Vec<Vertex>, Vec<u16>) {
             vertices[i] = Vertex {
                 pos: [position3.x, position3.y, position3.z],
                 col: [position3.x, position3.y, position3.z],
+                uv: [position.x, position.y],
+                // The sphere has radius 1 so the position is the normal.
+                normal: [position3.x, position3.y, position3.z],
             };
 
             if x < resolution - 1 && y < resolution - 1 {
@@ -352,18 +430,26 @@ const PLANE_VERTICES: &[Vertex] = &[
     Vertex {
         pos: [-1.0, 0.0, 1.0],
         col: [0.0, 0.0, 1.0],
+        uv: [0.0, 1.0],
+        normal: [0.0, 1.0, 0.0],
     },
     Vertex {
         pos: [1.0, 0.0, 1.0],
         col: [1.0, 0.0, 1.0],
+        uv: [1.0, 1.0],
+        normal: [0.0, 1.0, 0.0],
     },
     Vertex {
         pos: [1.0, 0.0, -1.0],
         col: [1.0, 1.0, 1.0],
+        uv: [1.0, 0.0],
+        normal: [0.0, 1.0, 0.0],
     },
     Vertex {
         pos: [-1.0, 0.0, -1.0],
         col: [0.0, 1.0, 1.0],
+        uv: [0.0, 0.0],
+        normal: [0.0, 1.0, 0.0],
     },
 ];
 
@@ -382,7 +468,12 @@ fn configure_plane_primitive(gpu: &AbstractGpu, a_server: &mut AssetServer) {
 
     let mesh = Mesh::new(v_buffer, i_buffer, PLANE_INDICES.len() as u32);
 
-    a_server.register_mesh(PLANE_PRIMITIVE_ID.to_owned(), mesh);
+    a_server.register_mesh(
+        PLANE_PRIMITIVE_ID.to_owned(),
+        mesh,
+        PLANE_VERTICES,
+        PLANE_INDICES,
+    );
 }
 
 // CONE
@@ -410,14 +501,21 @@ fn configure_cone_primitive(gpu: &AbstractGpu, a_server: &mut AssetServer) {
     );
 
     let mesh = Mesh::new(v_buffer, i_buffer, indices.len() as u32);
-    a_server.register_mesh(CONE_PRIMITIVE_ID.to_owned(), mesh)
+    a_server.register_mesh(
+        CONE_PRIMITIVE_ID.to_owned(),
+        mesh,
+        &vertices,
+        &indices,
+    )
 }
 
 fn generate_cone_mesh(resolution: usize) -> (Vec<Vertex>, Vec<u16>) {
     let mut vertices = vec![
         Vertex {
             pos: [0.0, 1.0, 0.0],
-            col: [0.0, 0.0, 0.0]
+            col: [0.0, 0.0, 0.0],
+            uv: [0.5, 0.5],
+            normal: [0.0, 1.0, 0.0],
         };
         resolution + 1
     ];
@@ -435,6 +533,10 @@ fn generate_cone_mesh(resolution: usize) -> (Vec<Vertex>, Vec<u16>) {
         vertices[i] = Vertex {
             pos: [x, -1.0, y],
             col: [x, 0.0, y],
+            uv: [x * 0.5 + 0.5, y * 0.5 + 0.5],
+            // The base vertices are shared with the sides, the normal is an
+            // average of both.
+            normal: side_normal(x, y, 0.5),
         };
 
         indices[index_cout] = 0;
@@ -490,14 +592,21 @@ fn configure_cylinder_primitive(gpu: &AbstractGpu, a_server: &mut AssetServer) {
     );
 
     let mesh = Mesh::new(v_buffer, i_buffer, indices.len() as u32);
-    a_server.register_mesh(CYLINDER_PRIMITIVE_ID.to_owned(), mesh)
+    a_server.register_mesh(
+        CYLINDER_PRIMITIVE_ID.to_owned(),
+        mesh,
+        &vertices,
+        &indices,
+    )
 }
 
 fn generate_cylinder_mesh(resolution: usize) -> (Vec<Vertex>, Vec<u16>) {
     let mut vertices = vec![
         Vertex {
             pos: [0.0, 0.0, 0.0],
-            col: [0.0, 0.0, 0.0]
+            col: [0.0, 0.0, 0.0],
+            uv: [0.0, 0.0],
+            normal: [0.0, 0.0, 0.0],
         };
         resolution * 2
     ];
@@ -518,12 +627,16 @@ fn generate_cylinder_mesh(resolution: usize) -> (Vec<Vertex>, Vec<u16>) {
         vertices[top_index] = Vertex {
             pos: [x, 1.0, y],
             col: [x, 0.0, y],
+            uv: [i as f32 / resolution as f32, 0.0],
+            normal: side_normal(x, y, 0.0),
         };
 
         // BOTTOM
         vertices[bottom_index] = Vertex {
             pos: [x, -1.0, y],
             col: [x, 0.0, y],
+            uv: [i as f32 / resolution as f32, 1.0],
+            normal: side_normal(x, y, 0.0),
         };
 
         // SIDE
@@ -561,3 +674,10 @@ fn generate_cylinder_mesh(resolution: usize) -> (Vec<Vertex>, Vec<u16>) {
 
     (vertices, indices)
 }
+
+/// Returns the normal of a vertex placed in the side of a cone or cylinder at
+/// `(x, z)`, `y` tilts the normal upwards.
+fn side_normal(x: f32, z: f32, y: f32) -> [f32; 3] {
+    let normal = Vector3::new(x, y, z).normalize();
+    [normal.x, normal.y, normal.z]
+}
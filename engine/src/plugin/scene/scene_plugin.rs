@@ -1,13 +1,28 @@
-use ahash::AHashMap;
+use ahash::{AHashMap, AHashSet};
 
 use shipyard::{Unique, UniqueView, UniqueViewMut, World};
 
 use crate::{
     app::App,
     graphics::{
-        camera::CameraUniform,
+        camera::{
+            camera_buffer_size, camera_offset, sync_scene_views_system,
+            CameraUniform, SceneView,
+        },
         gpu::AbstractGpu,
-        scene::{sync_main_scene_dynamic_entities_transform, Scene},
+        lights::{
+            lights_buffer_size, shadow_casters_buffer_size, shadow_maps_count,
+            sync_scene_lights_system, SHADOW_MAP_SIZE,
+        },
+        post_processing::{
+            post_process_buffer_size, sync_post_process_params_system,
+        },
+        scene::{
+            sync_main_scene_dynamic_entities_transform, CullingStats,
+            EnvironmentMaps, EnvironmentUniform, Scene, SceneTextures,
+            IRRADIANCE_MAP_SIZE, PREFILTERED_MAP_MIP_LEVELS,
+            PREFILTERED_MAP_SIZE,
+        },
         Texture, UniformBuffer,
     },
     host::window::Window,
@@ -18,7 +33,7 @@ use crate::{
             keyboard::Keyboard,
             mouse::{Cursor, CursorDelta, Mouse, MouseWheelDelta, MouseWheelStepDelta},
         },
-        scene::SceneDescriptor,
+        scene::{AntiAliasing, SceneDescriptor},
         scene_state::SceneState,
     },
     schedule::Schedule,
@@ -66,12 +81,63 @@ impl Pluggable for ScenePlugin {
         });
 
         app.schedule(Schedule::Update, |world| {
+            world.run(reload_changed_assets_system);
+            world.run(evict_unreferenced_assets_system);
+            world.run(sync_scene_views_system);
             world.run(sync_scene_cameras_with_their_uniforms_system);
+            world.run(sync_scene_lights_system);
+            world.run(sync_post_process_params_system);
             world.run(sync_main_scene_dynamic_entities_transform);
         });
     }
 }
 
+/// Reloads the assets whose source files changed on disk.
+fn reload_changed_assets_system(mut asset_server: UniqueViewMut<AssetServer>) {
+    asset_server.reload_changed_assets();
+}
+
+/// Unloads the assets without handles which are not used by any `Scene`.
+fn evict_unreferenced_assets_system(
+    mut asset_server: UniqueViewMut<AssetServer>,
+    scene_state: UniqueView<SceneState>,
+) {
+    let scenes = || {
+        std::iter::once(&scene_state.main)
+            .chain(scene_state.sub_scenes.values())
+    };
+
+    // The textures referenced by a material are in use as long as the
+    // material exists.
+    let material_textures = asset_server
+        .data
+        .read()
+        .expect("Unable to acquire read lock")
+        .materials
+        .values()
+        .flat_map(|m| m.textures())
+        .map(|t| t.0.clone())
+        .collect::<AHashSet<_>>();
+
+    // Only the batches used by an entity in the last frame count, the empty
+    // ones are kept for a while in case the entities come back.
+    asset_server.evict_unreferenced_assets(|id| {
+        material_textures.contains(id)
+            || scenes().any(|s| {
+                s.mesh_transform_buffers
+                    .iter()
+                    .filter(|(_, buffer)| buffer.is_used())
+                    .any(|((mesh, material, _), _)| {
+                        mesh.as_str() == id || material.as_str() == id
+                    })
+                    || s.post_processing
+                        .iter()
+                        .filter_map(|e| e.texture())
+                        .any(|t| t.as_str() == id)
+            })
+    });
+}
+
 /// Takes all the descriptors provided by the user and transform them in actual
 /// scenes.
 fn allocate_scenes(world: &World) {
@@ -89,28 +155,79 @@ fn allocate_scenes(world: &World) {
     let sub_scenes = &descriptors.sub_scenes;
 
     for scene_d in sub_scenes.iter().chain(std::iter::once(main)) {
-        let (camera_buffer, target_texture, depth_texture) =
-            allocate_scene_main_resources(&gpu, scene_d);
+        let anti_aliasing =
+            supported_anti_aliasing(&gpu, scene_d.anti_aliasing);
+        let (camera_buffer, textures) =
+            allocate_scene_main_resources(&gpu, scene_d, anti_aliasing);
 
-        let sky_texture = if scene_d.should_render_sky {
-            Some(allocate_sky_resources(&gpu))
+        let (sky_texture, environment_maps) = if scene_d.should_render_sky {
+            let (sky, environment) = allocate_sky_resources(&gpu);
+            (Some(sky), Some(environment))
         } else {
             //Some(allocate_sky_resources(&gpu))
-            None
+            (None, None)
         };
 
+        let lights_buffer = gpu.allocate_storage_buffer(
+            format!("{} lights buffer", scene_d.label).as_str(),
+            lights_buffer_size(&scene_d.light_limits),
+        );
+
+        let shadow_maps = gpu.allocate_shadow_maps_texture(
+            format!("{} shadow maps", scene_d.label).as_str(),
+            SHADOW_MAP_SIZE,
+            shadow_maps_count(&scene_d.light_limits),
+        );
+
+        let shadow_casters_buffer = gpu.allocate_storage_buffer(
+            format!("{} shadow casters buffer", scene_d.label).as_str(),
+            shadow_casters_buffer_size(&scene_d.light_limits),
+        );
+
+        let post_process_buffer = gpu.allocate_uniform_buffer(
+            format!("{} post process buffer", scene_d.label).as_str(),
+            &vec![0; post_process_buffer_size() as usize],
+        );
+
         let scene = Scene {
             label: scene_d.label.clone(),
             camera: scene_d.camera,
             projection: scene_d.projection,
+            ambient_color: scene_d.ambient_color,
+            debug_view: scene_d.debug_view,
+            views: vec![SceneView::full(scene_d.camera, scene_d.projection)],
             camera_buffer,
             mesh_transform_buffers: AHashMap::new(),
-            target_texture,
-            depth_texture,
+            culling_stats: CullingStats::default(),
+            lod_states: AHashMap::new(),
+            pick_targets: Vec::new(),
+            outline_targets: Vec::new(),
+            transparent_draws: Vec::new(),
+            target_texture: textures.target,
+            depth_texture: textures.depth,
+            anti_aliasing,
+            color_texture: textures.color,
+            post_process_textures: textures.post_process,
+            bloom_textures: Vec::new(),
+            post_process_buffer,
+            post_process_bind_groups: None,
+            post_processing: scene_d.post_processing.clone(),
+            fxaa_texture: textures.fxaa,
+            fxaa_bind_group: None,
             should_sync_resolution_to_window: scene_d.resolution.is_none(),
             camera_bind_group: None,
+            light_limits: scene_d.light_limits,
+            lights_buffer,
+            lights_bind_group: None,
             sky_texture,
             sky_env_bind_group: None,
+            environment_maps,
+            environment_bind_group: None,
+            shadow_distance: scene_d.shadow_distance,
+            shadow_maps,
+            shadow_casters_buffer,
+            shadow_maps_in_use: 0,
+            shadow_caster_bind_group: None,
         };
 
         scenes_finished.insert(scene_d.id.clone(), scene);
@@ -126,29 +243,20 @@ fn allocate_scenes(world: &World) {
 }
 
 /// If there was any change in any of the camera`Scene`s the GPU buffer must be
-/// updated to refect the changes.
+/// updated to refect the changes. Each view of a scene has its own uniform.
 fn sync_scene_cameras_with_their_uniforms_system(
     gpu: UniqueView<AbstractGpu>,
     s_state: UniqueView<SceneState>,
 ) {
-    let uniform = CameraUniform::view_proj(
-        &s_state.main.camera,
-        &s_state.main.projection,
-    );
-
-    gpu.write_uniform_buffer(
-        &s_state.main.camera_buffer,
-        0,
-        bytemuck::cast_slice(&[uniform]),
-    );
-
-    for s in s_state.sub_scenes.values() {
-        let uniform = CameraUniform::view_proj(&s.camera, &s.projection);
-        gpu.write_uniform_buffer(
-            &s.camera_buffer,
-            0,
-            bytemuck::cast_slice(&[uniform]),
-        );
+    for s in std::iter::once(&s_state.main).chain(s_state.sub_scenes.values()) {
+        for (i, view) in s.views.iter().enumerate() {
+            let uniform = CameraUniform::view_proj(view, s.debug_view);
+            gpu.write_uniform_buffer(
+                &s.camera_buffer,
+                camera_offset(i) as u64,
+                bytemuck::cast_slice(&[uniform]),
+            );
+        }
     }
 }
 
@@ -157,45 +265,63 @@ fn sync_scene_cameras_with_their_uniforms_system(
 fn allocate_scene_main_resources(
     gpu: &AbstractGpu,
     scene: &SceneDescriptor,
-) -> (Box<dyn UniformBuffer>, Box<dyn Texture>, Box<dyn Texture>) {
-    let uniform = CameraUniform::view_proj(&scene.camera, &scene.projection);
-
+    anti_aliasing: AntiAliasing,
+) -> (Box<dyn UniformBuffer>, SceneTextures) {
+    // The uniforms are written once the views of the scene are known.
     let camera_buffer = gpu.allocate_uniform_buffer(
         format!("{} Camera Buffer", scene.label).as_str(),
-        bytemuck::cast_slice(&[uniform]),
+        &vec![0; camera_buffer_size() as usize],
     );
 
     // TODO(Angel): Determine how we are going to handle resolution for sub
     // scenes.
-    let target_texture = gpu.allocate_target_texture(
-        format!("{} scene target texture", scene.label).as_ref(),
-        scene
-            .resolution
-            .map(|s| s.width)
-            .unwrap_or(gpu.surface_size().width),
-        scene
-            .resolution
-            .map(|s| s.height)
-            .unwrap_or(gpu.surface_size().height),
+    let textures = SceneTextures::allocate(
+        gpu,
+        &scene.label,
+        scene.resolution.unwrap_or(gpu.surface_size()),
+        anti_aliasing,
     );
 
-    let depth_texture = gpu.allocate_depth_texture(
-        format!("{} scene depth texture", scene.id).as_ref(),
-        scene
-            .resolution
-            .map(|s| s.width)
-            .unwrap_or(gpu.surface_size().width),
-        scene
-            .resolution
-            .map(|s| s.height)
-            .unwrap_or(gpu.surface_size().height),
-    );
+    (camera_buffer, textures)
+}
 
-    (camera_buffer, target_texture, depth_texture)
+/// Reduces the MSAA sample count to the highest one supported by the GPU.
+fn supported_anti_aliasing(
+    gpu: &AbstractGpu,
+    anti_aliasing: AntiAliasing,
+) -> AntiAliasing {
+    match anti_aliasing {
+        AntiAliasing::Off | AntiAliasing::Fxaa => anti_aliasing,
+        msaa => AntiAliasing::from_sample_count(
+            gpu.supported_sample_count(msaa.sample_count()),
+        ),
+    }
 }
 
-/// Allocate the required resources to render the sky.
-fn allocate_sky_resources(gpu: &AbstractGpu) -> Box<dyn Texture> {
+/// Allocate the required resources to render the sky and light the scene
+/// with it.
+fn allocate_sky_resources(
+    gpu: &AbstractGpu,
+) -> (Box<dyn Texture>, EnvironmentMaps) {
     // TODO(Angel): Change the 1080 resolution.
-    gpu.allocate_cubemap_texture("Sky cubemap", 1080)
+    let sky = gpu.allocate_cubemap_texture("Sky cubemap", 1080);
+
+    let environment = EnvironmentMaps {
+        irradiance: gpu.allocate_environment_cubemap_texture(
+            "Irradiance cubemap",
+            IRRADIANCE_MAP_SIZE,
+            1,
+        ),
+        prefiltered: gpu.allocate_environment_cubemap_texture(
+            "Prefiltered cubemap",
+            PREFILTERED_MAP_SIZE,
+            PREFILTERED_MAP_MIP_LEVELS,
+        ),
+        uniform: gpu.allocate_uniform_buffer(
+            "Environment uniform",
+            bytemuck::bytes_of(&EnvironmentUniform::new(false)),
+        ),
+    };
+
+    (sky, environment)
 }
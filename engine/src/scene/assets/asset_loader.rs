@@ -1,21 +1,21 @@
-use crate::types::Size;
+use crate::graphics::texture::TextureData;
 
 use super::model::Model;
 
 #[derive(Default)]
 pub struct AssetLoader {
-    pub(crate) texture_to_load: Vec<(String, Vec<u8>, Size<u32>)>,
+    pub(crate) texture_to_load: Vec<(String, TextureData)>,
     pub(crate) models_to_load: Vec<(String, Model)>,
 }
 
 impl AssetLoader {
     /// Logs a texture to be loaded.
-    pub fn load_texture(&mut self, id: String, data: Vec<u8>, size: Size<u32>) {
-        self.texture_to_load.push((id, data, size));
+    pub fn load_texture(&mut self, id: String, texture: TextureData) {
+        self.texture_to_load.push((id, texture));
     }
 
     /// Logs a model to be loaded.
     pub fn load_model(&mut self, id: String, model: Model) {
         self.models_to_load.push((id, model));
     }
-}
\ No newline at end of file
+}
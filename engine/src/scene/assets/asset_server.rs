@@ -1,40 +1,153 @@
 use std::{
     collections::HashMap,
+    env, fs, io,
+    path::{Path, PathBuf},
     sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant, SystemTime},
 };
 
 use ahash::AHashMap;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
 use shipyard::Unique;
 
-use crate::graphics::{gpu::AbstractGpu, mesh::Mesh, Texture};
+use crate::graphics::{
+    culling::MeshBounds, gpu::AbstractGpu, mesh::Mesh, picking::MeshGeometry,
+    vertex::Vertex, Texture,
+};
+
+use super::{
+    asset_loader::AssetLoader,
+    handle::{Handle, LoadState},
+    import::{import_model, import_texture, ImportSettings},
+    lod::{generated_lod_group, generated_lod_id, LodGroup},
+    manifest::{AssetManifest, AssetManifestError},
+    material::{Material, DEFAULT_MATERIAL_ID},
+    model::Model,
+    pack::{AssetPack, AssetPackError, PackData},
+    placeholder::{PLACEHOLDER_MESH_ID, PLACEHOLDER_TEXTURE_ID},
+    task_pool::AssetTaskPool,
+    AssetResourceID, MaterialResourceID, MeshResourceID, TextureResourceID,
+};
 
-use super::{asset_loader::AssetLoader, AssetResourceID, MeshResourceID};
+/// Time between each check of the source files of the assets.
+const HOT_RELOAD_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Time between each search of unreferenced assets.
+const EVICTION_CHECK_INTERVAL: Duration = Duration::from_secs(1);
 
 /// Conatins all the assets which a `Scene` can use.
 #[derive(Unique)]
 pub struct AssetServer {
     pub data: Arc<RwLock<AssetServerData>>,
     pub loader: Arc<Mutex<AssetLoader>>,
+    /// Determines if the assets loaded from files must be reloaded when the
+    /// file changes on disk, only the development builds do it by default.
+    pub hot_reload: bool,
+    /// Time an asset must remain unreferenced before it is evicted, `None`
+    /// disables the automatic eviction.
+    pub eviction_grace_period: Option<Duration>,
+    /// Contains the threads used to decode the files from disk.
+    task_pool: AssetTaskPool,
+    /// Contains the last time the source files were checked.
+    last_hot_reload_poll: Instant,
+    /// Contains the last time the unreferenced assets were searched.
+    last_eviction_check: Instant,
 }
 
 impl Default for AssetServer {
     fn default() -> Self {
+        let mut data = AssetServerData::default();
+        data.materials
+            .insert(DEFAULT_MATERIAL_ID.to_owned(), Arc::default());
+
         Self {
-            data: Arc::new(RwLock::new(AssetServerData::default())),
+            data: Arc::new(RwLock::new(data)),
             loader: Arc::new(Mutex::new(AssetLoader::default())),
+            hot_reload: cfg!(debug_assertions),
+            eviction_grace_period: Some(Duration::from_secs(10)),
+            task_pool: AssetTaskPool::default(),
+            last_hot_reload_poll: Instant::now(),
+            last_eviction_check: Instant::now(),
         }
     }
 }
 
 impl AssetServer {
-    /// Retrieves a particular `Mesh`.
+    /// Retrieves a particular `Mesh`. If the mesh is not loaded yet the
+    /// placeholder mesh is returned instead.
     pub fn load_mesh(&self, mesh: &MeshResourceID) -> Arc<Mesh> {
+        let data = self.data.read().expect("Unable to acquire read lock");
+
+        data.meshes
+            .get(&mesh.0)
+            .or_else(|| data.meshes.get(PLACEHOLDER_MESH_ID))
+            .expect("Mesh not found and there is no placeholder mesh")
+            .clone()
+    }
+
+    /// Retrieves the bounds of a particular `Mesh`, the ones of the
+    /// placeholder mesh if it is not loaded yet.
+    pub fn mesh_bounds(&self, mesh: &MeshResourceID) -> Option<MeshBounds> {
+        let data = self.data.read().expect("Unable to acquire read lock");
+
+        data.mesh_bounds
+            .get(&mesh.0)
+            .or_else(|| data.mesh_bounds.get(PLACEHOLDER_MESH_ID))
+            .copied()
+    }
+
+    /// Retrieves the triangles of a particular `Mesh`, the ones of the
+    /// placeholder mesh if it is not loaded yet.
+    pub fn mesh_geometry(
+        &self,
+        mesh: &MeshResourceID,
+    ) -> Option<Arc<MeshGeometry>> {
+        let data = self.data.read().expect("Unable to acquire read lock");
+
+        data.mesh_geometries
+            .get(&mesh.0)
+            .or_else(|| data.mesh_geometries.get(PLACEHOLDER_MESH_ID))
+            .cloned()
+    }
+
+    /// Retrieves the levels of detail of a particular `Mesh`, `None` if it
+    /// doesn't have them.
+    pub fn lod_group(&self, mesh: &MeshResourceID) -> Option<LodGroup> {
         self.data
             .read()
             .expect("Unable to acquire read lock")
-            .meshes
+            .lod_groups
             .get(&mesh.0)
-            .expect("Mesh not found")
+            .cloned()
+    }
+
+    /// Retrieves a particular `Texture`. If the texture is not loaded yet the
+    /// placeholder texture is returned instead.
+    pub fn load_texture(
+        &self,
+        texture: &TextureResourceID,
+    ) -> Arc<dyn Texture> {
+        let data = self.data.read().expect("Unable to acquire read lock");
+
+        data.textures
+            .get(&texture.0)
+            .or_else(|| data.textures.get(PLACEHOLDER_TEXTURE_ID))
+            .expect("Texture not found and there is no placeholder texture")
+            .clone()
+    }
+
+    /// Retrieves a particular `Material`. If the material does not exist the
+    /// default material is returned instead.
+    pub fn load_material(
+        &self,
+        material: &MaterialResourceID,
+    ) -> Arc<Material> {
+        let data = self.data.read().expect("Unable to acquire read lock");
+
+        data.materials
+            .get(&material.0)
+            .or_else(|| data.materials.get(DEFAULT_MATERIAL_ID))
+            .expect("Material not found and there is no default material")
             .clone()
     }
 
@@ -45,30 +158,201 @@ impl AssetServer {
             .expect("Unable to acquire read lock")
             .meshes
             .keys()
+            .filter(|k| k.as_str() != PLACEHOLDER_MESH_ID)
             .map(|k| MeshResourceID(k.clone()))
             .collect()
     }
+
+    /// Returns a list of IDs for all the materials in the server.
+    pub fn materials(&self) -> Vec<MaterialResourceID> {
+        self.data
+            .read()
+            .expect("Unable to acquire read lock")
+            .materials
+            .keys()
+            .map(|k| MaterialResourceID(k.clone()))
+            .collect()
+    }
+
+    /// Returns the state of the asset associated with the provided id, `None`
+    /// if the asset was never requested or registered.
+    pub fn load_state(&self, id: &str) -> Option<LoadState> {
+        self.data
+            .read()
+            .expect("Unable to acquire read lock")
+            .load_states
+            .get(id)
+            .cloned()
+    }
+
+    /// Returns the number of times the asset was registered, it changes
+    /// every time the asset is reloaded. Useful to know if any resource
+    /// derived from the asset must be recreated.
+    pub fn generation(&self, id: &str) -> u32 {
+        self.data
+            .read()
+            .expect("Unable to acquire read lock")
+            .generations
+            .get(id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns the GPU memory used by each type of asset.
+    pub fn memory_report(&self) -> AssetMemoryReport {
+        let data = self.data.read().expect("Unable to acquire read lock");
+
+        let meshes = data
+            .meshes
+            .iter()
+            .filter(|(id, _)| id.as_str() != PLACEHOLDER_MESH_ID)
+            .map(|(_, mesh)| mesh.size_in_bytes());
+
+        let textures = data
+            .textures
+            .iter()
+            .filter(|(id, _)| id.as_str() != PLACEHOLDER_TEXTURE_ID)
+            .map(|(_, texture)| texture.size_in_bytes());
+
+        AssetMemoryReport {
+            meshes: AssetTypeMemory::from_sizes(meshes),
+            textures: AssetTypeMemory::from_sizes(textures),
+        }
+    }
+}
+
+/// Contains the GPU memory used by the assets in the `AssetServer`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AssetMemoryReport {
+    pub meshes: AssetTypeMemory,
+    pub textures: AssetTypeMemory,
+}
+
+impl AssetMemoryReport {
+    /// Returns the number of bytes used by all the assets.
+    pub fn total_bytes(&self) -> u64 {
+        self.meshes.bytes + self.textures.bytes
+    }
 }
 
-/// Shipyard requires that `AssetServe` can be used from different threads. The
-/// `AssetServerDat` must be protected.
-unsafe impl Send for AssetServer {}
-unsafe impl Sync for AssetServer {}
+/// Contains the GPU memory used by one type of asset.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AssetTypeMemory {
+    /// Number of assets of the type.
+    pub count: usize,
+    /// Number of bytes used by all the assets of the type.
+    pub bytes: u64,
+}
+
+impl AssetTypeMemory {
+    fn from_sizes(sizes: impl Iterator<Item = u64>) -> Self {
+        sizes.fold(Self::default(), |acc, bytes| Self {
+            count: acc.count + 1,
+            bytes: acc.bytes + bytes,
+        })
+    }
+}
 
 #[derive(Default)]
 pub struct AssetServerData {
     pub meshes: HashMap<AssetResourceID, Arc<Mesh>>,
-    pub textures: AHashMap<AssetResourceID, Box<dyn Texture>>,
+    /// Contains the bounds of each mesh in its local space.
+    pub mesh_bounds: AHashMap<AssetResourceID, MeshBounds>,
+    /// Contains the triangles of each mesh, used to pick the entities.
+    pub mesh_geometries: AHashMap<AssetResourceID, Arc<MeshGeometry>>,
+    /// Contains the levels of detail of the meshes which have them.
+    pub lod_groups: AHashMap<AssetResourceID, LodGroup>,
+    pub textures: AHashMap<AssetResourceID, Arc<dyn Texture>>,
+    pub materials: AHashMap<AssetResourceID, Arc<Material>>,
+    /// Contains the state of each asset requested or registered.
+    pub load_states: AHashMap<AssetResourceID, LoadState>,
+    /// Contains the files from where the assets were loaded.
+    pub sources: AHashMap<AssetResourceID, AssetSource>,
+    /// Contains how many times each asset was registered.
+    pub generations: AHashMap<AssetResourceID, u32>,
+    /// Contains the counter shared with the `Handle`s of each asset. Only the
+    /// assets with a counter are evicted automatically.
+    ref_counts: AHashMap<AssetResourceID, Arc<()>>,
+    /// Contains when each asset stopped being referenced.
+    unreferenced_since: AHashMap<AssetResourceID, Instant>,
+}
+
+impl AssetServerData {
+    /// Returns a new `Handle` to the asset, the asset starts being tracked if
+    /// it was not.
+    fn handle<T: ?Sized>(&mut self, id: AssetResourceID) -> Handle<T> {
+        let ref_count = self.ref_counts.entry(id.clone()).or_default().clone();
+        self.unreferenced_since.remove(&id);
+
+        Handle::new(id, ref_count)
+    }
+
+    /// Returns `true` if at least one `Handle` to the asset is alive.
+    fn has_handles(&self, id: &str) -> bool {
+        self.ref_counts
+            .get(id)
+            // The server keeps one reference.
+            .map(|r| Arc::strong_count(r) > 1)
+            .unwrap_or(false)
+    }
+}
+
+/// The kind of asset generated from a source file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AssetKind {
+    Mesh,
+    Texture,
+}
+
+/// Describes the file used to load an asset.
+#[derive(Clone, Debug)]
+pub struct AssetSource {
+    pub path: PathBuf,
+    pub kind: AssetKind,
+    pub settings: ImportSettings,
+    /// Contains the last modification time seen for the file.
+    modified: Option<SystemTime>,
+}
+
+impl AssetSource {
+    fn new(path: PathBuf, kind: AssetKind, settings: ImportSettings) -> Self {
+        let modified = modification_time(&path);
+
+        Self {
+            path,
+            kind,
+            settings,
+            modified,
+        }
+    }
+}
+
+/// Contains the handles of all the assets requested from a manifest or pack.
+#[derive(Default)]
+pub struct LoadedAssets {
+    pub meshes: AHashMap<AssetResourceID, Handle<Mesh>>,
+    pub textures: AHashMap<AssetResourceID, Handle<dyn Texture>>,
 }
 
 impl AssetServer {
-    /// Registers a mesh into the Asset Server.
-    pub fn register_mesh(&mut self, id: AssetResourceID, mesh: Mesh) {
-        self.data
-            .write()
-            .expect("Unable to acquire write lock")
-            .meshes
-            .insert(id, Arc::new(mesh));
+    /// Registers a mesh into the Asset Server, the vertices and the indices
+    /// are used to compute its bounds and to pick it.
+    pub fn register_mesh(
+        &mut self,
+        id: AssetResourceID,
+        mesh: Mesh,
+        vertices: &[Vertex],
+        indices: &[u16],
+    ) {
+        let mut data = self.data.write().expect("Unable to acquire write lock");
+
+        data.load_states.insert(id.clone(), LoadState::Loaded);
+        *data.generations.entry(id.clone()).or_default() += 1;
+        data.mesh_bounds
+            .insert(id.clone(), MeshBounds::from_vertices(vertices));
+        data.mesh_geometries
+            .insert(id.clone(), Arc::new(MeshGeometry::new(vertices, indices)));
+        data.meshes.insert(id, Arc::new(mesh));
     }
 
     /// Registers a custom mesh into the server.
@@ -92,18 +376,491 @@ impl AssetServer {
         self.register_mesh(
             id,
             Mesh::new(v_buffer, i_buffer, indices.len() as u32),
+            &vertices
+                .chunks_exact(std::mem::size_of::<Vertex>())
+                .map(bytemuck::pod_read_unaligned)
+                .collect::<Vec<Vertex>>(),
+            &indices
+                .chunks_exact(std::mem::size_of::<u16>())
+                .map(bytemuck::pod_read_unaligned)
+                .collect::<Vec<u16>>(),
         )
     }
 
+    /// Registers the levels of detail used to draw a mesh, replacing the
+    /// previous ones.
+    pub fn register_lod_group(
+        &mut self,
+        mesh: MeshResourceID,
+        group: LodGroup,
+    ) {
+        self.data
+            .write()
+            .expect("Unable to acquire write lock")
+            .lod_groups
+            .insert(mesh.0, group);
+    }
+
     pub fn register_texture(
         &mut self,
         id: AssetResourceID,
         texture: Box<dyn Texture>,
     ) {
+        let mut data = self.data.write().expect("Unable to acquire write lock");
+
+        data.load_states.insert(id.clone(), LoadState::Loaded);
+        *data.generations.entry(id.clone()).or_default() += 1;
+        data.textures.insert(id, Arc::from(texture));
+    }
+
+    /// Marks an asset whose data could not be uploaded to the GPU as failed.
+    pub(crate) fn register_failure(
+        &mut self,
+        id: AssetResourceID,
+        reason: String,
+    ) {
+        warn!("Unable to load {}: {}", id, reason);
+
         self.data
             .write()
             .expect("Unable to acquire write lock")
-            .textures
-            .insert(id, texture);
+            .load_states
+            .insert(id, LoadState::Failed(reason));
     }
+
+    /// Registers a material into the server, if a material with the same id
+    /// already exists it is replaced.
+    pub fn register_material(
+        &mut self,
+        id: AssetResourceID,
+        material: Material,
+    ) {
+        let mut data = self.data.write().expect("Unable to acquire write lock");
+
+        data.load_states.insert(id.clone(), LoadState::Loaded);
+        *data.generations.entry(id.clone()).or_default() += 1;
+        data.materials.insert(id, Arc::new(material));
+    }
+
+    /// Removes the asset from the server, the GPU memory is released once
+    /// nothing else uses it. Returns `false` if there was no asset to unload.
+    ///
+    /// The placeholder assets and the default material cannot be unloaded.
+    pub fn unload(&mut self, id: &str) -> bool {
+        if id == PLACEHOLDER_MESH_ID
+            || id == PLACEHOLDER_TEXTURE_ID
+            || id == DEFAULT_MATERIAL_ID
+        {
+            return false;
+        }
+
+        let mut data = self.data.write().expect("Unable to acquire write lock");
+        unload_from_data(&mut data, id)
+    }
+
+    /// Unloads all the assets which have no alive `Handle`s and are not used
+    /// by `in_use` for longer than the grace period. Assets registered
+    /// directly (without a `Handle`) are never evicted automatically.
+    pub fn evict_unreferenced_assets(&mut self, in_use: impl Fn(&str) -> bool) {
+        let grace_period = match self.eviction_grace_period {
+            Some(g) => g,
+            None => return,
+        };
+
+        if self.last_eviction_check.elapsed() < EVICTION_CHECK_INTERVAL {
+            return;
+        }
+
+        let now = Instant::now();
+        self.last_eviction_check = now;
+
+        let mut data = self.data.write().expect("Unable to acquire write lock");
+
+        let tracked: Vec<AssetResourceID> =
+            data.ref_counts.keys().cloned().collect();
+
+        for id in tracked {
+            let is_referenced = data.has_handles(&id)
+                || in_use(&id)
+                // Wait for the asset to be ready, otherwise it will be
+                // registered after being evicted.
+                || data.load_states.get(&id) == Some(&LoadState::Loading);
+
+            if is_referenced {
+                data.unreferenced_since.remove(&id);
+                continue;
+            }
+
+            let since =
+                *data.unreferenced_since.entry(id.clone()).or_insert(now);
+
+            if now.duration_since(since) >= grace_period {
+                info!("Evicting unreferenced asset {}", id);
+                unload_from_data(&mut data, &id);
+            }
+        }
+    }
+}
+
+impl AssetServer {
+    /// Requests a model file (obj) to be loaded. The file is decoded in a
+    /// background thread and uploaded to the GPU once it is ready, all the
+    /// models in the file are merged in one single `Mesh`.
+    pub fn load_mesh_file(&self, path: impl AsRef<Path>) -> Handle<Mesh> {
+        let path = resolved_path(path.as_ref());
+        let id = asset_id_from_path(&path);
+
+        self.load_file(
+            id,
+            AssetSource::new(path, AssetKind::Mesh, ImportSettings::default()),
+        )
+    }
+
+    /// Requests an image file to be loaded as a texture. The file is decoded
+    /// in a background thread and uploaded to the GPU once it is ready.
+    pub fn load_texture_file(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Handle<dyn Texture> {
+        let path = resolved_path(path.as_ref());
+        let id = asset_id_from_path(&path);
+
+        self.load_file(
+            id,
+            AssetSource::new(
+                path,
+                AssetKind::Texture,
+                ImportSettings::default(),
+            ),
+        )
+    }
+
+    /// Requests all the assets listed in the manifest, the source files are
+    /// resolved relative to the manifest location.
+    pub fn load_manifest(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<LoadedAssets, AssetManifestError> {
+        let path = path.as_ref();
+        let manifest = AssetManifest::from_file(path)?;
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+
+        let mut loaded = LoadedAssets::default();
+
+        for entry in manifest.assets {
+            let source = AssetSource::new(
+                base_dir.join(&entry.path),
+                entry.kind,
+                entry.settings,
+            );
+
+            match entry.kind {
+                AssetKind::Mesh => {
+                    let handle = self.load_file(entry.id.clone(), source);
+                    loaded.meshes.insert(entry.id, handle);
+                }
+                AssetKind::Texture => {
+                    let handle = self.load_file(entry.id.clone(), source);
+                    loaded.textures.insert(entry.id, handle);
+                }
+            }
+        }
+
+        Ok(loaded)
+    }
+
+    /// Loads all the assets contained in the pack file. The pack is read and
+    /// validated in the calling thread, the data is uploaded to the GPU in
+    /// the next frame.
+    pub fn load_pack(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<LoadedAssets, AssetPackError> {
+        let file = fs::File::open(path.as_ref())?;
+        let pack = AssetPack::read(&mut io::BufReader::new(file))?;
+
+        let mut loaded = LoadedAssets::default();
+        let mut loader =
+            self.loader.lock().expect("Unable to acquire loader lock");
+        let mut data = self.data.write().expect("Unable to acquire write lock");
+
+        for entry in pack.entries {
+            data.load_states
+                .insert(entry.id.clone(), LoadState::Loading);
+
+            match entry.data {
+                PackData::Mesh { vertices, indices } => {
+                    loaded.meshes.insert(
+                        entry.id.clone(),
+                        data.handle(entry.id.clone()),
+                    );
+
+                    loader.load_model(
+                        entry.id.clone(),
+                        Model {
+                            name: entry.id,
+                            vertices,
+                            indices,
+                        },
+                    );
+                }
+                PackData::Texture(texture) => {
+                    loaded.textures.insert(
+                        entry.id.clone(),
+                        data.handle(entry.id.clone()),
+                    );
+
+                    loader.load_texture(entry.id, texture);
+                }
+            }
+        }
+
+        Ok(loaded)
+    }
+
+    /// Checks if any of the source files changed on disk and reloads the
+    /// associated assets. The new asset replaces the old one under the same
+    /// id, so all the components using it pick up the changes.
+    pub fn reload_changed_assets(&mut self) {
+        if !self.hot_reload
+            || self.last_hot_reload_poll.elapsed() < HOT_RELOAD_POLL_INTERVAL
+        {
+            return;
+        }
+
+        self.last_hot_reload_poll = Instant::now();
+
+        // The files are checked without the lock, so the loads are not
+        // blocked by the disk.
+        let sources: Vec<(AssetResourceID, PathBuf, Option<SystemTime>)> = self
+            .data
+            .read()
+            .expect("Unable to acquire read lock")
+            .sources
+            .iter()
+            .map(|(id, source)| {
+                (id.clone(), source.path.clone(), source.modified)
+            })
+            .collect();
+
+        let modified_files = sources
+            .into_iter()
+            .filter_map(|(id, path, previous)| {
+                let modified = modification_time(&path);
+
+                // Missing files are ignored, the file could be in the middle
+                // of being written.
+                (modified.is_some() && modified != previous)
+                    .then_some((id, path, modified))
+            })
+            .collect::<Vec<_>>();
+
+        if modified_files.is_empty() {
+            return;
+        }
+
+        let changed: Vec<(AssetResourceID, AssetSource)> = {
+            let mut data =
+                self.data.write().expect("Unable to acquire write lock");
+
+            modified_files
+                .into_iter()
+                .filter_map(|(id, path, modified)| {
+                    // The source could be replaced while the files were
+                    // checked.
+                    let source =
+                        data.sources.get_mut(&id).filter(|s| s.path == path)?;
+
+                    source.modified = modified;
+                    Some((id, source.clone()))
+                })
+                .collect()
+        };
+
+        for (id, source) in changed {
+            info!("Reloading {:?}", source.path);
+            self.spawn_load(id, source);
+        }
+    }
+
+    /// Requests the file to be loaded and returns a handle to the asset.
+    fn load_file<T: ?Sized>(
+        &self,
+        id: AssetResourceID,
+        source: AssetSource,
+    ) -> Handle<T> {
+        if self.begin_loading(&id, &source) {
+            self.spawn_load(id.clone(), source);
+        }
+
+        self.data
+            .write()
+            .expect("Unable to acquire write lock")
+            .handle(id)
+    }
+
+    /// Marks the asset as `Loading` and remembers its source file. Returns
+    /// `false` if the asset is already loaded or being loaded so the work is
+    /// not duplicated.
+    fn begin_loading(&self, id: &str, source: &AssetSource) -> bool {
+        let mut data = self.data.write().expect("Unable to acquire write lock");
+
+        match data.load_states.get(id) {
+            Some(LoadState::Loading) | Some(LoadState::Loaded) => false,
+            _ => {
+                data.load_states.insert(id.to_owned(), LoadState::Loading);
+                data.sources.insert(id.to_owned(), source.clone());
+                true
+            }
+        }
+    }
+
+    /// Decodes the file in the task pool and queues the result to be uploaded
+    /// to the GPU.
+    fn spawn_load(&self, id: AssetResourceID, source: AssetSource) {
+        let data = self.data.clone();
+        let loader = self.loader.clone();
+
+        self.task_pool.spawn(move || {
+            let AssetSource {
+                path,
+                kind,
+                settings,
+                ..
+            } = source;
+
+            let result = match kind {
+                AssetKind::Mesh => {
+                    import_model(&id, &path, &settings).map(|model| {
+                        let lods = settings
+                            .lods
+                            .iter()
+                            .enumerate()
+                            .map(|(i, lod)| {
+                                let lod_id = generated_lod_id(&id, i + 1);
+                                let lod_model = model
+                                    .simplify(lod_id.clone(), lod.cell_size);
+                                (lod_id, lod_model)
+                            })
+                            .collect::<Vec<_>>();
+
+                        let mut loader = loader
+                            .lock()
+                            .expect("Unable to acquire loader lock");
+
+                        loader.load_model(id.clone(), model);
+                        for (lod_id, lod_model) in lods {
+                            loader.load_model(lod_id, lod_model);
+                        }
+
+                        let mut data =
+                            data.write().expect("Unable to acquire write lock");
+
+                        if settings.lods.is_empty() {
+                            data.lod_groups.remove(&id);
+                        } else {
+                            let cross_fade = (settings.lod_cross_fade > 0.0)
+                                .then(|| {
+                                    Duration::from_secs_f32(
+                                        settings.lod_cross_fade,
+                                    )
+                                });
+
+                            data.lod_groups.insert(
+                                id.clone(),
+                                generated_lod_group(
+                                    &id,
+                                    &settings.lods,
+                                    cross_fade,
+                                ),
+                            );
+                        }
+                    })
+                }
+                AssetKind::Texture => {
+                    import_texture(&path, &settings).map(|texture| {
+                        loader
+                            .lock()
+                            .expect("Unable to acquire loader lock")
+                            .load_texture(id.clone(), texture)
+                    })
+                }
+            };
+
+            if let Err(e) = result {
+                mark_as_failed(&data, id, &path, e);
+            }
+        });
+    }
+}
+
+/// Returns the absolute path of the file, so the same file requested through
+/// different relative paths is the same asset. The path is kept as provided
+/// if the file doesn't exist, the load reports the error.
+fn resolved_path(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Generates the id of an asset based on its resolved path, relative to the
+/// working directory when the file is inside it. The files with the same name
+/// in different folders get different ids.
+fn asset_id_from_path(path: &Path) -> AssetResourceID {
+    let relative = env::current_dir()
+        .and_then(|dir| dir.canonicalize())
+        .ok()
+        .and_then(|dir| path.strip_prefix(dir).ok());
+
+    relative
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// Removes all the information associated with the asset. The generation is
+/// kept so a future asset with the same id is detected as a new one.
+fn unload_from_data(data: &mut AssetServerData, id: &str) -> bool {
+    let mesh = data.meshes.remove(id);
+    data.mesh_bounds.remove(id);
+    data.mesh_geometries.remove(id);
+
+    // The levels generated from the mesh are only used through it.
+    if let Some(group) = data.lod_groups.remove(id) {
+        for level in 1..group.levels.len() {
+            let lod_id = generated_lod_id(id, level);
+
+            if group.levels.iter().any(|l| l.mesh.0 == lod_id) {
+                unload_from_data(data, &lod_id);
+            }
+        }
+    }
+    let texture = data.textures.remove(id);
+    let material = data.materials.remove(id);
+
+    data.load_states.remove(id);
+    data.sources.remove(id);
+    data.ref_counts.remove(id);
+    data.unreferenced_since.remove(id);
+
+    mesh.is_some() || texture.is_some() || material.is_some()
+}
+
+/// Returns the last modification time of the file, `None` if it is not
+/// available.
+fn modification_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Sets the asset as `Failed` and logs the reason.
+fn mark_as_failed(
+    data: &RwLock<AssetServerData>,
+    id: AssetResourceID,
+    path: &Path,
+    reason: String,
+) {
+    warn!("Unable to load {:?}: {}", path, reason);
+
+    data.write()
+        .expect("Unable to acquire write lock")
+        .load_states
+        .insert(id, LoadState::Failed(reason));
 }
@@ -0,0 +1,175 @@
+use crate::{
+    graphics::texture::{TextureData, TextureFormat},
+    types::Size,
+};
+
+use super::pack::u32_at;
+
+/// Identifies the DDS files.
+const DDS_MAGIC: &[u8; 4] = b"DDS ";
+
+/// Size of the magic and the header, the extended header follows it.
+const HEADER_SIZE: usize = 128;
+/// Size of the header added by Direct3D 10 for the DXGI formats.
+const DX10_HEADER_SIZE: usize = 20;
+
+/// The header contains the number of mip levels.
+const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+/// The pixel format is described by a FourCC code.
+const DDPF_FOURCC: u32 = 0x4;
+/// The pixel format contains uncompressed RGB data.
+const DDPF_RGB: u32 = 0x40;
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+const DDSCAPS2_VOLUME: u32 = 0x200000;
+
+/// Reads a DDS file with a single 2D image and its mip levels. The legacy
+/// block formats do not specify a color space, the color ones are read as
+/// sRGB.
+///
+/// Layout (little endian): magic `DDS `, size, flags, height, width, pitch,
+/// depth, mip level count (u32 each), the pixel format at offset 76 and the
+/// capabilities at offset 108. The DXGI formats add an extended header with
+/// the format, dimension, flags and array size before the data.
+pub(crate) fn parse_dds(bytes: &[u8]) -> Result<TextureData, String> {
+    if bytes.get(..4) != Some(DDS_MAGIC.as_slice()) {
+        return Err("The file is not a DDS file".to_owned());
+    }
+
+    let field =
+        |offset: usize| u32_at(bytes, offset).ok_or("Truncated DDS header");
+
+    let flags = field(8)?;
+    let size = Size::new(field(16)?, field(12)?);
+    // A chain never has more levels than bits in the size.
+    let level_count = if flags & DDSD_MIPMAPCOUNT != 0 {
+        field(28)?.clamp(1, u32::BITS)
+    } else {
+        1
+    };
+
+    let pixel_flags = field(80)?;
+    let four_cc = bytes.get(84..88).ok_or("Truncated DDS header")?;
+    let caps2 = field(112)?;
+
+    if caps2 & (DDSCAPS2_CUBEMAP | DDSCAPS2_VOLUME) != 0 {
+        return Err("Only 2D textures are supported, not cubemaps or volumes"
+            .to_owned());
+    }
+
+    let (format, data_offset) =
+        if pixel_flags & DDPF_FOURCC != 0 && four_cc == b"DX10" {
+            let dxgi_format = field(HEADER_SIZE)?;
+            let misc_flags = field(HEADER_SIZE + 8)?;
+            let array_size = field(HEADER_SIZE + 12)?;
+
+            // The cubemaps are flagged with 0x4.
+            if misc_flags & 0x4 != 0 || array_size > 1 {
+                return Err(
+                    "Only 2D textures are supported, not arrays or cubemaps"
+                        .to_owned(),
+                );
+            }
+
+            let format = format_from_dxgi(dxgi_format).ok_or_else(|| {
+                format!("Unsupported DXGI format {}", dxgi_format)
+            })?;
+
+            (format, HEADER_SIZE + DX10_HEADER_SIZE)
+        } else if pixel_flags & DDPF_FOURCC != 0 {
+            let format = format_from_four_cc(four_cc).ok_or_else(|| {
+                format!(
+                    "Unsupported DDS format {}",
+                    String::from_utf8_lossy(four_cc)
+                )
+            })?;
+
+            (format, HEADER_SIZE)
+        } else if pixel_flags & DDPF_RGB != 0 && is_rgba8(bytes) {
+            (TextureFormat::Rgba8Srgb, HEADER_SIZE)
+        } else {
+            return Err("Unsupported DDS pixel format".to_owned());
+        };
+
+    // The levels are stored one after the other without padding.
+    let mut offset = data_offset;
+    let mut levels = Vec::new();
+
+    for level in 0..level_count {
+        let level_size = Size::new(
+            (size.width >> level).max(1),
+            (size.height >> level).max(1),
+        );
+        let length = format.level_size_in_bytes(level_size);
+
+        let data = bytes
+            .get(offset..offset.saturating_add(length))
+            .ok_or_else(|| format!("Truncated level {}", level))?;
+
+        levels.push(data.to_vec());
+        offset += length;
+    }
+
+    let mut levels = levels.into_iter();
+    let pixels = levels.next().unwrap_or_default();
+
+    Ok(TextureData {
+        mip_levels: levels.collect(),
+        ..TextureData::new(size, format, pixels)
+    })
+}
+
+/// Returns true if the uncompressed pixels are 32 bits stored in RGBA order.
+fn is_rgba8(bytes: &[u8]) -> bool {
+    // Bit count followed by the masks of the red, green and blue channels.
+    let layout = [
+        u32_at(bytes, 88),
+        u32_at(bytes, 92),
+        u32_at(bytes, 96),
+        u32_at(bytes, 100),
+    ];
+
+    layout
+        == [
+            Some(32),
+            Some(0x0000_00ff),
+            Some(0x0000_ff00),
+            Some(0x00ff_0000),
+        ]
+}
+
+/// Maps the legacy FourCC codes, and the Direct3D 9 format ids stored in
+/// their place, to the supported texture formats.
+fn format_from_four_cc(four_cc: &[u8]) -> Option<TextureFormat> {
+    let format = match four_cc {
+        b"DXT1" => TextureFormat::Bc1Srgb,
+        b"DXT5" => TextureFormat::Bc3Srgb,
+        b"ATI1" | b"BC4U" => TextureFormat::Bc4R,
+        b"ATI2" | b"BC5U" => TextureFormat::Bc5Rg,
+        [113, 0, 0, 0] => TextureFormat::Rgba16Float,
+        [116, 0, 0, 0] => TextureFormat::Rgba32Float,
+        _ => return None,
+    };
+
+    Some(format)
+}
+
+/// Maps the DXGI formats to the supported texture formats.
+fn format_from_dxgi(dxgi_format: u32) -> Option<TextureFormat> {
+    let format = match dxgi_format {
+        2 => TextureFormat::Rgba32Float,
+        10 => TextureFormat::Rgba16Float,
+        28 => TextureFormat::Rgba8Linear,
+        29 => TextureFormat::Rgba8Srgb,
+        71 => TextureFormat::Bc1Linear,
+        72 => TextureFormat::Bc1Srgb,
+        77 => TextureFormat::Bc3Linear,
+        78 => TextureFormat::Bc3Srgb,
+        80 => TextureFormat::Bc4R,
+        83 => TextureFormat::Bc5Rg,
+        98 => TextureFormat::Bc7Linear,
+        99 => TextureFormat::Bc7Srgb,
+        _ => return None,
+    };
+
+    Some(format)
+}
@@ -0,0 +1,92 @@
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    sync::Arc,
+};
+
+use crate::graphics::{mesh::Mesh, Texture};
+
+use super::{AssetResourceID, MeshResourceID, TextureResourceID};
+
+/// Represents the state of an asset inside the `AssetServer`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LoadState {
+    /// The asset was requested and it is being decoded or uploaded.
+    Loading,
+    /// The asset is ready to be used.
+    Loaded,
+    /// The asset could not be loaded, contains the reason.
+    Failed(String),
+}
+
+/// A typed reference to an asset stored in the `AssetServer`.
+///
+/// Handles are returned inmediately when a load is requested, the asset could
+/// still be loading in the background, use `AssetServer::load_state` to know
+/// if it is ready. While it is loading a placeholder is used instead.
+///
+/// Handles keep the asset alive, once all the handles are dropped and no
+/// `Scene` uses the asset it is evicted from the `AssetServer`.
+pub struct Handle<T: ?Sized> {
+    id: AssetResourceID,
+    /// Shared with the `AssetServer` to know how many handles are alive.
+    ref_count: Arc<()>,
+    _marker: PhantomData<fn() -> Box<T>>,
+}
+
+impl<T: ?Sized> Handle<T> {
+    /// Creates and returns a new `Handle` pointing to the provided id.
+    pub(crate) fn new(id: AssetResourceID, ref_count: Arc<()>) -> Self {
+        Self {
+            id,
+            ref_count,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the id of the asset in the `AssetServer`.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl Handle<Mesh> {
+    /// Returns the `MeshResourceID` which can be used in a `MeshComponent`.
+    pub fn resource_id(&self) -> MeshResourceID {
+        MeshResourceID(self.id.clone())
+    }
+}
+
+impl Handle<dyn Texture> {
+    /// Returns the `TextureResourceID` associated with the handle.
+    pub fn resource_id(&self) -> TextureResourceID {
+        TextureResourceID(self.id.clone())
+    }
+}
+
+impl<T: ?Sized> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self::new(self.id.clone(), self.ref_count.clone())
+    }
+}
+
+impl<T: ?Sized> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T: ?Sized> Eq for Handle<T> {}
+
+impl<T: ?Sized> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Handle({})", self.id)
+    }
+}
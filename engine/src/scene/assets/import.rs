@@ -0,0 +1,171 @@
+use std::path::Path;
+
+use image::DynamicImage;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    graphics::texture::{
+        f32_to_f16, SamplerSettings, TextureData, TextureFormat,
+    },
+    types::Size,
+};
+
+use super::{
+    dds::parse_dds,
+    ktx2::parse_ktx2,
+    lod::LodSettings,
+    model::{Model, ModelType},
+};
+
+/// Options applied while an asset is imported from its source file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImportSettings {
+    /// Uniform scale applied to the vertices of a mesh.
+    pub scale: f32,
+    /// Flips a texture vertically, some tools export the images upside down.
+    pub flip_vertically: bool,
+    /// Lower levels of detail generated from a mesh, from the most detailed
+    /// one.
+    pub lods: Vec<LodSettings>,
+    /// Seconds taken by the transition between two levels of detail, zero
+    /// switches them instantly.
+    pub lod_cross_fade: f32,
+    /// Format of a texture on the GPU. By default the HDR images use
+    /// `Rgba16Float` and the rest `Rgba8Srgb`, the textures containing data
+    /// instead of colors should use `Rgba8Linear`. The KTX2 and DDS files
+    /// keep the format they are stored in.
+    pub format: Option<TextureFormat>,
+    /// Generates the mip chain of a texture when it is uploaded.
+    pub mipmaps: bool,
+    /// How the materials sample a texture.
+    pub sampler: SamplerSettings,
+}
+
+impl Default for ImportSettings {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            flip_vertically: false,
+            lods: Vec::new(),
+            lod_cross_fade: 0.0,
+            format: None,
+            mipmaps: true,
+            sampler: SamplerSettings::default(),
+        }
+    }
+}
+
+/// Loads a model file (obj) and merges all its models in one.
+pub(crate) fn import_model(
+    id: &str,
+    path: &Path,
+    settings: &ImportSettings,
+) -> Result<Model, String> {
+    let mut model = ModelType::Obj(path)
+        .load_model()
+        .and_then(|models| Model::merge(id.to_owned(), models))
+        .map_err(|e| e.to_string())?;
+
+    if settings.scale != 1.0 {
+        model.vertices.iter_mut().for_each(|v| {
+            v.pos = v.pos.map(|c| c * settings.scale);
+        });
+    }
+
+    Ok(model)
+}
+
+/// Loads an image file (png, jpeg or hdr) and converts its pixels to the
+/// format of the settings. The KTX2 and DDS containers are read as they are.
+pub(crate) fn import_texture(
+    path: &Path,
+    settings: &ImportSettings,
+) -> Result<TextureData, String> {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase());
+
+    match extension.as_deref() {
+        Some("ktx2") => return import_container(path, settings, parse_ktx2),
+        Some("dds") => return import_container(path, settings, parse_dds),
+        _ => {}
+    }
+
+    let mut img = image::open(path).map_err(|e| e.to_string())?;
+
+    if settings.flip_vertically {
+        img = img.flipv();
+    }
+
+    let is_hdr = matches!(
+        img,
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_)
+    );
+
+    let format = settings.format.unwrap_or(if is_hdr {
+        TextureFormat::Rgba16Float
+    } else {
+        TextureFormat::Rgba8Srgb
+    });
+
+    let (width, height, pixels) = match format {
+        TextureFormat::Rgba8Srgb | TextureFormat::Rgba8Linear => {
+            let img = img.to_rgba8();
+            let (width, height) = img.dimensions();
+            (width, height, img.into_raw())
+        }
+        TextureFormat::Rgba16Float => {
+            let img = img.to_rgba32f();
+            let (width, height) = img.dimensions();
+            let pixels = img
+                .into_raw()
+                .into_iter()
+                .flat_map(|c| f32_to_f16(c).to_le_bytes())
+                .collect();
+            (width, height, pixels)
+        }
+        TextureFormat::Rgba32Float => {
+            let img = img.to_rgba32f();
+            let (width, height) = img.dimensions();
+            let pixels = img
+                .into_raw()
+                .into_iter()
+                .flat_map(|c| c.to_le_bytes())
+                .collect();
+            (width, height, pixels)
+        }
+    };
+
+    Ok(TextureData {
+        mipmaps: settings.mipmaps,
+        sampler: settings.sampler.clone(),
+        ..TextureData::new(Size::new(width, height), format, pixels)
+    })
+}
+
+/// Loads a texture container with its pixels already in the GPU format, they
+/// are uploaded as they are stored.
+fn import_container(
+    path: &Path,
+    settings: &ImportSettings,
+    parse: fn(&[u8]) -> Result<TextureData, String>,
+) -> Result<TextureData, String> {
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    let texture = parse(&bytes)?;
+
+    if settings.flip_vertically || settings.format.is_some() {
+        warn!(
+            "The flip and format settings are not applied to {:?}, the file \
+             is loaded as it is stored",
+            path
+        );
+    }
+
+    Ok(TextureData {
+        mipmaps: settings.mipmaps,
+        sampler: settings.sampler.clone(),
+        ..texture
+    })
+}
@@ -0,0 +1,126 @@
+use crate::{
+    graphics::texture::{TextureData, TextureFormat},
+    types::Size,
+};
+
+use super::pack::u32_at;
+
+/// Identifies the KTX2 files.
+const KTX2_MAGIC: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+
+/// Offset of the level index, after the header and the index of the data
+/// format descriptor, the key values and the supercompression data.
+const LEVEL_INDEX_OFFSET: usize = 80;
+
+/// Reads a KTX2 container with a single 2D image and its mip levels.
+/// Supercompressed files (Basis Universal, Zstandard) are not supported.
+///
+/// Layout (little endian): identifier, vkFormat, typeSize, width, height,
+/// depth, layers, faces, levels, supercompression (u32 each), the indexes
+/// of the other sections and one entry per level with its offset, length
+/// and uncompressed length (u64 each).
+pub(crate) fn parse_ktx2(bytes: &[u8]) -> Result<TextureData, String> {
+    if bytes.get(..12) != Some(KTX2_MAGIC.as_slice()) {
+        return Err("The file is not a KTX2 container".to_owned());
+    }
+
+    let field = |index: usize| {
+        u32_at(bytes, 12 + index * 4).ok_or("Truncated KTX2 header")
+    };
+
+    let vk_format = field(0)?;
+    let size = Size::new(field(2)?, field(3)?);
+    let (depth, layers, faces) = (field(4)?, field(5)?, field(6)?);
+    // A chain never has more levels than bits in the size.
+    let level_count = field(7)?.clamp(1, u32::BITS);
+    let supercompression = field(8)?;
+
+    if depth > 1 || layers > 1 || faces != 1 {
+        return Err(
+            "Only 2D textures are supported, not arrays, cubemaps or volumes"
+                .to_owned(),
+        );
+    }
+
+    if supercompression != 0 {
+        return Err(format!(
+            "Unsupported supercompression scheme {}",
+            supercompression
+        ));
+    }
+
+    let format = format_from_vk(vk_format)
+        .ok_or_else(|| format!("Unsupported VkFormat {}", vk_format))?;
+
+    let mut levels = (0..level_count as usize)
+        .map(|level| -> Result<Vec<u8>, String> {
+            let entry = LEVEL_INDEX_OFFSET + level * 24;
+            let offset = u64_at(bytes, entry).ok_or("Truncated level index")?;
+            let length =
+                u64_at(bytes, entry + 8).ok_or("Truncated level index")?;
+
+            let level_size = Size::new(
+                (size.width >> level).max(1),
+                (size.height >> level).max(1),
+            );
+
+            if length as usize != format.level_size_in_bytes(level_size) {
+                return Err(format!("Invalid length of the level {}", level));
+            }
+
+            let end = offset.checked_add(length).ok_or("Invalid level")?;
+
+            bytes
+                .get(offset as usize..end as usize)
+                .map(|data| data.to_vec())
+                .ok_or_else(|| format!("Truncated level {}", level))
+        })
+        .collect::<Result<Vec<_>, String>>()?
+        .into_iter();
+
+    let pixels = levels.next().unwrap_or_default();
+
+    Ok(TextureData {
+        mip_levels: levels.collect(),
+        ..TextureData::new(size, format, pixels)
+    })
+}
+
+/// Maps the Vulkan formats to the supported texture formats.
+fn format_from_vk(vk_format: u32) -> Option<TextureFormat> {
+    let format = match vk_format {
+        37 => TextureFormat::Rgba8Linear,
+        43 => TextureFormat::Rgba8Srgb,
+        97 => TextureFormat::Rgba16Float,
+        109 => TextureFormat::Rgba32Float,
+        // The RGB and RGBA variants of BC1 share the same blocks.
+        131 | 133 => TextureFormat::Bc1Linear,
+        132 | 134 => TextureFormat::Bc1Srgb,
+        137 => TextureFormat::Bc3Linear,
+        138 => TextureFormat::Bc3Srgb,
+        139 => TextureFormat::Bc4R,
+        141 => TextureFormat::Bc5Rg,
+        145 => TextureFormat::Bc7Linear,
+        146 => TextureFormat::Bc7Srgb,
+        147 => TextureFormat::Etc2Rgb8Linear,
+        148 => TextureFormat::Etc2Rgb8Srgb,
+        151 => TextureFormat::Etc2Rgba8Linear,
+        152 => TextureFormat::Etc2Rgba8Srgb,
+        157 => TextureFormat::Astc4x4Linear,
+        158 => TextureFormat::Astc4x4Srgb,
+        165 => TextureFormat::Astc6x6Linear,
+        166 => TextureFormat::Astc6x6Srgb,
+        171 => TextureFormat::Astc8x8Linear,
+        172 => TextureFormat::Astc8x8Srgb,
+        _ => return None,
+    };
+
+    Some(format)
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> Option<u64> {
+    let bytes = bytes.get(offset..offset + 8)?;
+    Some(u64::from_le_bytes(bytes.try_into().ok()?))
+}
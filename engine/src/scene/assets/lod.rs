@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::MeshResourceID;
+
+/// A level of a `LodGroup`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LodLevel {
+    pub mesh: MeshResourceID,
+    /// Minimum fraction of the screen height the mesh must cover for the
+    /// level to be used.
+    pub screen_size: f32,
+}
+
+/// Meshes with decreasing detail used to draw a mesh asset depending on the
+/// size it has on the screen, it is registered in the `AssetServer` under
+/// the id of the mesh it replaces.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LodGroup {
+    /// Contains the levels from the most detailed one, the first level whose
+    /// screen size is reached is used. The instances smaller than all of
+    /// them use the last level.
+    pub levels: Vec<LodLevel>,
+    /// Duration of the dithered transition between two levels, they are
+    /// switched instantly if it is `None`.
+    pub cross_fade: Option<Duration>,
+}
+
+impl LodGroup {
+    /// Returns the index of the level used for an instance covering the
+    /// provided fraction of the screen height.
+    pub fn select(&self, screen_size: f32) -> usize {
+        self.levels
+            .iter()
+            .position(|l| screen_size >= l.screen_size)
+            .unwrap_or_else(|| self.levels.len().saturating_sub(1))
+    }
+}
+
+/// Level of detail generated while a mesh is imported.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LodSettings {
+    /// Size of the cells whose vertices are merged together, relative to the
+    /// largest dimension of the mesh. Bigger cells remove more detail.
+    pub cell_size: f32,
+    /// The level replaces the previous one once the mesh covers less than
+    /// this fraction of the screen height.
+    pub screen_size: f32,
+}
+
+/// Returns the id of a level generated from a mesh while it is imported.
+pub(crate) fn generated_lod_id(mesh: &str, level: usize) -> String {
+    format!("{}#lod{}", mesh, level)
+}
+
+/// Creates the group of a mesh imported with the provided levels, the
+/// imported mesh is the first level.
+pub(crate) fn generated_lod_group(
+    mesh: &str,
+    lods: &[LodSettings],
+    cross_fade: Option<Duration>,
+) -> LodGroup {
+    // Each level is used until the next one replaces it.
+    let thresholds = lods.iter().map(|l| l.screen_size).chain([0.0]);
+
+    let levels = (0..=lods.len())
+        .zip(thresholds)
+        .map(|(level, screen_size)| LodLevel {
+            mesh: MeshResourceID(if level == 0 {
+                mesh.to_owned()
+            } else {
+                generated_lod_id(mesh, level)
+            }),
+            screen_size,
+        })
+        .collect();
+
+    LodGroup { levels, cross_fade }
+}
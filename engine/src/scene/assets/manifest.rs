@@ -0,0 +1,103 @@
+use std::{
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{asset_server::AssetKind, import::ImportSettings};
+
+/// Contains the current version of the manifest format.
+pub const ASSET_MANIFEST_VERSION: u32 = 1;
+
+/// Lists all the assets used by a game, it is stored as a RON file.
+///
+/// ```ron
+/// (
+///     version: 1,
+///     assets: [
+///         (id: "ship", path: "models/ship.obj", kind: Mesh),
+///         (
+///             id: "ship_diffuse",
+///             path: "textures/ship.png",
+///             kind: Texture,
+///             settings: (flip_vertically: true),
+///         ),
+///     ],
+/// )
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AssetManifest {
+    pub version: u32,
+    pub assets: Vec<ManifestEntry>,
+}
+
+/// Describes one asset in the manifest.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// The id used to register the asset in the `AssetServer`.
+    pub id: String,
+    /// Path of the source file, relative to the manifest file.
+    pub path: PathBuf,
+    pub kind: AssetKind,
+    #[serde(default)]
+    pub settings: ImportSettings,
+}
+
+#[derive(Debug)]
+pub enum AssetManifestError {
+    Io(io::Error),
+    Parse(String),
+    /// The manifest was written with a different version of the format.
+    UnsupportedVersion(u32),
+}
+
+impl Error for AssetManifestError {}
+
+impl fmt::Display for AssetManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssetManifestError::Io(e) => write!(f, "{}", e),
+            AssetManifestError::Parse(e) => {
+                write!(f, "Invalid manifest: {}", e)
+            }
+            AssetManifestError::UnsupportedVersion(v) => {
+                write!(f, "Unsupported manifest version {}", v)
+            }
+        }
+    }
+}
+
+impl AssetManifest {
+    /// Reads and validates the manifest stored in the provided file.
+    pub fn from_file(path: &Path) -> Result<Self, AssetManifestError> {
+        let content =
+            fs::read_to_string(path).map_err(AssetManifestError::Io)?;
+
+        Self::parse(&content)
+    }
+
+    /// Parses and validates the provided manifest content.
+    pub fn parse(content: &str) -> Result<Self, AssetManifestError> {
+        let manifest: AssetManifest = ron::from_str(content)
+            .map_err(|e| AssetManifestError::Parse(e.to_string()))?;
+
+        if manifest.version != ASSET_MANIFEST_VERSION {
+            return Err(AssetManifestError::UnsupportedVersion(
+                manifest.version,
+            ));
+        }
+
+        Ok(manifest)
+    }
+
+    /// Writes the manifest into the provided file.
+    pub fn save(&self, path: &Path) -> Result<(), AssetManifestError> {
+        let content =
+            ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+                .map_err(|e| AssetManifestError::Parse(e.to_string()))?;
+
+        fs::write(path, content).map_err(AssetManifestError::Io)
+    }
+}
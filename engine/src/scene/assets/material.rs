@@ -0,0 +1,68 @@
+use super::{MaterialResourceID, TextureResourceID};
+
+/// Contains the id of the material used by the entities without a
+/// `MaterialComponent`.
+pub const DEFAULT_MATERIAL_ID: &str = "DEFAULT_MATERIAL";
+
+/// Returns the `MaterialResourceID` of the default material.
+pub fn default_material_resource() -> MaterialResourceID {
+    MaterialResourceID(DEFAULT_MATERIAL_ID.to_owned())
+}
+
+/// Determines how the alpha channel of the base color is used.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AlphaMode {
+    /// The alpha channel is ignored.
+    Opaque,
+    /// The fragments with an alpha lower than the cutoff are discarded.
+    Mask(f32),
+    /// The fragments are blended with the ones behind.
+    Blend,
+}
+
+/// Describes the surface of a mesh using the metallic-roughness model. Each
+/// texture is multiplied by its factor, when a texture is not provided only
+/// the factor is used.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Material {
+    /// Linear RGBA color.
+    pub base_color: [f32; 4],
+    pub base_color_texture: Option<TextureResourceID>,
+    pub metallic: f32,
+    pub roughness: f32,
+    /// The roughness is read from the green channel and the metallic from
+    /// the blue one.
+    pub metallic_roughness_texture: Option<TextureResourceID>,
+    /// Linear RGB color emitted by the surface.
+    pub emissive: [f32; 3],
+    pub emissive_texture: Option<TextureResourceID>,
+    pub alpha_mode: AlphaMode,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            base_color: [1.0, 1.0, 1.0, 1.0],
+            base_color_texture: None,
+            metallic: 0.0,
+            roughness: 0.5,
+            metallic_roughness_texture: None,
+            emissive: [0.0, 0.0, 0.0],
+            emissive_texture: None,
+            alpha_mode: AlphaMode::Opaque,
+        }
+    }
+}
+
+impl Material {
+    /// Returns all the textures referenced by the material.
+    pub fn textures(&self) -> impl Iterator<Item = &TextureResourceID> {
+        [
+            &self.base_color_texture,
+            &self.metallic_roughness_texture,
+            &self.emissive_texture,
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
@@ -1,6 +1,16 @@
 pub mod asset_loader;
 pub mod asset_server;
+mod dds;
+pub mod handle;
+pub mod import;
+mod ktx2;
+pub mod lod;
+pub mod manifest;
+pub mod material;
 pub mod model;
+pub mod pack;
+pub mod placeholder;
+pub(crate) mod task_pool;
 
 use std::ops::Deref;
 
@@ -12,6 +22,9 @@ pub struct MeshResourceID(pub AssetResourceID);
 #[derive(Clone, Hash, PartialEq, Eq, Debug)]
 pub struct TextureResourceID(pub(crate) AssetResourceID);
 
+#[derive(Clone, Hash, PartialEq, Eq, Debug)]
+pub struct MaterialResourceID(pub AssetResourceID);
+
 impl Deref for MeshResourceID {
     type Target = AssetResourceID;
 
@@ -19,3 +32,19 @@ impl Deref for MeshResourceID {
         &self.0
     }
 }
+
+impl Deref for TextureResourceID {
+    type Target = AssetResourceID;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Deref for MaterialResourceID {
+    type Target = AssetResourceID;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
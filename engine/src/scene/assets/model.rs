@@ -1,6 +1,9 @@
 use std::{error::Error, fmt, path::Path};
 
-use crate::graphics::vertex::Vertex;
+use ahash::AHashMap;
+use nalgebra::{Vector2, Vector3};
+
+use crate::graphics::{culling::Aabb, vertex::Vertex};
 
 /// A representation of all the loadable model types.
 pub enum ModelType<'a> {
@@ -17,13 +20,169 @@ pub struct Model {
 #[derive(Debug)]
 pub enum ModelLoaderError {
     InvalidBuffer,
+    /// The file does not contain any model.
+    Empty,
+    /// The model has more vertices than the ones addressable by the index
+    /// buffer.
+    TooManyVertices,
 }
 
 impl Error for ModelLoaderError {}
 
 impl fmt::Display for ModelLoaderError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Oh no, something bad went down")
+        match self {
+            ModelLoaderError::InvalidBuffer => {
+                write!(f, "Oh no, something bad went down")
+            }
+            ModelLoaderError::Empty => write!(f, "The file contains no models"),
+            ModelLoaderError::TooManyVertices => {
+                write!(f, "The model exceeds the max number of vertices")
+            }
+        }
+    }
+}
+
+impl Model {
+    /// Combines all the provided models into a single one, this is used when
+    /// a file is loaded as one asset.
+    pub fn merge(
+        name: String,
+        models: Vec<Model>,
+    ) -> Result<Model, ModelLoaderError> {
+        if models.is_empty() {
+            return Err(ModelLoaderError::Empty);
+        }
+
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+
+        for model in models {
+            let offset = u16::try_from(vertices.len())
+                .map_err(|_| ModelLoaderError::TooManyVertices)?;
+
+            if vertices.len() + model.vertices.len() > u16::MAX as usize + 1 {
+                return Err(ModelLoaderError::TooManyVertices);
+            }
+
+            indices.extend(model.indices.iter().map(|i| i + offset));
+            vertices.extend(model.vertices);
+        }
+
+        Ok(Model {
+            name,
+            vertices,
+            indices,
+        })
+    }
+
+    /// Generates smooth normals for the vertices, each vertex uses the
+    /// average of the faces which share it.
+    pub fn compute_normals(&mut self) {
+        let mut normals = vec![Vector3::<f32>::zeros(); self.vertices.len()];
+
+        for triangle in self.indices.chunks_exact(3) {
+            let [a, b, c] =
+                [triangle[0], triangle[1], triangle[2]].map(|i| i as usize);
+
+            let pos = |i: usize| Vector3::from(self.vertices[i].pos);
+            // The cross product is not normalized so bigger faces have more
+            // weight.
+            let face = (pos(b) - pos(a)).cross(&(pos(c) - pos(a)));
+
+            normals[a] += face;
+            normals[b] += face;
+            normals[c] += face;
+        }
+
+        for (vertex, normal) in self.vertices.iter_mut().zip(normals) {
+            vertex.normal = normal
+                .try_normalize(f32::EPSILON)
+                .unwrap_or_else(Vector3::y)
+                .into();
+        }
+    }
+
+    /// Returns a version of the model with less triangles, used as a lower
+    /// level of detail. The vertices inside each cell of a grid are merged
+    /// into their average (vertex clustering) and the triangles which
+    /// collapse are removed.
+    ///
+    /// `cell_size` is relative to the largest dimension of the model.
+    pub fn simplify(&self, name: String, cell_size: f32) -> Model {
+        let bounds =
+            Aabb::from_points(self.vertices.iter().map(|v| v.pos.into()));
+        let cell =
+            ((bounds.max - bounds.min).max() * cell_size).max(f32::EPSILON);
+
+        let mut cells = AHashMap::<[i32; 3], u16>::new();
+        let mut clusters = Vec::<Cluster>::new();
+
+        let remap = self
+            .vertices
+            .iter()
+            .map(|v| {
+                let key = [0, 1, 2].map(|i| {
+                    ((v.pos[i] - bounds.min[i]) / cell).floor() as i32
+                });
+
+                let index = *cells.entry(key).or_insert_with(|| {
+                    clusters.push(Cluster::default());
+                    // There can't be more clusters than vertices.
+                    (clusters.len() - 1) as u16
+                });
+
+                clusters[index as usize].add(v);
+                index
+            })
+            .collect::<Vec<_>>();
+
+        let indices = self
+            .indices
+            .chunks_exact(3)
+            .map(|t| [t[0], t[1], t[2]].map(|i| remap[i as usize]))
+            .filter(|[a, b, c]| a != b && b != c && a != c)
+            .flatten()
+            .collect();
+
+        Model {
+            name,
+            vertices: clusters.iter().map(Cluster::vertex).collect(),
+            indices,
+        }
+    }
+}
+
+/// Sum of the vertices merged by `Model::simplify`.
+#[derive(Default)]
+struct Cluster {
+    pos: Vector3<f32>,
+    col: Vector3<f32>,
+    uv: Vector2<f32>,
+    normal: Vector3<f32>,
+    count: f32,
+}
+
+impl Cluster {
+    fn add(&mut self, vertex: &Vertex) {
+        self.pos += Vector3::from(vertex.pos);
+        self.col += Vector3::from(vertex.col);
+        self.uv += Vector2::from(vertex.uv);
+        self.normal += Vector3::from(vertex.normal);
+        self.count += 1.0;
+    }
+
+    fn vertex(&self) -> Vertex {
+        Vertex {
+            pos: (self.pos / self.count).into(),
+            col: (self.col / self.count).into(),
+            uv: (self.uv / self.count).into(),
+            normal: self
+                .normal
+                .try_normalize(f32::EPSILON)
+                .unwrap_or_else(Vector3::y)
+                .into(),
+        }
     }
 }
 
@@ -37,6 +196,9 @@ impl<'a> ModelType<'a> {
                     *path,
                     &tobj::LoadOptions {
                         triangulate: true,
+                        // The texture coordinates must share the indices of
+                        // the positions.
+                        single_index: true,
                         ..Default::default()
                     },
                 )
@@ -44,37 +206,64 @@ impl<'a> ModelType<'a> {
 
                 let local_models = models
                     .iter()
-                    .map(|m| Model {
-                        name: m.name.clone(),
-                        vertices: (0..m.mesh.positions.len() / 3)
-                            .map(|i| Vertex {
-                                pos: [
-                                    m.mesh.positions[i * 3],
-                                    m.mesh.positions[i * 3 + 1],
-                                    m.mesh.positions[i * 3 + 2],
-                                ],
-                                col: [
-                                    *m.mesh
-                                        .vertex_color
-                                        .get(i * 3)
-                                        .unwrap_or(&1.0),
-                                    *m.mesh
-                                        .vertex_color
-                                        .get(i * 3 + 1)
-                                        .unwrap_or(&1.0),
-                                    *m.mesh
-                                        .vertex_color
-                                        .get(i * 3 + 2)
-                                        .unwrap_or(&1.0),
-                                ],
-                            })
-                            .collect(),
-                        indices: m
-                            .mesh
-                            .indices
-                            .iter()
-                            .map(|index| *index as u16)
-                            .collect(),
+                    .map(|m| {
+                        let mut model = Model {
+                            name: m.name.clone(),
+                            vertices: (0..m.mesh.positions.len() / 3)
+                                .map(|i| Vertex {
+                                    pos: [
+                                        m.mesh.positions[i * 3],
+                                        m.mesh.positions[i * 3 + 1],
+                                        m.mesh.positions[i * 3 + 2],
+                                    ],
+                                    col: [
+                                        *m.mesh
+                                            .vertex_color
+                                            .get(i * 3)
+                                            .unwrap_or(&1.0),
+                                        *m.mesh
+                                            .vertex_color
+                                            .get(i * 3 + 1)
+                                            .unwrap_or(&1.0),
+                                        *m.mesh
+                                            .vertex_color
+                                            .get(i * 3 + 2)
+                                            .unwrap_or(&1.0),
+                                    ],
+                                    // Obj files use the bottom left corner as
+                                    // the origin.
+                                    uv: [
+                                        *m.mesh
+                                            .texcoords
+                                            .get(i * 2)
+                                            .unwrap_or(&0.0),
+                                        1.0 - *m
+                                            .mesh
+                                            .texcoords
+                                            .get(i * 2 + 1)
+                                            .unwrap_or(&1.0),
+                                    ],
+                                    normal: m
+                                        .mesh
+                                        .normals
+                                        .get(i * 3..i * 3 + 3)
+                                        .and_then(|n| n.try_into().ok())
+                                        .unwrap_or_default(),
+                                })
+                                .collect(),
+                            indices: m
+                                .mesh
+                                .indices
+                                .iter()
+                                .map(|index| *index as u16)
+                                .collect(),
+                        };
+
+                        if m.mesh.normals.is_empty() {
+                            model.compute_normals();
+                        }
+
+                        model
                     })
                     .collect::<Vec<_>>();
 
@@ -0,0 +1,645 @@
+use std::{
+    error::Error,
+    fmt, fs,
+    io::{self, Read, Write},
+    path::Path,
+};
+
+use crate::{
+    graphics::{
+        texture::{
+            AddressMode, FilterMode, SamplerSettings, TextureData,
+            TextureFormat,
+        },
+        vertex::Vertex,
+    },
+    types::Size,
+};
+
+use super::{
+    asset_server::AssetKind,
+    import::{import_model, import_texture},
+    manifest::{AssetManifest, AssetManifestError},
+};
+
+/// Identifies the asset pack files.
+const PACK_MAGIC: &[u8; 4] = b"CRPK";
+/// Contains the current version of the pack format.
+pub const ASSET_PACK_VERSION: u32 = 4;
+
+/// A single file containing all the assets of a game already decoded, so the
+/// game does not need to parse the source files.
+///
+/// Layout (little endian):
+/// - Header: magic `CRPK`, version (u32), number of entries (u32).
+/// - Entry: id length (u32), id (utf8), kind (u8), payload length (u64),
+///   payload crc32 (u32), payload.
+///
+/// Mesh payloads contain the number of vertices and indices (u32 each)
+/// followed by the raw data. Texture payloads contain the width and height
+/// (u32 each), the format, whether mipmaps are generated, the address modes
+/// and filters of the sampler (u8 each), the anisotropy (u16), the number of
+/// stored levels (u8) and the pixels of all the levels compressed with LZ4.
+pub struct AssetPack {
+    pub entries: Vec<PackEntry>,
+}
+
+pub struct PackEntry {
+    pub id: String,
+    pub data: PackData,
+}
+
+pub enum PackData {
+    Mesh {
+        vertices: Vec<Vertex>,
+        indices: Vec<u16>,
+    },
+    Texture(TextureData),
+}
+
+#[derive(Debug)]
+pub enum AssetPackError {
+    Io(io::Error),
+    /// The manifest used to build the pack could not be read.
+    Manifest(AssetManifestError),
+    /// The file is not an asset pack.
+    InvalidMagic,
+    /// The pack was written with a different version of the format.
+    UnsupportedVersion(u32),
+    /// The data of the entry does not match its checksum.
+    Corrupted(String),
+    /// The source file of the entry could not be imported.
+    Import(String, String),
+}
+
+impl Error for AssetPackError {}
+
+impl fmt::Display for AssetPackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssetPackError::Io(e) => write!(f, "{}", e),
+            AssetPackError::Manifest(e) => write!(f, "{}", e),
+            AssetPackError::InvalidMagic => {
+                write!(f, "The file is not an asset pack")
+            }
+            AssetPackError::UnsupportedVersion(v) => {
+                write!(f, "Unsupported asset pack version {}", v)
+            }
+            AssetPackError::Corrupted(id) => {
+                write!(f, "The asset {} is corrupted", id)
+            }
+            AssetPackError::Import(id, reason) => {
+                write!(f, "Unable to import {}: {}", id, reason)
+            }
+        }
+    }
+}
+
+impl From<io::Error> for AssetPackError {
+    fn from(e: io::Error) -> Self {
+        AssetPackError::Io(e)
+    }
+}
+
+impl From<AssetManifestError> for AssetPackError {
+    fn from(e: AssetManifestError) -> Self {
+        AssetPackError::Manifest(e)
+    }
+}
+
+impl AssetPack {
+    /// Imports all the assets listed in the manifest file and writes them in
+    /// a pack file, the source files are resolved relative to the manifest
+    /// location.
+    pub fn build(
+        manifest_path: &Path,
+        pack_path: &Path,
+    ) -> Result<(), AssetPackError> {
+        let manifest = AssetManifest::from_file(manifest_path)?;
+        let base_dir = manifest_path.parent().unwrap_or(Path::new(""));
+        let pack = Self::from_manifest(&manifest, base_dir)?;
+
+        let mut writer = io::BufWriter::new(fs::File::create(pack_path)?);
+        pack.write(&mut writer)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Imports all the assets listed in the manifest, the paths are resolved
+    /// relative to `base_dir`.
+    pub fn from_manifest(
+        manifest: &AssetManifest,
+        base_dir: &Path,
+    ) -> Result<Self, AssetPackError> {
+        let entries = manifest
+            .assets
+            .iter()
+            .map(|entry| {
+                let path = base_dir.join(&entry.path);
+
+                let data = match entry.kind {
+                    AssetKind::Mesh => {
+                        import_model(&entry.id, &path, &entry.settings).map(
+                            |model| PackData::Mesh {
+                                vertices: model.vertices,
+                                indices: model.indices,
+                            },
+                        )
+                    }
+                    AssetKind::Texture => {
+                        import_texture(&path, &entry.settings)
+                            .map(PackData::Texture)
+                    }
+                }
+                .map_err(|e| AssetPackError::Import(entry.id.clone(), e))?;
+
+                Ok(PackEntry {
+                    id: entry.id.clone(),
+                    data,
+                })
+            })
+            .collect::<Result<Vec<_>, AssetPackError>>()?;
+
+        Ok(Self { entries })
+    }
+
+    /// Serializes the pack into the provided writer.
+    pub fn write(&self, writer: &mut impl Write) -> Result<(), AssetPackError> {
+        writer.write_all(PACK_MAGIC)?;
+        writer.write_all(&ASSET_PACK_VERSION.to_le_bytes())?;
+        writer.write_all(&(self.entries.len() as u32).to_le_bytes())?;
+
+        for entry in &self.entries {
+            let (kind, payload) = match &entry.data {
+                PackData::Mesh { vertices, indices } => {
+                    let mut payload = Vec::new();
+                    payload.extend((vertices.len() as u32).to_le_bytes());
+                    payload.extend((indices.len() as u32).to_le_bytes());
+                    payload.extend(bytemuck::cast_slice(vertices));
+                    payload
+                        .extend(indices.iter().flat_map(|i| i.to_le_bytes()));
+
+                    (AssetKind::Mesh, payload)
+                }
+                PackData::Texture(texture) => {
+                    let sampler = &texture.sampler;
+
+                    let mut payload = Vec::new();
+                    payload.extend(texture.size.width.to_le_bytes());
+                    payload.extend(texture.size.height.to_le_bytes());
+                    payload.extend([
+                        format_to_byte(texture.format),
+                        texture.mipmaps as u8,
+                        address_mode_to_byte(sampler.address_mode_u),
+                        address_mode_to_byte(sampler.address_mode_v),
+                        filter_to_byte(sampler.mag_filter),
+                        filter_to_byte(sampler.min_filter),
+                        filter_to_byte(sampler.mipmap_filter),
+                    ]);
+                    payload.extend(sampler.anisotropy.to_le_bytes());
+                    payload.push(1 + texture.mip_levels.len() as u8);
+
+                    let levels = std::iter::once(&texture.pixels)
+                        .chain(&texture.mip_levels)
+                        .flatten()
+                        .copied()
+                        .collect::<Vec<_>>();
+                    payload.extend(lz4_flex::compress_prepend_size(&levels));
+
+                    (AssetKind::Texture, payload)
+                }
+            };
+
+            writer.write_all(&(entry.id.len() as u32).to_le_bytes())?;
+            writer.write_all(entry.id.as_bytes())?;
+            writer.write_all(&[kind_to_byte(kind)])?;
+            writer.write_all(&(payload.len() as u64).to_le_bytes())?;
+            writer.write_all(&crc32fast::hash(&payload).to_le_bytes())?;
+            writer.write_all(&payload)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads and validates a pack from the provided reader.
+    pub fn read(reader: &mut impl Read) -> Result<Self, AssetPackError> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+
+        if &magic != PACK_MAGIC {
+            return Err(AssetPackError::InvalidMagic);
+        }
+
+        let version = read_u32(reader)?;
+
+        if version != ASSET_PACK_VERSION {
+            return Err(AssetPackError::UnsupportedVersion(version));
+        }
+
+        let count = read_u32(reader)?;
+        let mut entries = Vec::new();
+
+        for _ in 0..count {
+            // The lengths are not trusted, a corrupted one must not allocate
+            // a huge buffer.
+            let id_len = read_u32(reader)? as u64;
+            let mut id = Vec::new();
+            reader.by_ref().take(id_len).read_to_end(&mut id)?;
+
+            if id.len() as u64 != id_len {
+                return Err(
+                    io::Error::from(io::ErrorKind::UnexpectedEof).into()
+                );
+            }
+
+            let id = String::from_utf8_lossy(&id).into_owned();
+
+            let mut kind = [0; 1];
+            reader.read_exact(&mut kind)?;
+
+            let len = read_u64(reader)?;
+            let checksum = read_u32(reader)?;
+
+            // The length is not trusted until the checksum is validated, read
+            // it incrementally instead of allocating the whole buffer.
+            let mut payload = Vec::new();
+            reader.by_ref().take(len).read_to_end(&mut payload)?;
+
+            if payload.len() as u64 != len {
+                return Err(AssetPackError::Corrupted(id));
+            }
+
+            if crc32fast::hash(&payload) != checksum {
+                return Err(AssetPackError::Corrupted(id));
+            }
+
+            let data = match byte_to_kind(kind[0]) {
+                Some(AssetKind::Mesh) => decode_mesh(&payload),
+                Some(AssetKind::Texture) => decode_texture(&payload),
+                None => None,
+            }
+            .ok_or_else(|| AssetPackError::Corrupted(id.clone()))?;
+
+            entries.push(PackEntry { id, data });
+        }
+
+        Ok(Self { entries })
+    }
+}
+
+fn kind_to_byte(kind: AssetKind) -> u8 {
+    match kind {
+        AssetKind::Mesh => 0,
+        AssetKind::Texture => 1,
+    }
+}
+
+fn byte_to_kind(byte: u8) -> Option<AssetKind> {
+    match byte {
+        0 => Some(AssetKind::Mesh),
+        1 => Some(AssetKind::Texture),
+        _ => None,
+    }
+}
+
+fn format_to_byte(format: TextureFormat) -> u8 {
+    TextureFormat::ALL
+        .iter()
+        .position(|f| *f == format)
+        .expect("The format is not listed in TextureFormat::ALL") as u8
+}
+
+fn byte_to_format(byte: u8) -> Option<TextureFormat> {
+    TextureFormat::ALL.get(byte as usize).copied()
+}
+
+fn address_mode_to_byte(mode: AddressMode) -> u8 {
+    match mode {
+        AddressMode::Repeat => 0,
+        AddressMode::MirrorRepeat => 1,
+        AddressMode::ClampToEdge => 2,
+    }
+}
+
+fn byte_to_address_mode(byte: u8) -> Option<AddressMode> {
+    match byte {
+        0 => Some(AddressMode::Repeat),
+        1 => Some(AddressMode::MirrorRepeat),
+        2 => Some(AddressMode::ClampToEdge),
+        _ => None,
+    }
+}
+
+fn filter_to_byte(filter: FilterMode) -> u8 {
+    match filter {
+        FilterMode::Nearest => 0,
+        FilterMode::Linear => 1,
+    }
+}
+
+fn byte_to_filter(byte: u8) -> Option<FilterMode> {
+    match byte {
+        0 => Some(FilterMode::Nearest),
+        1 => Some(FilterMode::Linear),
+        _ => None,
+    }
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Returns the u32 stored at the provided offset.
+pub(super) fn u32_at(payload: &[u8], offset: usize) -> Option<u32> {
+    let bytes = payload.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes(bytes.try_into().ok()?))
+}
+
+fn decode_mesh(payload: &[u8]) -> Option<PackData> {
+    let vertex_count = u32_at(payload, 0)? as usize;
+    let index_count = u32_at(payload, 4)? as usize;
+
+    let vertex_size = std::mem::size_of::<Vertex>();
+    let vertices_end = 8 + vertex_count * vertex_size;
+
+    let vertices = payload
+        .get(8..vertices_end)?
+        .chunks_exact(vertex_size)
+        .map(bytemuck::pod_read_unaligned)
+        .collect();
+
+    let indices = payload
+        .get(vertices_end..vertices_end + index_count * 2)?
+        .chunks_exact(2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .collect();
+
+    Some(PackData::Mesh { vertices, indices })
+}
+
+fn decode_texture(payload: &[u8]) -> Option<PackData> {
+    let size = Size::new(u32_at(payload, 0)?, u32_at(payload, 4)?);
+    let flags = payload.get(8..15)?;
+    let format = byte_to_format(flags[0])?;
+
+    let sampler = SamplerSettings {
+        address_mode_u: byte_to_address_mode(flags[2])?,
+        address_mode_v: byte_to_address_mode(flags[3])?,
+        mag_filter: byte_to_filter(flags[4])?,
+        min_filter: byte_to_filter(flags[5])?,
+        mipmap_filter: byte_to_filter(flags[6])?,
+        anisotropy: u16::from_le_bytes(payload.get(15..17)?.try_into().ok()?),
+    };
+
+    let level_count = *payload.get(17)? as u32;
+
+    let mut texture = TextureData {
+        mipmaps: flags[1] != 0,
+        sampler,
+        ..TextureData::new(size, format, Vec::new())
+    };
+
+    // The size stored before the compressed levels is checked before it is
+    // allocated, a crafted pack could request gigabytes with a valid
+    // checksum.
+    let compressed = payload.get(18..)?;
+    if u32_at(compressed, 0)? as usize
+        != levels_size_in_bytes(&texture, level_count)?
+    {
+        return None;
+    }
+
+    let mut data = lz4_flex::decompress_size_prepended(compressed).ok()?;
+
+    // Splits the levels from the smallest one.
+    let mut levels = (0..level_count.min(u32::BITS))
+        .rev()
+        .map(|level| {
+            let length =
+                format.level_size_in_bytes(texture.mip_level_size(level));
+            let start = data.len().checked_sub(length)?;
+            Some(data.split_off(start))
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    if !data.is_empty() || levels.is_empty() {
+        return None;
+    }
+
+    levels.reverse();
+    texture.pixels = levels.remove(0);
+    texture.mip_levels = levels;
+
+    Some(PackData::Texture(texture))
+}
+
+/// Returns the number of bytes of the first `level_count` levels of the
+/// texture, `None` if it overflows.
+fn levels_size_in_bytes(
+    texture: &TextureData,
+    level_count: u32,
+) -> Option<usize> {
+    let (block_width, block_height) = texture.format.block_dimensions();
+
+    (0..level_count.min(u32::BITS)).try_fold(0_usize, |total, level| {
+        let size = texture.mip_level_size(level);
+        let columns = size.width.div_ceil(block_width) as usize;
+        let rows = size.height.div_ceil(block_height) as usize;
+
+        columns
+            .checked_mul(rows)?
+            .checked_mul(texture.format.block_size() as usize)?
+            .checked_add(total)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Offset of the payload of the first entry, the id is "quad".
+    const FIRST_PAYLOAD: usize = 12 + 4 + 4 + 1 + 8 + 4;
+
+    fn vertex(i: u16) -> Vertex {
+        Vertex {
+            pos: [i as f32, 1.0, 2.0],
+            col: [0.5; 3],
+            uv: [0.25, 0.75],
+            normal: [0.0, 1.0, 0.0],
+        }
+    }
+
+    fn test_pack() -> AssetPack {
+        let mut texture = TextureData::new(
+            Size::new(4, 2),
+            TextureFormat::Rgba8Linear,
+            (0..32).collect(),
+        );
+        texture.mip_levels = vec![(32..40).collect(), (40..44).collect()];
+        texture.mipmaps = true;
+        texture.sampler = SamplerSettings {
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::MirrorRepeat,
+            mag_filter: FilterMode::Nearest,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: FilterMode::Nearest,
+            anisotropy: 8,
+        };
+
+        AssetPack {
+            entries: vec![
+                PackEntry {
+                    id: "quad".to_owned(),
+                    data: PackData::Mesh {
+                        vertices: (0..4).map(vertex).collect(),
+                        indices: vec![0, 1, 2, 2, 3, 0],
+                    },
+                },
+                PackEntry {
+                    id: "checker".to_owned(),
+                    data: PackData::Texture(texture),
+                },
+            ],
+        }
+    }
+
+    /// Returns the offset of the payload of the texture entry, which follows
+    /// the quad.
+    fn texture_payload_offset() -> usize {
+        let mesh_payload = 8 + 4 * std::mem::size_of::<Vertex>() + 6 * 2;
+        FIRST_PAYLOAD + mesh_payload + 4 + "checker".len() + 1 + 8 + 4
+    }
+
+    fn written(pack: &AssetPack) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        pack.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn round_trip_keeps_all_the_entries() {
+        let pack = test_pack();
+        let read = AssetPack::read(&mut written(&pack).as_slice()).unwrap();
+
+        assert_eq!(read.entries.len(), 2);
+        assert_eq!(read.entries[0].id, "quad");
+        assert_eq!(read.entries[1].id, "checker");
+
+        match (&read.entries[0].data, &pack.entries[0].data) {
+            (
+                PackData::Mesh { vertices, indices },
+                PackData::Mesh {
+                    vertices: expected_vertices,
+                    indices: expected_indices,
+                },
+            ) => {
+                assert_eq!(
+                    bytemuck::cast_slice::<Vertex, u8>(vertices),
+                    bytemuck::cast_slice::<Vertex, u8>(expected_vertices)
+                );
+                assert_eq!(indices, expected_indices);
+            }
+            _ => panic!("The first entry is not a mesh"),
+        }
+
+        match (&read.entries[1].data, &pack.entries[1].data) {
+            (PackData::Texture(texture), PackData::Texture(expected)) => {
+                assert_eq!(texture.size.width, expected.size.width);
+                assert_eq!(texture.size.height, expected.size.height);
+                assert_eq!(texture.format, expected.format);
+                assert_eq!(texture.pixels, expected.pixels);
+                assert_eq!(texture.mip_levels, expected.mip_levels);
+                assert_eq!(texture.mipmaps, expected.mipmaps);
+                assert_eq!(texture.sampler, expected.sampler);
+            }
+            _ => panic!("The second entry is not a texture"),
+        }
+    }
+
+    #[test]
+    fn corrupted_payload_fails_the_checksum() {
+        let mut bytes = written(&test_pack());
+        bytes[FIRST_PAYLOAD] ^= 0xff;
+
+        let result = AssetPack::read(&mut bytes.as_slice());
+        assert!(
+            matches!(result, Err(AssetPackError::Corrupted(id)) if id == "quad")
+        );
+    }
+
+    #[test]
+    fn corrupted_checksum_is_an_error() {
+        let mut bytes = written(&test_pack());
+        bytes[FIRST_PAYLOAD - 1] ^= 0xff;
+
+        let result = AssetPack::read(&mut bytes.as_slice());
+        assert!(matches!(result, Err(AssetPackError::Corrupted(_))));
+    }
+
+    #[test]
+    fn truncated_pack_is_an_error() {
+        let bytes = written(&test_pack());
+
+        for len in 0..bytes.len() {
+            assert!(AssetPack::read(&mut &bytes[..len]).is_err());
+        }
+    }
+
+    #[test]
+    fn huge_lengths_are_an_error() {
+        let mut bytes = written(&test_pack());
+        bytes[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(AssetPack::read(&mut bytes.as_slice()).is_err());
+
+        let mut bytes = written(&test_pack());
+        bytes[21..29].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(AssetPack::read(&mut bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn mismatched_texture_size_is_an_error() {
+        // The levels of the texture use 44 bytes.
+        for size in [0, 43, 45, u32::MAX] {
+            let mut bytes = written(&test_pack());
+            let payload = texture_payload_offset();
+            bytes[payload + 18..payload + 22]
+                .copy_from_slice(&size.to_le_bytes());
+
+            // The checksum is updated, so only the size is wrong.
+            let checksum = crc32fast::hash(&bytes[payload..]);
+            bytes[payload - 4..payload]
+                .copy_from_slice(&checksum.to_le_bytes());
+
+            let result = AssetPack::read(&mut bytes.as_slice());
+            assert!(matches!(
+                result,
+                Err(AssetPackError::Corrupted(id)) if id == "checker"
+            ));
+        }
+    }
+
+    #[test]
+    fn invalid_header_is_an_error() {
+        let mut bytes = written(&test_pack());
+        bytes[0] = b'X';
+        let result = AssetPack::read(&mut bytes.as_slice());
+        assert!(matches!(result, Err(AssetPackError::InvalidMagic)));
+
+        let mut bytes = written(&test_pack());
+        bytes[4..8].copy_from_slice(&(ASSET_PACK_VERSION + 1).to_le_bytes());
+        let result = AssetPack::read(&mut bytes.as_slice());
+        assert!(matches!(
+            result,
+            Err(AssetPackError::UnsupportedVersion(v)) if v == ASSET_PACK_VERSION + 1
+        ));
+    }
+}
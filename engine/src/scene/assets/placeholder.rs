@@ -0,0 +1,92 @@
+use crate::{graphics::vertex::Vertex, types::Size};
+
+/// Contains the id of the mesh used while a mesh is still loading.
+pub const PLACEHOLDER_MESH_ID: &str = "PLACEHOLDER_MESH";
+/// Contains the id of the texture used while a texture is still loading.
+pub const PLACEHOLDER_TEXTURE_ID: &str = "PLACEHOLDER_TEXTURE";
+
+const PLACEHOLDER_COLOR: [f32; 3] = [1.0, 0.0, 1.0];
+
+/// A small magenta cube, easy to spot in the scene.
+pub(crate) const PLACEHOLDER_VERTICES: &[Vertex] = &[
+    Vertex {
+        pos: [-0.5, -0.5, 0.5],
+        col: PLACEHOLDER_COLOR,
+        uv: [0.0, 1.0],
+        normal: [-0.57735027, -0.57735027, 0.57735027],
+    },
+    Vertex {
+        pos: [0.5, -0.5, 0.5],
+        col: PLACEHOLDER_COLOR,
+        uv: [1.0, 1.0],
+        normal: [0.57735027, -0.57735027, 0.57735027],
+    },
+    Vertex {
+        pos: [0.5, 0.5, 0.5],
+        col: PLACEHOLDER_COLOR,
+        uv: [1.0, 0.0],
+        normal: [0.57735027, 0.57735027, 0.57735027],
+    },
+    Vertex {
+        pos: [-0.5, 0.5, 0.5],
+        col: PLACEHOLDER_COLOR,
+        uv: [0.0, 0.0],
+        normal: [-0.57735027, 0.57735027, 0.57735027],
+    },
+    Vertex {
+        pos: [-0.5, -0.5, -0.5],
+        col: PLACEHOLDER_COLOR,
+        uv: [0.0, 1.0],
+        normal: [-0.57735027, -0.57735027, -0.57735027],
+    },
+    Vertex {
+        pos: [0.5, -0.5, -0.5],
+        col: PLACEHOLDER_COLOR,
+        uv: [1.0, 1.0],
+        normal: [0.57735027, -0.57735027, -0.57735027],
+    },
+    Vertex {
+        pos: [0.5, 0.5, -0.5],
+        col: PLACEHOLDER_COLOR,
+        uv: [1.0, 0.0],
+        normal: [0.57735027, 0.57735027, -0.57735027],
+    },
+    Vertex {
+        pos: [-0.5, 0.5, -0.5],
+        col: PLACEHOLDER_COLOR,
+        uv: [0.0, 0.0],
+        normal: [-0.57735027, 0.57735027, -0.57735027],
+    },
+];
+
+#[rustfmt::skip]
+pub(crate) const PLACEHOLDER_INDICES: &[u16] = &[
+    0, 1, 2, 2, 3, 0, // front
+    5, 4, 7, 7, 6, 5, // back
+    4, 0, 3, 3, 7, 4, // left
+    1, 5, 6, 6, 2, 1, // right
+    3, 2, 6, 6, 7, 3, // top
+    4, 5, 1, 1, 0, 4, // bottom
+];
+
+/// Contains the size of the placeholder texture.
+pub(crate) const PLACEHOLDER_TEXTURE_SIZE: Size<u32> = Size {
+    width: 8,
+    height: 8,
+};
+
+/// Generates a magenta and black checker RGBA8 texture.
+pub(crate) fn placeholder_texture_data() -> Vec<u8> {
+    let Size { width, height } = PLACEHOLDER_TEXTURE_SIZE;
+
+    (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .flat_map(|(x, y)| {
+            if (x / 2 + y / 2) % 2 == 0 {
+                [255, 0, 255, 255]
+            } else {
+                [0, 0, 0, 255]
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,89 @@
+use std::{
+    sync::{
+        mpsc::{self, Sender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+type Task = Box<dyn FnOnce() + Send + 'static>;
+
+/// Max number of threads used to decode assets.
+const MAX_ASSET_THREADS: usize = 4;
+
+/// A small pool of threads used to decode assets from disk without blocking
+/// the main loop.
+pub(crate) struct AssetTaskPool {
+    sender: Mutex<Option<Sender<Task>>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl Default for AssetTaskPool {
+    fn default() -> Self {
+        let size = thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+            .min(MAX_ASSET_THREADS);
+
+        Self::new(size)
+    }
+}
+
+impl AssetTaskPool {
+    /// Creates and returns a new `AssetTaskPool` with `size` threads.
+    pub(crate) fn new(size: usize) -> Self {
+        let (sender, receiver) = mpsc::channel::<Task>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let workers = (0..size.max(1))
+            .map(|i| {
+                let receiver = receiver.clone();
+
+                thread::Builder::new()
+                    .name(format!("Asset loader {}", i))
+                    .spawn(move || loop {
+                        // The lock is released as soon as the task is
+                        // received so other workers can take the next one.
+                        let task = match receiver.lock() {
+                            Ok(r) => r.recv(),
+                            Err(_) => return,
+                        };
+
+                        match task {
+                            Ok(task) => task(),
+                            // The pool was dropped.
+                            Err(_) => return,
+                        }
+                    })
+                    .expect("Unable to spawn asset loader thread")
+            })
+            .collect();
+
+        Self {
+            sender: Mutex::new(Some(sender)),
+            workers,
+        }
+    }
+
+    /// Queues a new task to be executed in one of the threads.
+    pub(crate) fn spawn(&self, task: impl FnOnce() + Send + 'static) {
+        let sender = self.sender.lock().expect("Unable to acquire pool lock");
+
+        if let Some(sender) = sender.as_ref() {
+            let _ = sender.send(Box::new(task));
+        }
+    }
+}
+
+impl Drop for AssetTaskPool {
+    fn drop(&mut self) {
+        // Closing the channel makes all the workers leave their loop.
+        if let Ok(sender) = self.sender.get_mut() {
+            sender.take();
+        }
+
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
@@ -1,4 +1,7 @@
-use nalgebra::{Matrix4, Point3, Vector3};
+use nalgebra::{Matrix4, Point3, Vector2, Vector3};
+use shipyard::Component;
+
+use super::{projection::Projection, scene::SceneTarget};
 
 #[derive(Copy, Clone)]
 /// Represents the main world camera.
@@ -36,6 +39,16 @@ impl Camera {
         }
     }
 
+    /// Creates and returns a `Camera` placed with the transformation, it
+    /// looks along its -Z axis and its up is the Y axis.
+    pub fn from_matrix(matrix: &Matrix4<f32>) -> Self {
+        let position = matrix.transform_point(&Point3::origin());
+        let forward = matrix.transform_vector(&-Vector3::z());
+        let up = matrix.transform_vector(&Vector3::y());
+
+        Camera::new(position, position + forward.normalize(), up.normalize())
+    }
+
     /// Returns the view matrix based on the provided camera information.
     pub fn view_matrix(&self) -> Matrix4<f32> {
         Matrix4::look_at_rh(&self.position, &self.target, &self.up)
@@ -67,3 +80,167 @@ impl Camera {
         self.target.z += direction.z * amount;
     }
 }
+
+/// Rect of the target texture where a camera draws, in fractions of the
+/// texture size from its top left corner.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Viewport::FULL
+    }
+}
+
+impl Viewport {
+    /// Covers the whole texture.
+    pub const FULL: Viewport = Viewport {
+        x: 0.0,
+        y: 0.0,
+        width: 1.0,
+        height: 1.0,
+    };
+
+    /// Creates and returns a new `Viewport` using the provided rect.
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Viewport {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Returns the part of the viewport inside the texture, `None` if it is
+    /// outside or empty.
+    pub(crate) fn clamped(&self) -> Option<Viewport> {
+        let x = self.x.clamp(0.0, 1.0);
+        let y = self.y.clamp(0.0, 1.0);
+        let width = (self.x + self.width).clamp(0.0, 1.0) - x;
+        let height = (self.y + self.height).clamp(0.0, 1.0) - y;
+
+        (width > 0.0 && height > 0.0)
+            .then_some(Viewport::new(x, y, width, height))
+    }
+
+    /// Returns true if the point, in fractions of the texture size, is
+    /// inside the viewport.
+    pub fn contains_point(&self, point: Vector2<f32>) -> bool {
+        point.x >= self.x
+            && point.x <= self.x + self.width
+            && point.y >= self.y
+            && point.y <= self.y + self.height
+    }
+
+    /// Returns true if the viewport covers the whole `other` one.
+    pub fn contains(&self, other: &Viewport) -> bool {
+        other.x >= self.x
+            && other.y >= self.y
+            && other.x + other.width <= self.x + self.width
+            && other.y + other.height <= self.y + self.height
+    }
+
+    /// Returns true if the viewports share any area.
+    pub fn overlaps(&self, other: &Viewport) -> bool {
+        self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height
+    }
+}
+
+/// Draws the scene it targets from the `Transform` of its entity, which can
+/// be attached to other entities with a `Hierarchy`. The camera looks along
+/// the -Z axis of the entity.
+///
+/// The scenes without active cameras are drawn from their own `Camera`.
+#[derive(Component, Clone)]
+pub struct CameraComponent {
+    /// Contains the scene drawn by the camera.
+    pub target: SceneTarget,
+    /// Contains the `Projection` used, the aspect ratio of the perspective
+    /// ones follows the size of the viewport.
+    pub projection: Projection,
+    /// Contains the rect of the target texture where the camera draws.
+    pub viewport: Viewport,
+    /// The cameras with a higher priority are drawn on top of the others,
+    /// the one with the highest priority is the main camera of the scene.
+    pub priority: i32,
+    /// Determines if the camera draws the scene.
+    pub active: bool,
+}
+
+impl CameraComponent {
+    /// Creates and returns a new `CameraComponent` which draws the whole
+    /// target texture.
+    pub fn new(target: SceneTarget, projection: Projection) -> Self {
+        CameraComponent {
+            target,
+            projection,
+            viewport: Viewport::FULL,
+            priority: 0,
+            active: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn viewport_out_of_range_is_clamped() {
+        assert_eq!(
+            Viewport::new(-0.5, 0.25, 1.0, 1.0).clamped(),
+            Some(Viewport::new(0.0, 0.25, 0.5, 0.75))
+        );
+        assert_eq!(
+            Viewport::new(-1.0, -1.0, 3.0, 3.0).clamped(),
+            Some(Viewport::FULL)
+        );
+        assert_eq!(
+            Viewport::new(0.25, 0.5, 0.5, 0.25).clamped(),
+            Some(Viewport::new(0.25, 0.5, 0.5, 0.25))
+        );
+    }
+
+    #[test]
+    fn viewport_outside_or_empty_is_discarded() {
+        assert_eq!(Viewport::new(1.5, 0.0, 0.5, 1.0).clamped(), None);
+        assert_eq!(Viewport::new(0.0, -2.0, 1.0, 1.0).clamped(), None);
+        assert_eq!(Viewport::new(0.25, 0.25, 0.0, 0.5).clamped(), None);
+        assert_eq!(Viewport::new(0.5, 0.5, -0.25, 0.25).clamped(), None);
+    }
+
+    #[test]
+    fn viewport_contains_and_overlaps() {
+        let left = Viewport::new(0.0, 0.0, 0.5, 1.0);
+        let right = Viewport::new(0.5, 0.0, 0.5, 1.0);
+        let center = Viewport::new(0.25, 0.25, 0.5, 0.5);
+
+        assert!(Viewport::FULL.contains(&center));
+        assert!(center.contains(&center));
+        assert!(!center.contains(&Viewport::FULL));
+        assert!(!left.contains(&center));
+
+        // The views sharing an edge do not overlap.
+        assert!(!left.overlaps(&right));
+        assert!(left.overlaps(&center));
+        assert!(right.overlaps(&center));
+    }
+
+    #[test]
+    fn viewport_contains_point() {
+        let right = Viewport::new(0.5, 0.0, 0.5, 1.0);
+
+        assert!(right.contains_point(Vector2::new(0.75, 0.5)));
+        assert!(right.contains_point(Vector2::new(0.5, 0.0)));
+        assert!(!right.contains_point(Vector2::new(0.25, 0.5)));
+        assert!(!right.contains_point(Vector2::new(0.75, 1.5)));
+    }
+}
@@ -0,0 +1,126 @@
+use shipyard::Component;
+
+/// Controls the shadows cast by a light.
+#[derive(Clone, Copy, Debug)]
+pub struct ShadowSettings {
+    /// Offset subtracted from the depth compared with the shadow map, removes
+    /// the shadow acne of the surfaces facing the light.
+    pub depth_bias: f32,
+    /// Offset applied along the surface normal, removes the shadow acne of
+    /// the surfaces almost parallel to the light.
+    pub normal_bias: f32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            depth_bias: 0.001,
+            normal_bias: 0.02,
+        }
+    }
+}
+
+/// Lights the whole scene from one direction, like the sun. The light points
+/// to the negative Z axis of the entity `Transform`.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct DirectionalLight {
+    /// Linear RGB color.
+    pub color: [f32; 3],
+    pub intensity: f32,
+    /// The light casts shadows when it is set.
+    pub shadows: Option<ShadowSettings>,
+}
+
+impl Default for DirectionalLight {
+    fn default() -> Self {
+        Self {
+            color: [1.0, 1.0, 1.0],
+            intensity: 1.0,
+            shadows: None,
+        }
+    }
+}
+
+/// Emits light in all directions from the position of the entity
+/// `Transform`.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct PointLight {
+    /// Linear RGB color.
+    pub color: [f32; 3],
+    pub intensity: f32,
+    /// Distance where the light stops affecting the surfaces.
+    pub range: f32,
+}
+
+impl Default for PointLight {
+    fn default() -> Self {
+        Self {
+            color: [1.0, 1.0, 1.0],
+            intensity: 10.0,
+            range: 10.0,
+        }
+    }
+}
+
+/// Emits a cone of light from the position of the entity `Transform`
+/// pointing to its negative Z axis.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct SpotLight {
+    /// Linear RGB color.
+    pub color: [f32; 3],
+    pub intensity: f32,
+    /// Distance where the light stops affecting the surfaces.
+    pub range: f32,
+    /// Angle in radians where the light starts to fade.
+    pub inner_angle: f32,
+    /// Angle in radians where the light is not visible anymore.
+    pub outer_angle: f32,
+    /// The light casts shadows when it is set.
+    pub shadows: Option<ShadowSettings>,
+}
+
+impl Default for SpotLight {
+    fn default() -> Self {
+        Self {
+            color: [1.0, 1.0, 1.0],
+            intensity: 10.0,
+            range: 10.0,
+            inner_angle: 20.0_f32.to_radians(),
+            outer_angle: 30.0_f32.to_radians(),
+            shadows: None,
+        }
+    }
+}
+
+/// Max number of lights of each type used to render a `Scene`. When there are
+/// more lights the ones closer to the camera are used.
+#[derive(Clone, Copy, Debug)]
+pub struct LightLimits {
+    pub directional: u32,
+    pub point: u32,
+    pub spot: u32,
+    /// Max number of directional lights casting shadows, each one uses a
+    /// shadow map per cascade.
+    pub directional_shadows: u32,
+    /// Max number of spot lights casting shadows.
+    pub spot_shadows: u32,
+}
+
+impl Default for LightLimits {
+    fn default() -> Self {
+        Self {
+            directional: 4,
+            point: 64,
+            spot: 16,
+            directional_shadows: 1,
+            spot_shadows: 4,
+        }
+    }
+}
+
+impl LightLimits {
+    /// Returns the max number of lights of all the types.
+    pub fn total(&self) -> u32 {
+        self.directional + self.point + self.spot
+    }
+}
@@ -3,7 +3,9 @@ pub mod camera;
 pub mod components;
 pub mod hierarchy;
 pub mod input;
+pub mod lights;
 pub mod perspective;
+pub mod post_processing;
 pub mod projection;
 pub mod scene;
 pub mod scene_state;
@@ -0,0 +1,52 @@
+use super::assets::TextureResourceID;
+
+/// Max number of post processing effects applied to a `Scene`, the
+/// remaining ones are ignored.
+pub const MAX_POST_PROCESS_EFFECTS: usize = 16;
+
+/// Curve used to map the HDR colors into the range shown by the screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tonemapping {
+    /// Compresses each channel, cheap but the image looks a bit flat.
+    Reinhard,
+    /// Filmic curve with a contrasted look, the very bright colors are
+    /// oversaturated.
+    Aces,
+    /// Desaturates the bright colors smoothly so they end up white.
+    Agx,
+}
+
+/// Effect applied to the HDR image of a `Scene` before it is written into
+/// its target texture, the effects are applied in the order they are listed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PostProcessEffect {
+    /// Scales the colors by 2 raised to the provided EV.
+    Exposure(f32),
+    Tonemapping(Tonemapping),
+    /// Makes the areas brighter than `threshold` glow, it should be applied
+    /// before the tonemapping.
+    Bloom {
+        threshold: f32,
+        intensity: f32,
+    },
+    /// Remaps the colors using a lookup table. The table is a strip of N
+    /// slices of NxN pixels placed side by side, the red channel grows to
+    /// the right of each slice, the green one downwards and the blue one
+    /// across the slices. The effect is skipped while the texture loads.
+    ColorGrading {
+        lut: TextureResourceID,
+    },
+    /// Raises the colors to `1 / gamma`, the target texture is already sRGB
+    /// encoded so 1.0 leaves the image untouched.
+    Gamma(f32),
+}
+
+impl PostProcessEffect {
+    /// Returns the texture read by the effect.
+    pub(crate) fn texture(&self) -> Option<&TextureResourceID> {
+        match self {
+            PostProcessEffect::ColorGrading { lut } => Some(lut),
+            _ => None,
+        }
+    }
+}
@@ -77,6 +77,14 @@ impl Projection {
         }
     }
 
+    /// Returns the distance to the near and far planes.
+    pub fn depth_range(&self) -> (f32, f32) {
+        match self {
+            Projection::Perspective { znear, zfar, .. }
+            | Projection::Orthograpic { znear, zfar, .. } => (*znear, *zfar),
+        }
+    }
+
     /// Returns the perspective in form of matrix.
     pub fn matrix(&self) -> Matrix4<f32> {
         match &self {
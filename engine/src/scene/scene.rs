@@ -1,16 +1,114 @@
 use shipyard::Component;
 
 use crate::{
-    scene::{camera::Camera, projection::Projection},
+    scene::{
+        camera::Camera, lights::LightLimits,
+        post_processing::PostProcessEffect, projection::Projection,
+    },
     types::Size,
 };
 
-#[derive(Component)]
+#[derive(Component, Clone, Debug, PartialEq, Eq)]
 pub enum SceneTarget {
     Main,
     SubScene(String),
 }
 
+/// Contains the ambient color used by the scenes by default.
+pub const DEFAULT_AMBIENT_COLOR: [f32; 3] = [0.1, 0.1, 0.1];
+
+/// Contains the distance covered by the directional shadows by default.
+pub const DEFAULT_SHADOW_DISTANCE: f32 = 100.0;
+
+/// Technique used to smooth the edges of the geometry of a `Scene`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AntiAliasing {
+    #[default]
+    Off,
+    /// Renders multiple samples per pixel, if the GPU does not support the
+    /// sample count the highest supported one is used.
+    Msaa2,
+    Msaa4,
+    Msaa8,
+    /// Smooths the edges in a post process pass, it is cheaper than MSAA but
+    /// it blurs the textures a bit.
+    Fxaa,
+}
+
+impl AntiAliasing {
+    /// Returns the number of samples per pixel of the render targets.
+    pub fn sample_count(&self) -> u32 {
+        match self {
+            AntiAliasing::Off | AntiAliasing::Fxaa => 1,
+            AntiAliasing::Msaa2 => 2,
+            AntiAliasing::Msaa4 => 4,
+            AntiAliasing::Msaa8 => 8,
+        }
+    }
+
+    /// Returns the MSAA mode which uses the provided sample count.
+    pub(crate) fn from_sample_count(sample_count: u32) -> Self {
+        match sample_count {
+            2 => AntiAliasing::Msaa2,
+            4 => AntiAliasing::Msaa4,
+            8 => AntiAliasing::Msaa8,
+            _ => AntiAliasing::Off,
+        }
+    }
+}
+
+/// Replaces the shading of the meshes of a `Scene` to inspect its geometry.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DebugView {
+    #[default]
+    Lit,
+    /// Draws the edges of the triangles on top of the lit meshes, it is the
+    /// same as `Lit` if the GPU can't draw lines.
+    Wireframe,
+    /// World space normals mapped to colors.
+    Normals,
+    /// Distance to the camera, brighter surfaces are closer.
+    Depth,
+    /// Checkerboard drawn with the texture coordinates.
+    UvChecker,
+    /// Adds a color for each mesh drawn on a pixel, the brighter areas are
+    /// drawn more times.
+    Overdraw,
+}
+
+impl DebugView {
+    pub const ALL: [DebugView; 6] = [
+        DebugView::Lit,
+        DebugView::Wireframe,
+        DebugView::Normals,
+        DebugView::Depth,
+        DebugView::UvChecker,
+        DebugView::Overdraw,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DebugView::Lit => "Lit",
+            DebugView::Wireframe => "Wireframe",
+            DebugView::Normals => "Normals",
+            DebugView::Depth => "Depth",
+            DebugView::UvChecker => "UV checker",
+            DebugView::Overdraw => "Overdraw",
+        }
+    }
+
+    /// Returns the value the shaders use to identify the view.
+    pub(crate) fn shader_mode(&self) -> u32 {
+        match self {
+            DebugView::Lit | DebugView::Wireframe => 0,
+            DebugView::Normals => 1,
+            DebugView::Depth => 2,
+            DebugView::UvChecker => 3,
+            DebugView::Overdraw => 4,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct SceneDescriptor {
     /// Contains a debug tag.
@@ -27,6 +125,20 @@ pub struct SceneDescriptor {
     pub should_render_grid: bool,
     /// Determins if the scene should render a sky or not.
     pub should_render_sky: bool,
+    /// Linear RGB light applied to all the surfaces.
+    pub ambient_color: [f32; 3],
+    /// Contains the max number of lights rendered.
+    pub light_limits: LightLimits,
+    /// Distance from the camera covered by the shadows of the directional
+    /// lights, the surfaces further away are not shadowed.
+    pub shadow_distance: f32,
+    /// Contains the technique used to smooth the edges.
+    pub anti_aliasing: AntiAliasing,
+    /// Contains the effects applied in order to the HDR image, without
+    /// effects the colors are just clamped.
+    pub post_processing: Vec<PostProcessEffect>,
+    /// Contains the view used to inspect the geometry of the scene.
+    pub debug_view: DebugView,
 }
 
 impl SceneDescriptor {
@@ -41,6 +153,12 @@ impl SceneDescriptor {
             resolution: Some(Size::new(2048, 1600)),
             should_render_grid: false,
             should_render_sky: false,
+            ambient_color: DEFAULT_AMBIENT_COLOR,
+            light_limits: LightLimits::default(),
+            shadow_distance: DEFAULT_SHADOW_DISTANCE,
+            anti_aliasing: AntiAliasing::default(),
+            post_processing: Vec::new(),
+            debug_view: DebugView::default(),
         }
     }
 }
@@ -2,24 +2,36 @@ use wgpu::{Buffer, BufferUsages};
 
 use crate::{
     graphics::{
-        BindGroup, BufferUsage, IndexBuffer, Texture, UniformBuffer,
-        VertexBuffer,
+        BindGroup, BufferUsage, IndexBuffer, StorageBuffer, Texture,
+        UniformBuffer, VertexBuffer,
     },
     types::Size,
 };
 
 pub struct WgpuVertexBuffer(pub(crate) Buffer);
 
-impl VertexBuffer for WgpuVertexBuffer {}
+impl VertexBuffer for WgpuVertexBuffer {
+    fn size_in_bytes(&self) -> u64 {
+        self.0.size()
+    }
+}
 
 pub struct WgpuIndexBuffer(pub(crate) Buffer);
 
-impl IndexBuffer for WgpuIndexBuffer {}
+impl IndexBuffer for WgpuIndexBuffer {
+    fn size_in_bytes(&self) -> u64 {
+        self.0.size()
+    }
+}
 
 pub struct WgpuUniformBuffer(pub(crate) Buffer);
 
 impl UniformBuffer for WgpuUniformBuffer {}
 
+pub struct WgpuStorageBuffer(pub(crate) Buffer);
+
+impl StorageBuffer for WgpuStorageBuffer {}
+
 pub struct WGPUBindGroup(pub(crate) wgpu::BindGroup);
 impl BindGroup for WGPUBindGroup {}
 
@@ -31,10 +43,46 @@ pub struct WGPUTexture {
 
 impl Texture for WGPUTexture {
     fn size(&self) -> Size<u32> {
-        Size::new(self.texture.size().width, self.texture.size().width)
+        Size::new(self.texture.size().width, self.texture.size().height)
+    }
+
+    fn size_in_bytes(&self) -> u64 {
+        let size = self.texture.size();
+        let format = self.texture.format();
+        // Depth formats do not have a defined block size, the driver decides.
+        let block_size = format.block_size(None).unwrap_or(4) as u64;
+        // The compressed formats store a block of several pixels.
+        let (block_width, block_height) = format.block_dimensions();
+
+        let base = size.width.div_ceil(block_width) as u64
+            * size.height.div_ceil(block_height) as u64
+            * size.depth_or_array_layers as u64
+            * self.texture.sample_count() as u64
+            * block_size;
+
+        // Each mip level is a quarter of the previous one.
+        (0..self.texture.mip_level_count())
+            .map(|level| base >> (2 * level))
+            .sum()
     }
 }
 
+/// Returns the view of a texture allocated by the WGPU `Gpu`.
+pub(crate) fn texture_view(texture: &dyn Texture) -> &wgpu::TextureView {
+    &texture
+        .downcast_ref::<WGPUTexture>()
+        .expect("Incorrect texture type, expecting WGPUTexture")
+        .view
+}
+
+/// Returns the bind group created by the WGPU `Gpu`.
+pub(crate) fn wgpu_bind_group(bind_group: &dyn BindGroup) -> &wgpu::BindGroup {
+    &bind_group
+        .downcast_ref::<WGPUBindGroup>()
+        .expect("Incorrect bind group type, expecting WGPUBindGroup")
+        .0
+}
+
 // TODO(Angel): Impl `into` instead.
 pub(crate) fn map_usages(usage: BufferUsage) -> BufferUsages {
     match usage {
@@ -1,35 +1,52 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
 use bytemuck::{AnyBitPattern, Pod};
 use wgpu::{
     util::{BufferInitDescriptor, DeviceExt},
-    Adapter, Buffer, BufferAddress, BufferUsages, Device, DeviceDescriptor,
-    Extent3d, Features, Limits, Queue, RequestAdapterOptions,
-    SamplerDescriptor, ShaderModule, Surface, SurfaceConfiguration,
-    TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
-    TextureViewDescriptor, COPY_BUFFER_ALIGNMENT,
+    Adapter, AstcBlock, AstcChannel, Buffer, BufferAddress, BufferUsages,
+    Device, DeviceDescriptor, Extent3d, Features, Limits, Queue,
+    RequestAdapterOptions, Sampler, SamplerDescriptor, ShaderModule, Surface,
+    SurfaceConfiguration, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureUsages, TextureViewDescriptor, COPY_BUFFER_ALIGNMENT,
 };
 
 use crate::{
     graphics::{
-        gpu::GpuAbstractor, BufferCreator, BufferHandler, IndexBuffer,
-        ShaderHandler, SurfaceHandler, Texture, UniformBuffer, VertexBuffer,
+        gpu::GpuAbstractor,
+        texture::{
+            self, AddressMode, FilterMode, SamplerSettings, TextureData,
+        },
+        BufferCreator, BufferHandler, IndexBuffer, ShaderHandler,
+        StorageBuffer, SurfaceHandler, Texture, UniformBuffer, VertexBuffer,
     },
     host::window::Window,
     types::Size,
 };
 
 use super::buffer::{
-    map_usages, WGPUTexture, WgpuIndexBuffer, WgpuUniformBuffer,
-    WgpuVertexBuffer,
+    map_usages, WGPUTexture, WgpuIndexBuffer, WgpuStorageBuffer,
+    WgpuUniformBuffer, WgpuVertexBuffer,
 };
 
 pub(crate) const DEPTH_TEXTURE_FORMAT: TextureFormat =
     TextureFormat::Depth32Float;
 
+/// Format of the textures where the final image of the scenes is written.
+pub(crate) const TARGET_TEXTURE_FORMAT: TextureFormat =
+    TextureFormat::Bgra8UnormSrgb;
+
+/// Format of the textures where the scenes are rendered and post processed,
+/// the lighting can exceed 1.0.
+pub(crate) const HDR_TEXTURE_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// Format of the textures which store the lighting computed from the sky.
+pub(crate) const ENVIRONMENT_TEXTURE_FORMAT: TextureFormat =
+    TextureFormat::Rgba16Float;
+
 /// Holds all the essential information required for GPU interaction.
 pub struct Gpu {
     pub surface: Surface,
     /// Represents a physical GPU device available in the system.
-    #[allow(dead_code)]
     pub adapter: Adapter,
     /// Represents a logical device that facilitates interaction with the
     /// underlying physical GPU (Adapter).
@@ -37,6 +54,8 @@ pub struct Gpu {
     pub queue: Queue,
     pub surface_config: SurfaceConfiguration,
     pub texture_format: TextureFormat,
+    /// Bytes written to the buffers since they were last taken.
+    uploaded_bytes: AtomicU64,
 }
 
 impl Gpu {
@@ -60,7 +79,15 @@ impl Gpu {
             .request_device(
                 &DeviceDescriptor {
                     label: None,
-                    features: Features::all_webgpu_mask(),
+                    // Only the features of the adapter are requested, the
+                    // compressed textures are decoded when their feature is
+                    // missing. The adapter specific features allow sample
+                    // counts other than 4, the line polygon mode draws the
+                    // wireframes.
+                    features: adapter.features()
+                        & (Features::all_webgpu_mask()
+                            | Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+                            | Features::POLYGON_MODE_LINE),
                     limits: Limits::default()
                         .using_resolution(adapter.limits()),
                 },
@@ -93,6 +120,7 @@ impl Gpu {
             queue,
             surface_config,
             texture_format,
+            uploaded_bytes: AtomicU64::new(0),
         }
     }
 
@@ -109,6 +137,23 @@ impl Gpu {
             })
     }
 
+    /// Runs `create` and returns the validation errors of the objects it
+    /// creates instead of panicking, the errors of the shaders contain the
+    /// diagnostic of the WGSL compiler.
+    pub(crate) fn validate<T>(
+        &self,
+        create: impl FnOnce() -> T,
+    ) -> Result<T, String> {
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+
+        let created = create();
+
+        match futures_lite::future::block_on(self.device.pop_error_scope()) {
+            Some(e) => Err(e.to_string()),
+            None => Ok(created),
+        }
+    }
+
     /// Allocates and initilizes a chunk of memory on the GPU.
     pub(crate) fn allocate_buffer_init<T: Pod + AnyBitPattern>(
         &self,
@@ -116,9 +161,12 @@ impl Gpu {
         content: T,
         usage: BufferUsages,
     ) -> Buffer {
+        let contents = bytemuck::cast_slice(&[content]);
+        self.count_upload(contents.len());
+
         self.device.create_buffer_init(&BufferInitDescriptor {
             label: Some(label),
-            contents: bytemuck::cast_slice(&[content]),
+            contents,
             usage,
         })
     }
@@ -129,6 +177,8 @@ impl Gpu {
         content: &[u8],
         usage: BufferUsages,
     ) -> Buffer {
+        self.count_upload(content.len());
+
         self.device.create_buffer_init(&BufferInitDescriptor {
             label: Some(label),
             contents: content,
@@ -142,14 +192,10 @@ impl Gpu {
         size: u64,
         usage: BufferUsages,
     ) -> Buffer {
-        let padded_size = match size.checked_sub(COPY_BUFFER_ALIGNMENT - 1) {
-            Some(subtracted) => subtracted & !(COPY_BUFFER_ALIGNMENT - 1),
-            None => 0,
-        };
-
         self.device.create_buffer(&wgpu::BufferDescriptor {
             label: Some(label),
-            size: padded_size,
+            // The size is rounded up so the whole content fits.
+            size: wgpu::util::align_to(size, COPY_BUFFER_ALIGNMENT),
             usage,
             mapped_at_creation: false, // We don't need it mapped at creation
         })
@@ -166,84 +212,203 @@ impl Gpu {
             Some(subtracted) => subtracted & !(COPY_BUFFER_ALIGNMENT - 1),
             None => 0,
         };
-        self.queue.write_buffer(buffer, aligned_offset, data);
+        self.write_buffer(buffer, aligned_offset, data);
+    }
+
+    /// Schedules a write of the buffer and counts the uploaded bytes.
+    pub(crate) fn write_buffer(
+        &self,
+        buffer: &Buffer,
+        offset: BufferAddress,
+        data: &[u8],
+    ) {
+        self.count_upload(data.len());
+        self.queue.write_buffer(buffer, offset, data);
+    }
+
+    /// Returns the bytes written to the buffers since the last call.
+    pub(crate) fn take_uploaded_bytes(&self) -> u64 {
+        self.uploaded_bytes.swap(0, Ordering::Relaxed)
+    }
+
+    fn count_upload(&self, bytes: usize) {
+        self.uploaded_bytes
+            .fetch_add(bytes as u64, Ordering::Relaxed);
     }
 
+    /// Returns true if the texture can be uploaded as it is stored. The
+    /// compressed formats require a feature of the adapter and a size
+    /// multiple of their blocks.
+    pub(crate) fn supports_texture(&self, data: &TextureData) -> bool {
+        let format = texture_format(data.format);
+        let (block_width, block_height) = format.block_dimensions();
+
+        self.device.features().contains(format.required_features())
+            && data.size.width % block_width == 0
+            && data.size.height % block_height == 0
+    }
+
+    /// Uploads the texture and its mip chain, the sampler is created from
+    /// the settings of the texture.
     pub(crate) fn allocate_texture(
         &self,
         label: &str,
-        width: u32,
-        height: u32,
-        diffuse: &[u8],
+        data: &TextureData,
     ) -> WGPUTexture {
         let texture = self.device.create_texture(&TextureDescriptor {
             label: Some(label),
             size: Extent3d {
-                width,
-                height,
+                width: data.size.width,
+                height: data.size.height,
                 depth_or_array_layers: 1,
             },
-            mip_level_count: 1,
+            mip_level_count: data.mip_level_count(),
             sample_count: 1,
             dimension: TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            format: texture_format(data.format),
             usage: TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
             view_formats: &[],
         });
 
         let view = texture.create_view(&TextureViewDescriptor::default());
+        let sampler = self.allocate_sampler(label, &data.sampler);
+
+        let (block_width, block_height) = data.format.block_dimensions();
+
+        for (mip_level, (size, pixels)) in data.levels().iter().enumerate() {
+            self.queue.write_texture(
+                // Tells wgpu where to copy the pixel data
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: mip_level as u32,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                // The actual pixel data
+                pixels,
+                // The layout of the texture, the rows of the compressed
+                // formats are rows of blocks.
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(data.format.bytes_per_row(size.width)),
+                    rows_per_image: Some(size.height.div_ceil(block_height)),
+                },
+                // The levels smaller than a block still cover a whole one.
+                wgpu::Extent3d {
+                    width: size.width.next_multiple_of(block_width),
+                    height: size.height.next_multiple_of(block_height),
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
 
-        let sampler = self.device.create_sampler(&SamplerDescriptor {
+        WGPUTexture {
+            texture,
+            view,
+            sampler: Some(sampler),
+        }
+    }
+
+    /// Creates a sampler from the settings of a texture.
+    pub(crate) fn allocate_sampler(
+        &self,
+        label: &str,
+        settings: &SamplerSettings,
+    ) -> Sampler {
+        // The anisotropic filtering is only valid with linear filters.
+        let anisotropy_clamp = if settings.mag_filter == FilterMode::Linear
+            && settings.min_filter == FilterMode::Linear
+            && settings.mipmap_filter == FilterMode::Linear
+        {
+            settings.anisotropy.clamp(1, 16)
+        } else {
+            1
+        };
+
+        self.device.create_sampler(&SamplerDescriptor {
             label: Some(label),
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_u: address_mode(settings.address_mode_u),
+            address_mode_v: address_mode(settings.address_mode_v),
             address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Linear,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            compare: Some(wgpu::CompareFunction::LessEqual), // 5.
+            mag_filter: filter_mode(settings.mag_filter),
+            min_filter: filter_mode(settings.min_filter),
+            mipmap_filter: filter_mode(settings.mipmap_filter),
             lod_min_clamp: 0.0,
             lod_max_clamp: 100.0,
+            anisotropy_clamp,
             ..Default::default()
-        });
+        })
+    }
+}
 
-        let size = wgpu::Extent3d {
-            width,
-            height,
-            depth_or_array_layers: 1,
-        };
+fn texture_format(format: texture::TextureFormat) -> TextureFormat {
+    let astc = |block, srgb| TextureFormat::Astc {
+        block,
+        channel: if srgb {
+            AstcChannel::UnormSrgb
+        } else {
+            AstcChannel::Unorm
+        },
+    };
+
+    match format {
+        texture::TextureFormat::Rgba8Srgb => TextureFormat::Rgba8UnormSrgb,
+        texture::TextureFormat::Rgba8Linear => TextureFormat::Rgba8Unorm,
+        texture::TextureFormat::Rgba16Float => TextureFormat::Rgba16Float,
+        texture::TextureFormat::Rgba32Float => TextureFormat::Rgba32Float,
+        texture::TextureFormat::Bc1Srgb => TextureFormat::Bc1RgbaUnormSrgb,
+        texture::TextureFormat::Bc1Linear => TextureFormat::Bc1RgbaUnorm,
+        texture::TextureFormat::Bc3Srgb => TextureFormat::Bc3RgbaUnormSrgb,
+        texture::TextureFormat::Bc3Linear => TextureFormat::Bc3RgbaUnorm,
+        texture::TextureFormat::Bc4R => TextureFormat::Bc4RUnorm,
+        texture::TextureFormat::Bc5Rg => TextureFormat::Bc5RgUnorm,
+        texture::TextureFormat::Bc7Srgb => TextureFormat::Bc7RgbaUnormSrgb,
+        texture::TextureFormat::Bc7Linear => TextureFormat::Bc7RgbaUnorm,
+        texture::TextureFormat::Etc2Rgb8Srgb => {
+            TextureFormat::Etc2Rgb8UnormSrgb
+        }
+        texture::TextureFormat::Etc2Rgb8Linear => TextureFormat::Etc2Rgb8Unorm,
+        texture::TextureFormat::Etc2Rgba8Srgb => {
+            TextureFormat::Etc2Rgba8UnormSrgb
+        }
+        texture::TextureFormat::Etc2Rgba8Linear => {
+            TextureFormat::Etc2Rgba8Unorm
+        }
+        texture::TextureFormat::Astc4x4Srgb => astc(AstcBlock::B4x4, true),
+        texture::TextureFormat::Astc4x4Linear => astc(AstcBlock::B4x4, false),
+        texture::TextureFormat::Astc6x6Srgb => astc(AstcBlock::B6x6, true),
+        texture::TextureFormat::Astc6x6Linear => astc(AstcBlock::B6x6, false),
+        texture::TextureFormat::Astc8x8Srgb => astc(AstcBlock::B8x8, true),
+        texture::TextureFormat::Astc8x8Linear => astc(AstcBlock::B8x8, false),
+    }
+}
 
-        self.queue.write_texture(
-            // Tells wgpu where to copy the pixel data
-            wgpu::ImageCopyTexture {
-                texture: &texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            // The actual pixel data
-            diffuse,
-            // The layout of the texture
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(4 * width),
-                rows_per_image: Some(height),
-            },
-            size,
-        );
+fn address_mode(mode: AddressMode) -> wgpu::AddressMode {
+    match mode {
+        AddressMode::Repeat => wgpu::AddressMode::Repeat,
+        AddressMode::MirrorRepeat => wgpu::AddressMode::MirrorRepeat,
+        AddressMode::ClampToEdge => wgpu::AddressMode::ClampToEdge,
+    }
+}
 
-        WGPUTexture {
-            texture,
-            view,
-            sampler: Some(sampler),
-        }
+fn filter_mode(filter: FilterMode) -> wgpu::FilterMode {
+    match filter {
+        FilterMode::Nearest => wgpu::FilterMode::Nearest,
+        FilterMode::Linear => wgpu::FilterMode::Linear,
     }
 }
 
 impl GpuAbstractor for Gpu {}
 
 impl ShaderHandler for Gpu {
-    fn compile_program(&self) {}
+    fn validate_program(
+        &self,
+        label: &str,
+        source: &str,
+    ) -> Result<(), String> {
+        self.validate(|| self.compile_program(label, source))
+            .map(|_| ())
+    }
 }
 
 impl SurfaceHandler for Gpu {
@@ -259,6 +424,8 @@ impl BufferCreator for Gpu {
         label: &str,
         data: &[u8],
     ) -> Box<dyn VertexBuffer> {
+        self.count_upload(data.len());
+
         let buffer = self.device.create_buffer_init(&BufferInitDescriptor {
             label: Some(label),
             contents: data,
@@ -274,6 +441,8 @@ impl BufferCreator for Gpu {
         label: &str,
         data: &[u8],
     ) -> Box<dyn IndexBuffer> {
+        self.count_upload(data.len());
+
         let buffer = self.device.create_buffer_init(&BufferInitDescriptor {
             label: Some(label),
             contents: data,
@@ -288,6 +457,7 @@ impl BufferCreator for Gpu {
         label: &str,
         width: u32,
         height: u32,
+        sample_count: u32,
     ) -> Box<dyn Texture> {
         let texture = self.device.create_texture(&TextureDescriptor {
             label: Some(label),
@@ -297,7 +467,7 @@ impl BufferCreator for Gpu {
                 depth_or_array_layers: 1,
             },
             mip_level_count: 1,
-            sample_count: 1,
+            sample_count,
             dimension: TextureDimension::D2,
             format: DEPTH_TEXTURE_FORMAT,
             usage: TextureUsages::RENDER_ATTACHMENT
@@ -333,6 +503,7 @@ impl BufferCreator for Gpu {
         label: &str,
         width: u32,
         height: u32,
+        sample_count: u32,
     ) -> Box<dyn Texture> {
         let texture = self.device.create_texture(&TextureDescriptor {
             label: Some(label),
@@ -342,9 +513,9 @@ impl BufferCreator for Gpu {
                 depth_or_array_layers: 1,
             },
             mip_level_count: 1,
-            sample_count: 1,
+            sample_count,
             dimension: TextureDimension::D2,
-            format: wgpu::TextureFormat::Bgra8UnormSrgb,
+            format: TARGET_TEXTURE_FORMAT,
             usage: TextureUsages::RENDER_ATTACHMENT
                 | TextureUsages::TEXTURE_BINDING,
             view_formats: &[],
@@ -369,6 +540,65 @@ impl BufferCreator for Gpu {
         })
     }
 
+    fn allocate_hdr_texture(
+        &self,
+        label: &str,
+        width: u32,
+        height: u32,
+        sample_count: u32,
+    ) -> Box<dyn Texture> {
+        let texture = self.device.create_texture(&TextureDescriptor {
+            label: Some(label),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: TextureDimension::D2,
+            format: HDR_TEXTURE_FORMAT,
+            usage: TextureUsages::RENDER_ATTACHMENT
+                | TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&TextureViewDescriptor::default());
+
+        Box::new(WGPUTexture {
+            texture,
+            view,
+            sampler: None,
+        })
+    }
+
+    fn supported_sample_count(&self, requested: u32) -> u32 {
+        let is_supported =
+            |format: TextureFormat, count: u32| {
+                // Without the adapter specific features only 4 samples are
+                // guaranteed.
+                if !self.device.features().contains(
+                    Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
+                ) {
+                    return count == 4;
+                }
+
+                self.adapter
+                    .get_texture_format_features(format)
+                    .flags
+                    .sample_count_supported(count)
+            };
+
+        [8, 4, 2]
+            .into_iter()
+            .filter(|count| *count <= requested)
+            .find(|count| {
+                is_supported(HDR_TEXTURE_FORMAT, *count)
+                    && is_supported(DEPTH_TEXTURE_FORMAT, *count)
+            })
+            .unwrap_or(1)
+    }
+
     fn allocate_cubemap_texture(
         &self,
         label: &str,
@@ -417,6 +647,103 @@ impl BufferCreator for Gpu {
         })
     }
 
+    fn allocate_shadow_maps_texture(
+        &self,
+        label: &str,
+        size: u32,
+        layers: u32,
+    ) -> Box<dyn Texture> {
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(format!("{} texture", label).as_ref()),
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: layers,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_TEXTURE_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        // The dimension must be explicit, a single layer is a 2D texture by
+        // default.
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some(format!("{} view", label).as_str()),
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+
+        let sampler = self.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some(format!("{} sampler", label).as_str()),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+
+        Box::new(WGPUTexture {
+            texture,
+            view,
+            sampler: Some(sampler),
+        })
+    }
+
+    fn allocate_environment_cubemap_texture(
+        &self,
+        label: &str,
+        size: u32,
+        mip_level_count: u32,
+    ) -> Box<dyn Texture> {
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(format!("{} texture", label).as_ref()),
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 6,
+            },
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            // Unlike `Rgba32Float` it can be filtered without extra features.
+            format: ENVIRONMENT_TEXTURE_FORMAT,
+            usage: wgpu::TextureUsages::STORAGE_BINDING
+                | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some(format!("{} view", label).as_str()),
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            array_layer_count: Some(6),
+            ..Default::default()
+        });
+
+        let sampler = self.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some(format!("{} sampler", label).as_str()),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Box::new(WGPUTexture {
+            texture,
+            view,
+            sampler: Some(sampler),
+        })
+    }
+
     fn allocate_uniform_buffer(
         &self,
         label: &str,
@@ -443,6 +770,22 @@ impl BufferCreator for Gpu {
             BufferUsages::VERTEX | map_usages(uses),
         )))
     }
+
+    fn allocate_storage_buffer(
+        &self,
+        label: &str,
+        size: u64,
+    ) -> Box<dyn StorageBuffer> {
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            // Storage buffers must be a multiple of 4 bytes.
+            size: wgpu::util::align_to(size, COPY_BUFFER_ALIGNMENT),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Box::new(WgpuStorageBuffer(buffer))
+    }
 }
 
 impl BufferHandler for Gpu {
@@ -456,7 +799,7 @@ impl BufferHandler for Gpu {
             .downcast_ref::<WgpuUniformBuffer>()
             .expect("Unable to downcast Uniform Buffer");
 
-        self.queue.write_buffer(&buffer.0, offset, data);
+        self.write_buffer(&buffer.0, offset, data);
     }
 
     fn write_vertex_buffer(
@@ -469,6 +812,19 @@ impl BufferHandler for Gpu {
             .downcast_ref::<WgpuVertexBuffer>()
             .expect("Unable to downcast Vertex Buffer");
 
-        self.queue.write_buffer(&buffer.0, offset, data);
+        self.write_buffer(&buffer.0, offset, data);
+    }
+
+    fn write_storage_buffer(
+        &self,
+        buffer: &Box<dyn StorageBuffer>,
+        offset: u64,
+        data: &[u8],
+    ) {
+        let buffer = buffer
+            .downcast_ref::<WgpuStorageBuffer>()
+            .expect("Unable to downcast Storage Buffer");
+
+        self.write_buffer(&buffer.0, offset, data);
     }
 }
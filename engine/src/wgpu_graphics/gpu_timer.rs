@@ -0,0 +1,200 @@
+use std::{
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use ahash::AHashMap;
+use wgpu::{
+    Buffer, BufferUsages, CommandEncoder, Features, MapMode, QuerySet,
+    RenderPassTimestampWrites,
+};
+
+use super::gpu::Gpu;
+
+/// Maximum number of render passes measured each frame.
+const MAX_TIMED_PASSES: u32 = 128;
+
+/// Number of bytes of each timestamp.
+const QUERY_SIZE: u64 = wgpu::QUERY_SIZE as u64;
+
+/// States of the readback buffer.
+const PENDING: u8 = 0;
+const MAPPED: u8 = 1;
+const FAILED: u8 = 2;
+
+/// Measures the time the GPU takes to run the render passes with timestamp
+/// queries. The timestamps are read back once the GPU is done with them,
+/// so the timings belong to a previous frame and the CPU never waits.
+pub(crate) struct GpuTimer {
+    query_set: QuerySet,
+    resolve_buffer: Buffer,
+    readback_buffer: Buffer,
+    /// Nanoseconds of each tick of the timestamps.
+    period: f64,
+    /// Contains the node of each pair of queries written in this frame.
+    passes: Vec<String>,
+    /// Contains the nodes of the queries being read back and the state of
+    /// the mapping.
+    readback: Option<(Vec<String>, Arc<AtomicU8>)>,
+    /// Contains the GPU time of each node of the last frame read back.
+    timings: AHashMap<String, Duration>,
+}
+
+impl GpuTimer {
+    /// Creates a new `GpuTimer`, it is `None` if the GPU doesn't support
+    /// timestamp queries.
+    pub(crate) fn new(gpu: &Gpu) -> Option<Self> {
+        if !gpu.device.features().contains(Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+
+        let count = MAX_TIMED_PASSES * 2;
+
+        let query_set =
+            gpu.device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("Pass timestamps"),
+                ty: wgpu::QueryType::Timestamp,
+                count,
+            });
+
+        let resolve_buffer = gpu.allocate_aligned_zero_buffer(
+            "Pass timestamps resolve buffer",
+            count as u64 * QUERY_SIZE,
+            BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+        );
+
+        let readback_buffer = gpu.allocate_aligned_zero_buffer(
+            "Pass timestamps readback buffer",
+            count as u64 * QUERY_SIZE,
+            BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+        );
+
+        Some(Self {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            period: gpu.queue.get_timestamp_period() as f64,
+            passes: Vec::new(),
+            readback: None,
+            timings: AHashMap::new(),
+        })
+    }
+
+    /// Returns the GPU time of each node of the last frame read back.
+    pub(crate) fn timings(&self) -> &AHashMap<String, Duration> {
+        &self.timings
+    }
+
+    /// Starts a frame, the timings of a previous frame are read if they are
+    /// ready.
+    pub(crate) fn begin_frame(&mut self) {
+        self.passes.clear();
+
+        let state = match &self.readback {
+            Some((_, state)) => state.load(Ordering::Acquire),
+            None => return,
+        };
+
+        if state == PENDING {
+            return;
+        }
+
+        let Some((nodes, _)) = self.readback.take() else {
+            return;
+        };
+
+        if state == FAILED {
+            return;
+        }
+
+        let size = nodes.len() as u64 * 2 * QUERY_SIZE;
+
+        {
+            let data = self.readback_buffer.slice(..size).get_mapped_range();
+            self.timings.clear();
+
+            // Each pass has a timestamp at its beginning and at its end.
+            for (node, pair) in nodes.iter().zip(data.chunks_exact(16)) {
+                let begin = u64::from_le_bytes(pair[..8].try_into().unwrap());
+                let end = u64::from_le_bytes(pair[8..].try_into().unwrap());
+                let nanos = end.saturating_sub(begin) as f64 * self.period;
+
+                *self.timings.entry(node.clone()).or_default() +=
+                    Duration::from_nanos(nanos as u64);
+            }
+        }
+
+        self.readback_buffer.unmap();
+    }
+
+    /// Returns the writes which measure the next render pass of the node.
+    /// The passes are not measured while the previous timings are read back
+    /// or once all the queries are used.
+    pub(crate) fn next_pass(
+        &mut self,
+        node: &str,
+    ) -> Option<RenderPassTimestampWrites<'_>> {
+        if self.readback.is_some()
+            || self.passes.len() as u32 >= MAX_TIMED_PASSES
+        {
+            return None;
+        }
+
+        let index = self.passes.len() as u32 * 2;
+        self.passes.push(node.to_owned());
+
+        Some(RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(index),
+            end_of_pass_write_index: Some(index + 1),
+        })
+    }
+
+    /// Copies the timestamps of the frame to the readback buffer, it must be
+    /// recorded after all the passes.
+    pub(crate) fn resolve(&self, encoder: &mut CommandEncoder) {
+        if self.passes.is_empty() {
+            return;
+        }
+
+        let count = self.passes.len() as u32 * 2;
+
+        encoder.resolve_query_set(
+            &self.query_set,
+            0..count,
+            &self.resolve_buffer,
+            0,
+        );
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            count as u64 * QUERY_SIZE,
+        );
+    }
+
+    /// Requests the timestamps once the frame is submitted.
+    pub(crate) fn end_frame(&mut self) {
+        if self.passes.is_empty() {
+            return;
+        }
+
+        let size = self.passes.len() as u64 * 2 * QUERY_SIZE;
+        let state = Arc::new(AtomicU8::new(PENDING));
+        let callback_state = state.clone();
+
+        self.readback_buffer.slice(..size).map_async(
+            MapMode::Read,
+            move |result| {
+                let mapped = if result.is_ok() { MAPPED } else { FAILED };
+                callback_state.store(mapped, Ordering::Release);
+            },
+        );
+
+        self.readback = Some((std::mem::take(&mut self.passes), state));
+    }
+}
@@ -0,0 +1,321 @@
+use std::sync::Arc;
+
+use ahash::AHashMap;
+use log::warn;
+use shipyard::{Unique, UniqueView, UniqueViewMut};
+use wgpu::{
+    BindGroup, BindGroupLayout, BindGroupLayoutEntry, Buffer, BufferUsages,
+    Sampler, ShaderStages,
+};
+
+use crate::{
+    graphics::{
+        gpu::AbstractGpu,
+        texture::{SamplerSettings, TextureData, TextureFormat},
+        Texture,
+    },
+    scene::assets::{
+        asset_server::AssetServer,
+        material::{AlphaMode, Material, DEFAULT_MATERIAL_ID},
+        MaterialResourceID, TextureResourceID,
+    },
+    types::Size,
+};
+
+use super::{buffer::WGPUTexture, gpu::Gpu};
+
+/// Contains the name of each texture slot of a material, in binding order.
+const TEXTURE_SLOTS: [&str; 3] =
+    ["base color", "metallic roughness", "emissive"];
+
+/// Material information as it is stored in the GPU.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct MaterialUniform {
+    base_color: [f32; 4],
+    /// The last component is not used.
+    emissive: [f32; 4],
+    metallic: f32,
+    roughness: f32,
+    alpha_cutoff: f32,
+    /// 0 opaque, 1 mask and 2 blend.
+    alpha_mode: u32,
+}
+
+impl From<&Material> for MaterialUniform {
+    fn from(material: &Material) -> Self {
+        let (alpha_mode, alpha_cutoff) = match material.alpha_mode {
+            AlphaMode::Opaque => (0, 0.0),
+            AlphaMode::Mask(cutoff) => (1, cutoff),
+            AlphaMode::Blend => (2, 0.0),
+        };
+
+        let [r, g, b] = material.emissive;
+
+        Self {
+            base_color: material.base_color,
+            emissive: [r, g, b, 0.0],
+            metallic: material.metallic,
+            roughness: material.roughness,
+            alpha_cutoff,
+            alpha_mode,
+        }
+    }
+}
+
+/// Contains the bind group of each material in the `AssetServer`. The bind
+/// groups are recreated when the material or any of its textures change.
+#[derive(Unique)]
+pub(crate) struct MaterialBindGroups {
+    pub(crate) layout: BindGroupLayout,
+    sampler: Sampler,
+    /// A 1x1 white texture bound in the slots without texture, so only the
+    /// factor is used.
+    white_texture: WGPUTexture,
+    entries: AHashMap<String, MaterialEntry>,
+}
+
+struct MaterialEntry {
+    /// Contains the generation of the material followed by the generation of
+    /// each of its textures.
+    generations: Vec<u32>,
+    bind_group: BindGroup,
+    /// Kept alive while the bind group is used.
+    _uniform: Buffer,
+}
+
+impl MaterialBindGroups {
+    /// Creates and returns a new `MaterialBindGroups` without materials.
+    pub(crate) fn new(gpu: &Gpu) -> MaterialBindGroups {
+        let texture_entry = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float {
+                    filterable: true,
+                },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+
+        let sampler_entry = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+        };
+
+        let layout = gpu.device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Material bind group layout"),
+                entries: &[
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    // Base color.
+                    texture_entry(1),
+                    // Metallic roughness.
+                    texture_entry(2),
+                    // Emissive.
+                    texture_entry(3),
+                    // The sampler of each texture, in the same order.
+                    sampler_entry(4),
+                    sampler_entry(5),
+                    sampler_entry(6),
+                ],
+            },
+        );
+
+        let sampler = gpu
+            .allocate_sampler("Material sampler", &SamplerSettings::default());
+
+        let white_texture = gpu.allocate_texture(
+            "Material white texture",
+            &TextureData::new(
+                Size::new(1, 1),
+                TextureFormat::Rgba8Srgb,
+                vec![255, 255, 255, 255],
+            ),
+        );
+
+        MaterialBindGroups {
+            layout,
+            sampler,
+            white_texture,
+            entries: AHashMap::new(),
+        }
+    }
+
+    /// Returns the bind group of the material, the default material is
+    /// returned if the material is not ready.
+    pub(crate) fn bind_group(&self, id: &MaterialResourceID) -> &BindGroup {
+        &self
+            .entries
+            .get(id.as_str())
+            .or_else(|| self.entries.get(DEFAULT_MATERIAL_ID))
+            .expect("The default material bind group is not created")
+            .bind_group
+    }
+
+    fn create_entry(
+        &self,
+        gpu: &Gpu,
+        id: &str,
+        material: &Material,
+        textures: &[Option<Arc<dyn Texture>>],
+        generations: Vec<u32>,
+    ) -> MaterialEntry {
+        let uniform = gpu.allocate_buffer_init(
+            &format!("Material({}) uniform", id),
+            MaterialUniform::from(material),
+            BufferUsages::UNIFORM,
+        );
+
+        let textures = textures
+            .iter()
+            .zip(TEXTURE_SLOTS)
+            .map(|(t, slot)| {
+                let texture = t
+                    .as_ref()?
+                    .downcast_ref::<WGPUTexture>()
+                    .expect("Incorrect texture type, expecting WGPUTexture");
+
+                // The slots are sampled with a filtering sampler, which
+                // requires an optional feature for `Rgba32Float`.
+                if texture.texture.format() == wgpu::TextureFormat::Rgba32Float
+                {
+                    warn!(
+                        "The {} texture of the material {} is Rgba32Float, \
+                         which can not be filtered. Import it as Rgba16Float",
+                        slot, id
+                    );
+                    return None;
+                }
+
+                Some(texture)
+            })
+            .map(|t| t.unwrap_or(&self.white_texture))
+            .collect::<Vec<_>>();
+
+        let views = textures.iter().map(|t| &t.view).collect::<Vec<_>>();
+
+        // Each texture is sampled as its import settings describe.
+        let samplers = textures
+            .iter()
+            .map(|t| t.sampler.as_ref().unwrap_or(&self.sampler))
+            .collect::<Vec<_>>();
+
+        let bind_group =
+            gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(&format!("Material({}) bind group", id)),
+                layout: &self.layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: uniform.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(views[0]),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(views[1]),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::TextureView(views[2]),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: wgpu::BindingResource::Sampler(samplers[0]),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 5,
+                        resource: wgpu::BindingResource::Sampler(samplers[1]),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 6,
+                        resource: wgpu::BindingResource::Sampler(samplers[2]),
+                    },
+                ],
+            });
+
+        MaterialEntry {
+            generations,
+            bind_group,
+            _uniform: uniform,
+        }
+    }
+}
+
+/// Creates the bind groups of the new materials and recreates the ones whose
+/// material or textures changed.
+pub(crate) fn sync_material_bind_groups_system(
+    gpu: UniqueView<AbstractGpu>,
+    asset_server: UniqueView<AssetServer>,
+    mut bind_groups: UniqueViewMut<MaterialBindGroups>,
+) {
+    let gpu = gpu
+        .downcast_ref::<Gpu>()
+        .expect("Incorrect GPU type expecting WGPU gpu");
+
+    let materials = asset_server
+        .data
+        .read()
+        .expect("Unable to acquire read lock")
+        .materials
+        .clone();
+
+    bind_groups
+        .entries
+        .retain(|id, _| materials.contains_key(id));
+
+    for (id, material) in materials {
+        let slots: [&Option<TextureResourceID>; 3] = [
+            &material.base_color_texture,
+            &material.metallic_roughness_texture,
+            &material.emissive_texture,
+        ];
+
+        let generations = std::iter::once(asset_server.generation(&id))
+            .chain(slots.iter().map(|t| match t {
+                Some(t) => asset_server.generation(t),
+                None => 0,
+            }))
+            .collect::<Vec<_>>();
+
+        let is_updated = bind_groups
+            .entries
+            .get(&id)
+            .map(|e| e.generations == generations)
+            .unwrap_or(false);
+
+        if is_updated {
+            continue;
+        }
+
+        let textures = slots
+            .iter()
+            .map(|t| t.as_ref().map(|t| asset_server.load_texture(t)))
+            .collect::<Vec<_>>();
+
+        let entry = bind_groups.create_entry(
+            gpu,
+            &id,
+            &material,
+            &textures,
+            generations,
+        );
+        bind_groups.entries.insert(id, entry);
+    }
+}
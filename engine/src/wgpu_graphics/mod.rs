@@ -1,61 +1,70 @@
 pub mod buffer;
 pub(crate) mod components;
 pub mod gpu;
+pub(crate) mod gpu_timer;
+pub(crate) mod materials;
 pub(crate) mod passes;
 pub mod pipelines;
+pub(crate) mod render_graph;
 pub(crate) mod rendering;
+pub(crate) mod shader_library;
 
-use crossbeam_queue::ArrayQueue;
 use shipyard::Unique;
-use wgpu::CommandBuffer;
+use wgpu::{CommandEncoder, RenderPassTimestampWrites};
 
-#[derive(Unique)]
-pub struct CommandQueue(pub(crate) OrderCommandQueue);
-
-/// Specifies the rendering order of different passes; a higher value indicates
-/// that the pass should be drawn later in the rendering pipeline.
-#[derive(Copy, Clone)]
-pub(crate) enum CommandSubmitOrder {
-    FrameComposition,
-    DebugGui,
-    DebugGrid,
-    DynamicMeshes,
-    Sky,
-}
+use crate::graphics::stats::DrawStats;
 
-impl CommandSubmitOrder {
-    fn as_index(&self) -> usize {
-        *self as usize
-    }
-}
+use self::gpu_timer::GpuTimer;
 
-/// Wraps a `wgpu` `CommandBuffer` and includes information about the position
-/// at which the command should be executed.
-pub(crate) struct OrderCommandBuffer {
-    #[allow(dead_code)]
-    pub(crate) label: Option<String>,
-    pub(crate) order: usize,
-    pub(crate) command: CommandBuffer,
+/// Contains the encoder where the nodes of the `RenderGraph` record their
+/// commands, it is only available while the graph runs.
+#[derive(Unique)]
+pub(crate) struct FrameEncoder {
+    pub(crate) encoder: Option<CommandEncoder>,
+    /// Measures the passes on the GPU, it is `None` if the GPU doesn't
+    /// support timestamp queries.
+    pub(crate) timer: Option<GpuTimer>,
+    /// Label of the node being recorded.
+    pub(crate) node: String,
+    /// Contains the draws recorded by the node.
+    pub(crate) draws: DrawStats,
 }
 
-impl OrderCommandBuffer {
-    /// Creates and returns a new `OrderCommandBuffer`.
-    pub(crate) fn new(
-        label: Option<String>,
-        order: CommandSubmitOrder,
-        command: CommandBuffer,
-    ) -> OrderCommandBuffer {
-        OrderCommandBuffer {
-            label,
-            order: order.as_index(),
-            command,
+impl FrameEncoder {
+    /// Creates and returns a new `FrameEncoder`.
+    pub(crate) fn new(timer: Option<GpuTimer>) -> Self {
+        Self {
+            encoder: None,
+            timer,
+            node: String::new(),
+            draws: DrawStats::default(),
         }
     }
-}
 
-/// Defines the max number of commands that can be performaned per frame, this
-/// is becase the `ArrayQueu` needs an start size.
-pub const MAX_NUMBER_IF_COMMANDS_PER_FRAME: usize = 20;
+    /// Returns the encoder of the current frame.
+    pub(crate) fn encoder(&mut self) -> &mut CommandEncoder {
+        self.encoder
+            .as_mut()
+            .expect("The passes can only be recorded by the render graph")
+    }
+
+    /// Returns the encoder of the current frame and the timestamp writes
+    /// measuring the next render pass, if they are available.
+    pub(crate) fn timed_encoder(
+        &mut self,
+    ) -> (&mut CommandEncoder, Option<RenderPassTimestampWrites<'_>>) {
+        let encoder = self
+            .encoder
+            .as_mut()
+            .expect("The passes can only be recorded by the render graph");
+        let timestamp_writes =
+            self.timer.as_mut().and_then(|t| t.next_pass(&self.node));
+
+        (encoder, timestamp_writes)
+    }
 
-/// A queue uses to store all the commands to be submited per frame.
-pub(crate) type OrderCommandQueue = ArrayQueue<OrderCommandBuffer>;
+    /// Adds the draws recorded by a pass to the stats of the node.
+    pub(crate) fn record_draws(&mut self, draws: DrawStats) {
+        self.draws += draws;
+    }
+}
@@ -0,0 +1,179 @@
+use shipyard::{UniqueView, UniqueViewMut};
+use wgpu::Operations;
+
+use crate::{
+    graphics::{
+        camera::camera_offset,
+        gpu::AbstractGpu,
+        render_pass::{CustomRenderPasses, PassDepth, PassTexture},
+        scene::Scene,
+        stats::DrawStats,
+    },
+    scene::scene_state::SceneState,
+    wgpu_graphics::{
+        buffer::{texture_view, wgpu_bind_group},
+        gpu::Gpu,
+        pipelines::custom_pass_pipeline::{
+            custom_pass_scene, CustomPassPipelines,
+        },
+        FrameEncoder,
+    },
+};
+
+use super::scene_color_attachment;
+
+/// Draws a custom pass into the scene it targets.
+pub(crate) fn custom_pass_system(
+    id: u64,
+    gpu: UniqueView<AbstractGpu>,
+    pipelines: UniqueView<CustomPassPipelines>,
+    passes: UniqueView<CustomRenderPasses>,
+    s_state: UniqueView<SceneState>,
+    mut frame: UniqueViewMut<FrameEncoder>,
+) {
+    let gpu = gpu.downcast_ref::<Gpu>().expect(
+        "Incorrect Gpu abstractor provided, it was expecting a Wgpu Gpu",
+    );
+
+    let pass = match passes.passes.iter().find(|p| p.id == id) {
+        Some(p) => p,
+        None => return,
+    };
+
+    let descriptor = &pass.descriptor;
+
+    let pipeline = match pipelines.pipelines.get(&pass.id) {
+        Some(p) => p,
+        None => return,
+    };
+
+    let scene = match custom_pass_scene(&s_state, &descriptor.scene) {
+        Some(s) => s,
+        None => return,
+    };
+
+    let camera_bind_group = match &scene.camera_bind_group {
+        Some(bg) => wgpu_bind_group(bg.as_ref()),
+        None => return,
+    };
+
+    // The textures removed after the pass was added are not allocated.
+    let textures_available = descriptor
+        .inputs
+        .iter()
+        .chain([&descriptor.output])
+        .all(|texture| match texture {
+            PassTexture::Custom(name) => pipelines.texture(name).is_some(),
+            _ => true,
+        });
+
+    if !textures_available {
+        return;
+    }
+
+    let view_of = |texture: &PassTexture| match texture {
+        // With MSAA the scene color is read once resolved.
+        PassTexture::SceneColor if scene.sample_count() > 1 => {
+            texture_view(scene.post_process_textures[0].as_ref())
+        }
+        PassTexture::SceneColor => texture_view(scene.color_texture.as_ref()),
+        PassTexture::SceneTarget => texture_view(scene.target_texture.as_ref()),
+        PassTexture::SceneDepth => texture_view(scene.depth_texture.as_ref()),
+        PassTexture::Custom(name) => {
+            &pipelines
+                .texture(name)
+                .expect("The custom texture is not allocated")
+                .view
+        }
+    };
+
+    let mut entries = vec![wgpu::BindGroupEntry {
+        binding: 0,
+        resource: pipeline.uniform.as_entire_binding(),
+    }];
+
+    for (i, input) in descriptor.inputs.iter().enumerate() {
+        entries.push(wgpu::BindGroupEntry {
+            binding: i as u32 + 1,
+            resource: wgpu::BindingResource::TextureView(view_of(input)),
+        });
+    }
+
+    entries.push(wgpu::BindGroupEntry {
+        binding: descriptor.inputs.len() as u32 + 1,
+        resource: wgpu::BindingResource::Sampler(&pipelines.sampler),
+    });
+
+    // The scene textures are reallocated when they are resized, so
+    // the bind group is created every frame.
+    let resources = gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: &pipeline.resources_layout,
+        entries: &entries,
+        label: Some(&format!("{} resources bind group", descriptor.label)),
+    });
+
+    let load = match descriptor.clear {
+        Some([r, g, b, a]) => wgpu::LoadOp::Clear(wgpu::Color { r, g, b, a }),
+        None => wgpu::LoadOp::Load,
+    };
+
+    let color_attachment = match &descriptor.output {
+        PassTexture::SceneColor => scene_color_attachment(scene, load),
+        output => wgpu::RenderPassColorAttachment {
+            view: view_of(output),
+            resolve_target: None,
+            ops: Operations {
+                load,
+                store: wgpu::StoreOp::Store,
+            },
+        },
+    };
+
+    {
+        let (encoder, timestamp_writes) = frame.timed_encoder();
+        let mut render_pass =
+            encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(&format!("{}, {}", descriptor.label, scene.label)),
+                color_attachments: &[Some(color_attachment)],
+                depth_stencil_attachment: depth_attachment(
+                    scene,
+                    descriptor.depth,
+                ),
+                timestamp_writes,
+                occlusion_query_set: None,
+            });
+
+        render_pass.set_pipeline(&pipeline.pipeline);
+        // The custom passes cover the whole output, they see the camera of
+        // the main view.
+        render_pass.set_bind_group(
+            0,
+            camera_bind_group,
+            &[camera_offset(scene.views.len().saturating_sub(1))],
+        );
+        render_pass.set_bind_group(1, &resources, &[]);
+        render_pass.draw(0..descriptor.vertex_count, 0..1);
+    }
+
+    let mut draws = DrawStats::default();
+    draws.record(descriptor.vertex_count / 3, 1);
+    frame.record_draws(draws);
+}
+
+fn depth_attachment(
+    scene: &Scene,
+    depth: PassDepth,
+) -> Option<wgpu::RenderPassDepthStencilAttachment<'_>> {
+    if depth == PassDepth::Ignore {
+        return None;
+    }
+
+    Some(wgpu::RenderPassDepthStencilAttachment {
+        view: texture_view(scene.depth_texture.as_ref()),
+        depth_ops: Some(Operations {
+            load: wgpu::LoadOp::Load,
+            store: wgpu::StoreOp::Store,
+        }),
+        stencil_ops: None,
+    })
+}
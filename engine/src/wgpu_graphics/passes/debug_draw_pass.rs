@@ -0,0 +1,114 @@
+use shipyard::{UniqueView, UniqueViewMut};
+use wgpu::{BufferUsages, Operations, RenderPassDepthStencilAttachment};
+
+use crate::{
+    graphics::{debug_draw::DebugDraw, gpu::AbstractGpu, stats::DrawStats},
+    scene::{scene::SceneTarget, scene_state::SceneState},
+    wgpu_graphics::{
+        buffer::{WGPUBindGroup, WGPUTexture},
+        gpu::Gpu,
+        passes::{draw_scene_views, scene_color_attachment},
+        pipelines::debug_draw_pipeline::DebugDrawPipeline,
+        FrameEncoder,
+    },
+};
+
+pub(crate) fn debug_draw_pass_system(
+    gpu: UniqueView<AbstractGpu>,
+    pipeline: UniqueView<DebugDrawPipeline>,
+    scenes: UniqueView<SceneState>,
+    mut debug_draw: UniqueViewMut<DebugDraw>,
+    mut frame: UniqueViewMut<FrameEncoder>,
+) {
+    let gpu = gpu.downcast_ref::<Gpu>().expect(
+        "Incorrect Gpu abstractor provided, it was expecting a Wgpu Gpu",
+    );
+
+    let mut draws = DrawStats::default();
+
+    let targets = std::iter::once((SceneTarget::Main, &scenes.main)).chain(
+        scenes
+            .sub_scenes
+            .iter()
+            .map(|(id, scene)| (SceneTarget::SubScene(id.clone()), scene)),
+    );
+
+    for (target, scene) in targets {
+        let [tested, overlay] =
+            debug_draw.scene_vertices(&target, &scene.main_view().camera);
+
+        if tested.is_empty() && overlay.is_empty() {
+            continue;
+        }
+
+        let camera_bind_group = match &scene.camera_bind_group {
+            Some(c) => c
+                .downcast_ref::<WGPUBindGroup>()
+                .expect("Incorrect bind group type"),
+            None => continue,
+        };
+
+        let depth_texture = scene
+            .depth_texture
+            .downcast_ref::<WGPUTexture>()
+            .expect("The provided scene texture is not a WGPU texture");
+
+        // Both kinds of lines share the buffer, it is rebuilt each frame as
+        // the shapes change every frame.
+        let tested_count = tested.len() as u32;
+        let vertices = [tested, overlay].concat();
+        let vertex_buffer = gpu.raw_allocate_buffer_init(
+            &format!("Debug draw vertex buffer, {}", scene.label),
+            bytemuck::cast_slice(&vertices),
+            BufferUsages::VERTEX,
+        );
+
+        {
+            let (encoder, timestamp_writes) = frame.timed_encoder();
+            let mut pass =
+                encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some(&format!("Debug draw pass, {}", scene.label)),
+                    color_attachments: &[
+                        // @location(0)
+                        Some(scene_color_attachment(scene, wgpu::LoadOp::Load)),
+                    ],
+                    depth_stencil_attachment: Some(
+                        RenderPassDepthStencilAttachment {
+                            view: &depth_texture.view,
+                            depth_ops: Some(Operations {
+                                load: wgpu::LoadOp::Load,
+                                store: wgpu::StoreOp::Store,
+                            }),
+                            stencil_ops: None,
+                        },
+                    ),
+                    timestamp_writes,
+                    occlusion_query_set: None,
+                });
+
+            pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+
+            let vertex_count = vertices.len() as u32;
+            let sample_count = scene.sample_count();
+
+            draw_scene_views(&mut pass, scene, &camera_bind_group.0, |pass| {
+                if tested_count > 0 {
+                    pass.set_pipeline(pipeline.pipeline.get(sample_count));
+                    pass.draw(0..tested_count, 0..1);
+                    draws.record(0, 1);
+                }
+
+                if vertex_count > tested_count {
+                    pass.set_pipeline(
+                        pipeline.overlay_pipeline.get(sample_count),
+                    );
+                    pass.draw(tested_count..vertex_count, 0..1);
+                    draws.record(0, 1);
+                }
+            });
+        }
+    }
+
+    frame.record_draws(draws);
+    debug_draw.end_frame();
+}
@@ -1,54 +1,48 @@
-use shipyard::UniqueView;
+use std::{ops::Range, sync::Arc};
+
+use shipyard::{UniqueView, UniqueViewMut};
 use wgpu::{
-    CommandEncoderDescriptor, Operations, RenderPassDepthStencilAttachment,
+    BindGroup, Operations, RenderPass, RenderPassDepthStencilAttachment,
 };
 
 use crate::{
-    graphics::gpu::AbstractGpu,
-    scene::{assets::asset_server::AssetServer, scene_state::SceneState},
+    graphics::{
+        mesh::Mesh,
+        scene::{InstanceBatchKey, InstanceBuffer, Scene},
+        stats::DrawStats,
+        VertexBuffer,
+    },
+    scene::{
+        assets::asset_server::AssetServer, scene::DebugView,
+        scene_state::SceneState,
+    },
     wgpu_graphics::{
         buffer::{
             WGPUBindGroup, WGPUTexture, WgpuIndexBuffer, WgpuVertexBuffer,
         },
-        gpu::Gpu,
-        pipelines::dynamic_mesh_pipeline::DynamicMeshPipeline,
-        CommandQueue, CommandSubmitOrder, OrderCommandBuffer,
+        materials::MaterialBindGroups,
+        passes::{draw_scene_views, scene_color_attachment},
+        pipelines::{
+            dynamic_mesh_pipeline::DynamicMeshPipeline,
+            ibl_pipeline::IblPipeline,
+        },
+        FrameEncoder,
     },
 };
 
 /// Renders the triangle test.
 // TODO(Angel): Add support for sub scenes.
 pub(crate) fn dynamic_mesh_pass_system(
-    gpu: UniqueView<AbstractGpu>,
     dyn_mesh_pipeline: UniqueView<DynamicMeshPipeline>,
-    queue: UniqueView<CommandQueue>,
+    materials: UniqueView<MaterialBindGroups>,
+    ibl_pipeline: UniqueView<IblPipeline>,
+    mut frame: UniqueViewMut<FrameEncoder>,
     asset_server: UniqueView<AssetServer>,
     scenes: UniqueView<SceneState>,
 ) {
-    let gpu = gpu.downcast_ref::<Gpu>().expect(
-        "Incorrect Gpu abstractor provided, it was expecting a Wgpu Gpu",
-    );
-
-    let mut encoder =
-        gpu.device
-            .create_command_encoder(&CommandEncoderDescriptor {
-                label: Some("Dynamic mesh encoder"),
-            });
-
-    let main_meshes = scenes
-        .main
-        .mesh_transform_buffers
-        .iter()
-        .map(|(mesh_id, (buffer, count))| {
-            (asset_server.load_mesh(mesh_id), buffer, count)
-        })
-        .collect::<Vec<_>>();
+    let (opaque, transparent) =
+        scene_batches(&scenes.main, &asset_server, &materials);
 
-    let main_texture = scenes
-        .main
-        .target_texture
-        .downcast_ref::<WGPUTexture>()
-        .expect("The provided scene texture is not a WGPU texture");
     let depth_texture = scenes
         .main
         .depth_texture
@@ -64,19 +58,22 @@ pub(crate) fn dynamic_mesh_pass_system(
         .downcast_ref::<WGPUBindGroup>()
         .expect("Incorrect bind group type");
 
+    let lights_bind_group = match &scenes.main.lights_bind_group {
+        Some(bg) => bg
+            .downcast_ref::<WGPUBindGroup>()
+            .expect("Incorrect bind group type"),
+        None => return,
+    };
+
+    let mut draws = DrawStats::default();
+
     {
+        let (encoder, timestamp_writes) = frame.timed_encoder();
         let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("Dynamic mesh pass"),
             color_attachments: &[
                 // @location(0)
-                Some(wgpu::RenderPassColorAttachment {
-                    view: &main_texture.view,
-                    resolve_target: None,
-                    ops: Operations {
-                        load: wgpu::LoadOp::Load,
-                        store: wgpu::StoreOp::Store,
-                    },
-                }),
+                Some(scene_color_attachment(&scenes.main, wgpu::LoadOp::Load)),
             ],
             depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
                 view: &depth_texture.view,
@@ -86,56 +83,36 @@ pub(crate) fn dynamic_mesh_pass_system(
                 }),
                 stencil_ops: None,
             }),
-            timestamp_writes: None,
+            timestamp_writes,
             occlusion_query_set: None,
         });
 
-        pass.set_pipeline(&dyn_mesh_pipeline.pipeline);
-        pass.set_bind_group(0, &camera_bind_group.0, &[]);
+        pass.set_bind_group(2, &lights_bind_group.0, &[]);
+        pass.set_bind_group(3, ibl_pipeline.bind_group(&scenes.main), &[]);
 
-        // Iterate over each mesh.
-        for (mesh, t_buffer, count) in main_meshes
-            .iter()
-            // Only execute the draw if there are entities for the mesh.
-            .filter(|(_, _, c)| **c >= 1)
-        {
-            let v_buffer = mesh
-                .vertex_buffer
-                .downcast_ref::<WgpuVertexBuffer>()
-                .expect("Incorrect vertex buffer type, expecting WGPU vertex buffer");
-
-            let i_buffer =
-                mesh.index_buffer.downcast_ref::<WgpuIndexBuffer>().expect(
-                    "Incorrect vertex buffer type, expecting WGPU index buffer",
+        draw_scene_views(
+            &mut pass,
+            &scenes.main,
+            &camera_bind_group.0,
+            |pass| {
+                draw_scene(
+                    pass,
+                    &dyn_mesh_pipeline,
+                    &scenes.main,
+                    &opaque,
+                    &transparent,
+                    &mut draws,
                 );
-
-            let t_buffer = t_buffer.downcast_ref::<WgpuVertexBuffer>().expect(
-                "Incorrect vertex buffer type, expecting WGPU vertex buffer",
-            );
-
-            pass.set_vertex_buffer(0, v_buffer.0.slice(..));
-            pass.set_vertex_buffer(1, t_buffer.0.slice(..));
-            pass.set_index_buffer(
-                i_buffer.0.slice(..),
-                wgpu::IndexFormat::Uint16,
-            );
-            pass.draw_indexed(0..mesh.index_count, 0, 0..**count as u32);
-        }
+            },
+        );
     }
 
+    frame.record_draws(draws);
+
     for (_id, scene) in &scenes.sub_scenes {
-        let main_meshes = scene
-            .mesh_transform_buffers
-            .iter()
-            .map(|(mesh_id, (buffer, count))| {
-                (asset_server.load_mesh(mesh_id), buffer, count)
-            })
-            .collect::<Vec<_>>();
-
-        let main_texture = scene
-            .target_texture
-            .downcast_ref::<WGPUTexture>()
-            .expect("The provided scene texture is not a WGPU texture");
+        let (opaque, transparent) =
+            scene_batches(scene, &asset_server, &materials);
+
         let depth_texture = scene
             .depth_texture
             .downcast_ref::<WGPUTexture>()
@@ -150,20 +127,23 @@ pub(crate) fn dynamic_mesh_pass_system(
             .downcast_ref::<WGPUBindGroup>()
             .expect("Incorrect bind group type");
 
+        let lights_bind_group = match &scene.lights_bind_group {
+            Some(bg) => bg
+                .downcast_ref::<WGPUBindGroup>()
+                .expect("Incorrect bind group type"),
+            None => return,
+        };
+
+        let mut draws = DrawStats::default();
+
         {
+            let (encoder, timestamp_writes) = frame.timed_encoder();
             let mut pass =
                 encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                     label: Some("Sub pass dynamic mesh pass"),
                     color_attachments: &[
                         // @location(0)
-                        Some(wgpu::RenderPassColorAttachment {
-                            view: &main_texture.view,
-                            resolve_target: None,
-                            ops: Operations {
-                                load: wgpu::LoadOp::Load,
-                                store: wgpu::StoreOp::Store,
-                            },
-                        }),
+                        Some(scene_color_attachment(scene, wgpu::LoadOp::Load)),
                     ],
                     depth_stencil_attachment: Some(
                         RenderPassDepthStencilAttachment {
@@ -175,46 +155,137 @@ pub(crate) fn dynamic_mesh_pass_system(
                             stencil_ops: None,
                         },
                     ),
-                    timestamp_writes: None,
+                    timestamp_writes,
                     occlusion_query_set: None,
                 });
 
-            pass.set_pipeline(&dyn_mesh_pipeline.pipeline);
-            pass.set_bind_group(0, &camera_bind_group.0, &[]);
-
-            for (mesh, t_buffer, count) in main_meshes
-                .iter()
-                // Only execute the draw if there are entities for the mesh.
-                .filter(|(_, _, c)| **c >= 1)
-            {
-                let v_buffer = mesh
-                    .vertex_buffer
-                    .downcast_ref::<WgpuVertexBuffer>()
-                    .expect("Incorrect vertex buffer type, expecting WGPU vertex buffer");
-
-                let i_buffer = mesh
-                    .index_buffer
-                    .downcast_ref::<WgpuIndexBuffer>()
-                    .expect("Incorrect vertex buffer type, expecting WGPU index buffer");
-
-                let t_buffer = t_buffer
-                    .downcast_ref::<WgpuVertexBuffer>()
-                    .expect("Incorrect vertex buffer type, expecting WGPU vertex buffer");
-
-                pass.set_vertex_buffer(0, v_buffer.0.slice(..));
-                pass.set_vertex_buffer(1, t_buffer.0.slice(..));
-                pass.set_index_buffer(
-                    i_buffer.0.slice(..),
-                    wgpu::IndexFormat::Uint16,
+            pass.set_bind_group(2, &lights_bind_group.0, &[]);
+            pass.set_bind_group(3, ibl_pipeline.bind_group(scene), &[]);
+
+            draw_scene_views(&mut pass, scene, &camera_bind_group.0, |pass| {
+                draw_scene(
+                    pass,
+                    &dyn_mesh_pipeline,
+                    scene,
+                    &opaque,
+                    &transparent,
+                    &mut draws,
                 );
-                pass.draw_indexed(0..mesh.index_count, 0, 0..**count as u32);
-            }
+            });
         }
+
+        frame.record_draws(draws);
+    }
+}
+
+/// A group of instances drawn together: the mesh, the material bind group,
+/// the instance buffer and the range of instances drawn.
+type Batch<'a> = (Arc<Mesh>, &'a BindGroup, &'a dyn VertexBuffer, Range<u32>);
+
+/// Returns the opaque and the transparent batches of the scene, the
+/// transparent ones are sorted back to front and a batch may be drawn in
+/// several parts between the others.
+fn scene_batches<'a>(
+    scene: &'a Scene,
+    asset_server: &AssetServer,
+    materials: &'a MaterialBindGroups,
+) -> (Vec<Batch<'a>>, Vec<Batch<'a>>) {
+    let batch = |(mesh_id, material_id, _): &InstanceBatchKey,
+                 instances: &'a InstanceBuffer,
+                 range: Range<u32>| {
+        (
+            asset_server.load_mesh(mesh_id),
+            materials.bind_group(material_id),
+            instances.buffer.as_ref(),
+            range,
+        )
+    };
+
+    let opaque = scene
+        .mesh_transform_buffers
+        .iter()
+        .filter(|(_, value)| !value.blended)
+        .map(|(key, value)| batch(key, value, 0..value.visible))
+        .collect();
+
+    let transparent = scene
+        .transparent_draws
+        .iter()
+        .filter_map(|draw| {
+            let value = scene.mesh_transform_buffers.get(&draw.key)?;
+            Some(batch(&draw.key, value, draw.instances.clone()))
+        })
+        .collect();
+
+    (opaque, transparent)
+}
+
+/// Draws the batches of the scene with the pipelines of its `DebugView`.
+fn draw_scene<'a>(
+    pass: &mut RenderPass<'a>,
+    pipeline: &'a DynamicMeshPipeline,
+    scene: &Scene,
+    opaque: &'a [Batch<'a>],
+    transparent: &'a [Batch<'a>],
+    draws: &mut DrawStats,
+) {
+    let sample_count = scene.sample_count();
+
+    // Every mesh is added to the color, the order doesn't matter.
+    if scene.debug_view == DebugView::Overdraw {
+        pass.set_pipeline(pipeline.overdraw_pipeline.get(sample_count));
+        draw_batches(pass, opaque, draws);
+        draw_batches(pass, transparent, draws);
+        return;
     }
 
-    let _ = queue.0.push(OrderCommandBuffer::new(
-        Some("Render dynamic meshes".to_owned()),
-        CommandSubmitOrder::DynamicMeshes,
-        encoder.finish(),
-    ));
+    pass.set_pipeline(pipeline.pipeline.get(sample_count));
+    draw_batches(pass, opaque, draws);
+
+    pass.set_pipeline(pipeline.transparent_pipeline.get(sample_count));
+    draw_batches(pass, transparent, draws);
+
+    if let (DebugView::Wireframe, Some(wireframe)) =
+        (scene.debug_view, &pipeline.wireframe_pipeline)
+    {
+        pass.set_pipeline(wireframe.get(sample_count));
+        draw_batches(pass, opaque, draws);
+        draw_batches(pass, transparent, draws);
+    }
+}
+
+/// Draws the batches with at least one instance using the current pipeline.
+fn draw_batches<'a>(
+    pass: &mut RenderPass<'a>,
+    batches: &'a [Batch<'a>],
+    draws: &mut DrawStats,
+) {
+    for (mesh, material, t_buffer, instances) in batches
+        .iter()
+        // Only execute the draw if there are entities for the mesh.
+        .filter(|(_, _, _, i)| !i.is_empty())
+    {
+        let v_buffer = mesh
+            .vertex_buffer
+            .downcast_ref::<WgpuVertexBuffer>()
+            .expect(
+                "Incorrect vertex buffer type, expecting WGPU vertex buffer",
+            );
+
+        let i_buffer =
+            mesh.index_buffer.downcast_ref::<WgpuIndexBuffer>().expect(
+                "Incorrect vertex buffer type, expecting WGPU index buffer",
+            );
+
+        let t_buffer = t_buffer.downcast_ref::<WgpuVertexBuffer>().expect(
+            "Incorrect vertex buffer type, expecting WGPU vertex buffer",
+        );
+
+        pass.set_bind_group(1, material, &[]);
+        pass.set_vertex_buffer(0, v_buffer.0.slice(..));
+        pass.set_vertex_buffer(1, t_buffer.0.slice(..));
+        pass.set_index_buffer(i_buffer.0.slice(..), wgpu::IndexFormat::Uint16);
+        pass.draw_indexed(0..mesh.index_count, 0, instances.clone());
+        draws.record(mesh.index_count / 3, instances.len() as u32);
+    }
 }
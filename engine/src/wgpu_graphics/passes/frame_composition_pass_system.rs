@@ -1,34 +1,24 @@
-use shipyard::UniqueView;
-use wgpu::{CommandEncoderDescriptor, Operations};
+use shipyard::{UniqueView, UniqueViewMut};
+use wgpu::Operations;
 
 use crate::{
-    graphics::gpu::AbstractGpu,
+    graphics::stats::DrawStats,
     scene::scene_state::SceneState,
     wgpu_graphics::{
         components::{ScreenFrame, ScreenTexture},
-        gpu::Gpu,
         pipelines::frame_composition_pipeline::FrameCompositionPipeline,
-        CommandQueue, CommandSubmitOrder, OrderCommandBuffer,
+        FrameEncoder,
     },
 };
 
 pub(crate) fn frame_composition_pass_system(
-    gpu: UniqueView<AbstractGpu>,
     _s_state: UniqueView<SceneState>,
     _screen_frame: UniqueView<ScreenFrame>,
     pipeline: UniqueView<FrameCompositionPipeline>,
     screen_texture: UniqueView<ScreenTexture>,
-    queue: UniqueView<CommandQueue>,
+    mut frame: UniqueViewMut<FrameEncoder>,
 ) {
-    let gpu = gpu.downcast_ref::<Gpu>().expect(
-        "Incorrect Gpu abstractor provided, it was expecting a Wgpu Gpu",
-    );
-
-    let mut encoder =
-        gpu.device
-            .create_command_encoder(&CommandEncoderDescriptor {
-                label: Some("Frame composition encoder"),
-            });
+    let mut draws = DrawStats::default();
 
     let s_texture = match &screen_texture.0 {
         Some(s_t) => s_t,
@@ -36,6 +26,7 @@ pub(crate) fn frame_composition_pass_system(
     };
 
     {
+        let (encoder, timestamp_writes) = frame.timed_encoder();
         let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("Frame composition pass"),
             color_attachments: &[
@@ -55,7 +46,7 @@ pub(crate) fn frame_composition_pass_system(
                 }),
             ],
             depth_stencil_attachment: None,
-            timestamp_writes: None,
+            timestamp_writes,
             occlusion_query_set: None,
         });
 
@@ -64,11 +55,8 @@ pub(crate) fn frame_composition_pass_system(
         }
         pass.set_pipeline(&pipeline.pipeline);
         pass.draw(0..6, 0..1);
+        draws.record(2, 1);
     }
 
-    let _ = queue.0.push(OrderCommandBuffer::new(
-        Some("Copy final texture to screen".to_owned()),
-        CommandSubmitOrder::FrameComposition,
-        encoder.finish(),
-    ));
+    frame.record_draws(draws);
 }
@@ -0,0 +1,68 @@
+use std::iter;
+
+use shipyard::{UniqueView, UniqueViewMut};
+use wgpu::Operations;
+
+use crate::{
+    graphics::stats::DrawStats,
+    scene::{scene::AntiAliasing, scene_state::SceneState},
+    wgpu_graphics::{
+        buffer::{WGPUBindGroup, WGPUTexture},
+        pipelines::fxaa_pipeline::FxaaPipeline,
+        FrameEncoder,
+    },
+};
+
+/// Filters the post processed image of the scenes which use FXAA into their
+/// target texture.
+pub(crate) fn fxaa_pass_system(
+    pipeline: UniqueView<FxaaPipeline>,
+    s_state: UniqueView<SceneState>,
+    mut frame: UniqueViewMut<FrameEncoder>,
+) {
+    let mut draws = DrawStats::default();
+
+    for scene in iter::once(&s_state.main).chain(s_state.sub_scenes.values()) {
+        if scene.anti_aliasing != AntiAliasing::Fxaa {
+            continue;
+        }
+
+        let bind_group = match &scene.fxaa_bind_group {
+            Some(bg) => bg
+                .downcast_ref::<WGPUBindGroup>()
+                .expect("Incorrect bind group type"),
+            None => continue,
+        };
+
+        let target_texture = scene
+            .target_texture
+            .downcast_ref::<WGPUTexture>()
+            .expect("The provided scene texture is not a WGPU texture");
+
+        let (encoder, timestamp_writes) = frame.timed_encoder();
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(&format!("FXAA pass, {}", scene.label)),
+            color_attachments: &[
+                // @location(0)
+                Some(wgpu::RenderPassColorAttachment {
+                    view: &target_texture.view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                }),
+            ],
+            depth_stencil_attachment: None,
+            timestamp_writes,
+            occlusion_query_set: None,
+        });
+
+        pass.set_pipeline(&pipeline.pipeline);
+        pass.set_bind_group(0, &bind_group.0, &[]);
+        pass.draw(0..3, 0..1);
+        draws.record(1, 1);
+    }
+
+    frame.record_draws(draws);
+}
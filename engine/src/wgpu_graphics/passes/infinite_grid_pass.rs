@@ -1,34 +1,23 @@
-use shipyard::UniqueView;
-use wgpu::{
-    CommandEncoderDescriptor, Operations, RenderPassDepthStencilAttachment,
-};
+use shipyard::{UniqueView, UniqueViewMut};
+use wgpu::{Operations, RenderPassDepthStencilAttachment};
 
 use crate::{
-    graphics::gpu::AbstractGpu,
+    graphics::stats::DrawStats,
     scene::scene_state::SceneState,
     wgpu_graphics::{
         buffer::{WGPUBindGroup, WGPUTexture},
-        gpu::Gpu,
+        passes::{draw_scene_views, scene_color_attachment},
         pipelines::infinite_grid_pipeline::InfiniteGridPipeline,
-        CommandQueue, CommandSubmitOrder, OrderCommandBuffer,
+        FrameEncoder,
     },
 };
 
 pub(crate) fn infinite_grid_pass_system(
-    gpu: UniqueView<AbstractGpu>,
     pipeline: UniqueView<InfiniteGridPipeline>,
     scenes: UniqueView<SceneState>,
-    queue: UniqueView<CommandQueue>,
+    mut frame: UniqueViewMut<FrameEncoder>,
 ) {
-    let gpu = gpu.downcast_ref::<Gpu>().expect(
-        "Incorrect Gpu abstractor provided, it was expecting a Wgpu Gpu",
-    );
-
-    let mut encoder =
-        gpu.device
-            .create_command_encoder(&CommandEncoderDescriptor {
-                label: Some("Infinite grid encoder"),
-            });
+    let mut draws = DrawStats::default();
 
     // Iterate over each scene and draw the grid only if it is requested.
     for (_id, scene) in &scenes.sub_scenes {
@@ -41,17 +30,13 @@ pub(crate) fn infinite_grid_pass_system(
             .downcast_ref::<WGPUBindGroup>()
             .expect("Incorrect bind group type");
 
-        let target_texture = scene
-            .target_texture
-            .downcast_ref::<WGPUTexture>()
-            .expect("The provided scene texture is not a WGPU texture");
-
         let depth_texture = scene
             .depth_texture
             .downcast_ref::<WGPUTexture>()
             .expect("The provided scene texture is not a WGPU texture");
 
         {
+            let (encoder, timestamp_writes) = frame.timed_encoder();
             let mut pass =
                 encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                     label: Some(&format!(
@@ -60,14 +45,7 @@ pub(crate) fn infinite_grid_pass_system(
                     )),
                     color_attachments: &[
                         // @location(0)
-                        Some(wgpu::RenderPassColorAttachment {
-                            view: &target_texture.view,
-                            resolve_target: None,
-                            ops: Operations {
-                                load: wgpu::LoadOp::Load,
-                                store: wgpu::StoreOp::Store,
-                            },
-                        }),
+                        Some(scene_color_attachment(scene, wgpu::LoadOp::Load)),
                     ],
                     depth_stencil_attachment: Some(
                         RenderPassDepthStencilAttachment {
@@ -79,19 +57,17 @@ pub(crate) fn infinite_grid_pass_system(
                             stencil_ops: None,
                         },
                     ),
-                    timestamp_writes: None,
+                    timestamp_writes,
                     occlusion_query_set: None,
                 });
 
-            pass.set_pipeline(&pipeline.pipeline);
-            pass.set_bind_group(0, &camera_bind_group.0, &[]);
-            pass.draw(0..6, 0..1)
+            pass.set_pipeline(pipeline.pipeline.get(scene.sample_count()));
+            draw_scene_views(&mut pass, scene, &camera_bind_group.0, |pass| {
+                pass.draw(0..6, 0..1);
+                draws.record(2, 1);
+            });
         }
     }
 
-    let _ = queue.0.push(OrderCommandBuffer::new(
-        Some("Copy final texture to screen".to_owned()),
-        CommandSubmitOrder::DebugGrid,
-        encoder.finish(),
-    ));
+    frame.record_draws(draws);
 }
@@ -1,4 +1,54 @@
+use wgpu::{BindGroup, Operations, RenderPass, RenderPassColorAttachment};
+
+use crate::{
+    graphics::{camera::camera_offset, scene::Scene},
+    wgpu_graphics::buffer::texture_view,
+};
+
+pub mod custom_pass;
+pub mod debug_draw_pass;
 pub mod dynamic_mesh_pass;
 pub mod frame_composition_pass_system;
+pub mod fxaa_pass;
 pub mod infinite_grid_pass;
+pub mod outline_pass;
+pub mod post_process_pass;
+pub mod shadow_pass;
 pub mod sky_pass;
+
+/// Returns the color attachment where the scene is drawn, when the scene uses
+/// MSAA the samples are resolved into the first post process texture.
+pub(crate) fn scene_color_attachment(
+    scene: &Scene,
+    load: wgpu::LoadOp<wgpu::Color>,
+) -> RenderPassColorAttachment<'_> {
+    RenderPassColorAttachment {
+        view: texture_view(scene.color_texture.as_ref()),
+        resolve_target: (scene.sample_count() > 1)
+            .then(|| texture_view(scene.post_process_textures[0].as_ref())),
+        ops: Operations {
+            load,
+            store: wgpu::StoreOp::Store,
+        },
+    }
+}
+
+/// Runs `draw` once per view of the scene, with the pass restricted to the
+/// rect of the view and its camera bound to the group 0.
+pub(crate) fn draw_scene_views<'a>(
+    pass: &mut RenderPass<'a>,
+    scene: &Scene,
+    camera_bind_group: &'a BindGroup,
+    mut draw: impl FnMut(&mut RenderPass<'a>),
+) {
+    let size = scene.target_texture.size();
+
+    for (i, view) in scene.views.iter().enumerate() {
+        let [x, y, width, height] = view.pixel_rect(size);
+        let [min_depth, max_depth] = view.depth_range;
+
+        pass.set_viewport(x, y, width, height, min_depth, max_depth);
+        pass.set_bind_group(0, camera_bind_group, &[camera_offset(i)]);
+        draw(pass);
+    }
+}
@@ -0,0 +1,123 @@
+use shipyard::{UniqueView, UniqueViewMut};
+use wgpu::{BufferUsages, Operations, RenderPassDepthStencilAttachment};
+
+use crate::{
+    graphics::{gpu::AbstractGpu, scene::OutlineInstance, stats::DrawStats},
+    scene::{assets::asset_server::AssetServer, scene_state::SceneState},
+    wgpu_graphics::{
+        buffer::{
+            WGPUBindGroup, WGPUTexture, WgpuIndexBuffer, WgpuVertexBuffer,
+        },
+        gpu::Gpu,
+        passes::{draw_scene_views, scene_color_attachment},
+        pipelines::outline_pipeline::OutlinePipeline,
+        FrameEncoder,
+    },
+};
+
+pub(crate) fn outline_pass_system(
+    gpu: UniqueView<AbstractGpu>,
+    pipeline: UniqueView<OutlinePipeline>,
+    asset_server: UniqueView<AssetServer>,
+    scenes: UniqueView<SceneState>,
+    mut frame: UniqueViewMut<FrameEncoder>,
+) {
+    let gpu = gpu.downcast_ref::<Gpu>().expect(
+        "Incorrect Gpu abstractor provided, it was expecting a Wgpu Gpu",
+    );
+
+    let mut draws = DrawStats::default();
+
+    let scenes =
+        std::iter::once(&scenes.main).chain(scenes.sub_scenes.values());
+
+    for scene in scenes.filter(|s| !s.outline_targets.is_empty()) {
+        let camera_bind_group = match &scene.camera_bind_group {
+            Some(c) => c
+                .downcast_ref::<WGPUBindGroup>()
+                .expect("Incorrect bind group type"),
+            None => continue,
+        };
+
+        let depth_texture = scene
+            .depth_texture
+            .downcast_ref::<WGPUTexture>()
+            .expect("The provided scene texture is not a WGPU texture");
+
+        // The outlined entities change with the selection, the buffer is
+        // rebuilt each frame.
+        let instances = scene
+            .outline_targets
+            .iter()
+            .map(|t| t.instance)
+            .collect::<Vec<OutlineInstance>>();
+        let instance_buffer = gpu.raw_allocate_buffer_init(
+            &format!("Outline instance buffer, {}", scene.label),
+            bytemuck::cast_slice(&instances),
+            BufferUsages::VERTEX,
+        );
+
+        let meshes = scene
+            .outline_targets
+            .iter()
+            .map(|t| asset_server.load_mesh(&t.mesh))
+            .collect::<Vec<_>>();
+
+        {
+            let (encoder, timestamp_writes) = frame.timed_encoder();
+            let mut pass =
+                encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some(&format!("Outline pass, {}", scene.label)),
+                    color_attachments: &[
+                        // @location(0)
+                        Some(scene_color_attachment(scene, wgpu::LoadOp::Load)),
+                    ],
+                    depth_stencil_attachment: Some(
+                        RenderPassDepthStencilAttachment {
+                            view: &depth_texture.view,
+                            depth_ops: Some(Operations {
+                                load: wgpu::LoadOp::Load,
+                                store: wgpu::StoreOp::Store,
+                            }),
+                            stencil_ops: None,
+                        },
+                    ),
+                    timestamp_writes,
+                    occlusion_query_set: None,
+                });
+
+            pass.set_pipeline(pipeline.pipeline.get(scene.sample_count()));
+            pass.set_vertex_buffer(1, instance_buffer.slice(..));
+
+            draw_scene_views(&mut pass, scene, &camera_bind_group.0, |pass| {
+                for (i, mesh) in meshes.iter().enumerate() {
+                    let v_buffer = mesh
+                        .vertex_buffer
+                        .downcast_ref::<WgpuVertexBuffer>()
+                        .expect("Incorrect vertex buffer type, expecting WGPU vertex buffer");
+
+                    let i_buffer = mesh
+                        .index_buffer
+                        .downcast_ref::<WgpuIndexBuffer>()
+                        .expect("Incorrect index buffer type, expecting WGPU index buffer");
+
+                    let instance = i as u32;
+
+                    pass.set_vertex_buffer(0, v_buffer.0.slice(..));
+                    pass.set_index_buffer(
+                        i_buffer.0.slice(..),
+                        wgpu::IndexFormat::Uint16,
+                    );
+                    pass.draw_indexed(
+                        0..mesh.index_count,
+                        0,
+                        instance..instance + 1,
+                    );
+                    draws.record(mesh.index_count / 3, 1);
+                }
+            });
+        }
+    }
+
+    frame.record_draws(draws);
+}
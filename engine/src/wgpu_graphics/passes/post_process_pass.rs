@@ -0,0 +1,251 @@
+use std::iter;
+
+use shipyard::{UniqueView, UniqueViewMut};
+use wgpu::{Operations, RenderPipeline, TextureView};
+
+use crate::{
+    graphics::{
+        post_processing::POST_PROCESS_PARAMS_STRIDE,
+        scene::PostProcessBindGroups, stats::DrawStats,
+    },
+    scene::{
+        post_processing::{
+            PostProcessEffect, Tonemapping, MAX_POST_PROCESS_EFFECTS,
+        },
+        scene_state::SceneState,
+    },
+    wgpu_graphics::{
+        buffer::{texture_view, wgpu_bind_group},
+        pipelines::post_process_pipeline::PostProcessPipeline,
+        FrameEncoder,
+    },
+};
+
+/// Applies the post processing effects of each scene and writes the result
+/// into its target texture, or into its FXAA texture when FXAA is used.
+pub(crate) fn post_process_pass_system(
+    pipeline: UniqueView<PostProcessPipeline>,
+    s_state: UniqueView<SceneState>,
+    mut frame: UniqueViewMut<FrameEncoder>,
+) {
+    let frame: &mut FrameEncoder = &mut frame;
+
+    for scene in iter::once(&s_state.main).chain(s_state.sub_scenes.values()) {
+        let bind_groups = match &scene.post_process_bind_groups {
+            Some(bg) => bg,
+            None => continue,
+        };
+
+        let params = wgpu_bind_group(bind_groups.params.as_ref());
+
+        // Contains the post process texture with the current image. With MSAA
+        // the color texture is already resolved into the first one.
+        let mut current = match &bind_groups.color {
+            Some(_) => None,
+            None => Some(0),
+        };
+
+        for (i, effect) in scene
+            .post_processing
+            .iter()
+            .take(MAX_POST_PROCESS_EFFECTS)
+            .enumerate()
+        {
+            let offset = [(i as u64 * POST_PROCESS_PARAMS_STRIDE) as u32];
+
+            let source = source_bind_group(bind_groups, current);
+
+            let next = current.map_or(0, |c| 1 - c);
+            let output =
+                texture_view(scene.post_process_textures[next].as_ref());
+
+            let single_pass =
+                |frame: &mut FrameEncoder,
+                 label: &str,
+                 effect: &RenderPipeline| {
+                    fullscreen_pass(
+                        frame,
+                        label,
+                        output,
+                        effect,
+                        &[(source, &[]), (params, &offset)],
+                        wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    );
+                };
+
+            match effect {
+                PostProcessEffect::Exposure(_) => {
+                    single_pass(frame, "Exposure", &pipeline.effects.exposure)
+                }
+                PostProcessEffect::Gamma(_) => {
+                    single_pass(frame, "Gamma", &pipeline.effects.gamma)
+                }
+                PostProcessEffect::Tonemapping(tonemapping) => {
+                    let tonemapping = match tonemapping {
+                        Tonemapping::Reinhard => &pipeline.effects.reinhard,
+                        Tonemapping::Aces => &pipeline.effects.aces,
+                        Tonemapping::Agx => &pipeline.effects.agx,
+                    };
+
+                    single_pass(frame, "Tonemapping", tonemapping)
+                }
+                PostProcessEffect::ColorGrading { lut } => {
+                    let lut = match pipeline.lut_bind_group(lut) {
+                        Some(bg) => bg,
+                        None => continue,
+                    };
+
+                    fullscreen_pass(
+                        frame,
+                        "Color grading",
+                        output,
+                        &pipeline.effects.color_grading,
+                        &[(source, &[]), (params, &offset), (lut, &[])],
+                        wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    );
+                }
+                PostProcessEffect::Bloom { .. } => {
+                    // The bloom chain is allocated at the start of the next
+                    // frame.
+                    if bind_groups.bloom.is_empty() {
+                        continue;
+                    }
+
+                    let mips = scene
+                        .bloom_textures
+                        .iter()
+                        .map(|t| texture_view(t.as_ref()))
+                        .collect::<Vec<_>>();
+
+                    let mip_bind_groups = bind_groups
+                        .bloom
+                        .iter()
+                        .map(|bg| wgpu_bind_group(bg.as_ref()))
+                        .collect::<Vec<_>>();
+
+                    fullscreen_pass(
+                        frame,
+                        "Bloom prefilter",
+                        mips[0],
+                        &pipeline.effects.bloom_prefilter,
+                        &[(source, &[]), (params, &offset)],
+                        wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    );
+
+                    for mip in 1..mips.len() {
+                        fullscreen_pass(
+                            frame,
+                            "Bloom downsample",
+                            mips[mip],
+                            &pipeline.effects.bloom_downsample,
+                            &[
+                                (mip_bind_groups[mip - 1], &[]),
+                                (params, &offset),
+                            ],
+                            wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        );
+                    }
+
+                    for mip in (1..mips.len()).rev() {
+                        fullscreen_pass(
+                            frame,
+                            "Bloom upsample",
+                            mips[mip - 1],
+                            &pipeline.effects.bloom_upsample,
+                            &[(mip_bind_groups[mip], &[]), (params, &offset)],
+                            wgpu::LoadOp::Load,
+                        );
+                    }
+
+                    fullscreen_pass(
+                        frame,
+                        "Bloom combine",
+                        output,
+                        &pipeline.effects.bloom_combine,
+                        &[
+                            (source, &[]),
+                            (params, &offset),
+                            (mip_bind_groups[0], &[]),
+                        ],
+                        wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    );
+                }
+            }
+
+            current = Some(next);
+        }
+
+        let source = source_bind_group(bind_groups, current);
+
+        let output = match &scene.fxaa_texture {
+            Some(t) => texture_view(t.as_ref()),
+            None => texture_view(scene.target_texture.as_ref()),
+        };
+
+        fullscreen_pass(
+            frame,
+            &format!("Post process output, {}", scene.label),
+            output,
+            &pipeline.effects.output,
+            &[(source, &[]), (params, &[0])],
+            wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+        );
+    }
+}
+
+/// Returns the bind group which reads the current image, `None` means that
+/// it is still in the color texture.
+fn source_bind_group(
+    bind_groups: &PostProcessBindGroups,
+    current: Option<usize>,
+) -> &wgpu::BindGroup {
+    wgpu_bind_group(match current {
+        Some(c) => bind_groups.post_process[c].as_ref(),
+        None => bind_groups
+            .color
+            .as_deref()
+            .expect("The color texture bind group is not created"),
+    })
+}
+
+/// Draws a triangle covering the whole `output`, the bind groups are set in
+/// order with their dynamic offsets.
+fn fullscreen_pass(
+    frame: &mut FrameEncoder,
+    label: &str,
+    output: &TextureView,
+    pipeline: &RenderPipeline,
+    bind_groups: &[(&wgpu::BindGroup, &[u32])],
+    load: wgpu::LoadOp<wgpu::Color>,
+) {
+    {
+        let (encoder, timestamp_writes) = frame.timed_encoder();
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(label),
+            color_attachments: &[
+                // @location(0)
+                Some(wgpu::RenderPassColorAttachment {
+                    view: output,
+                    resolve_target: None,
+                    ops: Operations {
+                        load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                }),
+            ],
+            depth_stencil_attachment: None,
+            timestamp_writes,
+            occlusion_query_set: None,
+        });
+
+        pass.set_pipeline(pipeline);
+        for (index, (bind_group, offsets)) in bind_groups.iter().enumerate() {
+            pass.set_bind_group(index as u32, bind_group, offsets);
+        }
+        pass.draw(0..3, 0..1);
+    }
+
+    let mut draws = DrawStats::default();
+    draws.record(1, 1);
+    frame.record_draws(draws);
+}
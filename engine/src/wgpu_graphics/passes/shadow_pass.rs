@@ -0,0 +1,128 @@
+use std::iter;
+
+use shipyard::{UniqueView, UniqueViewMut};
+use wgpu::{
+    Operations, RenderPassDepthStencilAttachment, TextureViewDescriptor,
+};
+
+use crate::{
+    graphics::{lights::SHADOW_CASTER_STRIDE, stats::DrawStats},
+    scene::{assets::asset_server::AssetServer, scene_state::SceneState},
+    wgpu_graphics::{
+        buffer::{
+            WGPUBindGroup, WGPUTexture, WgpuIndexBuffer, WgpuVertexBuffer,
+        },
+        pipelines::shadow_pipeline::ShadowPipeline,
+        FrameEncoder,
+    },
+};
+
+/// Renders the shadow maps of the lights casting shadows in each scene, it
+/// must be executed before the `dynamic_mesh_pass_system`.
+pub(crate) fn shadow_pass_system(
+    shadow_pipeline: UniqueView<ShadowPipeline>,
+    mut frame: UniqueViewMut<FrameEncoder>,
+    asset_server: UniqueView<AssetServer>,
+    scenes: UniqueView<SceneState>,
+) {
+    let mut draws = DrawStats::default();
+
+    for (_id, scene) in scenes
+        .sub_scenes
+        .iter()
+        .chain(iter::once((&"!internal_main".to_owned(), &scenes.main)))
+    {
+        let shadow_caster_bind_group = match &scene.shadow_caster_bind_group {
+            Some(bg) => bg
+                .downcast_ref::<WGPUBindGroup>()
+                .expect("Incorrect bind group type"),
+            None => continue,
+        };
+
+        let shadow_maps = scene
+            .shadow_maps
+            .downcast_ref::<WGPUTexture>()
+            .expect("The provided shadow maps are not a WGPU texture");
+
+        let casters = scene
+            .mesh_transform_buffers
+            .iter()
+            // The instances outside the camera are drawn too, their shadows
+            // may be visible.
+            .filter(|((_, _, flags), instances)| {
+                flags.casts_shadows && instances.count >= 1
+            })
+            .map(|((mesh_id, _, _), instances)| {
+                (
+                    asset_server.load_mesh(mesh_id),
+                    &instances.buffer,
+                    instances.count,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        for layer in 0..scene.shadow_maps_in_use {
+            let view =
+                shadow_maps.texture.create_view(&TextureViewDescriptor {
+                    label: Some("Shadow map view"),
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_array_layer: layer,
+                    array_layer_count: Some(1),
+                    ..Default::default()
+                });
+
+            let (encoder, timestamp_writes) = frame.timed_encoder();
+            let mut pass =
+                encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Shadow pass"),
+                    color_attachments: &[],
+                    depth_stencil_attachment: Some(
+                        RenderPassDepthStencilAttachment {
+                            view: &view,
+                            depth_ops: Some(Operations {
+                                load: wgpu::LoadOp::Clear(1.0),
+                                store: wgpu::StoreOp::Store,
+                            }),
+                            stencil_ops: None,
+                        },
+                    ),
+                    timestamp_writes,
+                    occlusion_query_set: None,
+                });
+
+            pass.set_pipeline(&shadow_pipeline.pipeline);
+            pass.set_bind_group(
+                0,
+                &shadow_caster_bind_group.0,
+                &[(layer as u64 * SHADOW_CASTER_STRIDE) as u32],
+            );
+
+            for (mesh, t_buffer, count) in &casters {
+                let v_buffer = mesh
+                    .vertex_buffer
+                    .downcast_ref::<WgpuVertexBuffer>()
+                    .expect("Incorrect vertex buffer type, expecting WGPU vertex buffer");
+
+                let i_buffer = mesh
+                    .index_buffer
+                    .downcast_ref::<WgpuIndexBuffer>()
+                    .expect("Incorrect vertex buffer type, expecting WGPU index buffer");
+
+                let t_buffer = t_buffer
+                    .downcast_ref::<WgpuVertexBuffer>()
+                    .expect("Incorrect vertex buffer type, expecting WGPU vertex buffer");
+
+                pass.set_vertex_buffer(0, v_buffer.0.slice(..));
+                pass.set_vertex_buffer(1, t_buffer.0.slice(..));
+                pass.set_index_buffer(
+                    i_buffer.0.slice(..),
+                    wgpu::IndexFormat::Uint16,
+                );
+                pass.draw_indexed(0..mesh.index_count, 0, 0..*count);
+                draws.record(mesh.index_count / 3, *count);
+            }
+        }
+    }
+
+    frame.record_draws(draws);
+}
@@ -1,47 +1,31 @@
 use std::iter;
 
-use shipyard::UniqueView;
-use wgpu::{
-    CommandEncoderDescriptor, Operations, RenderPassDepthStencilAttachment,
-};
+use shipyard::{UniqueView, UniqueViewMut};
+use wgpu::{Operations, RenderPassDepthStencilAttachment};
 
 use crate::{
-    graphics::gpu::AbstractGpu,
+    graphics::stats::DrawStats,
     scene::scene_state::SceneState,
     wgpu_graphics::{
         buffer::{WGPUBindGroup, WGPUTexture},
-        gpu::Gpu,
+        passes::{draw_scene_views, scene_color_attachment},
         pipelines::sky_pipeline::SkyPipeline,
-        CommandQueue, CommandSubmitOrder, OrderCommandBuffer,
+        FrameEncoder,
     },
 };
 
 pub(crate) fn sky_pass_system(
-    gpu: UniqueView<AbstractGpu>,
     s_state: UniqueView<SceneState>,
     sky_pipeline: UniqueView<SkyPipeline>,
-    queue: UniqueView<CommandQueue>,
+    mut frame: UniqueViewMut<FrameEncoder>,
 ) {
-    let gpu = gpu.downcast_ref::<Gpu>().expect(
-        "Incorrect Gpu abstractor provided, it was expecting a Wgpu Gpu",
-    );
-
-    let mut encoder =
-        gpu.device
-            .create_command_encoder(&CommandEncoderDescriptor {
-                label: Some("Sky encoder"),
-            });
+    let mut draws = DrawStats::default();
 
     for (_id, scene) in s_state
         .sub_scenes
         .iter()
         .chain(iter::once((&"!internal_main".to_owned(), &s_state.main)))
     {
-        let target_texture = scene
-            .target_texture
-            .downcast_ref::<WGPUTexture>()
-            .expect("The provided scene texture is not a WGPU Texture");
-
         let depth_texture = scene
             .depth_texture
             .downcast_ref::<WGPUTexture>()
@@ -67,23 +51,20 @@ pub(crate) fn sky_pass_system(
             .downcast_ref::<WGPUBindGroup>()
             .expect("Incorrect bind group type");
 
+        let (encoder, timestamp_writes) = frame.timed_encoder();
         let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("Sky pass"),
             color_attachments: &[
                 // @location(0)
-                Some(wgpu::RenderPassColorAttachment {
-                    view: &target_texture.view,
-                    resolve_target: None,
-                    ops: Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 1.0,
-                            g: 1.0,
-                            b: 1.0,
-                            a: 1.0,
-                        }),
-                        store: wgpu::StoreOp::Store,
-                    },
-                }),
+                Some(scene_color_attachment(
+                    scene,
+                    wgpu::LoadOp::Clear(wgpu::Color {
+                        r: 1.0,
+                        g: 1.0,
+                        b: 1.0,
+                        a: 1.0,
+                    }),
+                )),
             ],
             depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
                 view: &depth_texture.view,
@@ -93,19 +74,19 @@ pub(crate) fn sky_pass_system(
                 }),
                 stencil_ops: None,
             }),
-            timestamp_writes: None,
+            timestamp_writes,
             occlusion_query_set: None,
         });
 
-        pass.set_pipeline(&sky_pipeline.pipeline);
-        pass.set_bind_group(0, &camera_bind_group.0, &[]);
+        pass.set_pipeline(sky_pipeline.pipeline.get(scene.sample_count()));
         pass.set_bind_group(1, &sky_texture_bind_group.0, &[]);
-        pass.draw(0..3, 0..1);
+
+        // The targets are cleared once, each view draws the sky in its rect.
+        draw_scene_views(&mut pass, scene, &camera_bind_group.0, |pass| {
+            pass.draw(0..3, 0..1);
+            draws.record(1, 1);
+        });
     }
 
-    let _ = queue.0.push(OrderCommandBuffer::new(
-        Some("Render sky".to_owned()),
-        CommandSubmitOrder::Sky,
-        encoder.finish(),
-    ));
+    frame.record_draws(draws);
 }
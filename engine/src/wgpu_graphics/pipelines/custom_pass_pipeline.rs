@@ -0,0 +1,542 @@
+use ahash::{AHashMap, AHashSet};
+use log::error;
+use shipyard::{Unique, UniqueView, UniqueViewMut};
+use wgpu::{
+    BindGroupLayout, BindGroupLayoutEntry, BlendComponent, BlendFactor,
+    BlendOperation, BlendState, Buffer, ColorTargetState, ColorWrites,
+    Extent3d, FragmentState, PipelineLayoutDescriptor, RenderPipeline,
+    RenderPipelineDescriptor, Sampler, ShaderStages, TextureDescriptor,
+    TextureDimension, TextureFormat, TextureUsages, TextureViewDescriptor,
+    VertexState,
+};
+
+use crate::{
+    graphics::{
+        gpu::AbstractGpu,
+        render_pass::{
+            CustomRenderPass, CustomRenderPassDescriptor, CustomRenderPasses,
+            CustomTextureDescriptor, PassBlend, PassDepth, PassOrder,
+            PassTexture, PassTextureFormat,
+        },
+        scene::Scene,
+    },
+    scene::{scene::SceneTarget, scene_state::SceneState},
+    wgpu_graphics::{
+        buffer::WGPUTexture,
+        gpu::{
+            Gpu, DEPTH_TEXTURE_FORMAT, HDR_TEXTURE_FORMAT,
+            TARGET_TEXTURE_FORMAT,
+        },
+        passes::custom_pass::custom_pass_system,
+        render_graph::{builtin_node, GraphResource, RenderGraph, RenderNode},
+    },
+};
+
+use super::GlobalBindGroupLayouts;
+
+/// Pipeline of a custom pass, it matches the sample count of its scene.
+pub(crate) struct CustomPassPipeline {
+    pub(crate) pipeline: RenderPipeline,
+    /// Layout of the uniform, the inputs and the sampler of the pass.
+    pub(crate) resources_layout: BindGroupLayout,
+    pub(crate) uniform: Buffer,
+    sample_count: u32,
+}
+
+/// Contains the GPU resources of the passes added to the
+/// `CustomRenderPasses`.
+#[derive(Unique)]
+pub(crate) struct CustomPassPipelines {
+    /// Contains the pipeline of each pass by its id.
+    pub(crate) pipelines: AHashMap<u64, CustomPassPipeline>,
+    /// Contains the passes whose pipeline could not be created, they are
+    /// skipped until they are added again.
+    failed: AHashSet<u64>,
+    /// Contains the label of the `RenderGraph` node of each pass by its id.
+    nodes: AHashMap<u64, String>,
+    /// Contains the slot of each allocated custom texture.
+    textures: AHashMap<String, usize>,
+    /// Contains the allocated textures, the transient textures whose
+    /// lifetimes don't overlap share a slot.
+    slots: Vec<(CustomTextureDescriptor, WGPUTexture)>,
+    pub(crate) sampler: Sampler,
+}
+
+impl CustomPassPipelines {
+    /// Creates and returns a new `CustomPassPipelines`.
+    pub(crate) fn new(gpu: &Gpu) -> Self {
+        let sampler = gpu.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Custom pass sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self {
+            pipelines: AHashMap::new(),
+            failed: AHashSet::new(),
+            nodes: AHashMap::new(),
+            textures: AHashMap::new(),
+            slots: Vec::new(),
+            sampler,
+        }
+    }
+
+    /// Returns the texture allocated for a custom texture, the transient
+    /// textures which no pass uses are not allocated.
+    pub(crate) fn texture(&self, name: &str) -> Option<&WGPUTexture> {
+        self.textures.get(name).map(|slot| &self.slots[*slot].1)
+    }
+}
+
+/// Returns the scene used by a custom pass, the sub scenes may not be
+/// registered yet.
+pub(crate) fn custom_pass_scene<'a>(
+    s_state: &'a SceneState,
+    target: &SceneTarget,
+) -> Option<&'a Scene> {
+    match target {
+        SceneTarget::Main => Some(&s_state.main),
+        SceneTarget::SubScene(id) => s_state.sub_scenes.get(id),
+    }
+}
+
+/// Adds the custom passes to the `RenderGraph`, allocates their textures and
+/// creates their pipelines, then writes their pending uniforms.
+pub(crate) fn setup_custom_pass_pipelines_system(
+    gpu: UniqueView<AbstractGpu>,
+    s_state: UniqueView<SceneState>,
+    layouts: UniqueView<GlobalBindGroupLayouts>,
+    mut passes: UniqueViewMut<CustomRenderPasses>,
+    mut pipelines: UniqueViewMut<CustomPassPipelines>,
+    mut graph: UniqueViewMut<RenderGraph>,
+) {
+    let gpu = gpu
+        .downcast_ref::<Gpu>()
+        .expect("Incorrect GPU type expecting WGPU gpu");
+
+    let passes = &mut *passes;
+    let pipelines = &mut *pipelines;
+
+    let exists = |id: &u64| passes.passes.iter().any(|p| p.id == *id);
+
+    pipelines.nodes.retain(|id, label| {
+        if !exists(id) {
+            graph.remove(label);
+        }
+
+        exists(id)
+    });
+    pipelines.pipelines.retain(|id, _| exists(id));
+    pipelines.failed.retain(|id| exists(id));
+
+    for pass in &passes.passes {
+        if pipelines.nodes.contains_key(&pass.id)
+            || pipelines.failed.contains(&pass.id)
+        {
+            continue;
+        }
+
+        let node = custom_pass_node(pass);
+        let placed = match pass.descriptor.order {
+            PassOrder::Before(builtin) => {
+                graph.insert_before(builtin_node(builtin), node)
+            }
+            PassOrder::After(builtin) => {
+                graph.insert_after(builtin_node(builtin), node)
+            }
+        };
+
+        match placed {
+            Ok(()) => {
+                pipelines
+                    .nodes
+                    .insert(pass.id, pass.descriptor.label.clone());
+            }
+            Err(e) => {
+                error!(
+                    "Unable to add the custom pass {}: {}",
+                    pass.descriptor.label, e
+                );
+                pipelines.failed.insert(pass.id);
+            }
+        }
+    }
+
+    let (textures, slots) =
+        assign_texture_slots(&passes.textures, &graph.lifetimes());
+
+    // The slots whose descriptor changed are allocated again.
+    pipelines.slots.truncate(slots.len());
+    for (i, descriptor) in slots.iter().enumerate() {
+        let reusable = pipelines
+            .slots
+            .get(i)
+            .map(|(allocated, _)| same_texture(allocated, descriptor))
+            .unwrap_or(false);
+
+        if reusable {
+            continue;
+        }
+
+        let texture = allocate_custom_texture(
+            gpu,
+            &format!("Custom texture {}", i),
+            descriptor,
+        );
+
+        if i < pipelines.slots.len() {
+            pipelines.slots[i] = (*descriptor, texture);
+        } else {
+            pipelines.slots.push((*descriptor, texture));
+        }
+    }
+    pipelines.textures = textures;
+
+    for pass in &mut passes.passes {
+        if pipelines.failed.contains(&pass.id) {
+            continue;
+        }
+
+        let scene = match custom_pass_scene(&s_state, &pass.descriptor.scene) {
+            Some(s) => s,
+            None => continue,
+        };
+
+        let outdated = pipelines
+            .pipelines
+            .get(&pass.id)
+            .map(|p| p.sample_count != scene.sample_count())
+            .unwrap_or(true);
+
+        if outdated {
+            match create_custom_pass_pipeline(
+                gpu,
+                &layouts.camera,
+                &pass.descriptor,
+                scene.sample_count(),
+                &passes.textures,
+            ) {
+                Ok(pipeline) => {
+                    pipelines.pipelines.insert(pass.id, pipeline);
+                }
+                Err(e) => {
+                    error!(
+                        "Unable to create the custom pass {}: {}",
+                        pass.descriptor.label, e
+                    );
+                    pipelines.failed.insert(pass.id);
+                    continue;
+                }
+            }
+        }
+
+        if let Some(data) = pass.pending_uniform.take() {
+            let pipeline = &pipelines.pipelines[&pass.id];
+            gpu.write_buffer(&pipeline.uniform, 0, &data);
+        }
+    }
+}
+
+fn graph_resource(texture: &PassTexture) -> GraphResource {
+    match texture {
+        PassTexture::SceneColor => GraphResource::SceneColor,
+        PassTexture::SceneTarget => GraphResource::SceneTarget,
+        PassTexture::SceneDepth => GraphResource::SceneDepth,
+        PassTexture::Custom(name) => GraphResource::Custom(name.clone()),
+    }
+}
+
+/// Returns the `RenderGraph` node which draws a custom pass.
+fn custom_pass_node(pass: &CustomRenderPass) -> RenderNode {
+    let descriptor = &pass.descriptor;
+
+    let mut reads = descriptor
+        .inputs
+        .iter()
+        .map(graph_resource)
+        .collect::<Vec<_>>();
+    let mut writes = vec![graph_resource(&descriptor.output)];
+
+    if descriptor.depth != PassDepth::Ignore {
+        reads.push(GraphResource::SceneDepth);
+    }
+
+    if descriptor.depth == PassDepth::TestAndWrite {
+        writes.push(GraphResource::SceneDepth);
+    }
+
+    let id = pass.id;
+
+    RenderNode::new(&descriptor.label, reads, writes, move |world| {
+        world.run_with_data(custom_pass_system, id)
+    })
+}
+
+fn same_texture(
+    a: &CustomTextureDescriptor,
+    b: &CustomTextureDescriptor,
+) -> bool {
+    a.format == b.format
+        && a.size.width == b.size.width
+        && a.size.height == b.size.height
+}
+
+/// Returns the slot of each custom texture and the descriptor of each slot.
+/// The transient textures share a slot with the ones used before them, and
+/// they are not allocated when no pass uses them.
+fn assign_texture_slots(
+    textures: &AHashMap<String, CustomTextureDescriptor>,
+    lifetimes: &AHashMap<GraphResource, (usize, usize)>,
+) -> (AHashMap<String, usize>, Vec<CustomTextureDescriptor>) {
+    let mut used = textures
+        .iter()
+        .filter_map(|(name, descriptor)| {
+            let lifetime =
+                lifetimes.get(&GraphResource::Custom(name.clone())).copied();
+
+            match (descriptor.transient, lifetime) {
+                (true, None) => None,
+                (true, lifetime) => Some((name, descriptor, lifetime)),
+                (false, _) => Some((name, descriptor, None)),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // The persistent textures go first, then the transient ones by their
+    // first use.
+    used.sort_by(|a, b| a.2.cmp(&b.2).then_with(|| a.0.cmp(b.0)));
+
+    // Contains the last use of each slot, `None` for the persistent ones.
+    let mut slots = Vec::<(CustomTextureDescriptor, Option<usize>)>::new();
+    let mut assigned = AHashMap::new();
+
+    for (name, descriptor, lifetime) in used {
+        let free_slot = lifetime.and_then(|(first, _)| {
+            slots.iter().position(|(allocated, last)| {
+                last.map(|last| last < first).unwrap_or(false)
+                    && same_texture(allocated, descriptor)
+            })
+        });
+
+        let slot = match free_slot {
+            Some(slot) => {
+                slots[slot].1 = lifetime.map(|(_, last)| last);
+                slot
+            }
+            None => {
+                slots.push((*descriptor, lifetime.map(|(_, last)| last)));
+                slots.len() - 1
+            }
+        };
+
+        assigned.insert(name.clone(), slot);
+    }
+
+    (assigned, slots.into_iter().map(|(d, _)| d).collect())
+}
+
+fn texture_format(format: PassTextureFormat) -> TextureFormat {
+    match format {
+        PassTextureFormat::Rgba8 => TextureFormat::Rgba8Unorm,
+        PassTextureFormat::Rgba16Float => TextureFormat::Rgba16Float,
+    }
+}
+
+fn allocate_custom_texture(
+    gpu: &Gpu,
+    name: &str,
+    descriptor: &CustomTextureDescriptor,
+) -> WGPUTexture {
+    let texture = gpu.device.create_texture(&TextureDescriptor {
+        label: Some(name),
+        size: Extent3d {
+            width: descriptor.size.width.max(1),
+            height: descriptor.size.height.max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: texture_format(descriptor.format),
+        usage: TextureUsages::RENDER_ATTACHMENT
+            | TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+
+    let view = texture.create_view(&TextureViewDescriptor::default());
+
+    WGPUTexture {
+        texture,
+        view,
+        sampler: None,
+    }
+}
+
+/// Creates the pipeline of a custom pass, the errors of its shader are
+/// returned instead of aborting.
+fn create_custom_pass_pipeline(
+    gpu: &Gpu,
+    camera_layout: &BindGroupLayout,
+    descriptor: &CustomRenderPassDescriptor,
+    sample_count: u32,
+    textures: &AHashMap<String, CustomTextureDescriptor>,
+) -> Result<CustomPassPipeline, String> {
+    let format_of = |texture: &PassTexture| match texture {
+        PassTexture::SceneColor => Ok(HDR_TEXTURE_FORMAT),
+        PassTexture::SceneTarget => Ok(TARGET_TEXTURE_FORMAT),
+        PassTexture::SceneDepth => Ok(DEPTH_TEXTURE_FORMAT),
+        PassTexture::Custom(name) => textures
+            .get(name)
+            .map(|t| texture_format(t.format))
+            .ok_or_else(|| format!("The texture {} was removed", name)),
+    };
+
+    let mut entries = vec![BindGroupLayoutEntry {
+        binding: 0,
+        visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }];
+
+    for (i, input) in descriptor.inputs.iter().enumerate() {
+        // The scene color is read once resolved.
+        let (sample_type, multisampled) = match input {
+            PassTexture::SceneDepth => {
+                (wgpu::TextureSampleType::Depth, sample_count > 1)
+            }
+            _ => {
+                format_of(input)?;
+                (wgpu::TextureSampleType::Float { filterable: true }, false)
+            }
+        };
+
+        entries.push(BindGroupLayoutEntry {
+            binding: i as u32 + 1,
+            visibility: ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled,
+            },
+            count: None,
+        });
+    }
+
+    entries.push(BindGroupLayoutEntry {
+        binding: descriptor.inputs.len() as u32 + 1,
+        visibility: ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        count: None,
+    });
+
+    // Only the scene color is multisampled.
+    let (output_format, target_sample_count) = match &descriptor.output {
+        PassTexture::SceneColor => (HDR_TEXTURE_FORMAT, sample_count),
+        output => (format_of(output)?, 1),
+    };
+
+    let blend = match descriptor.blend {
+        PassBlend::Replace => None,
+        PassBlend::Alpha => Some(BlendState::ALPHA_BLENDING),
+        PassBlend::Additive => {
+            let additive = BlendComponent {
+                src_factor: BlendFactor::One,
+                dst_factor: BlendFactor::One,
+                operation: BlendOperation::Add,
+            };
+
+            Some(BlendState {
+                color: additive,
+                alpha: additive,
+            })
+        }
+    };
+
+    let depth_stencil = match descriptor.depth {
+        PassDepth::Ignore => None,
+        depth => Some(wgpu::DepthStencilState {
+            format: DEPTH_TEXTURE_FORMAT,
+            depth_write_enabled: depth == PassDepth::TestAndWrite,
+            depth_compare: wgpu::CompareFunction::LessEqual,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+    };
+
+    let label = &descriptor.label;
+
+    let (pipeline, resources_layout) = gpu.validate(|| {
+        let program = gpu.compile_program(label, &descriptor.shader);
+
+        let resources_layout = gpu.device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some(&format!("{} resources bind group layout", label)),
+                entries: &entries,
+            },
+        );
+
+        let layout =
+            gpu.device
+                .create_pipeline_layout(&PipelineLayoutDescriptor {
+                    label: Some(&format!("{} pipeline layout", label)),
+                    bind_group_layouts: &[camera_layout, &resources_layout],
+                    push_constant_ranges: &[],
+                });
+
+        let pipeline =
+            gpu.device
+                .create_render_pipeline(&RenderPipelineDescriptor {
+                    label: Some(&format!("{} render pipeline", label)),
+                    layout: Some(&layout),
+                    vertex: VertexState {
+                        module: &program,
+                        entry_point: "vs_main",
+                        buffers: &[],
+                    },
+                    primitive: wgpu::PrimitiveState::default(),
+                    depth_stencil,
+                    multisample: wgpu::MultisampleState {
+                        count: target_sample_count,
+                        ..Default::default()
+                    },
+                    fragment: Some(FragmentState {
+                        module: &program,
+                        entry_point: "fs_main",
+                        targets: &[Some(ColorTargetState {
+                            format: output_format,
+                            blend,
+                            write_mask: ColorWrites::ALL,
+                        })],
+                    }),
+                    multiview: None,
+                });
+
+        (pipeline, resources_layout)
+    })?;
+
+    // The uniform buffers must be at least 16 bytes and a multiple of them.
+    let uniform_size = (descriptor.uniform_size.max(1) + 15) / 16 * 16;
+
+    let uniform = gpu.device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(&format!("{} uniform", label)),
+        size: uniform_size,
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    Ok(CustomPassPipeline {
+        pipeline,
+        resources_layout,
+        uniform,
+        sample_count,
+    })
+}
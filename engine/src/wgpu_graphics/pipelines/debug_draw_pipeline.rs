@@ -0,0 +1,98 @@
+use shipyard::Unique;
+
+use wgpu::{
+    vertex_attr_array, BindGroupLayout, ColorTargetState, CompareFunction,
+    FragmentState, PipelineLayoutDescriptor, RenderPipelineDescriptor,
+    ShaderModule, VertexBufferLayout, VertexState,
+};
+
+use crate::{
+    graphics::debug_draw::DebugVertex,
+    wgpu_graphics::gpu::{Gpu, DEPTH_TEXTURE_FORMAT, HDR_TEXTURE_FORMAT},
+};
+
+use super::MultisamplePipeline;
+
+#[derive(Unique)]
+pub struct DebugDrawPipeline {
+    /// Draws the lines hidden by the geometry in front of them.
+    pub(crate) pipeline: MultisamplePipeline,
+    /// Draws the lines on top of the scene.
+    pub(crate) overlay_pipeline: MultisamplePipeline,
+}
+
+impl DebugDrawPipeline {
+    /// Creates and returns a new `DebugDrawPipeline`.
+    pub(crate) fn new(
+        gpu: &Gpu,
+        program: &ShaderModule,
+        camera_bind_group_layout: &BindGroupLayout,
+        sample_counts: &[u32],
+    ) -> Self {
+        let layout =
+            gpu.device
+                .create_pipeline_layout(&PipelineLayoutDescriptor {
+                    label: Some("Debug draw pipeline layout"),
+                    bind_group_layouts: &[camera_bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+
+        let create = |count: u32, depth_compare: CompareFunction| {
+            gpu.device
+                .create_render_pipeline(&RenderPipelineDescriptor {
+                    label: Some("Debug draw render pipeline"),
+                    layout: Some(&layout),
+                    vertex: VertexState {
+                        module: program,
+                        entry_point: "vs_main",
+                        buffers: &[VertexBufferLayout {
+                            array_stride: std::mem::size_of::<DebugVertex>()
+                                as wgpu::BufferAddress,
+                            step_mode: wgpu::VertexStepMode::Vertex,
+                            attributes: &vertex_attr_array![
+                                0 => Float32x3,
+                                1 => Float32x4
+                            ],
+                        }],
+                    },
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::LineList,
+                        ..Default::default()
+                    },
+                    // The lines never write the depth, so they don't hide
+                    // each other.
+                    depth_stencil: Some(wgpu::DepthStencilState {
+                        format: DEPTH_TEXTURE_FORMAT,
+                        depth_write_enabled: false,
+                        depth_compare,
+                        stencil: wgpu::StencilState::default(),
+                        bias: wgpu::DepthBiasState::default(),
+                    }),
+                    multisample: wgpu::MultisampleState {
+                        count,
+                        ..Default::default()
+                    },
+                    fragment: Some(FragmentState {
+                        module: program,
+                        entry_point: "fs_main",
+                        targets: &[Some(ColorTargetState {
+                            format: HDR_TEXTURE_FORMAT,
+                            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                    }),
+                    multiview: None,
+                })
+        };
+
+        DebugDrawPipeline {
+            pipeline: MultisamplePipeline::new(sample_counts, |count| {
+                create(count, CompareFunction::LessEqual)
+            }),
+            overlay_pipeline: MultisamplePipeline::new(
+                sample_counts,
+                |count| create(count, CompareFunction::Always),
+            ),
+        }
+    }
+}
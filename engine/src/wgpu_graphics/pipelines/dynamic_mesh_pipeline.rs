@@ -1,107 +1,228 @@
+use log::warn;
 use shipyard::Unique;
 
 use wgpu::{
-    vertex_attr_array, BindGroupLayout, BlendComponent, BufferAddress,
-    ColorTargetState, ColorWrites, DepthBiasState, DepthStencilState,
-    FragmentState, MultisampleState, PipelineLayoutDescriptor, PrimitiveState,
-    RenderPipeline, RenderPipelineDescriptor, StencilState, VertexBufferLayout,
-    VertexState,
+    vertex_attr_array, BindGroupLayout, BlendComponent, BlendFactor,
+    BlendOperation, BlendState, BufferAddress, ColorTargetState, ColorWrites,
+    CompareFunction, DepthBiasState, DepthStencilState, Features,
+    FragmentState, MultisampleState, PipelineLayout, PipelineLayoutDescriptor,
+    PolygonMode, PrimitiveState, RenderPipeline, RenderPipelineDescriptor,
+    ShaderModule, StencilState, VertexBufferLayout, VertexState,
 };
 
 use crate::{
-    graphics::vertex::Vertex,
-    wgpu_graphics::gpu::{Gpu, DEPTH_TEXTURE_FORMAT},
+    graphics::{scene::InstanceData, vertex::Vertex},
+    wgpu_graphics::gpu::{Gpu, DEPTH_TEXTURE_FORMAT, HDR_TEXTURE_FORMAT},
 };
 
+use super::MultisamplePipeline;
+
 #[derive(Unique)]
 pub struct DynamicMeshPipeline {
     /// Contains a reference to the pipeline.
-    pub(crate) pipeline: RenderPipeline,
+    pub(crate) pipeline: MultisamplePipeline,
+    /// Draws the meshes with blended materials, they are blended with the
+    /// color behind and do not write the depth.
+    pub(crate) transparent_pipeline: MultisamplePipeline,
+    /// Adds the meshes to the color without testing the depth, used by
+    /// `DebugView::Overdraw`.
+    pub(crate) overdraw_pipeline: MultisamplePipeline,
+    /// Draws the edges of the triangles on top of the meshes, it is `None`
+    /// when the GPU can't draw the polygons as lines.
+    pub(crate) wireframe_pipeline: Option<MultisamplePipeline>,
+}
+
+/// Render states of each pipeline created from the dynamic mesh shader.
+struct PipelineVariant<'a> {
+    label: &'a str,
+    fragment_entry_point: &'a str,
+    blend: BlendState,
+    depth_write: bool,
+    depth_compare: CompareFunction,
+    depth_bias: DepthBiasState,
+    polygon_mode: PolygonMode,
+}
+
+impl<'a> PipelineVariant<'a> {
+    fn new(label: &'a str, blend: BlendState, depth_write: bool) -> Self {
+        Self {
+            label,
+            fragment_entry_point: "fs_main",
+            blend,
+            depth_write,
+            depth_compare: CompareFunction::Less,
+            depth_bias: DepthBiasState::default(),
+            polygon_mode: PolygonMode::Fill,
+        }
+    }
 }
 
 impl DynamicMeshPipeline {
     /// Creates and returns a new `DynamicMeshPipeline`.
     pub(crate) fn new(
         gpu: &Gpu,
+        program: &ShaderModule,
         camera_bind_group_layout: &BindGroupLayout,
+        material_bind_group_layout: &BindGroupLayout,
+        lights_bind_group_layout: &BindGroupLayout,
+        environment_bind_group_layout: &BindGroupLayout,
+        sample_counts: &[u32],
     ) -> DynamicMeshPipeline {
-        let program = gpu.compile_program(
-            "dynamic_mesh_program",
-            include_str!("../shaders/dynamic_mesh.wgsl"),
-        );
-
         let layout =
             gpu.device
                 .create_pipeline_layout(&PipelineLayoutDescriptor {
                     label: Some("Dynamic mesh pipeline layout"),
-                    bind_group_layouts: &[camera_bind_group_layout],
+                    bind_group_layouts: &[
+                        camera_bind_group_layout,
+                        material_bind_group_layout,
+                        lights_bind_group_layout,
+                        environment_bind_group_layout,
+                    ],
                     push_constant_ranges: &[],
                 });
 
-        let pipeline = gpu
-            .device
-            .create_render_pipeline(&RenderPipelineDescriptor {
-                label: Some("Dynamic mesh render pipeline"),
-                layout: Some(&layout),
-                vertex: VertexState {
-                    module: &program,
-                    entry_point: "vs_main",
-                    buffers: &[
-                        // Defines the `Vertex` layout format.
-                        VertexBufferLayout {
-                            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-                            step_mode: wgpu::VertexStepMode::Vertex,
-                            attributes: &vertex_attr_array![0 => Float32x3, 1 => Float32x3],
-                        },
-                        // Defines the Vertex transform.
-                        VertexBufferLayout {
-                            array_stride: std::mem::size_of::<[[f32; 4]; 4]>() as BufferAddress,
-                            step_mode: wgpu::VertexStepMode::Instance,
-                            attributes: &vertex_attr_array![
-                                2 => Float32x4,
-                                3 => Float32x4,
-                                4 => Float32x4,
-                                5 => Float32x4,
-                            ],
-                        },
-                    ],
-                },
-                primitive: PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::TriangleList,
-                    strip_index_format: None,
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: Some(wgpu::Face::Back),
-                    unclipped_depth: false,
-                    polygon_mode: wgpu::PolygonMode::Fill,
-                    conservative: false,
-                },
-                depth_stencil: Some(DepthStencilState {
-                    format: DEPTH_TEXTURE_FORMAT,
-                    depth_write_enabled: true,
-                    depth_compare: wgpu::CompareFunction::Less,
-                    stencil: StencilState::default(),
-                    bias: DepthBiasState::default(),
-                }),
-                multisample: MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
+        let create = |variant: &PipelineVariant| {
+            MultisamplePipeline::new(sample_counts, |count| {
+                create_pipeline(gpu, &layout, program, variant, count)
+            })
+        };
+
+        let pipeline = create(&PipelineVariant::new(
+            "Dynamic mesh render pipeline",
+            BlendState {
+                color: BlendComponent::REPLACE,
+                alpha: BlendComponent::REPLACE,
+            },
+            true,
+        ));
+
+        let transparent_pipeline = create(&PipelineVariant::new(
+            "Dynamic mesh transparent render pipeline",
+            BlendState::ALPHA_BLENDING,
+            false,
+        ));
+
+        let additive = BlendComponent {
+            src_factor: BlendFactor::One,
+            dst_factor: BlendFactor::One,
+            operation: BlendOperation::Add,
+        };
+
+        let overdraw_pipeline = create(&PipelineVariant {
+            depth_compare: CompareFunction::Always,
+            ..PipelineVariant::new(
+                "Dynamic mesh overdraw render pipeline",
+                BlendState {
+                    color: additive,
+                    alpha: additive,
                 },
-                fragment: Some(FragmentState {
-                    module: &program,
-                    entry_point: "fs_main",
-                    targets: &[Some(ColorTargetState {
-                        format: gpu.surface_config.format,
-                        blend: Some(wgpu::BlendState {
-                            color: BlendComponent::REPLACE,
-                            alpha: BlendComponent::REPLACE,
-                        }),
-                        write_mask: ColorWrites::ALL,
-                    })],
-                }),
-                multiview: None,
-            });
+                false,
+            )
+        });
+
+        let wireframe_pipeline =
+            if gpu.device.features().contains(Features::POLYGON_MODE_LINE) {
+                Some(create(&PipelineVariant {
+                    fragment_entry_point: "fs_wireframe",
+                    depth_compare: CompareFunction::LessEqual,
+                    // Pulls the lines in front of the triangles they belong to.
+                    depth_bias: DepthBiasState {
+                        constant: -2,
+                        slope_scale: -1.0,
+                        clamp: 0.0,
+                    },
+                    polygon_mode: PolygonMode::Line,
+                    ..PipelineVariant::new(
+                        "Dynamic mesh wireframe render pipeline",
+                        BlendState::ALPHA_BLENDING,
+                        false,
+                    )
+                }))
+            } else {
+                warn!(
+                    "The GPU can't draw lines, the wireframe view is disabled"
+                );
+                None
+            };
 
-        DynamicMeshPipeline { pipeline }
+        DynamicMeshPipeline {
+            pipeline,
+            transparent_pipeline,
+            overdraw_pipeline,
+            wireframe_pipeline,
+        }
     }
 }
+
+/// Creates a pipeline which draws the meshes using the render states of the
+/// variant.
+fn create_pipeline(
+    gpu: &Gpu,
+    layout: &PipelineLayout,
+    program: &ShaderModule,
+    variant: &PipelineVariant,
+    sample_count: u32,
+) -> RenderPipeline {
+    gpu
+        .device
+        .create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some(variant.label),
+            layout: Some(layout),
+            vertex: VertexState {
+                module: program,
+                entry_point: "vs_main",
+                buffers: &[
+                    // Defines the `Vertex` layout format.
+                    VertexBufferLayout {
+                        array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+                        step_mode: wgpu::VertexStepMode::Vertex,
+                        attributes: &vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x2, 3 => Float32x3],
+                    },
+                    // Defines the `InstanceData` layout format.
+                    VertexBufferLayout {
+                        array_stride: std::mem::size_of::<InstanceData>() as BufferAddress,
+                        step_mode: wgpu::VertexStepMode::Instance,
+                        attributes: &vertex_attr_array![
+                            4 => Float32x4,
+                            5 => Float32x4,
+                            6 => Float32x4,
+                            7 => Float32x4,
+                            8 => Uint32,
+                            9 => Float32,
+                        ],
+                    },
+                ],
+            },
+            primitive: PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                unclipped_depth: false,
+                polygon_mode: variant.polygon_mode,
+                conservative: false,
+            },
+            depth_stencil: Some(DepthStencilState {
+                format: DEPTH_TEXTURE_FORMAT,
+                depth_write_enabled: variant.depth_write,
+                depth_compare: variant.depth_compare,
+                stencil: StencilState::default(),
+                bias: variant.depth_bias,
+            }),
+            multisample: MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            fragment: Some(FragmentState {
+                module: program,
+                entry_point: variant.fragment_entry_point,
+                targets: &[Some(ColorTargetState {
+                    format: HDR_TEXTURE_FORMAT,
+                    blend: Some(variant.blend),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        })
+}
@@ -3,7 +3,7 @@ use shipyard::{Unique, UniqueView, UniqueViewMut};
 use wgpu::{
     BindGroup, BindGroupLayout, BlendComponent, ColorTargetState, ColorWrites,
     FragmentState, PipelineLayoutDescriptor, RenderPipeline,
-    RenderPipelineDescriptor, Sampler, TextureView, VertexState,
+    RenderPipelineDescriptor, Sampler, ShaderModule, TextureView, VertexState,
 };
 
 use crate::{
@@ -24,12 +24,7 @@ pub struct FrameCompositionPipeline {
 
 impl FrameCompositionPipeline {
     /// Creates and returns a new `FrameCompositionPipeline`.
-    pub(crate) fn new(gpu: &Gpu) -> Self {
-        let program = gpu.compile_program(
-            "frame_composition",
-            include_str!("../shaders/frame_composition.wgsl"),
-        );
-
+    pub(crate) fn new(gpu: &Gpu, program: &ShaderModule) -> Self {
         let texture_bind_group_layout = gpu.device.create_bind_group_layout(
             &wgpu::BindGroupLayoutDescriptor {
                 entries: &[
@@ -60,41 +55,8 @@ impl FrameCompositionPipeline {
             },
         );
 
-        let layout =
-            gpu.device
-                .create_pipeline_layout(&PipelineLayoutDescriptor {
-                    label: Some("Frame composition pipeline layout"),
-                    bind_group_layouts: &[&texture_bind_group_layout],
-                    push_constant_ranges: &[],
-                });
-
         let pipeline =
-            gpu.device
-                .create_render_pipeline(&RenderPipelineDescriptor {
-                    label: Some("Frame composition render pipeline"),
-                    layout: Some(&layout),
-                    vertex: VertexState {
-                        module: &program,
-                        entry_point: "vs_main",
-                        buffers: &[],
-                    },
-                    primitive: wgpu::PrimitiveState::default(),
-                    depth_stencil: None,
-                    multisample: wgpu::MultisampleState::default(),
-                    fragment: Some(FragmentState {
-                        module: &program,
-                        entry_point: "fs_main",
-                        targets: &[Some(ColorTargetState {
-                            format: gpu.surface_config.format,
-                            blend: Some(wgpu::BlendState {
-                                color: BlendComponent::REPLACE,
-                                alpha: BlendComponent::REPLACE,
-                            }),
-                            write_mask: ColorWrites::ALL,
-                        })],
-                    }),
-                    multiview: None,
-                });
+            create_pipeline(gpu, program, &texture_bind_group_layout);
 
         FrameCompositionPipeline {
             pipeline,
@@ -102,6 +64,61 @@ impl FrameCompositionPipeline {
             texture_bind_group: None,
         }
     }
+
+    /// Creates the pipeline again with a new program, the pipeline is kept
+    /// if the program is not compatible.
+    pub(crate) fn reload(
+        &mut self,
+        gpu: &Gpu,
+        program: &ShaderModule,
+    ) -> Result<(), String> {
+        self.pipeline = gpu.validate(|| {
+            create_pipeline(gpu, program, &self.texture_bind_group_layout)
+        })?;
+
+        Ok(())
+    }
+}
+
+fn create_pipeline(
+    gpu: &Gpu,
+    program: &ShaderModule,
+    texture_bind_group_layout: &BindGroupLayout,
+) -> RenderPipeline {
+    let layout = gpu
+        .device
+        .create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Frame composition pipeline layout"),
+            bind_group_layouts: &[texture_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+    gpu.device
+        .create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Frame composition render pipeline"),
+            layout: Some(&layout),
+            vertex: VertexState {
+                module: program,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(FragmentState {
+                module: program,
+                entry_point: "fs_main",
+                targets: &[Some(ColorTargetState {
+                    format: gpu.surface_config.format,
+                    blend: Some(wgpu::BlendState {
+                        color: BlendComponent::REPLACE,
+                        alpha: BlendComponent::REPLACE,
+                    }),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        })
 }
 
 pub(crate) fn create_frame_composition_texture_bind_group(
@@ -0,0 +1,188 @@
+use std::iter;
+
+use shipyard::{Unique, UniqueView, UniqueViewMut};
+
+use wgpu::{
+    BindGroupLayout, ColorTargetState, ColorWrites, FragmentState,
+    PipelineLayoutDescriptor, RenderPipeline, RenderPipelineDescriptor,
+    Sampler, ShaderModule, VertexState,
+};
+
+use crate::{
+    graphics::gpu::AbstractGpu,
+    scene::{scene::AntiAliasing, scene_state::SceneState},
+    wgpu_graphics::{
+        buffer::{WGPUBindGroup, WGPUTexture},
+        gpu::Gpu,
+    },
+};
+
+/// Filters the post processed image of the scenes which use FXAA into their
+/// target texture.
+#[derive(Unique)]
+pub(crate) struct FxaaPipeline {
+    /// Contains a reference to the pipeline.
+    pub(crate) pipeline: RenderPipeline,
+    pub(crate) texture_bind_group_layout: BindGroupLayout,
+    /// Samples the FXAA texture, FXAA reads between pixels so it must be
+    /// linear.
+    pub(crate) sampler: Sampler,
+}
+
+impl FxaaPipeline {
+    /// Creates and returns a new `FxaaPipeline`.
+    pub(crate) fn new(gpu: &Gpu, program: &ShaderModule) -> Self {
+        let texture_bind_group_layout = gpu.device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float {
+                                filterable: true,
+                            },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(
+                            wgpu::SamplerBindingType::Filtering,
+                        ),
+                        count: None,
+                    },
+                ],
+                label: Some("fxaa_texture_bind_group_layout"),
+            },
+        );
+
+        let pipeline =
+            create_pipeline(gpu, program, &texture_bind_group_layout);
+
+        let sampler = gpu.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("FXAA sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        FxaaPipeline {
+            pipeline,
+            texture_bind_group_layout,
+            sampler,
+        }
+    }
+
+    /// Creates the pipeline again with a new program, the pipeline is kept
+    /// if the program is not compatible.
+    pub(crate) fn reload(
+        &mut self,
+        gpu: &Gpu,
+        program: &ShaderModule,
+    ) -> Result<(), String> {
+        self.pipeline = gpu.validate(|| {
+            create_pipeline(gpu, program, &self.texture_bind_group_layout)
+        })?;
+
+        Ok(())
+    }
+}
+
+fn create_pipeline(
+    gpu: &Gpu,
+    program: &ShaderModule,
+    texture_bind_group_layout: &BindGroupLayout,
+) -> RenderPipeline {
+    let layout = gpu
+        .device
+        .create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("FXAA pipeline layout"),
+            bind_group_layouts: &[texture_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+    gpu.device
+        .create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("FXAA render pipeline"),
+            layout: Some(&layout),
+            vertex: VertexState {
+                module: program,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(FragmentState {
+                module: program,
+                entry_point: "fs_main",
+                targets: &[Some(ColorTargetState {
+                    format: gpu.surface_config.format,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        })
+}
+
+/// Creates the bind groups of the scenes which use FXAA, they are created
+/// again after the scene textures are reallocated.
+pub(crate) fn setup_fxaa_bind_groups_system(
+    gpu: UniqueView<AbstractGpu>,
+    mut s_state: UniqueViewMut<SceneState>,
+    pipeline: UniqueView<FxaaPipeline>,
+) {
+    let gpu = gpu
+        .downcast_ref::<Gpu>()
+        .expect("Incorrect GPU type expecting WGPU gpu");
+
+    let scenes = &mut *s_state;
+
+    for scene in
+        iter::once(&mut scenes.main).chain(scenes.sub_scenes.values_mut())
+    {
+        if scene.anti_aliasing != AntiAliasing::Fxaa
+            || scene.fxaa_bind_group.is_some()
+        {
+            continue;
+        }
+
+        let fxaa_texture = match &scene.fxaa_texture {
+            Some(t) => t.downcast_ref::<WGPUTexture>().expect(
+                "The provided scene FXAA texture is not a WGPU texture",
+            ),
+            None => continue,
+        };
+
+        let bind_group =
+            gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &pipeline.texture_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(
+                            &fxaa_texture.view,
+                        ),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(
+                            &pipeline.sampler,
+                        ),
+                    },
+                ],
+                label: Some(&format!("{} FXAA bind group", scene.label)),
+            });
+
+        scene.fxaa_bind_group = Some(Box::new(WGPUBindGroup(bind_group)));
+    }
+}
@@ -0,0 +1,574 @@
+use shipyard::{Unique, UniqueView, UniqueViewMut};
+use wgpu::{
+    BindGroupLayout, BindGroupLayoutEntry, Buffer, BufferUsages,
+    CommandEncoder, ComputePipeline, Sampler, ShaderModule, ShaderStages,
+    TextureView,
+};
+
+use crate::{
+    graphics::{
+        gpu::AbstractGpu,
+        scene::{
+            EnvironmentMaps, EnvironmentUniform, Scene,
+            PREFILTERED_MAP_MIP_LEVELS,
+        },
+        BindGroup, BufferCreator, Texture,
+    },
+    scene::scene_state::SceneState,
+    wgpu_graphics::{
+        buffer::{texture_view, WGPUBindGroup, WGPUTexture, WgpuUniformBuffer},
+        gpu::{Gpu, ENVIRONMENT_TEXTURE_FORMAT},
+    },
+};
+
+/// Size of the BRDF lookup texture.
+const BRDF_LUT_SIZE: u32 = 256;
+/// Number of samples taken for each texel of the prefiltered cubemap.
+const PREFILTER_SAMPLE_COUNT: u32 = 256;
+
+/// Parameters used to prefilter one mip level.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct PrefilterParams {
+    roughness: f32,
+    sample_count: u32,
+    _padding: [u32; 2],
+}
+
+/// Contains the compute pipelines which convert the sky cubemap into the
+/// environment maps, and the resources shared by all the scenes to sample
+/// them.
+#[derive(Unique)]
+pub(crate) struct IblPipeline {
+    prefilter_layout: BindGroupLayout,
+    irradiance_pipeline: ComputePipeline,
+    prefiltered_pipeline: ComputePipeline,
+
+    /// Layout used by the lit pipelines to sample the environment maps.
+    pub(crate) environment_layout: BindGroupLayout,
+    sampler: Sampler,
+    /// Contains the scale and bias applied to F0 for each angle and
+    /// roughness, it is the same for all the scenes.
+    brdf_lut: WGPUTexture,
+    /// Bound in the scenes without sky, it tells the shaders to use the
+    /// ambient color.
+    default_bind_group: wgpu::BindGroup,
+    /// Kept alive while the default bind group is used.
+    _default_resources: (Box<dyn Texture>, Buffer),
+}
+
+impl IblPipeline {
+    /// Creates the pipelines and computes the BRDF lookup texture.
+    pub(crate) fn new(gpu: &Gpu, module: &ShaderModule) -> IblPipeline {
+        let prefilter_layout = gpu.device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Prefilter layout"),
+                entries: &[
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Texture {
+                            // The sky is stored as `Rgba32Float`.
+                            sample_type: wgpu::TextureSampleType::Float {
+                                filterable: false,
+                            },
+                            view_dimension: wgpu::TextureViewDimension::Cube,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Sampler(
+                            wgpu::SamplerBindingType::NonFiltering,
+                        ),
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format: ENVIRONMENT_TEXTURE_FORMAT,
+                            view_dimension: wgpu::TextureViewDimension::D2Array,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            },
+        );
+
+        let (irradiance_pipeline, prefiltered_pipeline) =
+            create_prefilter_pipelines(gpu, module, &prefilter_layout);
+
+        let environment_layout = create_environment_layout(gpu);
+
+        let sampler = gpu.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Environment sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let brdf_lut = compute_brdf_lut(gpu, module);
+
+        // The default maps are never sampled, the uniform tells the shaders
+        // to use the ambient color instead.
+        let default_cubemap = gpu.allocate_environment_cubemap_texture(
+            "Default environment",
+            1,
+            1,
+        );
+
+        let default_uniform = gpu.allocate_buffer_init(
+            "Default environment uniform",
+            EnvironmentUniform::new(false),
+            BufferUsages::UNIFORM,
+        );
+
+        let default_bind_group = create_environment_bind_group(
+            gpu,
+            &environment_layout,
+            &default_uniform,
+            texture_view(default_cubemap.as_ref()),
+            texture_view(default_cubemap.as_ref()),
+            &brdf_lut.view,
+            &sampler,
+        );
+
+        IblPipeline {
+            prefilter_layout,
+            irradiance_pipeline,
+            prefiltered_pipeline,
+            environment_layout,
+            sampler,
+            brdf_lut,
+            default_bind_group,
+            _default_resources: (default_cubemap, default_uniform),
+        }
+    }
+
+    /// Creates the pipelines which filter the sky again with a new program,
+    /// they are used the next time a sky is loaded. The BRDF lookup texture
+    /// is not computed again.
+    pub(crate) fn reload(
+        &mut self,
+        gpu: &Gpu,
+        module: &ShaderModule,
+    ) -> Result<(), String> {
+        (self.irradiance_pipeline, self.prefiltered_pipeline) =
+            gpu.validate(|| {
+                create_prefilter_pipelines(gpu, module, &self.prefilter_layout)
+            })?;
+
+        Ok(())
+    }
+
+    /// Returns the environment bind group of the scene, the default one is
+    /// returned if the scene does not render the sky.
+    pub(crate) fn bind_group<'a>(
+        &'a self,
+        scene: &'a Scene,
+    ) -> &'a wgpu::BindGroup {
+        match &scene.environment_bind_group {
+            Some(bg) => {
+                &bg.downcast_ref::<WGPUBindGroup>()
+                    .expect("Incorrect bind group type")
+                    .0
+            }
+            None => &self.default_bind_group,
+        }
+    }
+
+    /// Records the passes which compute the irradiance and the prefiltered
+    /// cubemaps from the sky cubemap.
+    pub(crate) fn compute_environment_maps(
+        &self,
+        gpu: &Gpu,
+        encoder: &mut CommandEncoder,
+        sky: &WGPUTexture,
+        maps: &EnvironmentMaps,
+    ) {
+        let sky_sampler = sky
+            .sampler
+            .as_ref()
+            .expect("The sky cubemap does not contain sampler");
+
+        let irradiance = maps
+            .irradiance
+            .downcast_ref::<WGPUTexture>()
+            .expect("Incorrect texture type, expecting WGPUTexture");
+
+        let prefiltered = maps
+            .prefiltered
+            .downcast_ref::<WGPUTexture>()
+            .expect("Incorrect texture type, expecting WGPUTexture");
+
+        // Irradiance, the roughness is not used.
+        self.dispatch_prefilter(
+            gpu,
+            encoder,
+            &self.irradiance_pipeline,
+            (sky, sky_sampler),
+            irradiance,
+            0,
+            0.0,
+        );
+
+        // Each mip level is prefiltered for a higher roughness.
+        for mip_level in 0..PREFILTERED_MAP_MIP_LEVELS {
+            let roughness =
+                mip_level as f32 / (PREFILTERED_MAP_MIP_LEVELS - 1) as f32;
+
+            self.dispatch_prefilter(
+                gpu,
+                encoder,
+                &self.prefiltered_pipeline,
+                (sky, sky_sampler),
+                prefiltered,
+                mip_level,
+                roughness,
+            );
+        }
+
+        gpu.write_buffer(
+            &maps
+                .uniform
+                .downcast_ref::<WgpuUniformBuffer>()
+                .expect("Incorrect uniform buffer type")
+                .0,
+            0,
+            bytemuck::bytes_of(&EnvironmentUniform::new(true)),
+        );
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn dispatch_prefilter(
+        &self,
+        gpu: &Gpu,
+        encoder: &mut CommandEncoder,
+        pipeline: &ComputePipeline,
+        (sky, sky_sampler): (&WGPUTexture, &Sampler),
+        output: &WGPUTexture,
+        mip_level: u32,
+        roughness: f32,
+    ) {
+        let output_view =
+            output.texture.create_view(&wgpu::TextureViewDescriptor {
+                label: Some("Environment map mip view"),
+                dimension: Some(wgpu::TextureViewDimension::D2Array),
+                base_mip_level: mip_level,
+                mip_level_count: Some(1),
+                ..Default::default()
+            });
+
+        let params = gpu.allocate_buffer_init(
+            "Prefilter params",
+            PrefilterParams {
+                roughness,
+                sample_count: PREFILTER_SAMPLE_COUNT,
+                _padding: [0; 2],
+            },
+            BufferUsages::UNIFORM,
+        );
+
+        let bind_group =
+            gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Prefilter bind group"),
+                layout: &self.prefilter_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&sky.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(sky_sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(
+                            &output_view,
+                        ),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: params.as_entire_binding(),
+                    },
+                ],
+            });
+
+        let size = (output.texture.width() >> mip_level).max(1);
+        let num_workgroups = (size + 7) / 8;
+
+        let mut pass =
+            encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Environment map prefilter"),
+                timestamp_writes: None,
+            });
+
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups(num_workgroups, num_workgroups, 6);
+    }
+}
+
+/// Creates the layout used to sample the environment maps while rendering.
+fn create_environment_layout(gpu: &Gpu) -> BindGroupLayout {
+    let texture_entry = |binding, view_dimension| BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension,
+            multisampled: false,
+        },
+        count: None,
+    };
+
+    gpu.device
+        .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Environment bind group layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // Irradiance.
+                texture_entry(1, wgpu::TextureViewDimension::Cube),
+                // Prefiltered.
+                texture_entry(2, wgpu::TextureViewDimension::Cube),
+                // BRDF lookup texture.
+                texture_entry(3, wgpu::TextureViewDimension::D2),
+                BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(
+                        wgpu::SamplerBindingType::Filtering,
+                    ),
+                    count: None,
+                },
+            ],
+        })
+}
+
+fn create_environment_bind_group(
+    gpu: &Gpu,
+    layout: &BindGroupLayout,
+    uniform: &Buffer,
+    irradiance: &TextureView,
+    prefiltered: &TextureView,
+    brdf_lut: &TextureView,
+    sampler: &Sampler,
+) -> wgpu::BindGroup {
+    gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("environment_bind_group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(irradiance),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(prefiltered),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::TextureView(brdf_lut),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ],
+    })
+}
+
+/// Creates the pipelines which compute the irradiance and the prefiltered
+/// maps from the sky.
+fn create_prefilter_pipelines(
+    gpu: &Gpu,
+    module: &ShaderModule,
+    prefilter_layout: &BindGroupLayout,
+) -> (ComputePipeline, ComputePipeline) {
+    let layout =
+        gpu.device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Prefilter pipeline layout"),
+                bind_group_layouts: &[prefilter_layout],
+                push_constant_ranges: &[],
+            });
+
+    let irradiance_pipeline =
+        gpu.device
+            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Irradiance pipeline"),
+                layout: Some(&layout),
+                module,
+                entry_point: "compute_irradiance",
+            });
+
+    let prefiltered_pipeline =
+        gpu.device
+            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Prefiltered pipeline"),
+                layout: Some(&layout),
+                module,
+                entry_point: "compute_prefiltered",
+            });
+
+    (irradiance_pipeline, prefiltered_pipeline)
+}
+
+/// Computes the BRDF lookup texture, it does not depend on the scene so it is
+/// only computed once.
+fn compute_brdf_lut(gpu: &Gpu, module: &wgpu::ShaderModule) -> WGPUTexture {
+    let texture = gpu.device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("BRDF lookup texture"),
+        size: wgpu::Extent3d {
+            width: BRDF_LUT_SIZE,
+            height: BRDF_LUT_SIZE,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: ENVIRONMENT_TEXTURE_FORMAT,
+        usage: wgpu::TextureUsages::STORAGE_BINDING
+            | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    let layout =
+        gpu.device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("BRDF lookup layout"),
+                entries: &[BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: ENVIRONMENT_TEXTURE_FORMAT,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                }],
+            });
+
+    let pipeline_layout =
+        gpu.device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("BRDF lookup pipeline layout"),
+                bind_group_layouts: &[&layout],
+                push_constant_ranges: &[],
+            });
+
+    let pipeline =
+        gpu.device
+            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("BRDF lookup pipeline"),
+                layout: Some(&pipeline_layout),
+                module,
+                entry_point: "compute_brdf_lut",
+            });
+
+    let bind_group = gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("BRDF lookup bind group"),
+        layout: &layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 4,
+            resource: wgpu::BindingResource::TextureView(&view),
+        }],
+    });
+
+    let mut encoder = gpu.device.create_command_encoder(&Default::default());
+    {
+        let mut pass =
+            encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("BRDF lookup texture"),
+                timestamp_writes: None,
+            });
+
+        let num_workgroups = (BRDF_LUT_SIZE + 7) / 8;
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups(num_workgroups, num_workgroups, 1);
+    }
+    gpu.queue.submit([encoder.finish()]);
+
+    WGPUTexture {
+        texture,
+        view,
+        sampler: None,
+    }
+}
+
+/// Creates the environment bind group of each scene which renders the sky.
+pub(crate) fn setup_environment_bind_groups_system(
+    gpu: UniqueView<AbstractGpu>,
+    ibl_pipeline: UniqueView<IblPipeline>,
+    mut s_state: UniqueViewMut<SceneState>,
+) {
+    let gpu = gpu
+        .downcast_ref::<Gpu>()
+        .expect("Incorrect GPU type expecting WGPU gpu");
+
+    s_state.main.environment_bind_group =
+        generate_environment_bind_group(gpu, &s_state.main, &ibl_pipeline);
+
+    for (_, scene) in s_state.sub_scenes.iter_mut() {
+        scene.environment_bind_group =
+            generate_environment_bind_group(gpu, scene, &ibl_pipeline);
+    }
+}
+
+fn generate_environment_bind_group(
+    gpu: &Gpu,
+    scene: &Scene,
+    ibl_pipeline: &IblPipeline,
+) -> Option<Box<dyn BindGroup>> {
+    let maps = scene.environment_maps.as_ref()?;
+
+    let uniform = maps
+        .uniform
+        .downcast_ref::<WgpuUniformBuffer>()
+        .expect("Incorrect uniform buffer type");
+
+    Some(Box::new(WGPUBindGroup(create_environment_bind_group(
+        gpu,
+        &ibl_pipeline.environment_layout,
+        &uniform.0,
+        texture_view(maps.irradiance.as_ref()),
+        texture_view(maps.prefiltered.as_ref()),
+        &ibl_pipeline.brdf_lut.view,
+        &ibl_pipeline.sampler,
+    ))))
+}
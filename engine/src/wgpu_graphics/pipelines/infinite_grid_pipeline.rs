@@ -2,28 +2,27 @@ use shipyard::Unique;
 
 use wgpu::{
     BindGroupLayout, ColorTargetState, FragmentState, PipelineLayoutDescriptor,
-    RenderPipeline, RenderPipelineDescriptor, VertexState,
+    RenderPipelineDescriptor, ShaderModule, VertexState,
 };
 
-use crate::wgpu_graphics::gpu::Gpu;
+use crate::wgpu_graphics::gpu::{Gpu, HDR_TEXTURE_FORMAT};
+
+use super::MultisamplePipeline;
 
 #[derive(Unique)]
 pub struct InfiniteGridPipeline {
     /// Contains a reference to the pipeline.
-    pub(crate) pipeline: RenderPipeline,
+    pub(crate) pipeline: MultisamplePipeline,
 }
 
 impl InfiniteGridPipeline {
     /// Creates and returns a new `InfiniteGridPipeline`.
     pub(crate) fn new(
         gpu: &Gpu,
+        program: &ShaderModule,
         camera_bind_group_layout: &BindGroupLayout,
+        sample_counts: &[u32],
     ) -> Self {
-        let program = gpu.compile_program(
-            "infinite_grid_composition",
-            include_str!("../shaders/infinite_grid.wgsl"),
-        );
-
         let layout =
             gpu.device
                 .create_pipeline_layout(&PipelineLayoutDescriptor {
@@ -32,13 +31,13 @@ impl InfiniteGridPipeline {
                     push_constant_ranges: &[],
                 });
 
-        let pipeline =
+        let pipeline = MultisamplePipeline::new(sample_counts, |count| {
             gpu.device
                 .create_render_pipeline(&RenderPipelineDescriptor {
                     label: Some("Infinite grid render pipeline"),
                     layout: Some(&layout),
                     vertex: VertexState {
-                        module: &program,
+                        module: program,
                         entry_point: "vs_main",
                         buffers: &[],
                     },
@@ -50,12 +49,15 @@ impl InfiniteGridPipeline {
                         stencil: wgpu::StencilState::default(),
                         bias: wgpu::DepthBiasState::default(),
                     }),
-                    multisample: wgpu::MultisampleState::default(),
+                    multisample: wgpu::MultisampleState {
+                        count,
+                        ..Default::default()
+                    },
                     fragment: Some(FragmentState {
-                        module: &program,
+                        module: program,
                         entry_point: "fs_main",
                         targets: &[Some(ColorTargetState {
-                            format: gpu.surface_config.format,
+                            format: HDR_TEXTURE_FORMAT,
                             blend: Some(
                                 wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING,
                             ),
@@ -63,7 +65,8 @@ impl InfiniteGridPipeline {
                         })],
                     }),
                     multiview: None,
-                });
+                })
+        });
 
         InfiniteGridPipeline { pipeline }
     }
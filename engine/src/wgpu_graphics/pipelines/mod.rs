@@ -1,24 +1,83 @@
+use std::num::NonZeroU64;
+
+use ahash::AHashMap;
 use shipyard::{Unique, UniqueView, UniqueViewMut};
 use wgpu::{
     BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry,
-    ShaderStages,
+    RenderPipeline, ShaderStages,
 };
 
-use crate::{graphics::gpu::AbstractGpu, scene::scene_state::SceneState};
+use crate::{
+    graphics::{
+        camera::CAMERA_UNIFORM_STRIDE, gpu::AbstractGpu, scene::Scene,
+        BindGroup, BufferCreator,
+    },
+    scene::scene_state::SceneState,
+};
 
 use super::{
-    buffer::{WGPUBindGroup, WgpuUniformBuffer},
+    buffer::{
+        WGPUBindGroup, WGPUTexture, WgpuStorageBuffer, WgpuUniformBuffer,
+    },
     gpu::Gpu,
 };
 
+pub(crate) mod custom_pass_pipeline;
+pub(crate) mod debug_draw_pipeline;
 pub(crate) mod dynamic_mesh_pipeline;
 pub(crate) mod frame_composition_pipeline;
+pub(crate) mod fxaa_pipeline;
+pub(crate) mod ibl_pipeline;
 pub(crate) mod infinite_grid_pipeline;
+pub(crate) mod outline_pipeline;
+pub(crate) mod post_process_pipeline;
+pub(crate) mod shadow_pipeline;
 pub mod sky_pipeline;
 
+/// Size of the view projection matrix of each shadow map, the rest of the
+/// `ShadowCasterUniform` is padding.
+const SHADOW_CASTER_MATRIX_SIZE: u64 = 64;
+
+/// Contains a variant of a render pipeline for each sample count, the
+/// pipeline must match the sample count of the `Scene` targets.
+pub(crate) struct MultisamplePipeline(AHashMap<u32, RenderPipeline>);
+
+impl MultisamplePipeline {
+    /// Creates a variant for each of the provided sample counts.
+    pub(crate) fn new(
+        sample_counts: &[u32],
+        create: impl Fn(u32) -> RenderPipeline,
+    ) -> Self {
+        Self(
+            sample_counts
+                .iter()
+                .map(|count| (*count, create(*count)))
+                .collect(),
+        )
+    }
+
+    /// Returns the variant which renders with the provided sample count.
+    pub(crate) fn get(&self, sample_count: u32) -> &RenderPipeline {
+        self.0
+            .get(&sample_count)
+            .expect("There is no pipeline for the sample count")
+    }
+}
+
+/// Returns the sample counts which can be used by the scenes, the
+/// `Scene::anti_aliasing` is always one of them.
+pub(crate) fn scene_sample_counts(gpu: &Gpu) -> Vec<u32> {
+    [1, 2, 4, 8]
+        .into_iter()
+        .filter(|count| gpu.supported_sample_count(*count) == *count)
+        .collect()
+}
+
 #[derive(Unique)]
 pub(crate) struct GlobalBindGroupLayouts {
     pub(crate) camera: BindGroupLayout,
+    pub(crate) lights: BindGroupLayout,
+    pub(crate) shadow_caster: BindGroupLayout,
 }
 
 /// Creates and returns a commond camera bind group layout.
@@ -31,7 +90,7 @@ pub(crate) fn create_camera_bind_group_layout(gpu: &Gpu) -> BindGroupLayout {
                 visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
                 ty: wgpu::BindingType::Buffer {
                     ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
+                    has_dynamic_offset: true,
                     min_binding_size: None,
                 },
                 count: None,
@@ -39,6 +98,83 @@ pub(crate) fn create_camera_bind_group_layout(gpu: &Gpu) -> BindGroupLayout {
         })
 }
 
+/// Returns the binding of the camera uniform of a view, the view is selected
+/// with the dynamic offset.
+fn camera_binding(
+    camera_buffer: &WgpuUniformBuffer,
+) -> wgpu::BindingResource<'_> {
+    wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+        buffer: &camera_buffer.0,
+        offset: 0,
+        size: NonZeroU64::new(CAMERA_UNIFORM_STRIDE),
+    })
+}
+
+/// Creates and returns the bind group layout of the scene lights and their
+/// shadow maps.
+pub(crate) fn create_lights_bind_group_layout(gpu: &Gpu) -> BindGroupLayout {
+    let storage_entry = |binding| BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: true },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    };
+
+    gpu.device
+        .create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Lights bind group"),
+            entries: &[
+                storage_entry(0),
+                storage_entry(1),
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(
+                        wgpu::SamplerBindingType::Comparison,
+                    ),
+                    count: None,
+                },
+            ],
+        })
+}
+
+/// Creates and returns the bind group layout used to select the shadow map
+/// being rendered, the offset points to its `ShadowCasterUniform`.
+pub(crate) fn create_shadow_caster_bind_group_layout(
+    gpu: &Gpu,
+) -> BindGroupLayout {
+    gpu.device
+        .create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Shadow caster bind group"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: true,
+                    min_binding_size: wgpu::BufferSize::new(
+                        SHADOW_CASTER_MATRIX_SIZE,
+                    ),
+                },
+                count: None,
+            }],
+        })
+}
+
 /// Setups the uniforms of all the scenes.
 pub(crate) fn setup_scenes_uniforms_system(
     gpu: UniqueView<AbstractGpu>,
@@ -61,12 +197,24 @@ pub(crate) fn setup_scenes_uniforms_system(
             layout: &global_bind_group_layouts.camera,
             entries: &[wgpu::BindGroupEntry {
                 binding: 0,
-                resource: camera_buffer.0.as_entire_binding(),
+                resource: camera_binding(camera_buffer),
             }],
             label: Some("camera_bind_group"),
         }),
     )));
 
+    s_state.main.lights_bind_group = Some(create_lights_bind_group(
+        gpu,
+        &global_bind_group_layouts.lights,
+        &s_state.main,
+    ));
+    s_state.main.shadow_caster_bind_group =
+        Some(create_shadow_caster_bind_group(
+            gpu,
+            &global_bind_group_layouts.shadow_caster,
+            &s_state.main,
+        ));
+
     // Sub scenes.
     for (_id, scene) in &mut s_state.sub_scenes {
         let camera_buffer = scene
@@ -79,10 +227,101 @@ pub(crate) fn setup_scenes_uniforms_system(
                 layout: &global_bind_group_layouts.camera,
                 entries: &[wgpu::BindGroupEntry {
                     binding: 0,
-                    resource: camera_buffer.0.as_entire_binding(),
+                    resource: camera_binding(camera_buffer),
                 }],
                 label: Some("camera_bind_group"),
             }),
         )));
+
+        scene.lights_bind_group = Some(create_lights_bind_group(
+            gpu,
+            &global_bind_group_layouts.lights,
+            scene,
+        ));
+        scene.shadow_caster_bind_group = Some(create_shadow_caster_bind_group(
+            gpu,
+            &global_bind_group_layouts.shadow_caster,
+            scene,
+        ));
     }
 }
+
+fn create_lights_bind_group(
+    gpu: &Gpu,
+    layout: &BindGroupLayout,
+    scene: &Scene,
+) -> Box<dyn BindGroup> {
+    let lights_buffer = scene
+        .lights_buffer
+        .downcast_ref::<WgpuStorageBuffer>()
+        .expect("Incorrect storage buffer type");
+
+    let shadow_casters_buffer = scene
+        .shadow_casters_buffer
+        .downcast_ref::<WgpuStorageBuffer>()
+        .expect("Incorrect storage buffer type");
+
+    let shadow_maps = scene
+        .shadow_maps
+        .downcast_ref::<WGPUTexture>()
+        .expect("Incorrect texture type, expecting WGPUTexture");
+
+    Box::new(WGPUBindGroup(gpu.device.create_bind_group(
+        &wgpu::BindGroupDescriptor {
+            layout,
+            entries:
+                &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: lights_buffer.0.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: shadow_casters_buffer.0.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(
+                            &shadow_maps.view,
+                        ),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource:
+                            wgpu::BindingResource::Sampler(
+                                shadow_maps.sampler.as_ref().expect(
+                                    "The shadow maps must have a sampler",
+                                ),
+                            ),
+                    },
+                ],
+            label: Some("lights_bind_group"),
+        },
+    )))
+}
+
+fn create_shadow_caster_bind_group(
+    gpu: &Gpu,
+    layout: &BindGroupLayout,
+    scene: &Scene,
+) -> Box<dyn BindGroup> {
+    let shadow_casters_buffer = scene
+        .shadow_casters_buffer
+        .downcast_ref::<WgpuStorageBuffer>()
+        .expect("Incorrect storage buffer type");
+
+    Box::new(WGPUBindGroup(gpu.device.create_bind_group(
+        &wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &shadow_casters_buffer.0,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(SHADOW_CASTER_MATRIX_SIZE),
+                }),
+            }],
+            label: Some("shadow_caster_bind_group"),
+        },
+    )))
+}
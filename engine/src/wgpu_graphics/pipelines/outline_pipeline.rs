@@ -0,0 +1,107 @@
+use shipyard::Unique;
+
+use wgpu::{
+    vertex_attr_array, BindGroupLayout, BufferAddress, ColorTargetState,
+    FragmentState, PipelineLayoutDescriptor, RenderPipelineDescriptor,
+    ShaderModule, VertexBufferLayout, VertexState,
+};
+
+use crate::{
+    graphics::{scene::OutlineInstance, vertex::Vertex},
+    wgpu_graphics::gpu::{Gpu, DEPTH_TEXTURE_FORMAT, HDR_TEXTURE_FORMAT},
+};
+
+use super::MultisamplePipeline;
+
+#[derive(Unique)]
+pub struct OutlinePipeline {
+    /// Contains a reference to the pipeline.
+    pub(crate) pipeline: MultisamplePipeline,
+}
+
+impl OutlinePipeline {
+    /// Creates and returns a new `OutlinePipeline`.
+    pub(crate) fn new(
+        gpu: &Gpu,
+        program: &ShaderModule,
+        camera_bind_group_layout: &BindGroupLayout,
+        sample_counts: &[u32],
+    ) -> Self {
+        let layout =
+            gpu.device
+                .create_pipeline_layout(&PipelineLayoutDescriptor {
+                    label: Some("Outline pipeline layout"),
+                    bind_group_layouts: &[camera_bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+
+        let instance_stride =
+            std::mem::size_of::<OutlineInstance>() as BufferAddress;
+
+        let pipeline = MultisamplePipeline::new(sample_counts, |count| {
+            gpu.device
+                .create_render_pipeline(&RenderPipelineDescriptor {
+                    label: Some("Outline render pipeline"),
+                    layout: Some(&layout),
+                    vertex: VertexState {
+                        module: program,
+                        entry_point: "vs_main",
+                        buffers: &[
+                            VertexBufferLayout {
+                                array_stride: std::mem::size_of::<Vertex>()
+                                    as BufferAddress,
+                                step_mode: wgpu::VertexStepMode::Vertex,
+                                attributes: &vertex_attr_array![
+                                    0 => Float32x3,
+                                    1 => Float32x3,
+                                    2 => Float32x2,
+                                    3 => Float32x3
+                                ],
+                            },
+                            VertexBufferLayout {
+                                array_stride: instance_stride,
+                                step_mode: wgpu::VertexStepMode::Instance,
+                                attributes: &vertex_attr_array![
+                                    4 => Float32x4,
+                                    5 => Float32x4,
+                                    6 => Float32x4,
+                                    7 => Float32x4,
+                                    8 => Float32x4,
+                                    9 => Float32x2
+                                ],
+                            },
+                        ],
+                    },
+                    // Only the back faces are drawn, so the mesh hides the
+                    // inside of the outline.
+                    primitive: wgpu::PrimitiveState {
+                        cull_mode: Some(wgpu::Face::Front),
+                        ..Default::default()
+                    },
+                    depth_stencil: Some(wgpu::DepthStencilState {
+                        format: DEPTH_TEXTURE_FORMAT,
+                        depth_write_enabled: false,
+                        depth_compare: wgpu::CompareFunction::LessEqual,
+                        stencil: wgpu::StencilState::default(),
+                        bias: wgpu::DepthBiasState::default(),
+                    }),
+                    multisample: wgpu::MultisampleState {
+                        count,
+                        ..Default::default()
+                    },
+                    fragment: Some(FragmentState {
+                        module: program,
+                        entry_point: "fs_main",
+                        targets: &[Some(ColorTargetState {
+                            format: HDR_TEXTURE_FORMAT,
+                            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                    }),
+                    multiview: None,
+                })
+        });
+
+        OutlinePipeline { pipeline }
+    }
+}
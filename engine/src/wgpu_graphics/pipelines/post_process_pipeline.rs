@@ -0,0 +1,451 @@
+use std::{iter, num::NonZeroU64};
+
+use ahash::AHashMap;
+use shipyard::{Unique, UniqueView, UniqueViewMut};
+use wgpu::{
+    BindGroupLayout, BindGroupLayoutEntry, BlendComponent, BlendState,
+    ColorTargetState, ColorWrites, FragmentState, PipelineLayout,
+    PipelineLayoutDescriptor, RenderPipeline, RenderPipelineDescriptor,
+    Sampler, ShaderModule, ShaderStages, TextureFormat, VertexState,
+};
+
+use crate::{
+    graphics::{
+        gpu::AbstractGpu,
+        post_processing::{BLOOM_MIP_COUNT, POST_PROCESS_PARAMS_STRIDE},
+        scene::PostProcessBindGroups,
+        BindGroup, BufferCreator, Texture,
+    },
+    scene::{
+        assets::asset_server::AssetServer, post_processing::PostProcessEffect,
+        scene_state::SceneState,
+    },
+    wgpu_graphics::{
+        buffer::{texture_view, WGPUBindGroup, WgpuUniformBuffer},
+        gpu::{Gpu, HDR_TEXTURE_FORMAT},
+    },
+};
+
+/// Contains the pipelines of the post processing effects, each effect reads
+/// the previous image (group 0) and its parameters (group 1).
+#[derive(Unique)]
+pub(crate) struct PostProcessPipeline {
+    /// Layout used to read a texture, it is also used by the effects which
+    /// read a second texture (group 2).
+    texture_layout: BindGroupLayout,
+    params_layout: BindGroupLayout,
+    sampler: Sampler,
+
+    pub(crate) effects: EffectPipelines,
+
+    /// Contains the bind group of each lookup table with the generation of
+    /// its texture.
+    luts: AHashMap<String, (u32, wgpu::BindGroup)>,
+}
+
+/// Contains a pipeline for each entry point of the post processing program.
+pub(crate) struct EffectPipelines {
+    pub(crate) exposure: RenderPipeline,
+    pub(crate) gamma: RenderPipeline,
+    pub(crate) reinhard: RenderPipeline,
+    pub(crate) aces: RenderPipeline,
+    pub(crate) agx: RenderPipeline,
+    pub(crate) color_grading: RenderPipeline,
+    pub(crate) bloom_prefilter: RenderPipeline,
+    pub(crate) bloom_downsample: RenderPipeline,
+    /// Adds the upsampled mip to the previous one.
+    pub(crate) bloom_upsample: RenderPipeline,
+    pub(crate) bloom_combine: RenderPipeline,
+    /// Writes the HDR image into the target texture of the scene.
+    pub(crate) output: RenderPipeline,
+}
+
+impl PostProcessPipeline {
+    /// Creates and returns a new `PostProcessPipeline`.
+    pub(crate) fn new(gpu: &Gpu, program: &ShaderModule) -> Self {
+        let texture_layout = gpu.device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float {
+                                filterable: true,
+                            },
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(
+                            wgpu::SamplerBindingType::Filtering,
+                        ),
+                        count: None,
+                    },
+                ],
+                label: Some("post_process_texture_bind_group_layout"),
+            },
+        );
+
+        let params_layout = gpu.device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                entries: &[BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: NonZeroU64::new(16),
+                    },
+                    count: None,
+                }],
+                label: Some("post_process_params_bind_group_layout"),
+            },
+        );
+
+        let effects =
+            EffectPipelines::new(gpu, program, &texture_layout, &params_layout);
+
+        let sampler = gpu.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Post process sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        PostProcessPipeline {
+            texture_layout,
+            params_layout,
+            sampler,
+            effects,
+            luts: AHashMap::new(),
+        }
+    }
+
+    /// Creates the pipelines of the effects again with a new program, they
+    /// are kept if the program is not compatible.
+    pub(crate) fn reload(
+        &mut self,
+        gpu: &Gpu,
+        program: &ShaderModule,
+    ) -> Result<(), String> {
+        self.effects = gpu.validate(|| {
+            EffectPipelines::new(
+                gpu,
+                program,
+                &self.texture_layout,
+                &self.params_layout,
+            )
+        })?;
+
+        Ok(())
+    }
+
+    /// Returns the bind group of the lookup table, `None` while its texture
+    /// is loading.
+    pub(crate) fn lut_bind_group(&self, id: &str) -> Option<&wgpu::BindGroup> {
+        self.luts.get(id).map(|(_, bind_group)| bind_group)
+    }
+
+    /// Creates a bind group to read the provided texture.
+    fn create_texture_bind_group(
+        &self,
+        gpu: &Gpu,
+        label: &str,
+        texture: &dyn Texture,
+    ) -> wgpu::BindGroup {
+        gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.texture_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(texture_view(
+                        texture,
+                    )),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+            label: Some(label),
+        })
+    }
+}
+
+impl EffectPipelines {
+    fn new(
+        gpu: &Gpu,
+        program: &ShaderModule,
+        texture_layout: &BindGroupLayout,
+        params_layout: &BindGroupLayout,
+    ) -> Self {
+        let layout =
+            gpu.device
+                .create_pipeline_layout(&PipelineLayoutDescriptor {
+                    label: Some("Post process pipeline layout"),
+                    bind_group_layouts: &[texture_layout, params_layout],
+                    push_constant_ranges: &[],
+                });
+
+        // Used by the effects which read a second texture.
+        let extra_texture_layout =
+            gpu.device
+                .create_pipeline_layout(&PipelineLayoutDescriptor {
+                    label: Some("Post process extra texture pipeline layout"),
+                    bind_group_layouts: &[
+                        texture_layout,
+                        params_layout,
+                        texture_layout,
+                    ],
+                    push_constant_ranges: &[],
+                });
+
+        let additive = BlendState {
+            color: BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: BlendComponent::REPLACE,
+        };
+
+        let effect = |entry_point: &str| {
+            create_pipeline(
+                gpu,
+                &layout,
+                program,
+                entry_point,
+                HDR_TEXTURE_FORMAT,
+                None,
+            )
+        };
+
+        EffectPipelines {
+            exposure: effect("fs_exposure"),
+            gamma: effect("fs_gamma"),
+            reinhard: effect("fs_reinhard"),
+            aces: effect("fs_aces"),
+            agx: effect("fs_agx"),
+            color_grading: create_pipeline(
+                gpu,
+                &extra_texture_layout,
+                program,
+                "fs_color_grading",
+                HDR_TEXTURE_FORMAT,
+                None,
+            ),
+            bloom_prefilter: effect("fs_bloom_prefilter"),
+            bloom_downsample: effect("fs_bloom_downsample"),
+            bloom_upsample: create_pipeline(
+                gpu,
+                &layout,
+                program,
+                "fs_bloom_upsample",
+                HDR_TEXTURE_FORMAT,
+                Some(additive),
+            ),
+            bloom_combine: create_pipeline(
+                gpu,
+                &extra_texture_layout,
+                program,
+                "fs_bloom_combine",
+                HDR_TEXTURE_FORMAT,
+                None,
+            ),
+            output: create_pipeline(
+                gpu,
+                &layout,
+                program,
+                "fs_output",
+                gpu.surface_config.format,
+                None,
+            ),
+        }
+    }
+}
+
+fn create_pipeline(
+    gpu: &Gpu,
+    layout: &PipelineLayout,
+    program: &ShaderModule,
+    entry_point: &str,
+    format: TextureFormat,
+    blend: Option<BlendState>,
+) -> RenderPipeline {
+    gpu.device
+        .create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some(&format!("Post process {} pipeline", entry_point)),
+            layout: Some(layout),
+            vertex: VertexState {
+                module: program,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(FragmentState {
+                module: program,
+                entry_point,
+                targets: &[Some(ColorTargetState {
+                    format,
+                    blend,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        })
+}
+
+/// Allocates the bloom chain of the scenes which need it and creates the
+/// bind groups used to read the post processing textures.
+pub(crate) fn setup_post_process_bind_groups_system(
+    gpu: UniqueView<AbstractGpu>,
+    mut s_state: UniqueViewMut<SceneState>,
+    pipeline: UniqueView<PostProcessPipeline>,
+) {
+    let gpu = gpu
+        .downcast_ref::<Gpu>()
+        .expect("Incorrect GPU type expecting WGPU gpu");
+
+    let scenes = &mut *s_state;
+
+    for scene in
+        iter::once(&mut scenes.main).chain(scenes.sub_scenes.values_mut())
+    {
+        let uses_bloom = scene
+            .post_processing
+            .iter()
+            .any(|e| matches!(e, PostProcessEffect::Bloom { .. }));
+
+        if uses_bloom && scene.bloom_textures.is_empty() {
+            let size = scene.target_texture.size();
+
+            scene.bloom_textures = (1..=BLOOM_MIP_COUNT)
+                .map(|mip| {
+                    gpu.allocate_hdr_texture(
+                        &format!("{} bloom texture {}", scene.label, mip),
+                        (size.width >> mip).max(1),
+                        (size.height >> mip).max(1),
+                        1,
+                    )
+                })
+                .collect();
+
+            // The bloom chain must be readable too.
+            scene.post_process_bind_groups = None;
+        }
+
+        if scene.post_process_bind_groups.is_some() {
+            continue;
+        }
+
+        let texture_bind_group =
+            |name: &str, texture: &dyn Texture| -> Box<dyn BindGroup> {
+                Box::new(WGPUBindGroup(pipeline.create_texture_bind_group(
+                    gpu,
+                    &format!("{} {} bind group", scene.label, name),
+                    texture,
+                )))
+            };
+
+        let params_buffer = scene
+            .post_process_buffer
+            .downcast_ref::<WgpuUniformBuffer>()
+            .expect("Incorrect uniform buffer type");
+
+        let params = gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &pipeline.params_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &params_buffer.0,
+                    offset: 0,
+                    size: NonZeroU64::new(POST_PROCESS_PARAMS_STRIDE),
+                }),
+            }],
+            label: Some(&format!("{} post process params", scene.label)),
+        });
+
+        scene.post_process_bind_groups = Some(PostProcessBindGroups {
+            color: (scene.sample_count() == 1).then(|| {
+                texture_bind_group("color", scene.color_texture.as_ref())
+            }),
+            post_process: [0, 1].map(|i| {
+                texture_bind_group(
+                    &format!("post process {}", i),
+                    scene.post_process_textures[i].as_ref(),
+                )
+            }),
+            bloom: scene
+                .bloom_textures
+                .iter()
+                .enumerate()
+                .map(|(i, t)| {
+                    texture_bind_group(&format!("bloom {}", i), t.as_ref())
+                })
+                .collect(),
+            params: Box::new(WGPUBindGroup(params)),
+        });
+    }
+}
+
+/// Creates the bind groups of the lookup tables used by the scenes, they are
+/// recreated when the texture changes.
+pub(crate) fn sync_lut_bind_groups_system(
+    gpu: UniqueView<AbstractGpu>,
+    asset_server: UniqueView<AssetServer>,
+    s_state: UniqueView<SceneState>,
+    mut pipeline: UniqueViewMut<PostProcessPipeline>,
+) {
+    let gpu = gpu
+        .downcast_ref::<Gpu>()
+        .expect("Incorrect GPU type expecting WGPU gpu");
+
+    let luts = iter::once(&s_state.main)
+        .chain(s_state.sub_scenes.values())
+        .flat_map(|s| s.post_processing.iter().filter_map(|e| e.texture()))
+        .collect::<Vec<_>>();
+
+    pipeline
+        .luts
+        .retain(|id, _| luts.iter().any(|lut| lut.as_str() == id.as_str()));
+
+    for lut in luts {
+        // The texture is not loaded yet.
+        let generation = asset_server.generation(lut);
+        if generation == 0 {
+            continue;
+        }
+
+        let is_updated = pipeline
+            .luts
+            .get(lut.as_str())
+            .map(|(g, _)| *g == generation)
+            .unwrap_or(false);
+
+        if is_updated {
+            continue;
+        }
+
+        let texture = asset_server.load_texture(lut);
+        let bind_group = pipeline.create_texture_bind_group(
+            gpu,
+            &format!("LUT({}) bind group", lut.as_str()),
+            texture.as_ref(),
+        );
+
+        pipeline
+            .luts
+            .insert(lut.as_str().to_owned(), (generation, bind_group));
+    }
+}
@@ -0,0 +1,103 @@
+use shipyard::Unique;
+
+use wgpu::{
+    vertex_attr_array, BindGroupLayout, BufferAddress, DepthBiasState,
+    DepthStencilState, MultisampleState, PipelineLayoutDescriptor,
+    PrimitiveState, RenderPipeline, RenderPipelineDescriptor, ShaderModule,
+    StencilState, VertexBufferLayout, VertexState,
+};
+
+use crate::{
+    graphics::{scene::InstanceData, vertex::Vertex},
+    wgpu_graphics::gpu::{Gpu, DEPTH_TEXTURE_FORMAT},
+};
+
+/// Renders the depth of the meshes which cast shadows into the shadow maps.
+#[derive(Unique)]
+pub struct ShadowPipeline {
+    /// Contains a reference to the pipeline.
+    pub(crate) pipeline: RenderPipeline,
+}
+
+impl ShadowPipeline {
+    /// Creates and returns a new `ShadowPipeline`.
+    pub(crate) fn new(
+        gpu: &Gpu,
+        program: &ShaderModule,
+        shadow_caster_bind_group_layout: &BindGroupLayout,
+    ) -> ShadowPipeline {
+        let layout =
+            gpu.device
+                .create_pipeline_layout(&PipelineLayoutDescriptor {
+                    label: Some("Shadow pipeline layout"),
+                    bind_group_layouts: &[shadow_caster_bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+
+        let pipeline =
+            gpu.device
+                .create_render_pipeline(&RenderPipelineDescriptor {
+                    label: Some("Shadow render pipeline"),
+                    layout: Some(&layout),
+                    vertex: VertexState {
+                        module: program,
+                        entry_point: "vs_main",
+                        buffers: &[
+                            // Only the position of the `Vertex` is needed.
+                            VertexBufferLayout {
+                                array_stride: std::mem::size_of::<Vertex>()
+                                    as BufferAddress,
+                                step_mode: wgpu::VertexStepMode::Vertex,
+                                attributes: &vertex_attr_array![0 => Float32x3],
+                            },
+                            // Only the transform of the `InstanceData` is needed.
+                            VertexBufferLayout {
+                                array_stride: std::mem::size_of::<InstanceData>(
+                                )
+                                    as BufferAddress,
+                                step_mode: wgpu::VertexStepMode::Instance,
+                                attributes: &vertex_attr_array![
+                                    4 => Float32x4,
+                                    5 => Float32x4,
+                                    6 => Float32x4,
+                                    7 => Float32x4,
+                                ],
+                            },
+                        ],
+                    },
+                    primitive: PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleList,
+                        strip_index_format: None,
+                        front_face: wgpu::FrontFace::Ccw,
+                        // Planes and open meshes must cast shadows from both
+                        // sides.
+                        cull_mode: None,
+                        unclipped_depth: false,
+                        polygon_mode: wgpu::PolygonMode::Fill,
+                        conservative: false,
+                    },
+                    depth_stencil: Some(DepthStencilState {
+                        format: DEPTH_TEXTURE_FORMAT,
+                        depth_write_enabled: true,
+                        depth_compare: wgpu::CompareFunction::LessEqual,
+                        stencil: StencilState::default(),
+                        // Reduces the shadow acne of the surfaces almost parallel
+                        // to the light.
+                        bias: DepthBiasState {
+                            constant: 2,
+                            slope_scale: 2.0,
+                            clamp: 0.0,
+                        },
+                    }),
+                    multisample: MultisampleState {
+                        count: 1,
+                        mask: !0,
+                        alpha_to_coverage_enabled: false,
+                    },
+                    fragment: None,
+                    multiview: None,
+                });
+
+        ShadowPipeline { pipeline }
+    }
+}
@@ -3,7 +3,7 @@ use shipyard::{Unique, UniqueView, UniqueViewMut, World};
 use wgpu::{
     BindGroupLayout, BlendComponent, ColorTargetState, ColorWrites,
     ComputePipeline, DepthBiasState, DepthStencilState, FragmentState,
-    PipelineLayoutDescriptor, RenderPipeline, RenderPipelineDescriptor,
+    PipelineLayoutDescriptor, RenderPipelineDescriptor, ShaderModule,
     StencilState, TextureFormat, VertexState,
 };
 
@@ -12,10 +12,12 @@ use crate::{
     scene::{assets::asset_server::AssetServer, scene_state::SceneState},
     wgpu_graphics::{
         buffer::{WGPUBindGroup, WGPUTexture},
-        gpu::{Gpu, DEPTH_TEXTURE_FORMAT},
+        gpu::{Gpu, DEPTH_TEXTURE_FORMAT, HDR_TEXTURE_FORMAT},
     },
 };
 
+use super::{ibl_pipeline::IblPipeline, MultisamplePipeline};
+
 // TODO(Angel): Move this out wgpu graphics.
 #[derive(Unique)]
 pub struct SkyUpdater {
@@ -34,7 +36,7 @@ impl SkyUpdater {
 
 #[derive(Unique)]
 pub(crate) struct SkyPipeline {
-    pub(crate) pipeline: RenderPipeline,
+    pub(crate) pipeline: MultisamplePipeline,
 
     pub(crate) texture_format: TextureFormat,
     pub(crate) equirect_layout: BindGroupLayout,
@@ -45,11 +47,11 @@ pub(crate) struct SkyPipeline {
 impl SkyPipeline {
     pub(crate) fn new(
         gpu: &Gpu,
+        program: &ShaderModule,
+        equirectangular_program: &ShaderModule,
         camera_bind_group_layout: &BindGroupLayout,
+        sample_counts: &[u32],
     ) -> SkyPipeline {
-        let program =
-            gpu.compile_program("sky", include_str!("../shaders/sky.wgsl"));
-
         let environment_layout = gpu.device.create_bind_group_layout(
             &wgpu::BindGroupLayoutDescriptor {
                 label: Some("environment_layout"),
@@ -78,55 +80,14 @@ impl SkyPipeline {
             },
         );
 
-        let layout =
-            gpu.device
-                .create_pipeline_layout(&PipelineLayoutDescriptor {
-                    label: Some("Sky pipeline layout"),
-                    bind_group_layouts: &[
-                        camera_bind_group_layout,
-                        &environment_layout,
-                    ],
-                    push_constant_ranges: &[],
-                });
-
-        let pipeline =
-            gpu.device
-                .create_render_pipeline(&RenderPipelineDescriptor {
-                    label: Some("Sky render pipeline"),
-                    layout: Some(&layout),
-                    vertex: VertexState {
-                        module: &program,
-                        entry_point: "vs_main",
-                        buffers: &[],
-                    },
-                    primitive: wgpu::PrimitiveState::default(),
-                    depth_stencil: Some(DepthStencilState {
-                        format: DEPTH_TEXTURE_FORMAT,
-                        depth_write_enabled: true,
-                        depth_compare: wgpu::CompareFunction::LessEqual,
-                        stencil: StencilState::default(),
-                        bias: DepthBiasState::default(),
-                    }),
-                    multisample: wgpu::MultisampleState::default(),
-                    fragment: Some(FragmentState {
-                        module: &program,
-                        entry_point: "fs_main",
-                        targets: &[Some(ColorTargetState {
-                            format: gpu.surface_config.format,
-                            blend: Some(wgpu::BlendState {
-                                color: BlendComponent::REPLACE,
-                                alpha: BlendComponent::REPLACE,
-                            }),
-                            write_mask: ColorWrites::ALL,
-                        })],
-                    }),
-                    multiview: None,
-                });
-
-        let module = gpu.compile_program(
-            "Equirectangular sky converter",
-            include_str!("../shaders/equirectangular.wgsl"),
+        let pipeline = create_sky_pipeline(
+            gpu,
+            program,
+            camera_bind_group_layout,
+            &environment_layout,
+            sample_counts,
         );
+
         let texture_format = wgpu::TextureFormat::Rgba32Float;
         let equirect_layout = gpu.device.create_bind_group_layout(
             &wgpu::BindGroupLayoutDescriptor {
@@ -158,21 +119,10 @@ impl SkyPipeline {
             },
         );
 
-        let pipeline_layout = gpu.device.create_pipeline_layout(
-            &wgpu::PipelineLayoutDescriptor {
-                label: None,
-                bind_group_layouts: &[&equirect_layout],
-                push_constant_ranges: &[],
-            },
-        );
-
-        let equirect_to_cubemap = gpu.device.create_compute_pipeline(
-            &wgpu::ComputePipelineDescriptor {
-                label: Some("equirect_to_cubemap"),
-                layout: Some(&pipeline_layout),
-                module: &module,
-                entry_point: "compute_equirect_to_cubemap",
-            },
+        let equirect_to_cubemap = create_equirectangular_pipeline(
+            gpu,
+            equirectangular_program,
+            &equirect_layout,
         );
 
         SkyPipeline {
@@ -183,6 +133,118 @@ impl SkyPipeline {
             environment_layout,
         }
     }
+
+    /// Creates the sky pipeline again with a new program, the pipeline is
+    /// kept if the program is not compatible.
+    pub(crate) fn reload(
+        &mut self,
+        gpu: &Gpu,
+        program: &ShaderModule,
+        camera_bind_group_layout: &BindGroupLayout,
+        sample_counts: &[u32],
+    ) -> Result<(), String> {
+        self.pipeline = gpu.validate(|| {
+            create_sky_pipeline(
+                gpu,
+                program,
+                camera_bind_group_layout,
+                &self.environment_layout,
+                sample_counts,
+            )
+        })?;
+
+        Ok(())
+    }
+
+    /// Creates the conversion pipeline again with a new program, it is used
+    /// the next time a sky is loaded.
+    pub(crate) fn reload_equirectangular(
+        &mut self,
+        gpu: &Gpu,
+        program: &ShaderModule,
+    ) -> Result<(), String> {
+        self.equirectangular_conversion_pipeline = gpu.validate(|| {
+            create_equirectangular_pipeline(gpu, program, &self.equirect_layout)
+        })?;
+
+        Ok(())
+    }
+}
+
+fn create_sky_pipeline(
+    gpu: &Gpu,
+    program: &ShaderModule,
+    camera_bind_group_layout: &BindGroupLayout,
+    environment_layout: &BindGroupLayout,
+    sample_counts: &[u32],
+) -> MultisamplePipeline {
+    let layout = gpu
+        .device
+        .create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Sky pipeline layout"),
+            bind_group_layouts: &[camera_bind_group_layout, environment_layout],
+            push_constant_ranges: &[],
+        });
+
+    MultisamplePipeline::new(sample_counts, |count| {
+        gpu.device
+            .create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some("Sky render pipeline"),
+                layout: Some(&layout),
+                vertex: VertexState {
+                    module: program,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: Some(DepthStencilState {
+                    format: DEPTH_TEXTURE_FORMAT,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::LessEqual,
+                    stencil: StencilState::default(),
+                    bias: DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count,
+                    ..Default::default()
+                },
+                fragment: Some(FragmentState {
+                    module: program,
+                    entry_point: "fs_main",
+                    targets: &[Some(ColorTargetState {
+                        format: HDR_TEXTURE_FORMAT,
+                        blend: Some(wgpu::BlendState {
+                            color: BlendComponent::REPLACE,
+                            alpha: BlendComponent::REPLACE,
+                        }),
+                        write_mask: ColorWrites::ALL,
+                    })],
+                }),
+                multiview: None,
+            })
+    })
+}
+
+fn create_equirectangular_pipeline(
+    gpu: &Gpu,
+    program: &ShaderModule,
+    equirect_layout: &BindGroupLayout,
+) -> ComputePipeline {
+    let pipeline_layout =
+        gpu.device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[equirect_layout],
+                push_constant_ranges: &[],
+            });
+
+    gpu.device
+        .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("equirect_to_cubemap"),
+            layout: Some(&pipeline_layout),
+            module: program,
+            entry_point: "compute_equirect_to_cubemap",
+        })
 }
 
 /// Takes from the `AssetStorage` the indicated texture, generates the cubemap
@@ -191,6 +253,7 @@ pub(crate) fn sync_sky_pipeline_uniforms(world: &World) {
     let gpu = world.borrow::<UniqueView<AbstractGpu>>().unwrap();
     let asset_server = world.borrow::<UniqueView<AssetServer>>().unwrap();
     let sky_pipeline = world.borrow::<UniqueView<SkyPipeline>>().unwrap();
+    let ibl_pipeline = world.borrow::<UniqueView<IblPipeline>>().unwrap();
 
     // If there is a `SkyUpdater` component it means that the sky of some
     // scene must be updated.
@@ -280,6 +343,16 @@ pub(crate) fn sync_sky_pipeline_uniforms(world: &World) {
 
     drop(pass);
 
+    // Light the scene with the new sky.
+    if let Some(maps) = &scene.environment_maps {
+        ibl_pipeline.compute_environment_maps(
+            gpu,
+            &mut encoder,
+            scene_sky_texture,
+            maps,
+        );
+    }
+
     gpu.queue.submit([encoder.finish()]);
 }
 
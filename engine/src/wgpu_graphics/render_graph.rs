@@ -0,0 +1,490 @@
+use std::{
+    cmp::Reverse, collections::BinaryHeap, error::Error, fmt, iter,
+    time::Instant,
+};
+
+use ahash::{AHashMap, AHashSet};
+use log::error;
+use shipyard::{Unique, UniqueView, UniqueViewMut, World};
+use wgpu::{CommandEncoderDescriptor, Maintain};
+
+use crate::{
+    graphics::{
+        gpu::AbstractGpu,
+        render_pass::BuiltinPass,
+        stats::{DrawStats, PassStats, RenderStats},
+    },
+    scene::assets::asset_server::AssetServer,
+};
+
+use super::{gpu::Gpu, FrameEncoder};
+
+pub(crate) const SHADOWS_NODE: &str = "Shadows";
+pub(crate) const SKY_NODE: &str = "Sky";
+pub(crate) const DYNAMIC_MESHES_NODE: &str = "Dynamic meshes";
+pub(crate) const OUTLINES_NODE: &str = "Outlines";
+pub(crate) const DEBUG_GRID_NODE: &str = "Debug grid";
+pub(crate) const DEBUG_DRAW_NODE: &str = "Debug draw";
+pub(crate) const POST_PROCESSING_NODE: &str = "Post processing";
+pub(crate) const FXAA_NODE: &str = "FXAA";
+pub(crate) const DEBUG_GUI_NODE: &str = "Debug GUI";
+pub(crate) const FRAME_COMPOSITION_NODE: &str = "Frame composition";
+
+/// Returns the label of the node which draws a built-in pass.
+pub(crate) fn builtin_node(pass: BuiltinPass) -> &'static str {
+    match pass {
+        BuiltinPass::Shadows => SHADOWS_NODE,
+        BuiltinPass::Sky => SKY_NODE,
+        BuiltinPass::DynamicMeshes => DYNAMIC_MESHES_NODE,
+        BuiltinPass::Outlines => OUTLINES_NODE,
+        BuiltinPass::DebugGrid => DEBUG_GRID_NODE,
+        BuiltinPass::DebugDraw => DEBUG_DRAW_NODE,
+        BuiltinPass::PostProcessing => POST_PROCESSING_NODE,
+        BuiltinPass::Fxaa => FXAA_NODE,
+        BuiltinPass::DebugGui => DEBUG_GUI_NODE,
+        BuiltinPass::FrameComposition => FRAME_COMPOSITION_NODE,
+    }
+}
+
+/// Resource read or written by the nodes of the `RenderGraph`, the scene
+/// resources stand for the textures of every scene.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum GraphResource {
+    ShadowMaps,
+    /// The HDR color of the scenes, with MSAA its resolved texture too.
+    SceneColor,
+    SceneDepth,
+    FxaaTexture,
+    SceneTarget,
+    Screen,
+    /// A texture added to the `CustomRenderPasses`.
+    Custom(String),
+}
+
+/// A pass of the `RenderGraph`, `run` records its commands into the
+/// `FrameEncoder`.
+pub(crate) struct RenderNode {
+    pub(crate) label: String,
+    pub(crate) reads: Vec<GraphResource>,
+    pub(crate) writes: Vec<GraphResource>,
+    /// Contains the node it was placed after, if any.
+    placed_after: Option<String>,
+    run: Box<dyn Fn(&World) + Send + Sync>,
+}
+
+impl RenderNode {
+    /// Creates and returns a new `RenderNode`.
+    pub(crate) fn new(
+        label: &str,
+        reads: Vec<GraphResource>,
+        writes: Vec<GraphResource>,
+        run: impl Fn(&World) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            label: label.to_owned(),
+            reads,
+            writes,
+            placed_after: None,
+            run: Box::new(run),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum RenderGraphError {
+    /// There is already a node with the label.
+    DuplicatedLabel(String),
+    /// The node used to place another one doesn't exist.
+    MissingAnchor { node: String, anchor: String },
+}
+
+impl Error for RenderGraphError {}
+
+impl fmt::Display for RenderGraphError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RenderGraphError::DuplicatedLabel(l) => {
+                write!(f, "There is already a pass labeled {}", l)
+            }
+            RenderGraphError::MissingAnchor { node, anchor } => write!(
+                f,
+                "The pass {} is placed next to {}, which is not rendered",
+                node, anchor
+            ),
+        }
+    }
+}
+
+/// Contains the passes drawn each frame.
+///
+/// The writes of a resource happen in the order the nodes are listed, and
+/// the nodes which only read it run after all of them. The nodes reading a
+/// resource nobody writes are skipped.
+#[derive(Unique, Default)]
+pub(crate) struct RenderGraph {
+    nodes: Vec<RenderNode>,
+    /// Contains the indices of the nodes in the order they run, it is `None`
+    /// after the nodes change.
+    order: Option<Vec<usize>>,
+}
+
+impl RenderGraph {
+    /// Adds a node after the listed ones.
+    pub(crate) fn add(
+        &mut self,
+        node: RenderNode,
+    ) -> Result<(), RenderGraphError> {
+        self.insert(self.nodes.len(), node)
+    }
+
+    /// Adds a node right before the one labeled `anchor`.
+    pub(crate) fn insert_before(
+        &mut self,
+        anchor: &str,
+        node: RenderNode,
+    ) -> Result<(), RenderGraphError> {
+        let index = self.anchor_index(anchor, &node)?;
+        self.insert(index, node)
+    }
+
+    /// Adds a node right after the one labeled `anchor`, and after the ones
+    /// already added after it.
+    pub(crate) fn insert_after(
+        &mut self,
+        anchor: &str,
+        mut node: RenderNode,
+    ) -> Result<(), RenderGraphError> {
+        let index = self.anchor_index(anchor, &node)?;
+        let index = index
+            + 1
+            + self.nodes[index + 1..]
+                .iter()
+                .take_while(|n| n.placed_after.as_deref() == Some(anchor))
+                .count();
+
+        node.placed_after = Some(anchor.to_owned());
+        self.insert(index, node)
+    }
+
+    /// Removes the node with the provided label, returns if it existed.
+    pub(crate) fn remove(&mut self, label: &str) -> bool {
+        let len = self.nodes.len();
+        self.nodes.retain(|n| n.label != label);
+
+        if len == self.nodes.len() {
+            return false;
+        }
+
+        self.order = None;
+        true
+    }
+
+    fn insert(
+        &mut self,
+        index: usize,
+        node: RenderNode,
+    ) -> Result<(), RenderGraphError> {
+        if self.nodes.iter().any(|n| n.label == node.label) {
+            return Err(RenderGraphError::DuplicatedLabel(node.label));
+        }
+
+        self.nodes.insert(index, node);
+        self.order = None;
+
+        Ok(())
+    }
+
+    fn anchor_index(
+        &self,
+        anchor: &str,
+        node: &RenderNode,
+    ) -> Result<usize, RenderGraphError> {
+        self.nodes
+            .iter()
+            .position(|n| n.label == anchor)
+            .ok_or_else(|| RenderGraphError::MissingAnchor {
+                node: node.label.clone(),
+                anchor: anchor.to_owned(),
+            })
+    }
+
+    /// Returns the indices of the nodes in the order they run, it is derived
+    /// again after the nodes change.
+    pub(crate) fn order(&mut self) -> &[usize] {
+        if self.order.is_none() {
+            self.order = Some(self.derive_order());
+        }
+
+        self.order.as_deref().unwrap_or_default()
+    }
+
+    /// Returns the first and the last position in the order of the nodes
+    /// using each resource.
+    pub(crate) fn lifetimes(
+        &mut self,
+    ) -> AHashMap<GraphResource, (usize, usize)> {
+        let order = self.order().to_vec();
+        let mut lifetimes = AHashMap::<GraphResource, (usize, usize)>::new();
+
+        for (position, index) in order.into_iter().enumerate() {
+            let node = &self.nodes[index];
+
+            for resource in node.reads.iter().chain(&node.writes) {
+                lifetimes
+                    .entry(resource.clone())
+                    .and_modify(|(_, last)| *last = position)
+                    .or_insert((position, position));
+            }
+        }
+
+        lifetimes
+    }
+
+    fn derive_order(&self) -> Vec<usize> {
+        let enabled = self.enabled_nodes();
+
+        // Contains the nodes which must run before each node.
+        let mut dependencies = vec![AHashSet::<usize>::new(); self.nodes.len()];
+        let mut writers = AHashMap::<&GraphResource, Vec<usize>>::new();
+
+        for (i, node) in self.nodes.iter().enumerate() {
+            if !enabled[i] {
+                continue;
+            }
+
+            for resource in &node.writes {
+                let resource_writers = writers.entry(resource).or_default();
+
+                if let Some(previous) = resource_writers.last() {
+                    dependencies[i].insert(*previous);
+                }
+
+                resource_writers.push(i);
+            }
+        }
+
+        for (i, node) in self.nodes.iter().enumerate() {
+            if !enabled[i] {
+                continue;
+            }
+
+            for resource in &node.reads {
+                if node.writes.contains(resource) {
+                    continue;
+                }
+
+                if let Some(last) = writers.get(resource).and_then(|w| w.last())
+                {
+                    dependencies[i].insert(*last);
+                }
+            }
+        }
+
+        // The ready nodes run in the order they are listed.
+        let mut pending =
+            dependencies.iter().map(|d| d.len()).collect::<Vec<_>>();
+        let mut ready = (0..self.nodes.len())
+            .filter(|i| enabled[*i] && pending[*i] == 0)
+            .map(Reverse)
+            .collect::<BinaryHeap<_>>();
+
+        let mut order = Vec::with_capacity(self.nodes.len());
+
+        while let Some(Reverse(i)) = ready.pop() {
+            order.push(i);
+
+            for (j, d) in dependencies.iter().enumerate() {
+                if d.contains(&i) {
+                    pending[j] -= 1;
+
+                    if pending[j] == 0 {
+                        ready.push(Reverse(j));
+                    }
+                }
+            }
+        }
+
+        let cycle = (0..self.nodes.len())
+            .filter(|i| enabled[*i] && !order.contains(i))
+            .collect::<Vec<_>>();
+
+        if !cycle.is_empty() {
+            error!(
+                "The passes {} depend on each other, they run in the order they were added",
+                self.labels(&cycle)
+            );
+            order.extend(cycle);
+        }
+
+        order
+    }
+
+    /// Returns which nodes can run, the nodes reading a resource which no
+    /// other node writes are disabled along with the ones depending on them.
+    fn enabled_nodes(&self) -> Vec<bool> {
+        let mut enabled = vec![true; self.nodes.len()];
+
+        loop {
+            let mut changed = false;
+
+            for (i, node) in self.nodes.iter().enumerate() {
+                if !enabled[i] {
+                    continue;
+                }
+
+                let missing = node
+                    .reads
+                    .iter()
+                    .filter(|&resource| {
+                        !self.nodes.iter().enumerate().any(|(j, other)| {
+                            j != i
+                                && enabled[j]
+                                && other.writes.contains(resource)
+                        })
+                    })
+                    .map(|resource| format!("{:?}", resource))
+                    .collect::<Vec<_>>();
+
+                if !missing.is_empty() {
+                    error!(
+                        "The pass {} is skipped, no pass writes its inputs {}",
+                        node.label,
+                        missing.join(", ")
+                    );
+
+                    enabled[i] = false;
+                    changed = true;
+                }
+            }
+
+            if !changed {
+                return enabled;
+            }
+        }
+    }
+
+    fn labels(&self, nodes: &[usize]) -> String {
+        nodes
+            .iter()
+            .map(|i| self.nodes[*i].label.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Records the nodes of the `RenderGraph` into a single encoder and submits
+/// it, the cost of each node is stored in the `RenderStats`.
+pub(crate) fn run_render_graph(world: &World) {
+    let order = world
+        .borrow::<UniqueViewMut<RenderGraph>>()
+        .expect("Unable to acquire the render graph")
+        .order()
+        .to_vec();
+
+    {
+        let gpu = world
+            .borrow::<UniqueView<AbstractGpu>>()
+            .expect("Unable to acquire AbtractGpu");
+
+        let gpu = gpu
+            .downcast_ref::<Gpu>()
+            .expect("Unable to acquire Wgpu GPU");
+
+        let mut frame = world
+            .borrow::<UniqueViewMut<FrameEncoder>>()
+            .expect("Unable to acquire the frame encoder");
+
+        frame.encoder = Some(gpu.device.create_command_encoder(
+            &CommandEncoderDescriptor {
+                label: Some("Frame encoder"),
+            },
+        ));
+
+        if let Some(timer) = frame.timer.as_mut() {
+            // Runs the callback of the timestamps read back, if they are
+            // ready.
+            gpu.device.poll(Maintain::Poll);
+            timer.begin_frame();
+        }
+    }
+
+    let mut passes = Vec::with_capacity(order.len());
+
+    {
+        let graph = world
+            .borrow::<UniqueView<RenderGraph>>()
+            .expect("Unable to acquire the render graph");
+
+        for index in order {
+            let node = &graph.nodes[index];
+
+            {
+                let mut frame = world
+                    .borrow::<UniqueViewMut<FrameEncoder>>()
+                    .expect("Unable to acquire the frame encoder");
+                frame.node.clone_from(&node.label);
+                frame.draws = DrawStats::default();
+            }
+
+            let start = Instant::now();
+            (node.run)(world);
+            let cpu_time = start.elapsed();
+
+            passes.push(PassStats {
+                label: node.label.clone(),
+                draws: world
+                    .borrow::<UniqueView<FrameEncoder>>()
+                    .expect("Unable to acquire the frame encoder")
+                    .draws,
+                cpu_time,
+                gpu_time: None,
+            });
+        }
+    }
+
+    let mut frame = world
+        .borrow::<UniqueViewMut<FrameEncoder>>()
+        .expect("Unable to acquire the frame encoder");
+
+    let mut encoder = frame
+        .encoder
+        .take()
+        .expect("The frame encoder was taken by a pass");
+
+    let gpu = world
+        .borrow::<UniqueView<AbstractGpu>>()
+        .expect("Unable to acquire AbtractGpu");
+
+    let gpu = gpu
+        .downcast_ref::<Gpu>()
+        .expect("Unable to acquire Wgpu GPU");
+
+    if let Some(timer) = frame.timer.as_mut() {
+        timer.resolve(&mut encoder);
+        gpu.queue.submit(iter::once(encoder.finish()));
+        timer.end_frame();
+
+        for pass in &mut passes {
+            pass.gpu_time = timer.timings().get(&pass.label).copied();
+        }
+    } else {
+        gpu.queue.submit(iter::once(encoder.finish()));
+    }
+
+    let mut stats = world
+        .borrow::<UniqueViewMut<RenderStats>>()
+        .expect("Unable to acquire the render stats");
+
+    stats.draws = passes.iter().fold(DrawStats::default(), |mut draws, p| {
+        draws += p.draws;
+        draws
+    });
+    stats.buffer_upload_bytes = gpu.take_uploaded_bytes();
+    stats.texture_memory = world
+        .borrow::<UniqueView<AssetServer>>()
+        .expect("Unable to acquire the asset server")
+        .memory_report()
+        .textures
+        .bytes;
+    stats.passes = passes;
+}
@@ -1,14 +1,15 @@
 use shipyard::{UniqueView, UniqueViewMut};
-use wgpu::CommandBuffer;
 
 use crate::{
-    graphics::{components::DepthTexture, gpu::AbstractGpu, BufferCreator},
+    graphics::{
+        components::DepthTexture, gpu::AbstractGpu, scene::SceneTextures,
+        BufferCreator,
+    },
     host::window::Window,
     scene::scene_state::SceneState,
     wgpu_graphics::{
         components::{ScreenFrame, ScreenTexture},
         gpu::Gpu,
-        CommandQueue, OrderCommandBuffer,
     },
 };
 
@@ -44,22 +45,20 @@ pub(crate) fn reconfigure_main_textures_if_needed_system(
             "Global depth texture",
             window.size.width,
             window.size.height,
+            1,
         );
 
         // Sync all the scenes which does not have a default resolution.
         // TODO(Angel): Add support for sub scenes.
         if s_state.main.should_sync_resolution_to_window {
-            s_state.main.target_texture = gpu.allocate_target_texture(
+            let textures = SceneTextures::allocate(
+                &*gpu,
                 &s_state.main.label,
-                window.size.width,
-                window.size.height,
+                window.size,
+                s_state.main.anti_aliasing,
             );
 
-            s_state.main.depth_texture = gpu.allocate_depth_texture(
-                &s_state.main.label,
-                window.size.width,
-                window.size.height,
-            );
+            s_state.main.set_textures(textures);
 
             let main_texture = s_state
                 .main
@@ -120,28 +119,3 @@ pub fn present_screen_texture(mut s_frame: UniqueViewMut<ScreenFrame>) {
         panic!("Unable to acquire texture frame");
     }
 }
-
-pub(crate) fn submit_commands_in_order(
-    gpu: UniqueView<AbstractGpu>,
-    c_queue: UniqueView<CommandQueue>,
-) {
-    let gpu = gpu.downcast_ref::<Gpu>().expect(
-        "Incorrect Gpu abstractor provided, it was expecting a Wgpu Gpu",
-    );
-
-    let mut commands =
-        Vec::<OrderCommandBuffer>::with_capacity(c_queue.0.len());
-
-    while let Some(c) = c_queue.0.pop() {
-        commands.push(c);
-    }
-
-    commands.sort_by_key(|c| c.order);
-
-    let mut wgpu_commands = Vec::<CommandBuffer>::new();
-    while let Some(c) = commands.pop() {
-        wgpu_commands.push(c.command);
-    }
-
-    gpu.queue.submit(wgpu_commands);
-}
@@ -0,0 +1,413 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use ahash::AHashMap;
+use log::{error, info, warn};
+use shipyard::{Unique, UniqueView, UniqueViewMut, World};
+use wgpu::ShaderModule;
+
+use crate::graphics::gpu::AbstractGpu;
+
+use super::{
+    gpu::Gpu,
+    materials::MaterialBindGroups,
+    pipelines::{
+        debug_draw_pipeline::DebugDrawPipeline,
+        dynamic_mesh_pipeline::DynamicMeshPipeline,
+        frame_composition_pipeline::FrameCompositionPipeline,
+        fxaa_pipeline::FxaaPipeline, ibl_pipeline::IblPipeline,
+        infinite_grid_pipeline::InfiniteGridPipeline,
+        outline_pipeline::OutlinePipeline,
+        post_process_pipeline::PostProcessPipeline, scene_sample_counts,
+        shadow_pipeline::ShadowPipeline, sky_pipeline::SkyPipeline,
+        GlobalBindGroupLayouts,
+    },
+};
+
+/// Time between each check of the shader files.
+const HOT_RELOAD_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Programs used by the built-in pipelines.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum BuiltinShader {
+    DebugLines,
+    DynamicMesh,
+    Equirectangular,
+    FrameComposition,
+    Fxaa,
+    Ibl,
+    InfiniteGrid,
+    Outline,
+    PostProcess,
+    Shadow,
+    Sky,
+}
+
+impl BuiltinShader {
+    const ALL: [BuiltinShader; 11] = [
+        BuiltinShader::DebugLines,
+        BuiltinShader::DynamicMesh,
+        BuiltinShader::Equirectangular,
+        BuiltinShader::FrameComposition,
+        BuiltinShader::Fxaa,
+        BuiltinShader::Ibl,
+        BuiltinShader::InfiniteGrid,
+        BuiltinShader::Outline,
+        BuiltinShader::PostProcess,
+        BuiltinShader::Shadow,
+        BuiltinShader::Sky,
+    ];
+
+    /// Returns the name of the file in the shaders directory.
+    pub(crate) fn file_name(self) -> &'static str {
+        match self {
+            BuiltinShader::DebugLines => "debug_lines.wgsl",
+            BuiltinShader::DynamicMesh => "dynamic_mesh.wgsl",
+            BuiltinShader::Equirectangular => "equirectangular.wgsl",
+            BuiltinShader::FrameComposition => "frame_composition.wgsl",
+            BuiltinShader::Fxaa => "fxaa.wgsl",
+            BuiltinShader::Ibl => "ibl.wgsl",
+            BuiltinShader::InfiniteGrid => "infinite_grid.wgsl",
+            BuiltinShader::Outline => "outline.wgsl",
+            BuiltinShader::PostProcess => "post_process.wgsl",
+            BuiltinShader::Shadow => "shadow.wgsl",
+            BuiltinShader::Sky => "sky.wgsl",
+        }
+    }
+
+    /// Returns the source embedded into the binary.
+    fn embedded_source(self) -> &'static str {
+        match self {
+            BuiltinShader::DebugLines => {
+                include_str!("shaders/debug_lines.wgsl")
+            }
+            BuiltinShader::DynamicMesh => {
+                include_str!("shaders/dynamic_mesh.wgsl")
+            }
+            BuiltinShader::Equirectangular => {
+                include_str!("shaders/equirectangular.wgsl")
+            }
+            BuiltinShader::FrameComposition => {
+                include_str!("shaders/frame_composition.wgsl")
+            }
+            BuiltinShader::Fxaa => include_str!("shaders/fxaa.wgsl"),
+            BuiltinShader::Ibl => include_str!("shaders/ibl.wgsl"),
+            BuiltinShader::InfiniteGrid => {
+                include_str!("shaders/infinite_grid.wgsl")
+            }
+            BuiltinShader::Outline => include_str!("shaders/outline.wgsl"),
+            BuiltinShader::PostProcess => {
+                include_str!("shaders/post_process.wgsl")
+            }
+            BuiltinShader::Shadow => include_str!("shaders/shadow.wgsl"),
+            BuiltinShader::Sky => include_str!("shaders/sky.wgsl"),
+        }
+    }
+}
+
+/// Source of a shader and the modification time of its file.
+struct ShaderSource {
+    code: String,
+    modified: Option<SystemTime>,
+}
+
+/// Contains the sources of the built-in shaders.
+///
+/// The development builds read them from the shaders directory of the engine
+/// and rebuild the pipelines when a file changes, the release builds use the
+/// sources embedded into the binary.
+#[derive(Unique)]
+pub(crate) struct ShaderLibrary {
+    /// Directory the shaders are read from, `None` when the embedded sources
+    /// are used.
+    directory: Option<PathBuf>,
+    sources: AHashMap<BuiltinShader, ShaderSource>,
+    /// Contains the last time the shader files were checked.
+    last_poll: Instant,
+}
+
+impl ShaderLibrary {
+    /// Creates and returns a new `ShaderLibrary`, reading the shaders from
+    /// their directory if it is available.
+    pub(crate) fn new() -> Self {
+        let directory = shaders_directory();
+
+        let sources = BuiltinShader::ALL
+            .into_iter()
+            .map(|shader| {
+                let source = directory
+                    .as_ref()
+                    .and_then(|d| read_source(&d.join(shader.file_name())))
+                    .unwrap_or_else(|| ShaderSource {
+                        code: shader.embedded_source().to_owned(),
+                        modified: None,
+                    });
+
+                (shader, source)
+            })
+            .collect();
+
+        Self {
+            directory,
+            sources,
+            last_poll: Instant::now(),
+        }
+    }
+
+    /// Compiles the shader, the embedded source is used if the one read from
+    /// the shaders directory doesn't compile.
+    pub(crate) fn compile(
+        &self,
+        gpu: &Gpu,
+        shader: BuiltinShader,
+    ) -> ShaderModule {
+        let label = shader.file_name();
+        let code = &self.sources[&shader].code;
+
+        match gpu.validate(|| gpu.compile_program(label, code)) {
+            Ok(program) => program,
+            Err(e) => {
+                error!(
+                    "Unable to compile {}, using the embedded one: {}",
+                    label, e
+                );
+                gpu.compile_program(label, shader.embedded_source())
+            }
+        }
+    }
+
+    /// Reads the shader files modified since the last check and returns
+    /// their shaders.
+    fn poll_changes(&mut self) -> Vec<BuiltinShader> {
+        let directory = match &self.directory {
+            Some(d) if self.last_poll.elapsed() >= HOT_RELOAD_POLL_INTERVAL => {
+                d
+            }
+            _ => return Vec::new(),
+        };
+
+        self.last_poll = Instant::now();
+
+        let mut changed = Vec::new();
+
+        for (shader, source) in &mut self.sources {
+            let path = directory.join(shader.file_name());
+            let modified = modification_time(&path);
+
+            // Missing files are ignored, the file could be in the middle of
+            // being written.
+            if modified.is_none() || modified == source.modified {
+                continue;
+            }
+
+            match fs::read_to_string(&path) {
+                Ok(code) => {
+                    source.code = code;
+                    source.modified = modified;
+                    changed.push(*shader);
+                }
+                Err(e) => warn!("Unable to read the shader {:?}: {}", path, e),
+            }
+        }
+
+        changed
+    }
+}
+
+/// Recompiles the shaders whose file changed and rebuilds the pipelines
+/// which use them. The compilation errors are logged and the pipelines keep
+/// the previous version of the shader.
+pub(crate) fn reload_changed_shaders(world: &World) {
+    let changed = world
+        .borrow::<UniqueViewMut<ShaderLibrary>>()
+        .expect("Unable to acquire the shader library")
+        .poll_changes();
+
+    if changed.is_empty() {
+        return;
+    }
+
+    let a_gpu = world
+        .borrow::<UniqueView<AbstractGpu>>()
+        .expect("Unable to acquire AbtractGpu");
+
+    let gpu = a_gpu
+        .downcast_ref::<Gpu>()
+        .expect("Unable to acquire Wgpu GPU");
+
+    let library = world
+        .borrow::<UniqueView<ShaderLibrary>>()
+        .expect("Unable to acquire the shader library");
+
+    for shader in changed {
+        let label = shader.file_name();
+        let code = &library.sources[&shader].code;
+
+        let result =
+            gpu.validate(|| gpu.compile_program(label, code)).and_then(
+                |program| rebuild_pipelines(world, gpu, shader, &program),
+            );
+
+        match result {
+            Ok(()) => info!("Reloaded the shader {}", label),
+            Err(e) => error!("Unable to reload the shader {}: {}", label, e),
+        }
+    }
+}
+
+/// Creates again the pipelines which use the shader with its new program.
+fn rebuild_pipelines(
+    world: &World,
+    gpu: &Gpu,
+    shader: BuiltinShader,
+    program: &ShaderModule,
+) -> Result<(), String> {
+    let layouts = world
+        .borrow::<UniqueView<GlobalBindGroupLayouts>>()
+        .expect("Unable to acquire the global bind group layouts");
+
+    let sample_counts = scene_sample_counts(gpu);
+
+    match shader {
+        BuiltinShader::DynamicMesh => {
+            let materials = world
+                .borrow::<UniqueView<MaterialBindGroups>>()
+                .expect("Unable to acquire the material bind groups");
+
+            let ibl = world
+                .borrow::<UniqueView<IblPipeline>>()
+                .expect("Unable to acquire the IBL pipeline");
+
+            let pipeline = gpu.validate(|| {
+                DynamicMeshPipeline::new(
+                    gpu,
+                    program,
+                    &layouts.camera,
+                    &materials.layout,
+                    &layouts.lights,
+                    &ibl.environment_layout,
+                    &sample_counts,
+                )
+            })?;
+
+            *world
+                .borrow::<UniqueViewMut<DynamicMeshPipeline>>()
+                .expect("Unable to acquire the dynamic mesh pipeline") =
+                pipeline;
+        }
+        BuiltinShader::Shadow => {
+            let pipeline = gpu.validate(|| {
+                ShadowPipeline::new(gpu, program, &layouts.shadow_caster)
+            })?;
+
+            *world
+                .borrow::<UniqueViewMut<ShadowPipeline>>()
+                .expect("Unable to acquire the shadow pipeline") = pipeline;
+        }
+        BuiltinShader::InfiniteGrid => {
+            let pipeline = gpu.validate(|| {
+                InfiniteGridPipeline::new(
+                    gpu,
+                    program,
+                    &layouts.camera,
+                    &sample_counts,
+                )
+            })?;
+
+            *world
+                .borrow::<UniqueViewMut<InfiniteGridPipeline>>()
+                .expect("Unable to acquire the infinite grid pipeline") =
+                pipeline;
+        }
+        BuiltinShader::Outline => {
+            let pipeline = gpu.validate(|| {
+                OutlinePipeline::new(
+                    gpu,
+                    program,
+                    &layouts.camera,
+                    &sample_counts,
+                )
+            })?;
+
+            *world
+                .borrow::<UniqueViewMut<OutlinePipeline>>()
+                .expect("Unable to acquire the outline pipeline") = pipeline;
+        }
+        BuiltinShader::DebugLines => {
+            let pipeline = gpu.validate(|| {
+                DebugDrawPipeline::new(
+                    gpu,
+                    program,
+                    &layouts.camera,
+                    &sample_counts,
+                )
+            })?;
+
+            *world
+                .borrow::<UniqueViewMut<DebugDrawPipeline>>()
+                .expect("Unable to acquire the debug draw pipeline") = pipeline;
+        }
+        BuiltinShader::Sky => world
+            .borrow::<UniqueViewMut<SkyPipeline>>()
+            .expect("Unable to acquire the sky pipeline")
+            .reload(gpu, program, &layouts.camera, &sample_counts)?,
+        BuiltinShader::Equirectangular => world
+            .borrow::<UniqueViewMut<SkyPipeline>>()
+            .expect("Unable to acquire the sky pipeline")
+            .reload_equirectangular(gpu, program)?,
+        BuiltinShader::Ibl => world
+            .borrow::<UniqueViewMut<IblPipeline>>()
+            .expect("Unable to acquire the IBL pipeline")
+            .reload(gpu, program)?,
+        BuiltinShader::PostProcess => world
+            .borrow::<UniqueViewMut<PostProcessPipeline>>()
+            .expect("Unable to acquire the post process pipeline")
+            .reload(gpu, program)?,
+        BuiltinShader::Fxaa => world
+            .borrow::<UniqueViewMut<FxaaPipeline>>()
+            .expect("Unable to acquire the FXAA pipeline")
+            .reload(gpu, program)?,
+        BuiltinShader::FrameComposition => world
+            .borrow::<UniqueViewMut<FrameCompositionPipeline>>()
+            .expect("Unable to acquire the frame composition pipeline")
+            .reload(gpu, program)?,
+    }
+
+    Ok(())
+}
+
+/// Returns the directory of the shader sources, it is only used by the
+/// development builds.
+fn shaders_directory() -> Option<PathBuf> {
+    if !cfg!(debug_assertions) {
+        return None;
+    }
+
+    let directory =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("src/wgpu_graphics/shaders");
+
+    directory.is_dir().then_some(directory)
+}
+
+/// Reads the shader file, `None` if it is not available.
+fn read_source(path: &Path) -> Option<ShaderSource> {
+    match fs::read_to_string(path) {
+        Ok(code) => Some(ShaderSource {
+            code,
+            modified: modification_time(path),
+        }),
+        Err(e) => {
+            warn!("Unable to read the shader {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
+/// Returns the last modification time of the file, `None` if it is not
+/// available.
+fn modification_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
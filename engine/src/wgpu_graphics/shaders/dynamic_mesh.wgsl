@@ -4,27 +4,128 @@ struct CameraUniform {
     view: mat4x4<f32>,
     inv_proj: mat4x4<f32>,
     inv_view: mat4x4<f32>,
+    // x contains the debug view: 0 lit, 1 normals, 2 depth, 3 UV checker and
+    // 4 overdraw.
+    debug_view: vec4<u32>,
 };
 
-struct TransformUniform {
-    matrix: mat4x4<f32>,
-}
+struct MaterialUniform {
+    base_color: vec4<f32>,
+    emissive: vec4<f32>,
+    metallic: f32,
+    roughness: f32,
+    alpha_cutoff: f32,
+    // 0 opaque, 1 mask and 2 blend.
+    alpha_mode: u32,
+};
+
+struct Light {
+    // w contains the range.
+    position: vec4<f32>,
+    direction: vec4<f32>,
+    // w contains the intensity.
+    color: vec4<f32>,
+    // x and y contain the cosine of the inner and outer angles.
+    cone: vec4<f32>,
+    // x contains the first shadow map (-1 without shadows), y the depth bias
+    // and z the normal bias.
+    shadow: vec4<f32>,
+};
+
+struct Lights {
+    ambient_color: vec4<f32>,
+    // Number of directional, point and spot lights.
+    counts: vec4<u32>,
+    // Distance from the camera where each shadow cascade ends.
+    cascade_splits: vec4<f32>,
+    // Sorted by type: directional, point and spot.
+    lights: array<Light>,
+};
 
 @group(0) @binding(0)
 var<uniform> camera: CameraUniform;
 
+@group(1) @binding(0)
+var<uniform> material: MaterialUniform;
+@group(1) @binding(1)
+var base_color_texture: texture_2d<f32>;
+@group(1) @binding(2)
+var metallic_roughness_texture: texture_2d<f32>;
+@group(1) @binding(3)
+var emissive_texture: texture_2d<f32>;
+@group(1) @binding(4)
+var base_color_sampler: sampler;
+@group(1) @binding(5)
+var metallic_roughness_sampler: sampler;
+@group(1) @binding(6)
+var emissive_sampler: sampler;
+
+struct ShadowCaster {
+    view_proj: mat4x4<f32>,
+    // Each caster is aligned to 256 bytes so it can be bound with an offset.
+    _padding: array<vec4<f32>, 12>,
+};
+
+@group(2) @binding(0)
+var<storage, read> lights: Lights;
+@group(2) @binding(1)
+var<storage, read> shadow_casters: array<ShadowCaster>;
+@group(2) @binding(2)
+var shadow_maps: texture_depth_2d_array;
+@group(2) @binding(3)
+var shadow_sampler: sampler_comparison;
+
+struct EnvironmentUniform {
+    // 1 when the maps are computed from the sky, otherwise the ambient color
+    // is used.
+    is_ready: u32,
+    prefiltered_mip_levels: f32,
+    _padding: vec2<u32>,
+};
+
+@group(3) @binding(0)
+var<uniform> environment: EnvironmentUniform;
+@group(3) @binding(1)
+var irradiance_map: texture_cube<f32>;
+@group(3) @binding(2)
+var prefiltered_map: texture_cube<f32>;
+@group(3) @binding(3)
+var brdf_lut: texture_2d<f32>;
+@group(3) @binding(4)
+var environment_sampler: sampler;
+
+const PI: f32 = 3.14159265359;
+const SHADOW_CASCADES: u32 = 4u;
+// Added by each mesh drawn on a pixel with the overdraw view.
+const OVERDRAW_COLOR: vec3<f32> = vec3<f32>(0.08, 0.04, 0.02);
+const WIREFRAME_COLOR: vec4<f32> = vec4<f32>(0.0, 0.0, 0.0, 0.6);
+
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) color: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+    @location(3) normal: vec3<f32>,
 };
 
 struct TransformInput {
-    @location(2) t0: vec4<f32>,
-    @location(3) t1: vec4<f32>,
-    @location(4) t2: vec4<f32>,
-    @location(5) t3: vec4<f32>,
+    @location(4) t0: vec4<f32>,
+    @location(5) t1: vec4<f32>,
+    @location(6) t2: vec4<f32>,
+    @location(7) t3: vec4<f32>,
+    @location(8) receives_shadows: u32,
+    @location(9) lod_fade: f32,
 }
 
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) world_position: vec3<f32>,
+    @location(3) world_normal: vec3<f32>,
+    @location(4) @interpolate(flat) receives_shadows: u32,
+    @location(5) @interpolate(flat) lod_fade: f32,
+};
+
 @vertex
 fn vs_main(
     model: VertexInput,
@@ -37,18 +138,328 @@ fn vs_main(
         transform.t3,
     );
 
+    // The cofactor matrix keeps the normals perpendicular when the scale is
+    // not uniform, it is the inverse transpose multiplied by the determinant.
+    let m = mat3x3<f32>(transform.t0.xyz, transform.t1.xyz, transform.t2.xyz);
+    let normal_matrix = mat3x3<f32>(
+        cross(m[1], m[2]),
+        cross(m[2], m[0]),
+        cross(m[0], m[1]),
+    );
+
+    let world_position = transform_matrix * vec4<f32>(model.position, 1.0);
+
     var out: VertexOutput;
     out.color = model.color;
-    out.position = camera.view_proj * transform_matrix * vec4<f32>(model.position, 1.0);
+    out.uv = model.uv;
+    out.world_position = world_position.xyz;
+    out.world_normal = normal_matrix * model.normal;
+    out.receives_shadows = transform.receives_shadows;
+    out.lod_fade = transform.lod_fade;
+    out.position = camera.view_proj * world_position;
     return out;
 }
 
-struct VertexOutput {
-    @builtin(position) position: vec4<f32>,
-    @location(0) color: vec3<f32>,
+// Normal distribution function (Trowbridge-Reitz GGX).
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+
+    return a2 / (PI * d * d);
+}
+
+// Geometry function (Smith with Schlick-GGX).
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let r = roughness + 1.0;
+    let k = (r * r) / 8.0;
+
+    let ggx_v = n_dot_v / (n_dot_v * (1.0 - k) + k);
+    let ggx_l = n_dot_l / (n_dot_l * (1.0 - k) + k);
+
+    return ggx_v * ggx_l;
+}
+
+fn fresnel_schlick(cos_theta: f32, f0: vec3<f32>) -> vec3<f32> {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// Fresnel used for the environment light, rough surfaces reflect less light
+// at grazing angles.
+fn fresnel_schlick_roughness(
+    cos_theta: f32,
+    f0: vec3<f32>,
+    roughness: f32,
+) -> vec3<f32> {
+    let max_reflectance = max(vec3<f32>(1.0 - roughness), f0);
+    return f0 + (max_reflectance - f0)
+        * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// Smoothly reduces the light to zero when it reaches the range.
+fn range_attenuation(distance: f32, range: f32) -> f32 {
+    let ratio = distance / max(range, 0.0001);
+    let window = clamp(1.0 - ratio * ratio * ratio * ratio, 0.0, 1.0);
+
+    return window * window / max(distance * distance, 0.0001);
+}
+
+// Returns the fraction of the light which reaches the position using the
+// shadow map `layer`, the 3x3 samples around it soften the edges.
+fn shadow_factor(
+    light: Light,
+    layer: u32,
+    position: vec3<f32>,
+    normal: vec3<f32>,
+) -> f32 {
+    // Moving the position along the normal avoids the shadow acne.
+    let offset_position = position + normal * light.shadow.z;
+    let clip = shadow_casters[layer].view_proj * vec4<f32>(offset_position, 1.0);
+    let ndc = clip.xyz / clip.w;
+    let uv = ndc.xy * vec2<f32>(0.5, -0.5) + 0.5;
+    let depth = ndc.z - light.shadow.y;
+
+    // Outside of the shadow map there is no information.
+    if any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) || depth > 1.0 {
+        return 1.0;
+    }
+
+    let texel = 1.0 / vec2<f32>(textureDimensions(shadow_maps));
+    var visibility = 0.0;
+    for (var x = -1; x <= 1; x++) {
+        for (var y = -1; y <= 1; y++) {
+            visibility += textureSampleCompareLevel(
+                shadow_maps,
+                shadow_sampler,
+                uv + vec2<f32>(f32(x), f32(y)) * texel,
+                layer,
+                depth,
+            );
+        }
+    }
+
+    return visibility / 9.0;
+}
+
+// Returns the fraction of the directional light which reaches the position,
+// the cascade is selected by the distance to the camera.
+fn directional_shadow(light: Light, position: vec3<f32>, normal: vec3<f32>) -> f32 {
+    let view_depth = -(camera.view * vec4<f32>(position, 1.0)).z;
+
+    var cascade = SHADOW_CASCADES;
+    for (var i = 0u; i < SHADOW_CASCADES; i++) {
+        if view_depth <= lights.cascade_splits[i] {
+            cascade = i;
+            break;
+        }
+    }
+
+    // Beyond the last cascade the shadows are not rendered.
+    if cascade == SHADOW_CASCADES {
+        return 1.0;
+    }
+
+    return shadow_factor(light, u32(light.shadow.x) + cascade, position, normal);
+}
+
+struct Surface {
+    albedo: vec3<f32>,
+    metallic: f32,
+    roughness: f32,
+    normal: vec3<f32>,
+    view: vec3<f32>,
+    f0: vec3<f32>,
 };
 
+// Returns the light reflected towards the camera by a light coming from
+// `light_dir` (pointing to the light) with the provided radiance.
+fn brdf(surface: Surface, light_dir: vec3<f32>, radiance: vec3<f32>) -> vec3<f32> {
+    let h = normalize(surface.view + light_dir);
+
+    let n_dot_l = max(dot(surface.normal, light_dir), 0.0);
+    let n_dot_v = max(dot(surface.normal, surface.view), 0.0001);
+    let n_dot_h = max(dot(surface.normal, h), 0.0);
+
+    let d = distribution_ggx(n_dot_h, surface.roughness);
+    let g = geometry_smith(n_dot_v, n_dot_l, surface.roughness);
+    let f = fresnel_schlick(max(dot(h, surface.view), 0.0), surface.f0);
+
+    let specular = d * g * f / max(4.0 * n_dot_v * n_dot_l, 0.0001);
+    // Metals do not have diffuse light.
+    let diffuse = (vec3<f32>(1.0) - f) * (1.0 - surface.metallic);
+
+    return (diffuse * surface.albedo / PI + specular) * radiance * n_dot_l;
+}
+
+// Returns the light received from the environment, the sky when the scene
+// renders one, otherwise the ambient color.
+fn ambient_light(surface: Surface) -> vec3<f32> {
+    // Sampled before the branch, the textures must be sampled in uniform
+    // control flow.
+    let n_dot_v = max(dot(surface.normal, surface.view), 0.0);
+    let reflected = reflect(-surface.view, surface.normal);
+
+    let irradiance =
+        textureSample(irradiance_map, environment_sampler, surface.normal).rgb;
+    let prefiltered = textureSampleLevel(
+        prefiltered_map,
+        environment_sampler,
+        reflected,
+        surface.roughness * (environment.prefiltered_mip_levels - 1.0),
+    ).rgb;
+    let scale_bias = textureSample(
+        brdf_lut,
+        environment_sampler,
+        vec2<f32>(n_dot_v, surface.roughness),
+    ).rg;
+
+    if environment.is_ready == 0u {
+        return lights.ambient_color.rgb * surface.albedo;
+    }
+
+    let f = fresnel_schlick_roughness(n_dot_v, surface.f0, surface.roughness);
+    let diffuse = (vec3<f32>(1.0) - f) * (1.0 - surface.metallic)
+        * irradiance * surface.albedo;
+    let specular = prefiltered * (f * scale_bias.x + scale_bias.y);
+
+    return diffuse + specular;
+}
+
+// Interleaved gradient noise, used to dither the cross-fade between two
+// levels of detail.
+fn lod_dither(position: vec2<f32>) -> f32 {
+    return fract(52.9829189 * fract(dot(position, vec2<f32>(0.06711056, 0.00583715))));
+}
+
+// Checkerboard of 8x8 squares per texture, tinted with the coordinates.
+fn uv_checker(uv: vec2<f32>) -> vec3<f32> {
+    let cell = floor(uv * 8.0);
+    let checker = abs(cell.x + cell.y) % 2.0;
+    let tint = 0.5 + 0.5 * vec3<f32>(fract(uv), 1.0);
+
+    return mix(vec3<f32>(0.25), vec3<f32>(1.0), checker) * tint;
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return vec4<f32>(in.color, 1.0);
+    // The level fading out keeps the fragments the one fading in discards.
+    let dither = lod_dither(in.position.xy);
+    if (in.lod_fade > 1.0 && dither < in.lod_fade - 1.0)
+        || (in.lod_fade <= 1.0 && dither >= in.lod_fade) {
+        discard;
+    }
+
+    let base_color = material.base_color
+        * textureSample(base_color_texture, base_color_sampler, in.uv)
+        * vec4<f32>(in.color, 1.0);
+
+    if material.alpha_mode == 1u && base_color.a < material.alpha_cutoff {
+        discard;
+    }
+
+    // The debug views replace the shading.
+    switch camera.debug_view.x {
+        case 1u: {
+            return vec4<f32>(normalize(in.world_normal) * 0.5 + 0.5, 1.0);
+        }
+        case 2u: {
+            let depth = -(camera.view * vec4<f32>(in.world_position, 1.0)).z;
+            return vec4<f32>(vec3<f32>(1.0 / (1.0 + depth * 0.1)), 1.0);
+        }
+        case 3u: {
+            return vec4<f32>(uv_checker(in.uv), 1.0);
+        }
+        case 4u: {
+            return vec4<f32>(OVERDRAW_COLOR, 1.0);
+        }
+        default: {}
+    }
+
+    // Roughness is stored in the green channel and metallic in the blue one.
+    let metallic_roughness =
+        textureSample(
+            metallic_roughness_texture,
+            metallic_roughness_sampler,
+            in.uv,
+        );
+
+    var surface: Surface;
+    surface.albedo = base_color.rgb;
+    surface.metallic = clamp(material.metallic * metallic_roughness.b, 0.0, 1.0);
+    // Very low values produce a tiny highlight that disappears.
+    surface.roughness = clamp(material.roughness * metallic_roughness.g, 0.04, 1.0);
+    surface.normal = normalize(in.world_normal);
+    surface.view = normalize(camera.view_pos.xyz - in.world_position);
+    surface.f0 = mix(vec3<f32>(0.04), surface.albedo, surface.metallic);
+
+    var color = ambient_light(surface);
+
+    let directional_count = lights.counts.x;
+    let point_end = directional_count + lights.counts.y;
+    let spot_end = point_end + lights.counts.z;
+
+    for (var i = 0u; i < directional_count; i++) {
+        let light = lights.lights[i];
+        let radiance = light.color.rgb * light.color.w;
+
+        var shadow = 1.0;
+        if in.receives_shadows != 0u && light.shadow.x >= 0.0 {
+            shadow = directional_shadow(light, in.world_position, surface.normal);
+        }
+
+        color += brdf(surface, -normalize(light.direction.xyz), radiance)
+            * shadow;
+    }
+
+    for (var i = directional_count; i < point_end; i++) {
+        let light = lights.lights[i];
+        let to_light = light.position.xyz - in.world_position;
+        let distance = length(to_light);
+        let radiance = light.color.rgb * light.color.w
+            * range_attenuation(distance, light.position.w);
+
+        color += brdf(surface, to_light / max(distance, 0.0001), radiance);
+    }
+
+    for (var i = point_end; i < spot_end; i++) {
+        let light = lights.lights[i];
+        let to_light = light.position.xyz - in.world_position;
+        let distance = length(to_light);
+        let light_dir = to_light / max(distance, 0.0001);
+
+        let cos_angle = dot(-light_dir, normalize(light.direction.xyz));
+        let cone = smoothstep(light.cone.y, light.cone.x, cos_angle);
+
+        let radiance = light.color.rgb * light.color.w * cone
+            * range_attenuation(distance, light.position.w);
+
+        var shadow = 1.0;
+        if in.receives_shadows != 0u && light.shadow.x >= 0.0 {
+            shadow = shadow_factor(
+                light,
+                u32(light.shadow.x),
+                in.world_position,
+                surface.normal,
+            );
+        }
+
+        color += brdf(surface, light_dir, radiance) * shadow;
+    }
+
+    let emissive = material.emissive.rgb
+        * textureSample(emissive_texture, emissive_sampler, in.uv).rgb;
+    color += emissive;
+
+    var alpha = 1.0;
+    if material.alpha_mode == 2u {
+        alpha = base_color.a;
+    }
+
+    return vec4<f32>(color, alpha);
+}
+
+// Draws the edges of the triangles with the line polygon mode.
+@fragment
+fn fs_wireframe(in: VertexOutput) -> @location(0) vec4<f32> {
+    return WIREFRAME_COLOR;
 }
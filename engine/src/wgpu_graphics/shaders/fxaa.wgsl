@@ -0,0 +1,155 @@
+// Fast approximate anti-aliasing, it finds the edges using the luma of the
+// pixels and blends the pixels across them.
+
+@group(0) @binding(0)
+var color_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var color_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+// The edges with a lower contrast are not smoothed.
+const EDGE_THRESHOLD_MIN: f32 = 0.0312;
+const EDGE_THRESHOLD_MAX: f32 = 0.125;
+// Amount of blending applied to the edges thinner than a pixel.
+const SUBPIXEL_QUALITY: f32 = 0.75;
+// Max number of pixels explored along each side of an edge.
+const SEARCH_STEPS: i32 = 12;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // A triangle which covers the whole screen.
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+fn luma(color: vec3<f32>) -> f32 {
+    // The colors are linear, the square root approximates the perceived
+    // brightness.
+    return sqrt(dot(color, vec3<f32>(0.299, 0.587, 0.114)));
+}
+
+fn sample_luma(uv: vec2<f32>) -> f32 {
+    return luma(textureSampleLevel(color_texture, color_sampler, uv, 0.0).rgb);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(color_texture));
+    let center = textureSampleLevel(color_texture, color_sampler, in.uv, 0.0);
+
+    let luma_center = luma(center.rgb);
+    let luma_up = sample_luma(in.uv + vec2<f32>(0.0, -texel.y));
+    let luma_down = sample_luma(in.uv + vec2<f32>(0.0, texel.y));
+    let luma_left = sample_luma(in.uv + vec2<f32>(-texel.x, 0.0));
+    let luma_right = sample_luma(in.uv + vec2<f32>(texel.x, 0.0));
+
+    let luma_min = min(luma_center, min(min(luma_up, luma_down), min(luma_left, luma_right)));
+    let luma_max = max(luma_center, max(max(luma_up, luma_down), max(luma_left, luma_right)));
+    let luma_range = luma_max - luma_min;
+
+    if luma_range < max(EDGE_THRESHOLD_MIN, luma_max * EDGE_THRESHOLD_MAX) {
+        return center;
+    }
+
+    let luma_up_left = sample_luma(in.uv + vec2<f32>(-texel.x, -texel.y));
+    let luma_up_right = sample_luma(in.uv + vec2<f32>(texel.x, -texel.y));
+    let luma_down_left = sample_luma(in.uv + vec2<f32>(-texel.x, texel.y));
+    let luma_down_right = sample_luma(in.uv + vec2<f32>(texel.x, texel.y));
+
+    let luma_up_down = luma_up + luma_down;
+    let luma_left_right = luma_left + luma_right;
+    let luma_left_corners = luma_up_left + luma_down_left;
+    let luma_right_corners = luma_up_right + luma_down_right;
+    let luma_up_corners = luma_up_left + luma_up_right;
+    let luma_down_corners = luma_down_left + luma_down_right;
+
+    let edge_horizontal = abs(-2.0 * luma_left + luma_left_corners)
+        + abs(-2.0 * luma_center + luma_up_down) * 2.0
+        + abs(-2.0 * luma_right + luma_right_corners);
+    let edge_vertical = abs(-2.0 * luma_up + luma_up_corners)
+        + abs(-2.0 * luma_center + luma_left_right) * 2.0
+        + abs(-2.0 * luma_down + luma_down_corners);
+    let is_horizontal = edge_horizontal >= edge_vertical;
+
+    // The first neighbour is in the negative direction (up or left).
+    let luma_1 = select(luma_left, luma_up, is_horizontal);
+    let luma_2 = select(luma_right, luma_down, is_horizontal);
+    let gradient_1 = luma_1 - luma_center;
+    let gradient_2 = luma_2 - luma_center;
+    let is_1_steepest = abs(gradient_1) >= abs(gradient_2);
+    let gradient_scaled = 0.25 * max(abs(gradient_1), abs(gradient_2));
+
+    var step_length = select(texel.x, texel.y, is_horizontal);
+    var luma_local_average = 0.5 * (luma_2 + luma_center);
+    if is_1_steepest {
+        step_length = -step_length;
+        luma_local_average = 0.5 * (luma_1 + luma_center);
+    }
+
+    // Moves half a pixel towards the edge and explores along it.
+    var edge_uv = in.uv;
+    if is_horizontal {
+        edge_uv.y += step_length * 0.5;
+    } else {
+        edge_uv.x += step_length * 0.5;
+    }
+
+    let offset = select(vec2<f32>(0.0, texel.y), vec2<f32>(texel.x, 0.0), is_horizontal);
+    var uv_1 = edge_uv - offset;
+    var uv_2 = edge_uv + offset;
+    var luma_end_1 = sample_luma(uv_1) - luma_local_average;
+    var luma_end_2 = sample_luma(uv_2) - luma_local_average;
+    var reached_1 = abs(luma_end_1) >= gradient_scaled;
+    var reached_2 = abs(luma_end_2) >= gradient_scaled;
+
+    for (var i = 0; i < SEARCH_STEPS && !(reached_1 && reached_2); i++) {
+        if !reached_1 {
+            uv_1 -= offset;
+            luma_end_1 = sample_luma(uv_1) - luma_local_average;
+            reached_1 = abs(luma_end_1) >= gradient_scaled;
+        }
+
+        if !reached_2 {
+            uv_2 += offset;
+            luma_end_2 = sample_luma(uv_2) - luma_local_average;
+            reached_2 = abs(luma_end_2) >= gradient_scaled;
+        }
+    }
+
+    let distance_1 = select(in.uv.y - uv_1.y, in.uv.x - uv_1.x, is_horizontal);
+    let distance_2 = select(uv_2.y - in.uv.y, uv_2.x - in.uv.x, is_horizontal);
+    let is_direction_1 = distance_1 < distance_2;
+    let distance_final = min(distance_1, distance_2);
+    let edge_length = distance_1 + distance_2;
+
+    // Only blends when the end of the edge has the opposite variation than
+    // the center.
+    let is_luma_center_smaller = luma_center < luma_local_average;
+    let luma_end = select(luma_end_2, luma_end_1, is_direction_1);
+    let correct_variation = (luma_end < 0.0) != is_luma_center_smaller;
+    var final_offset = select(0.0, 0.5 - distance_final / edge_length, correct_variation);
+
+    // Blends the edges thinner than a pixel with the neighbours.
+    let luma_average = (1.0 / 12.0) * (2.0 * (luma_up_down + luma_left_right)
+        + luma_left_corners + luma_right_corners);
+    let subpixel_1 = clamp(abs(luma_average - luma_center) / luma_range, 0.0, 1.0);
+    let subpixel_2 = (-2.0 * subpixel_1 + 3.0) * subpixel_1 * subpixel_1;
+    final_offset = max(final_offset, subpixel_2 * subpixel_2 * SUBPIXEL_QUALITY);
+
+    var final_uv = in.uv;
+    if is_horizontal {
+        final_uv.y += final_offset * step_length;
+    } else {
+        final_uv.x += final_offset * step_length;
+    }
+
+    return textureSampleLevel(color_texture, color_sampler, final_uv, 0.0);
+}
@@ -0,0 +1,225 @@
+// Precomputes the textures used to light the surfaces with the sky, based on
+// the split sum approximation.
+
+const PI: f32 = 3.14159265359;
+
+struct PrefilterParams {
+    roughness: f32,
+    sample_count: u32,
+    _padding: vec2<u32>,
+};
+
+@group(0) @binding(0)
+var environment: texture_cube<f32>;
+@group(0) @binding(1)
+var environment_sampler: sampler;
+@group(0) @binding(2)
+var output: texture_storage_2d_array<rgba16float, write>;
+@group(0) @binding(3)
+var<uniform> params: PrefilterParams;
+
+// The LUT uses its own layout, which only contains this binding.
+@group(0) @binding(4)
+var brdf_lut: texture_storage_2d<rgba16float, write>;
+
+// Returns the direction sampled by the texel of a cubemap face, it follows the
+// cubemap convention used when sampling a cube texture.
+fn cube_direction(gid: vec3<u32>, size: vec2<u32>) -> vec3<f32> {
+    let uv = (vec2<f32>(gid.xy) + 0.5) / vec2<f32>(size) * 2.0 - 1.0;
+
+    var direction: vec3<f32>;
+    switch gid.z {
+        // +X
+        case 0u: { direction = vec3(1.0, -uv.y, -uv.x); }
+        // -X
+        case 1u: { direction = vec3(-1.0, -uv.y, uv.x); }
+        // +Y
+        case 2u: { direction = vec3(uv.x, 1.0, uv.y); }
+        // -Y
+        case 3u: { direction = vec3(uv.x, -1.0, -uv.y); }
+        // +Z
+        case 4u: { direction = vec3(uv.x, -uv.y, 1.0); }
+        // -Z
+        default: { direction = vec3(-uv.x, -uv.y, -1.0); }
+    }
+
+    return normalize(direction);
+}
+
+// Transforms a vector from the tangent space around `normal` to world space.
+fn tangent_to_world(v: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    var up = vec3(0.0, 1.0, 0.0);
+    if abs(normal.y) > 0.999 {
+        up = vec3(0.0, 0.0, 1.0);
+    }
+
+    let tangent = normalize(cross(up, normal));
+    let bitangent = cross(normal, tangent);
+
+    return tangent * v.x + bitangent * v.y + normal * v.z;
+}
+
+// Low discrepancy sequence, spreads the samples better than random numbers.
+fn hammersley(i: u32, count: u32) -> vec2<f32> {
+    let radical_inverse = f32(reverseBits(i)) * 2.3283064365386963e-10;
+    return vec2(f32(i) / f32(count), radical_inverse);
+}
+
+// Returns a halfway vector oriented around `normal`, the vectors concentrate
+// where the GGX distribution is higher.
+fn importance_sample_ggx(
+    xi: vec2<f32>,
+    normal: vec3<f32>,
+    roughness: f32,
+) -> vec3<f32> {
+    let a = roughness * roughness;
+
+    let phi = 2.0 * PI * xi.x;
+    let cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    let sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+
+    let h = vec3(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+
+    return normalize(tangent_to_world(h, normal));
+}
+
+@compute
+@workgroup_size(8, 8, 1)
+fn compute_irradiance(
+    @builtin(global_invocation_id)
+    gid: vec3<u32>,
+) {
+    let size = textureDimensions(output);
+    if gid.x >= size.x || gid.y >= size.y {
+        return;
+    }
+
+    let normal = cube_direction(gid, size);
+
+    var irradiance = vec3(0.0);
+    var sample_count = 0.0;
+    let delta = 0.05;
+
+    // Convolution of the hemisphere around the normal.
+    for (var phi = 0.0; phi < 2.0 * PI; phi += delta) {
+        for (var theta = 0.0; theta < 0.5 * PI; theta += delta) {
+            let tangent_sample = vec3(
+                sin(theta) * cos(phi),
+                sin(theta) * sin(phi),
+                cos(theta),
+            );
+            let direction = tangent_to_world(tangent_sample, normal);
+
+            irradiance += textureSampleLevel(
+                environment,
+                environment_sampler,
+                direction,
+                0.0,
+            ).rgb * cos(theta) * sin(theta);
+            sample_count += 1.0;
+        }
+    }
+
+    irradiance = PI * irradiance / sample_count;
+
+    textureStore(output, gid.xy, gid.z, vec4(irradiance, 1.0));
+}
+
+@compute
+@workgroup_size(8, 8, 1)
+fn compute_prefiltered(
+    @builtin(global_invocation_id)
+    gid: vec3<u32>,
+) {
+    let size = textureDimensions(output);
+    if gid.x >= size.x || gid.y >= size.y {
+        return;
+    }
+
+    // Assumes that the view direction is the reflected direction.
+    let normal = cube_direction(gid, size);
+    let view = normal;
+
+    var color = vec3(0.0);
+    var total_weight = 0.0;
+
+    for (var i = 0u; i < params.sample_count; i++) {
+        let xi = hammersley(i, params.sample_count);
+        let h = importance_sample_ggx(xi, normal, params.roughness);
+        let light = normalize(2.0 * dot(view, h) * h - view);
+
+        let n_dot_l = dot(normal, light);
+        if n_dot_l > 0.0 {
+            color += textureSampleLevel(
+                environment,
+                environment_sampler,
+                light,
+                0.0,
+            ).rgb * n_dot_l;
+            total_weight += n_dot_l;
+        }
+    }
+
+    color = color / max(total_weight, 0.0001);
+
+    textureStore(output, gid.xy, gid.z, vec4(color, 1.0));
+}
+
+// Geometry function (Smith with Schlick-GGX) using the remapping of the
+// roughness for image based lighting.
+fn geometry_smith_ibl(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let k = (roughness * roughness) / 2.0;
+
+    let ggx_v = n_dot_v / (n_dot_v * (1.0 - k) + k);
+    let ggx_l = n_dot_l / (n_dot_l * (1.0 - k) + k);
+
+    return ggx_v * ggx_l;
+}
+
+@compute
+@workgroup_size(8, 8, 1)
+fn compute_brdf_lut(
+    @builtin(global_invocation_id)
+    gid: vec3<u32>,
+) {
+    let size = textureDimensions(brdf_lut);
+    if gid.x >= size.x || gid.y >= size.y {
+        return;
+    }
+
+    // The x axis contains the angle between the normal and the view and the
+    // y axis the roughness.
+    let n_dot_v = (f32(gid.x) + 0.5) / f32(size.x);
+    let roughness = (f32(gid.y) + 0.5) / f32(size.y);
+
+    let view = vec3(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+    let normal = vec3(0.0, 0.0, 1.0);
+
+    // Scale and bias applied to F0.
+    var scale = 0.0;
+    var bias = 0.0;
+
+    let sample_count = 512u;
+    for (var i = 0u; i < sample_count; i++) {
+        let xi = hammersley(i, sample_count);
+        let h = importance_sample_ggx(xi, normal, roughness);
+        let light = normalize(2.0 * dot(view, h) * h - view);
+
+        let n_dot_l = max(light.z, 0.0);
+        let n_dot_h = max(h.z, 0.0);
+        let v_dot_h = max(dot(view, h), 0.0);
+
+        if n_dot_l > 0.0 {
+            let g = geometry_smith_ibl(n_dot_v, n_dot_l, roughness);
+            let g_vis = g * v_dot_h / max(n_dot_h * n_dot_v, 0.0001);
+            let fresnel = pow(1.0 - v_dot_h, 5.0);
+
+            scale += (1.0 - fresnel) * g_vis;
+            bias += fresnel * g_vis;
+        }
+    }
+
+    let result = vec2(scale, bias) / f32(sample_count);
+
+    textureStore(brdf_lut, gid.xy, vec4(result, 0.0, 1.0));
+}
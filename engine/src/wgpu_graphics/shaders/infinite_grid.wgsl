@@ -4,6 +4,9 @@ struct CameraUniform {
     view: mat4x4<f32>,
     inv_proj: mat4x4<f32>,
     inv_view: mat4x4<f32>,
+    debug_view: vec4<u32>,
+    // The part of the depth buffer used by the view, in `x` and `y`.
+    depth_range: vec4<f32>,
 };
 
 @group(0) @binding(0)
@@ -135,7 +138,10 @@ fn fs_main(in: GridOutput) -> FragOut {
 
     let color = (grid(pos, 1.0, false) + grid(pos, 0.1, true)) * f32(t > 0.0);
 
-    return FragOut(depth, color * fading);
+    // The written depth is not mapped to the depth range of the viewport.
+    let view_depth = mix(camera.depth_range.x, camera.depth_range.y, depth);
+
+    return FragOut(view_depth, color * fading);
 }
 
 // https://github.com/martin-pr/possumwood/wiki/Infinite-ground-plane-using-GLSL-shaders
@@ -0,0 +1,70 @@
+struct CameraUniform {
+    view_pos: vec4<f32>,
+    view_proj: mat4x4<f32>,
+    view: mat4x4<f32>,
+    inv_proj: mat4x4<f32>,
+    inv_view: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+    @location(3) normal: vec3<f32>,
+};
+
+struct OutlineInput {
+    @location(4) t0: vec4<f32>,
+    @location(5) t1: vec4<f32>,
+    @location(6) t2: vec4<f32>,
+    @location(7) t3: vec4<f32>,
+    @location(8) color: vec4<f32>,
+    // Width in normalized device coordinates.
+    @location(9) width: vec2<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+};
+
+// Draws the back faces of the mesh pushed out along their normals on the
+// screen, the mesh hides them except around its silhouette.
+@vertex
+fn vs_main(model: VertexInput, outline: OutlineInput) -> VertexOutput {
+    let transform = mat4x4<f32>(outline.t0, outline.t1, outline.t2, outline.t3);
+
+    // The cofactor matrix keeps the normals perpendicular when the scale is
+    // not uniform.
+    let m = mat3x3<f32>(outline.t0.xyz, outline.t1.xyz, outline.t2.xyz);
+    let normal_matrix = mat3x3<f32>(
+        cross(m[1], m[2]),
+        cross(m[2], m[0]),
+        cross(m[0], m[1]),
+    );
+
+    let world_normal = normal_matrix * model.normal;
+    var position = camera.view_proj * transform * vec4<f32>(model.position, 1.0);
+    let screen_normal = (camera.view_proj * vec4<f32>(world_normal, 0.0)).xy;
+
+    // The direction is normalized in pixels, so the width is the same on
+    // both axes. The offset is multiplied by w so it keeps its size after
+    // the perspective division.
+    if length(screen_normal) > 0.0001 && all(outline.width > vec2<f32>(0.0)) {
+        let direction = normalize(screen_normal / outline.width) * outline.width;
+        position += vec4<f32>(direction * position.w, 0.0, 0.0);
+    }
+
+    var out: VertexOutput;
+    out.position = position;
+    out.color = outline.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
@@ -0,0 +1,226 @@
+// Post processing effects, each one reads the previous image and writes a
+// new one using a triangle which covers the whole screen.
+
+@group(0) @binding(0)
+var source_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var source_sampler: sampler;
+
+struct Params {
+    // Exposure: scale. Bloom: threshold and intensity. Gamma: inverse of the
+    // gamma.
+    values: vec4<f32>,
+};
+
+@group(1) @binding(0)
+var<uniform> params: Params;
+
+// Second image read by some effects, the bloom chain or the lookup table of
+// the color grading.
+@group(2) @binding(0)
+var extra_texture: texture_2d<f32>;
+@group(2) @binding(1)
+var extra_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+fn source(uv: vec2<f32>) -> vec4<f32> {
+    return textureSampleLevel(source_texture, source_sampler, uv, 0.0);
+}
+
+// Writes the image into the target texture, the colors out of range are
+// clamped.
+@fragment
+fn fs_output(in: VertexOutput) -> @location(0) vec4<f32> {
+    return source(in.uv);
+}
+
+@fragment
+fn fs_exposure(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = source(in.uv);
+    return vec4<f32>(color.rgb * params.values.x, color.a);
+}
+
+@fragment
+fn fs_gamma(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = source(in.uv);
+    return vec4<f32>(pow(max(color.rgb, vec3<f32>(0.0)), vec3<f32>(params.values.x)), color.a);
+}
+
+// Tonemapping.
+
+@fragment
+fn fs_reinhard(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = source(in.uv);
+    let rgb = max(color.rgb, vec3<f32>(0.0));
+    return vec4<f32>(rgb / (1.0 + rgb), color.a);
+}
+
+// Fit of the ACES filmic curve by Krzysztof Narkowicz.
+@fragment
+fn fs_aces(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = source(in.uv);
+    let x = max(color.rgb, vec3<f32>(0.0));
+    let rgb = (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14);
+    return vec4<f32>(clamp(rgb, vec3<f32>(0.0), vec3<f32>(1.0)), color.a);
+}
+
+// Polynomial fit of the AgX base contrast curve by Benjamin Wrensch.
+fn agx_contrast(x: vec3<f32>) -> vec3<f32> {
+    let x2 = x * x;
+    let x4 = x2 * x2;
+
+    return 15.5 * x4 * x2
+        - 40.14 * x4 * x
+        + 31.96 * x4
+        - 6.868 * x2 * x
+        + 0.4298 * x2
+        + 0.1191 * x
+        - 0.00232;
+}
+
+@fragment
+fn fs_agx(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = source(in.uv);
+
+    // Moves the colors in and out of the AgX working space.
+    let inset = mat3x3<f32>(
+        vec3<f32>(0.842479062253094, 0.0423282422610123, 0.0423756549057051),
+        vec3<f32>(0.0784335999999992, 0.878468636469772, 0.0784336),
+        vec3<f32>(0.0792237451477643, 0.0791661274605434, 0.879142973793104),
+    );
+    let outset = mat3x3<f32>(
+        vec3<f32>(1.19687900512017, -0.0528968517574562, -0.0529716355144438),
+        vec3<f32>(-0.0980208811401368, 1.15190312990417, -0.0980434501171241),
+        vec3<f32>(-0.0990297440797205, -0.0989611768448433, 1.15107367264116),
+    );
+
+    let min_ev = -12.47393;
+    let max_ev = 4.026069;
+
+    var rgb = inset * max(color.rgb, vec3<f32>(1e-10));
+    rgb = clamp(log2(rgb), vec3<f32>(min_ev), vec3<f32>(max_ev));
+    rgb = (rgb - min_ev) / (max_ev - min_ev);
+    rgb = outset * agx_contrast(rgb);
+
+    // The curve returns display encoded colors, the target texture encodes
+    // them again.
+    rgb = pow(max(rgb, vec3<f32>(0.0)), vec3<f32>(2.2));
+
+    return vec4<f32>(rgb, color.a);
+}
+
+// Color grading.
+
+fn linear_to_srgb(color: vec3<f32>) -> vec3<f32> {
+    let low = color * 12.92;
+    let high = 1.055 * pow(color, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, color <= vec3<f32>(0.0031308));
+}
+
+@fragment
+fn fs_color_grading(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = source(in.uv);
+    let size = f32(textureDimensions(extra_texture).y);
+
+    // The tables are authored for encoded colors, the texture is sRGB so the
+    // samples are already decoded.
+    let c = linear_to_srgb(clamp(color.rgb, vec3<f32>(0.0), vec3<f32>(1.0)));
+
+    let blue = c.b * (size - 1.0);
+    let first_slice = floor(blue);
+    let second_slice = min(first_slice + 1.0, size - 1.0);
+
+    let x = c.r * (size - 1.0) + 0.5;
+    let y = (c.g * (size - 1.0) + 0.5) / size;
+
+    let first = textureSampleLevel(
+        extra_texture,
+        extra_sampler,
+        vec2<f32>((first_slice * size + x) / (size * size), y),
+        0.0,
+    );
+    let second = textureSampleLevel(
+        extra_texture,
+        extra_sampler,
+        vec2<f32>((second_slice * size + x) / (size * size), y),
+        0.0,
+    );
+
+    return vec4<f32>(mix(first.rgb, second.rgb, blue - first_slice), color.a);
+}
+
+// Bloom, the bright areas are downsampled to a chain of smaller textures and
+// upsampled back adding the blurred result of each level.
+
+// Averages 4x4 texels of the source.
+fn downsample(uv: vec2<f32>) -> vec3<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(source_texture));
+
+    return 0.25 * (
+        source(uv + texel * vec2<f32>(-1.0, -1.0)).rgb
+        + source(uv + texel * vec2<f32>(1.0, -1.0)).rgb
+        + source(uv + texel * vec2<f32>(-1.0, 1.0)).rgb
+        + source(uv + texel * vec2<f32>(1.0, 1.0)).rgb
+    );
+}
+
+// Keeps the part of the colors above the threshold, with a soft transition.
+@fragment
+fn fs_bloom_prefilter(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = downsample(in.uv);
+    let threshold = params.values.x;
+    let knee = threshold * 0.5;
+
+    let brightness = max(color.r, max(color.g, color.b));
+    var soft = clamp(brightness - threshold + knee, 0.0, 2.0 * knee);
+    soft = soft * soft / (4.0 * knee + 1e-5);
+
+    let contribution = max(soft, brightness - threshold) / max(brightness, 1e-5);
+
+    return vec4<f32>(color * contribution, 1.0);
+}
+
+@fragment
+fn fs_bloom_downsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(downsample(in.uv), 1.0);
+}
+
+// Tent filter of 3x3 texels, the result is added to the target.
+@fragment
+fn fs_bloom_upsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(source_texture));
+
+    var color = source(in.uv).rgb * 4.0;
+    color += (source(in.uv + vec2<f32>(-texel.x, 0.0)).rgb
+        + source(in.uv + vec2<f32>(texel.x, 0.0)).rgb
+        + source(in.uv + vec2<f32>(0.0, -texel.y)).rgb
+        + source(in.uv + vec2<f32>(0.0, texel.y)).rgb) * 2.0;
+    color += source(in.uv + vec2<f32>(-texel.x, -texel.y)).rgb
+        + source(in.uv + vec2<f32>(texel.x, -texel.y)).rgb
+        + source(in.uv + vec2<f32>(-texel.x, texel.y)).rgb
+        + source(in.uv + vec2<f32>(texel.x, texel.y)).rgb;
+
+    return vec4<f32>(color / 16.0, 1.0);
+}
+
+@fragment
+fn fs_bloom_combine(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = source(in.uv);
+    let bloom = textureSampleLevel(extra_texture, extra_sampler, in.uv, 0.0).rgb;
+
+    return vec4<f32>(color.rgb + bloom * params.values.y, color.a);
+}
@@ -0,0 +1,29 @@
+// View projection matrix of the shadow map being rendered.
+@group(0) @binding(0)
+var<storage, read> shadow_caster: mat4x4<f32>;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+};
+
+struct TransformInput {
+    @location(4) t0: vec4<f32>,
+    @location(5) t1: vec4<f32>,
+    @location(6) t2: vec4<f32>,
+    @location(7) t3: vec4<f32>,
+}
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+    transform: TransformInput,
+) -> @builtin(position) vec4<f32> {
+    let transform_matrix = mat4x4<f32>(
+        transform.t0,
+        transform.t1,
+        transform.t2,
+        transform.t3,
+    );
+
+    return shadow_caster * transform_matrix * vec4<f32>(model.position, 1.0);
+}
@@ -1,15 +1,21 @@
 use egui_demo_lib::DemoWindows;
 
 use engine::graphics::components::MeshComponent;
+use engine::graphics::stats::RenderStats;
 use engine::nalgebra::{Point3, Unit, UnitQuaternion, Vector3};
 
 use engine::plugin::graphics::egui::EguiSceneSelector;
 use engine::plugin::scene::primitives_plugin::PrimitivesPlugin;
 
 use engine::scene::components::Transform;
+use engine::scene::lights::LightLimits;
 use engine::scene::mouse::CursorDelta;
+use engine::scene::post_processing::{PostProcessEffect, Tonemapping};
 use engine::scene::projection::Projection;
-use engine::scene::scene::SceneDescriptor;
+use engine::scene::scene::{
+    AntiAliasing, DebugView, SceneDescriptor, DEFAULT_AMBIENT_COLOR,
+    DEFAULT_SHADOW_DISTANCE,
+};
 use engine::scene::scene_state::SceneState;
 use engine::{
     app::App,
@@ -29,14 +35,13 @@ use engine::{
     schedule::Schedule,
     shipyard::{Component, Unique, UniqueView, UniqueViewMut},
 };
-use shipyard::{View, ViewMut};
+use shipyard::{NonSendSync, View, ViewMut};
 
+/// `DemoWindows` is not thread-safe, it is stored as a non `Send` + `Sync`
+/// unique so it is only accessed from the main thread.
 #[derive(Unique)]
 struct Demo(DemoWindows);
 
-unsafe impl Send for Demo {}
-unsafe impl Sync for Demo {}
-
 #[derive(Unique)]
 pub struct RotCubeAngle(f32);
 
@@ -65,7 +70,7 @@ struct Pos(f32, f32);
 
 fn set_ui(
     egui: UniqueView<EguiContext>,
-    _demo: UniqueViewMut<Demo>,
+    _demo: NonSendSync<UniqueViewMut<Demo>>,
     clock: UniqueView<Clock>,
     mouse_position: UniqueView<Cursor>,
 ) {
@@ -125,6 +130,47 @@ fn set_ui(
     //demo.0.ui(&egui.0);
 }
 
+/// Shows the cost of the last frame over the game.
+fn render_stats_overlay(
+    egui: UniqueView<EguiContext>,
+    stats: UniqueView<RenderStats>,
+) {
+    let millis = |d: std::time::Duration| d.as_secs_f64() * 1000.0;
+
+    engine::egui::Window::new("Render stats")
+        .id(engine::egui::Id::new("render_stats_window"))
+        .anchor(engine::egui::Align2::RIGHT_TOP, [-10.0, 10.0])
+        .resizable(false)
+        .show(&egui.0, |ui| {
+            ui.label(format!(
+                "Draw calls: {}, triangles: {}",
+                stats.draws.draw_calls, stats.draws.triangles
+            ));
+            ui.label(format!("CPU: {:.3} ms", millis(stats.cpu_time())));
+
+            if let Some(gpu_time) = stats.gpu_time() {
+                ui.label(format!("GPU: {:.3} ms", millis(gpu_time)));
+            }
+
+            engine::egui::CollapsingHeader::new("Passes").show(ui, |ui| {
+                for pass in &stats.passes {
+                    let gpu_time = pass
+                        .gpu_time
+                        .map(|t| format!(", GPU {:.3} ms", millis(t)))
+                        .unwrap_or_default();
+
+                    ui.label(format!(
+                        "{}: {} draws, CPU {:.3} ms{}",
+                        pass.label,
+                        pass.draws.draw_calls,
+                        millis(pass.cpu_time),
+                        gpu_time
+                    ));
+                }
+            });
+        });
+}
+
 fn camera_system(
     keyboard: UniqueView<Keyboard>,
     mut scenes: UniqueViewMut<SceneState>,
@@ -233,7 +279,7 @@ impl Pluggable for PlayerPlugin {
     fn configure(&self, app: &mut App) {
         let demo = egui_demo_lib::DemoWindows::default();
 
-        app.world.add_unique(Demo(demo));
+        app.world.add_unique_non_send_sync(Demo(demo));
         app.world.add_unique(FlyCamera::default());
         app.world.add_unique(RotCubeAngle(0.0));
 
@@ -331,6 +377,7 @@ impl Pluggable for PlayerPlugin {
 
         app.schedule(Schedule::RequestRedraw, |world| {
             world.run(set_ui);
+            world.run(render_stats_overlay);
         });
 
         app.schedule(Schedule::CursorDelta, |_world| {});
@@ -346,7 +393,7 @@ impl Pluggable for PlayerPlugin {
 pub fn main() {
     App::new()
         .add_plugin(WinitWindowPlugin::new("My game", 1024, 800))
-        .add_plugin(WgpuRendererPlugin)
+        .add_plugin(WgpuRendererPlugin::default())
         .add_plugin(ScenePlugin {
             main: SceneDescriptor {
                 label: "Main Scene".to_owned(),
@@ -356,6 +403,14 @@ pub fn main() {
                 resolution: None,
                 should_render_grid: false,
                 should_render_sky: false,
+                ambient_color: DEFAULT_AMBIENT_COLOR,
+                light_limits: LightLimits::default(),
+                shadow_distance: DEFAULT_SHADOW_DISTANCE,
+                anti_aliasing: AntiAliasing::Fxaa,
+                post_processing: vec![PostProcessEffect::Tonemapping(
+                    Tonemapping::Agx,
+                )],
+                debug_view: DebugView::default(),
             },
             sub_scenes: Vec::new(),
         })